@@ -7,9 +7,20 @@ fn main() {
     let dest = env::var("OUT_DIR").unwrap();
     let mut file = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
 
-    Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::None, [])
-        .write_bindings(GlobalGenerator, &mut file)
-        .unwrap();
+    let mut extensions = Vec::new();
+    if env::var_os("CARGO_FEATURE_GL_DEBUG_CALLBACK").is_some() {
+        extensions.push("GL_KHR_debug");
+    }
+
+    Registry::new(
+        Api::Gles2,
+        (3, 0),
+        Profile::Core,
+        Fallbacks::None,
+        extensions,
+    )
+    .write_bindings(GlobalGenerator, &mut file)
+    .unwrap();
 
     #[cfg(target_os = "macos")]
     println!("cargo:rustc-link-arg=-Wl,-rpath,@loader_path");
@@ -0,0 +1,112 @@
+//! The `--benchmark` mode: runs the simulation as fast as it goes, without
+//! rendering anything, and prints how long it took.
+
+use crate::ship_game::{find_path, PathfindingMap, ShipGame, ShipModels};
+use glam::Vec2;
+use std::time::{Duration, Instant};
+
+/// How many updates are simulated, at 60 updates per second.
+const SIMULATION_STEPS: usize = 3600;
+/// How many locations the ship sails through during the simulation.
+const ROUTE_LENGTH: usize = 5;
+/// How many times each path is searched for in the `--benchmark` mode.
+const PATHFINDING_ROUNDS: usize = 1000;
+
+pub fn run() {
+    let models = ShipModels::load();
+    let mut ship_game = ShipGame::new(&models, 42);
+    // Not a real voyage, so it shouldn't be recorded or overwrite the
+    // autosave, just like a replay
+    ship_game.replaying = true;
+    // Sail around, so that sailing and docking are simulated too
+    for location in 0..ship_game.locations.len().min(ROUTE_LENGTH) {
+        ship_game.add_waypoint(location);
+    }
+    ship_game.autopilot = true;
+
+    let start_days = ship_game.world_time;
+    let start = Instant::now();
+    for _ in 0..SIMULATION_STEPS {
+        ship_game.update(1.0 / 60.0);
+    }
+    let elapsed = start.elapsed().as_secs_f32();
+    let days = ship_game.world_time - start_days;
+    let sim_speed = SIMULATION_STEPS as f32 / 60.0 / elapsed;
+    println!("Simulated {days:.1} days in {elapsed:.3}s ({sim_speed:.0}x realtime)");
+
+    let destinations = ship_game.pathfinding_destinations();
+    let pairs = ship_game
+        .characters
+        .iter()
+        .flat_map(|character| destinations.iter().map(|&to| (character.position, to)))
+        .collect::<Vec<_>>();
+    let (mean, p99) = pathfinding_latency(&ship_game.pf_map, &pairs, PATHFINDING_ROUNDS);
+    println!(
+        "Pathfinding over {} paths: mean {mean:?}, p99 {p99:?}",
+        pairs.len(),
+    );
+}
+
+/// Searches for each path `rounds` times, and returns the mean and the 99th
+/// percentile of how long one search took.
+fn pathfinding_latency(
+    map: &PathfindingMap,
+    pairs: &[(Vec2, Vec2)],
+    rounds: usize,
+) -> (Duration, Duration) {
+    let mut latencies = Vec::with_capacity(pairs.len() * rounds);
+    for _ in 0..rounds {
+        for &(from, to) in pairs {
+            let start = Instant::now();
+            std::hint::black_box(find_path(map, from, to));
+            latencies.push(start.elapsed());
+        }
+    }
+    assert!(!latencies.is_empty(), "no paths to search for");
+    latencies.sort_unstable();
+    let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    let p99 = latencies[(latencies.len() - 1) * 99 / 100];
+    (mean, p99)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::IVec2;
+
+    /// Just the pathfinding part, on a map that doesn't change with the
+    /// models. Run with `cargo test -- --nocapture benchmark` to see the
+    /// numbers. Fewer rounds than in the `--benchmark` mode, to keep it
+    /// quick enough to run with the rest of the tests.
+    #[test]
+    fn benchmark() {
+        // An open 16x16 grid, like the floor of a few rooms
+        let mut map = PathfindingMap::new();
+        for y in 0..16 {
+            for x in 0..16 {
+                let node = IVec2::new(x, y);
+                let neighbors = (-1..=1)
+                    .flat_map(|yo| (-1..=1).map(move |xo| node + IVec2::new(xo, yo)))
+                    .filter(|n| {
+                        *n != node && n.cmpge(IVec2::ZERO).all() && n.cmplt(IVec2::splat(16)).all()
+                    })
+                    .collect();
+                map.insert(node, neighbors);
+            }
+        }
+        let corners = [(0.5, 0.5), (15.5, 0.5), (0.5, 15.5), (15.5, 15.5)].map(Vec2::from);
+        let pairs = corners
+            .iter()
+            .flat_map(|&from| corners.iter().map(move |&to| (from, to)))
+            .filter(|(from, to)| from != to)
+            .collect::<Vec<_>>();
+        for &(from, to) in &pairs {
+            assert!(find_path(&map, from, to).is_some());
+        }
+        let (mean, p99) = pathfinding_latency(&map, &pairs, 20);
+        println!(
+            "Pathfinding over {} paths: mean {mean:?}, p99 {p99:?}",
+            pairs.len()
+        );
+    }
+}
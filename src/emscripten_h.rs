@@ -1,4 +1,4 @@
-use std::ffi::{c_char, c_int, c_uint, CString};
+use std::ffi::{c_char, c_int, c_uint, CStr, CString};
 
 pub fn set_main_loop(func: EmCallbackFunc) -> ! {
     unsafe { emscripten_set_main_loop(func, 0, 1) };
@@ -16,6 +16,17 @@ pub fn run_javascript(script: &str) {
     unsafe { emscripten_run_script(script.as_c_str().as_ptr()) };
 }
 
+/// Runs the script and returns its result, converted to a string.
+#[allow(dead_code)]
+pub fn run_javascript_string(script: &str) -> String {
+    let mut script = Vec::from(script.as_bytes());
+    script.push(0);
+    let script = CString::from_vec_with_nul(script).unwrap();
+    let result =
+        unsafe { CStr::from_ptr(emscripten_run_script_string(script.as_c_str().as_ptr())) };
+    result.to_string_lossy().into_owned()
+}
+
 pub type EmCallbackFunc = extern "C" fn();
 extern "C" {
     /// https://emscripten.org/docs/api_reference/emscripten.h.html#c.emscripten_set_main_loop
@@ -28,6 +39,9 @@ extern "C" {
     /// https://emscripten.org/docs/api_reference/emscripten.h.html#c.emscripten_run_script
     pub fn emscripten_run_script(script: *const c_char);
 
+    /// https://emscripten.org/docs/api_reference/emscripten.h.html#c.emscripten_run_script_string
+    pub fn emscripten_run_script_string(script: *const c_char) -> *const c_char;
+
     /// https://emscripten.org/docs/api_reference/emscripten.h.html#c.emscripten_sleep
     pub fn emscripten_sleep(ms: c_uint);
 }
@@ -1,64 +1,442 @@
 //! The thing shown on the dashboard in-game.
 
-use crate::ship_game::{ShipGame, Task};
+use crate::renderer::Renderer;
+use crate::settings::{GameOption, KeyAction};
+use crate::ship_game::{
+    item_weight, RoomType, ShipGame, Task, AUTOSAVE_SLOT, MAX_CREW_SIZE, SAVE_SLOTS, SCHEDULE_SLOTS,
+};
+use glam::Vec2;
 use sdl2::{
     mouse::{Cursor, SystemCursor},
     rect::{Point, Rect},
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Button {
     Tab(usize),
     TaskPicker(Task),
-    TaskAssigner { time: usize, character: usize },
+    TaskAssigner {
+        time: usize,
+        character: usize,
+    },
     LocationList(usize),
+    /// Adds the location (by index) to the end of [ShipGame::target_queue].
+    AddWaypoint(usize),
+    /// Removes the waypoint (by its index in [ShipGame::target_queue]).
+    RemoveWaypoint(usize),
+    ToggleAutopilot,
+    /// Buys one of the item (by index) in the port's inventory.
+    BuyItem(usize),
+    /// Sells one of the item (by index) in the ship's cargo hold.
+    SellItem(usize),
+    /// Picks the room type to build by clicking on the ship.
+    PlaceRoom(RoomType),
+    /// Picks tearing down rooms by clicking on them.
+    RemoveRoom,
+    TextInput(usize),
+    ClearSchedule(usize),
+    /// Scrolls the schedule grid by the given amount of slots.
+    ScrollSchedule(i32),
+    ConfirmYes,
+    ConfirmNo,
+    /// Picks a response (by index) to the pending event.
+    EventOption(usize),
+    /// Hires the recruit with the index from [ShipGame::available_recruits].
+    HireCharacter(usize),
+    /// Dismisses the crew member with the index from [ShipGame::characters].
+    DismissCharacter(usize),
+    /// Opens the save/load screen.
+    OpenSaveLoad,
+    /// Saves the game into the slot, overwriting what's there.
+    SaveToSlot(usize),
+    LoadFromSlot(usize),
+    DeleteSlot(usize),
+    /// Starts capturing a new key for the action.
+    KeyBinding(KeyAction),
+    ToggleFullscreen,
+    /// Switches between the key bindings and the options in the settings.
+    SettingsPage,
+    /// Steps the option up or down by the given amount of steps.
+    AdjustOption(GameOption, i32),
+    CycleBloom,
+    CycleDof,
+    WipeProgress,
+}
+
+/// What clicking on the ship does while the Build tab is open.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BuildTool {
+    Place(RoomType),
+    Remove,
+}
+
+/// An action that needs to be confirmed before it's done.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PendingAction {
+    /// Resetting a character's (by index) schedule to all sleep.
+    ClearSchedule(usize),
+    /// Deleting the saved games and starting over.
+    WipeProgress,
+    /// Deleting the save in the slot.
+    DeleteSlot(usize),
+}
+
+/// A description of a button, shown after hovering over it for a while.
+pub struct Tooltip {
+    pub button: Button,
+    pub text: String,
+    /// How long the cursor needs to stay on the button for the tooltip to
+    /// show up, in seconds.
+    pub delay: f32,
+    /// How long the cursor has been on the button.
+    pub timer: f32,
+}
+
+impl Tooltip {
+    const DEFAULT_DELAY: f32 = 0.6;
+}
+
+/// A message about something that happened in the game, shown briefly at the
+/// top of the screen.
+pub struct Notification {
+    pub text: String,
+    /// How long the notification is shown for, in seconds.
+    pub duration: f32,
+    /// How much of the duration is left.
+    pub ttl: f32,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Tab {
     Navigation,
     Schedule,
     Deliveries,
     GameSettings,
+    Crew,
+    Map,
+    Trade,
+    Log,
+    Build,
+    /// The save slots, opened from the settings instead of a tab label.
+    SaveLoad,
+}
+
+impl Tab {
+    /// The tab with the label at the index, in the order they're drawn.
+    pub fn from_index(index: usize) -> Tab {
+        match index {
+            0 => Tab::Navigation,
+            1 => Tab::Schedule,
+            2 => Tab::Deliveries,
+            3 => Tab::GameSettings,
+            4 => Tab::Crew,
+            5 => Tab::Map,
+            6 => Tab::Trade,
+            7 => Tab::Log,
+            8 => Tab::Build,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A single-line text field. The cursor is a byte index into the text, always
+/// at a char boundary.
+#[derive(Default)]
+pub struct TextInput {
+    pub text: String,
+    pub cursor: usize,
+    pub focused: bool,
+}
+
+impl TextInput {
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Deletes the character before the cursor.
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+}
+
+/// A list that can be scrolled with the mouse wheel, when there are more items
+/// than fit in the visible area. Vertical, unless stated otherwise.
+#[derive(Clone, Copy)]
+pub struct ScrollableList {
+    /// How far down the list has been scrolled, in interface pixels.
+    pub scroll_offset: f32,
+    /// The offset [ScrollableList::scroll_offset] is easing towards.
+    pub target_scroll_offset: f32,
+    pub item_height: f32,
+    pub visible_height: f32,
+    /// The amount of items in the list, updated when the list is drawn.
+    pub item_count: usize,
+}
+
+impl ScrollableList {
+    /// How fast the scroll offset catches up to the target, per second.
+    const EASING_SPEED: f32 = 15.0;
+
+    pub fn new(item_height: f32, visible_height: f32) -> ScrollableList {
+        ScrollableList {
+            scroll_offset: 0.0,
+            target_scroll_offset: 0.0,
+            item_height,
+            visible_height,
+            item_count: 0,
+        }
+    }
+
+    pub fn max_scroll_offset(&self) -> f32 {
+        (self.item_count as f32 * self.item_height - self.visible_height).max(0.0)
+    }
+
+    /// Scrolls the list down by the given amount of items (up, if negative).
+    pub fn scroll(&mut self, items: f32) {
+        self.target_scroll_offset = (self.target_scroll_offset + items * self.item_height)
+            .clamp(0.0, self.max_scroll_offset());
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // The item count might've changed since the last scroll
+        self.target_scroll_offset = self.target_scroll_offset.min(self.max_scroll_offset());
+        let t = (Self::EASING_SPEED * dt).min(1.0);
+        self.scroll_offset += (self.target_scroll_offset - self.scroll_offset) * t;
+    }
+
+    /// Returns true if an item that's `y` pixels from the start of the list
+    /// (before scrolling) is completely inside the visible area.
+    pub fn is_visible(&self, y: f32) -> bool {
+        let y = y - self.scroll_offset;
+        y >= -0.5 && y + self.item_height <= self.visible_height + 0.5
+    }
 }
 
 pub struct Interface {
     pub buttons: HashMap<Button, Rect>,
+    /// The buttons in the order they were added, for keyboard navigation.
+    button_order: Vec<Button>,
+    /// The button selected with the keyboard, clicked with Return.
+    pub focused_button: Option<Button>,
     /// The inner screen area.
     pub screen_area: Rect,
     /// The area of the whole UI.
     pub safe_area: Rect,
     pub hovered_tab: Option<usize>,
     pub tab: Option<Tab>,
+    /// How far the open tab has slid into view, from 0.0 (closed) to 1.0
+    /// (fully open).
+    pub tab_anim: f32,
+    /// How much `tab_anim` changes per second.
+    pub tab_anim_speed: f32,
     pub selected_task: Task,
+    /// The last schedule cell (character and time) painted with
+    /// [Interface::selected_task], if the left mouse button was pressed on
+    /// the schedule and is still held.
+    pub schedule_drag: Option<(usize, usize)>,
+    /// An action waiting for the player to answer a confirmation dialog. Other
+    /// buttons can't be clicked while this is set.
+    pub pending_confirm: Option<PendingAction>,
+    /// The action waiting for a new key, after its key binding was clicked.
+    /// The next key pressed is bound to it.
+    pub capturing_key_binding: Option<KeyAction>,
+    /// Set when the fullscreen button is clicked. The window isn't
+    /// accessible from here, so the toggling happens in `run_frame`.
+    pub toggle_fullscreen: bool,
+    /// Set when the bloom button is clicked, handled in `run_frame` since
+    /// the settings aren't accessible from here either.
+    pub cycle_bloom: bool,
+    /// Set when the depth of field button is clicked, like `cycle_bloom`.
+    pub cycle_dof: bool,
+    /// Set when an option's arrow button is clicked, like `cycle_bloom`.
+    pub adjust_option: Option<(GameOption, i32)>,
+    /// Set when the settings show the options instead of the key bindings.
+    pub options_page: bool,
+    /// The tool picked in the Build tab, used when the ship is clicked while
+    /// the tab is open.
+    pub build_tool: Option<BuildTool>,
+    /// The text fields of the interface, indexed by [Button::TextInput].
+    pub text_inputs: Vec<TextInput>,
+    /// The list of locations in the Navigation tab.
+    pub location_list: ScrollableList,
+    /// The list of deliveries in the Deliveries tab.
+    pub delivery_list: ScrollableList,
+    /// The slots of the schedule grid in the Schedule tab, which scrolls
+    /// horizontally.
+    pub schedule_grid: ScrollableList,
+    /// The entries of the ship's log in the Log tab, newest first.
+    pub log_list: ScrollableList,
+    /// The save slots in the SaveLoad tab.
+    pub save_slot_list: ScrollableList,
+    /// Descriptions of the saves in each slot, None for empty slots. Read
+    /// from storage when the SaveLoad tab is opened.
+    pub save_slots: Vec<Option<String>>,
+    /// The tooltip of the hovered button, waiting for its delay.
+    pub pending_tooltip: Option<Tooltip>,
+    /// The tooltip text to draw, and the cursor position it's drawn at.
+    pub active_tooltip: Option<(String, Point)>,
+    /// Notifications waiting to be shown, the front one is currently shown.
+    pub notifications: VecDeque<Notification>,
+    /// The cursor position, as of the last [Interface::hover].
+    hover_position: Point,
     normal_cursor: Cursor,
     button_hover_cursor: Cursor,
     was_hovering_button: bool,
 }
 
 impl Interface {
+    /// The index of the location search field in the Navigation tab.
+    pub const LOCATION_SEARCH: usize = 0;
+
     pub fn new() -> Interface {
         Interface {
             buttons: HashMap::new(),
+            button_order: Vec::new(),
+            focused_button: None,
             screen_area: Rect::new(0, 0, 0, 0),
             safe_area: Rect::new(0, 0, 0, 0),
             hovered_tab: None,
             tab: None,
+            tab_anim: 0.0,
+            tab_anim_speed: 8.0,
             selected_task: Task::Sleep,
+            schedule_drag: None,
+            pending_confirm: None,
+            capturing_key_binding: None,
+            toggle_fullscreen: false,
+            cycle_bloom: false,
+            cycle_dof: false,
+            adjust_option: None,
+            options_page: false,
+            build_tool: None,
+            text_inputs: vec![TextInput::default()],
+            location_list: ScrollableList::new(15.0, 45.0),
+            delivery_list: ScrollableList::new(32.0, 100.0),
+            schedule_grid: ScrollableList::new(20.0, 240.0),
+            log_list: ScrollableList::new(12.0, 96.0),
+            save_slot_list: ScrollableList::new(16.0, 96.0),
+            save_slots: vec![None; SAVE_SLOTS],
+            pending_tooltip: None,
+            active_tooltip: None,
+            notifications: VecDeque::new(),
+            hover_position: Point::new(0, 0),
             normal_cursor: Cursor::from_system(SystemCursor::Arrow).unwrap(),
             button_hover_cursor: Cursor::from_system(SystemCursor::Hand).unwrap(),
             was_hovering_button: false,
         }
     }
 
-    pub fn hover(&mut self, position: Point) {
-        let mut is_hovering_button = false;
-        for (_, button_area) in &self.buttons {
+    pub fn clear_buttons(&mut self) {
+        self.buttons.clear();
+        self.button_order.clear();
+    }
+
+    pub fn add_button(&mut self, button: Button, area: Rect) {
+        if self.buttons.insert(button, area).is_none() {
+            self.button_order.push(button);
+        }
+    }
+
+    /// Returns the area of the focused button, if it's currently visible.
+    pub fn focused_button_rect(&self) -> Option<Rect> {
+        self.buttons.get(&self.focused_button?).copied()
+    }
+
+    /// Moves the keyboard focus to the next button (or the previous one, if
+    /// `backwards`), in the order they were added.
+    pub fn focus_next_button(&mut self, backwards: bool) {
+        let count = self.button_order.len();
+        if count == 0 {
+            return;
+        }
+        let current = self
+            .focused_button
+            .and_then(|focused| self.button_order.iter().position(|&b| b == focused));
+        let next = match (current, backwards) {
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+            (None, false) => 0,
+            (None, true) => count - 1,
+        };
+        self.focused_button = Some(self.button_order[next]);
+    }
+
+    /// Moves the keyboard focus to the closest button in the direction
+    /// (in window coordinates, so +Y is down) from the focused one.
+    pub fn focus_button_towards(&mut self, direction: Vec2) {
+        let Some(from) = self.focused_button_rect() else {
+            self.focus_next_button(false);
+            return;
+        };
+        let center = |rect: &Rect| Vec2::new(rect.center().x() as f32, rect.center().y() as f32);
+        let from = center(&from);
+        let mut closest = None;
+        let mut closest_score = f32::INFINITY;
+        for button in &self.button_order {
+            let to = center(&self.buttons[button]) - from;
+            let alignment = to.normalize_or_zero().dot(direction);
+            if alignment <= 0.0 {
+                continue;
+            }
+            // Prefer buttons straight in the direction over closer ones off to
+            // the side
+            let score = to.length() / alignment.powi(2);
+            if score < closest_score {
+                closest = Some(*button);
+                closest_score = score;
+            }
+        }
+        if closest.is_some() {
+            self.focused_button = closest;
+        }
+    }
+
+    /// Clicks the button focused with the keyboard.
+    pub fn click_focused_button(&mut self, ship_game: &mut ShipGame, renderer: &mut Renderer) {
+        if let Some(rect) = self.focused_button_rect() {
+            self.click(rect.center(), ship_game, renderer, false);
+        }
+    }
+
+    pub fn hover(&mut self, position: Point, ship_game: &ShipGame) {
+        let mut hovered_button = None;
+        for (button, button_area) in &self.buttons {
             if button_area.contains_point(position) {
-                is_hovering_button = true;
+                hovered_button = Some(*button);
                 break;
             }
         }
+        let is_hovering_button = hovered_button.is_some();
+        self.hover_position = position;
+        let pending_button = self.pending_tooltip.as_ref().map(|tooltip| tooltip.button);
+        if hovered_button != pending_button {
+            self.active_tooltip = None;
+            self.pending_tooltip = hovered_button.and_then(|button| {
+                Some(Tooltip {
+                    button,
+                    text: tooltip_text(button, ship_game)?,
+                    delay: Tooltip::DEFAULT_DELAY,
+                    timer: 0.0,
+                })
+            });
+        }
         if is_hovering_button && !self.was_hovering_button {
             self.button_hover_cursor.set();
         } else if !is_hovering_button && self.was_hovering_button {
@@ -67,41 +445,378 @@ impl Interface {
         self.was_hovering_button = is_hovering_button;
     }
 
-    pub fn click(&mut self, position: Point, ship_game: &mut ShipGame, held: bool) {
+    pub fn click(
+        &mut self,
+        position: Point,
+        ship_game: &mut ShipGame,
+        renderer: &mut Renderer,
+        held: bool,
+    ) {
         let mut open_tab = None;
+        if !held {
+            self.schedule_drag = None;
+            self.capturing_key_binding = None;
+            for text_input in &mut self.text_inputs {
+                text_input.focused = false;
+            }
+        }
         for (button, button_area) in &self.buttons {
             if button_area.contains_point(position) {
+                let modal_open =
+                    self.pending_confirm.is_some() || ship_game.pending_event.is_some();
+                if modal_open
+                    && !matches!(
+                        button,
+                        Button::ConfirmYes | Button::ConfirmNo | Button::EventOption(_)
+                    )
+                {
+                    continue;
+                }
                 match button {
                     Button::Tab(i) if !held => {
-                        open_tab = Some(*i);
+                        open_tab = Some(Tab::from_index(*i));
                         break;
                     }
                     Button::TaskPicker(task) if !held => {
                         self.selected_task = *task;
                     }
                     Button::TaskAssigner { time, character } => {
-                        ship_game.characters[*character].schedule[*time] = self.selected_task;
+                        let cell = (*character, *time);
+                        // Dragging only paints the cells the cursor enters,
+                        // and only if the drag started on the schedule
+                        if !held || self.schedule_drag.is_some_and(|last| last != cell) {
+                            ship_game.set_schedule(*character, *time, self.selected_task);
+                            self.schedule_drag = Some(cell);
+                        }
                     }
                     Button::LocationList(i) if !held => {
-                        ship_game.current_target = ship_game.locations[*i].1;
+                        ship_game.set_target(ship_game.locations[*i].position);
+                        ship_game.target_queue.clear();
+                        renderer.animate_camera_to(ship_game.current_target);
+                    }
+                    Button::AddWaypoint(i) if !held => {
+                        ship_game.add_waypoint(*i);
+                    }
+                    Button::RemoveWaypoint(i) if !held => {
+                        ship_game.target_queue.remove(*i);
+                    }
+                    Button::ToggleAutopilot if !held => {
+                        ship_game.toggle_autopilot();
+                    }
+                    Button::PlaceRoom(room_type) if !held => {
+                        let tool = BuildTool::Place(*room_type);
+                        self.build_tool = (self.build_tool != Some(tool)).then_some(tool);
+                    }
+                    Button::RemoveRoom if !held => {
+                        let tool = BuildTool::Remove;
+                        self.build_tool = (self.build_tool != Some(tool)).then_some(tool);
+                    }
+                    Button::BuyItem(i) if !held => {
+                        if let Some(message) = ship_game.buy(*i) {
+                            self.push_notification(message, 3.0);
+                        }
+                        break;
+                    }
+                    Button::SellItem(i) if !held => {
+                        if let Some(message) = ship_game.sell(*i) {
+                            self.push_notification(message, 3.0);
+                        }
+                        break;
+                    }
+                    Button::TextInput(i) if !held => {
+                        self.text_inputs[*i].focused = true;
+                    }
+                    Button::ScrollSchedule(slots) if !held => {
+                        self.schedule_grid.scroll(*slots as f32);
+                    }
+                    Button::ClearSchedule(i) if !held => {
+                        self.pending_confirm = Some(PendingAction::ClearSchedule(*i));
+                    }
+                    Button::ConfirmYes if !held => {
+                        match self.pending_confirm.take() {
+                            Some(PendingAction::ClearSchedule(i)) => {
+                                for time in 0..SCHEDULE_SLOTS {
+                                    ship_game.set_schedule(i, time, Task::Sleep);
+                                }
+                            }
+                            Some(PendingAction::WipeProgress) => {
+                                let result: anyhow::Result<()> =
+                                    (0..SAVE_SLOTS).try_for_each(ShipGame::delete_save);
+                                if let Err(err) = result {
+                                    eprintln!("deleting the saves failed: {err:?}");
+                                    self.push_notification("Couldn't delete the saves", 3.0);
+                                } else {
+                                    *ship_game =
+                                        ShipGame::new(&renderer.ship_models, ship_game.seed);
+                                    self.schedule_drag = None;
+                                    self.push_notification("Starting over", 3.0);
+                                }
+                                self.refresh_save_slots();
+                            }
+                            Some(PendingAction::DeleteSlot(slot)) => {
+                                if let Err(err) = ShipGame::delete_save(slot) {
+                                    eprintln!("deleting the save failed: {err:?}");
+                                    self.push_notification("Couldn't delete the save", 3.0);
+                                }
+                                self.refresh_save_slots();
+                            }
+                            None => {}
+                        }
+                        break;
+                    }
+                    Button::ConfirmNo if !held => {
+                        self.pending_confirm = None;
+                        break;
+                    }
+                    Button::HireCharacter(i) if !held => {
+                        ship_game.hire(&renderer.ship_models, *i);
+                        break;
+                    }
+                    Button::DismissCharacter(i) if !held => {
+                        if ship_game.dismiss(*i) {
+                            // The indices after the dismissed character shift
+                            self.schedule_drag = None;
+                            self.pending_confirm = None;
+                        }
+                        break;
+                    }
+                    Button::OpenSaveLoad if !held => {
+                        open_tab = Some(Tab::SaveLoad);
+                        break;
+                    }
+                    Button::SaveToSlot(slot) if !held => {
+                        match ship_game.save(*slot) {
+                            Ok(()) => self.push_notification("Game saved", 2.0),
+                            Err(err) => {
+                                eprintln!("saving failed: {err:?}");
+                                self.push_notification("Couldn't save the game", 3.0);
+                            }
+                        }
+                        self.refresh_save_slots();
+                        break;
+                    }
+                    Button::DeleteSlot(slot) if !held => {
+                        self.pending_confirm = Some(PendingAction::DeleteSlot(*slot));
+                        break;
+                    }
+                    Button::LoadFromSlot(slot) if !held => {
+                        match ShipGame::load(&renderer.ship_models, *slot) {
+                            Ok(Some(loaded)) => {
+                                *ship_game = loaded;
+                                self.schedule_drag = None;
+                                self.push_notification("Game loaded", 2.0);
+                            }
+                            Ok(None) => self.push_notification("There's no saved game", 3.0),
+                            Err(err) => {
+                                eprintln!("loading failed: {err:?}");
+                                self.push_notification("Couldn't load the game", 3.0);
+                            }
+                        }
+                        break;
+                    }
+                    Button::ToggleFullscreen if !held => {
+                        self.toggle_fullscreen = true;
+                        break;
+                    }
+                    Button::WipeProgress if !held => {
+                        self.pending_confirm = Some(PendingAction::WipeProgress);
+                        break;
+                    }
+                    Button::SettingsPage if !held => {
+                        self.options_page = !self.options_page;
+                        break;
+                    }
+                    Button::AdjustOption(option, step) if !held => {
+                        self.adjust_option = Some((*option, *step));
+                        break;
+                    }
+                    Button::CycleBloom if !held => {
+                        self.cycle_bloom = true;
+                        break;
+                    }
+                    Button::CycleDof if !held => {
+                        self.cycle_dof = true;
+                        break;
+                    }
+                    Button::KeyBinding(action) if !held => {
+                        self.capturing_key_binding = Some(*action);
+                        break;
+                    }
+                    Button::EventOption(i) if !held => {
+                        if let Some(event) = ship_game.pending_event.take() {
+                            ship_game.apply_event_effect(event.options[*i].1);
+                        }
+                        break;
                     }
                     _ => {}
                 }
             }
         }
-        if let Some(i) = open_tab {
-            self.open_tab(i);
+        if let Some(tab) = open_tab {
+            self.show_tab(tab);
         }
     }
 
-    pub fn open_tab(&mut self, tab_index: usize) {
-        let tab = match tab_index {
-            0 => Tab::Navigation,
-            1 => Tab::Schedule,
-            2 => Tab::Deliveries,
-            3 => Tab::GameSettings,
-            _ => unreachable!(),
+    pub fn update(&mut self, dt: f32) {
+        let tab_anim_target = if self.tab.is_some() { 1.0 } else { 0.0 };
+        let tab_anim_step = self.tab_anim_speed * dt;
+        self.tab_anim += (tab_anim_target - self.tab_anim).clamp(-tab_anim_step, tab_anim_step);
+        self.location_list.update(dt);
+        self.delivery_list.update(dt);
+        self.schedule_grid.update(dt);
+        self.log_list.update(dt);
+        self.save_slot_list.update(dt);
+        if let Some(notification) = self.notifications.front_mut() {
+            notification.ttl -= dt;
+            if notification.ttl <= 0.0 {
+                self.notifications.pop_front();
+            }
+        }
+        if let Some(tooltip) = &mut self.pending_tooltip {
+            tooltip.timer += dt;
+            if tooltip.timer >= tooltip.delay && self.active_tooltip.is_none() {
+                self.active_tooltip = Some((tooltip.text.clone(), self.hover_position));
+            }
+        }
+    }
+
+    /// Queues up a notification to be shown for `duration` seconds, after the
+    /// previous ones.
+    pub fn push_notification(&mut self, text: impl Into<String>, duration: f32) {
+        self.notifications.push_back(Notification {
+            text: text.into(),
+            duration,
+            ttl: duration,
+        });
+    }
+
+    /// Hides the tooltip, until the cursor moves to another button.
+    pub fn clear_tooltip(&mut self) {
+        if let Some(tooltip) = &mut self.pending_tooltip {
+            tooltip.timer = f32::NEG_INFINITY;
+        }
+        self.active_tooltip = None;
+    }
+
+    /// Scrolls the list in the open tab, if there is one. Returns false if
+    /// there's nothing to scroll.
+    pub fn scroll(&mut self, items: f32) -> bool {
+        let list = match self.tab {
+            Some(Tab::Navigation) => &mut self.location_list,
+            Some(Tab::Deliveries) => &mut self.delivery_list,
+            Some(Tab::Schedule) => &mut self.schedule_grid,
+            Some(Tab::Log) => &mut self.log_list,
+            Some(Tab::SaveLoad) => &mut self.save_slot_list,
+            _ => return false,
         };
+        list.scroll(items);
+        true
+    }
+
+    pub fn focused_text_input(&mut self) -> Option<&mut TextInput> {
+        self.text_inputs
+            .iter_mut()
+            .find(|text_input| text_input.focused)
+    }
+
+    pub fn open_tab(&mut self, tab_index: usize) {
+        self.show_tab(Tab::from_index(tab_index));
+    }
+
+    pub fn show_tab(&mut self, tab: Tab) {
+        if self.tab != Some(tab) {
+            // Slide the new tab in from the start
+            self.tab_anim = 0.0;
+        }
+        if tab == Tab::SaveLoad {
+            self.refresh_save_slots();
+        }
         self.tab = Some(tab);
     }
+
+    /// Re-reads the descriptions of the saves from storage.
+    pub fn refresh_save_slots(&mut self) {
+        self.save_slots = (0..SAVE_SLOTS).map(ShipGame::describe_save).collect();
+    }
+}
+
+fn tooltip_text(button: Button, ship_game: &ShipGame) -> Option<String> {
+    match button {
+        Button::TaskPicker(Task::Work) => Some("Assign crew to active duty".to_string()),
+        Button::TaskPicker(Task::Sleep) => Some("Assign crew to rest".to_string()),
+        Button::TaskAssigner { time, .. } => {
+            Some(format!("Shift from {:02}:00 to {:02}:00", time, time + 1))
+        }
+        Button::LocationList(i) => {
+            let location = ship_game.locations.get(i)?;
+            let (x, y) = (location.position.x, location.position.y);
+            let faction = location.faction.name();
+            if location.is_fuel_depot {
+                Some(format!("Coordinates: {x:.0}, {y:.0}, {faction} fuel depot"))
+            } else {
+                Some(format!("Coordinates: {x:.0}, {y:.0}, {faction}"))
+            }
+        }
+        Button::AddWaypoint(_) => Some("Sail here after the current destination".to_string()),
+        Button::RemoveWaypoint(_) => Some("Remove from the route".to_string()),
+        Button::ToggleAutopilot => Some("Continue along the route after arriving".to_string()),
+        Button::TextInput(Interface::LOCATION_SEARCH) => {
+            Some("Type to filter the locations".to_string())
+        }
+        Button::ClearSchedule(_) => Some("Reset the schedule to rest only".to_string()),
+        Button::ScrollSchedule(_) => Some("Show other hours of the day".to_string()),
+        Button::HireCharacter(_) if ship_game.characters.len() >= MAX_CREW_SIZE => {
+            Some("There's no room for more crew".to_string())
+        }
+        Button::HireCharacter(_) => Some("Add to the crew".to_string()),
+        Button::DismissCharacter(_) if ship_game.characters.len() <= 1 => {
+            Some("The ship needs at least one crew member".to_string())
+        }
+        Button::DismissCharacter(_) => Some("Remove from the crew".to_string()),
+        Button::PlaceRoom(_) => Some("Then click on the ship to build it".to_string()),
+        Button::RemoveRoom => Some("Then click on a room to tear it down".to_string()),
+        Button::BuyItem(i) => {
+            let port = ship_game.port_here()?;
+            let (name, _, _) = ship_game.locations[port].port_inventory.get(i)?;
+            Some(format!("Weighs {:.0} t", item_weight(name)))
+        }
+        Button::SellItem(i) => {
+            let (name, _, price) = ship_game.cargo_hold.get(i)?;
+            if ship_game
+                .deliveries
+                .iter()
+                .any(|delivery| delivery.name == name)
+            {
+                Some("Deliver it at its destination".to_string())
+            } else {
+                Some(format!("Bought for {price:.0} credits each"))
+            }
+        }
+        Button::OpenSaveLoad => Some("Save into and load from slots".to_string()),
+        Button::SaveToSlot(AUTOSAVE_SLOT) => Some("Saving also happens when docking".to_string()),
+        Button::LoadFromSlot(AUTOSAVE_SLOT) => Some("Continue from the last dock".to_string()),
+        Button::SaveToSlot(_) | Button::LoadFromSlot(_) => None,
+        Button::DeleteSlot(_) => Some("Empty this slot".to_string()),
+        Button::WipeProgress => Some("Delete all saves and start over".to_string()),
+        Button::KeyBinding(_) => Some("Click, then press the new key".to_string()),
+        Button::ToggleFullscreen => Some("Also toggled with F11".to_string()),
+        Button::CycleBloom => Some("How much bright lights glow".to_string()),
+        Button::CycleDof => Some("How blurry things out of focus are".to_string()),
+        Button::AdjustOption(GameOption::UiScale, _) => {
+            Some("On top of the scaling for the window size".to_string())
+        }
+        Button::AdjustOption(GameOption::MaxCameraDistance, _) => {
+            Some("How far the camera can zoom out".to_string())
+        }
+        Button::AdjustOption(GameOption::Vsync, _) => {
+            Some("Wait for the display between frames".to_string())
+        }
+        Button::Tab(_)
+        | Button::TextInput(_)
+        | Button::ConfirmYes
+        | Button::ConfirmNo
+        | Button::EventOption(_)
+        | Button::AdjustOption(..)
+        | Button::SettingsPage => None,
+    }
 }
@@ -1,28 +1,34 @@
 use anyhow::Context;
 use glam::Vec2;
+use sdl2::controller::{Axis, Button as ControllerButton, GameController};
 use sdl2::event::{Event, WindowEvent};
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::mouse::{MouseButton, MouseWheelDirection};
 use sdl2::rect::Point;
-use sdl2::sys::{SDL_Event, SDL_EventType, SDL_KeyCode};
-use sdl2::video::{GLProfile, Window};
-use sdl2::{EventPump, TimerSubsystem};
+use sdl2::sys::{SDL_Event, SDL_EventType};
+use sdl2::video::{FullscreenType, GLProfile, SwapInterval, Window};
+use sdl2::{EventPump, GameControllerSubsystem, TimerSubsystem};
 use std::error::Error;
 use std::ffi::{c_int, c_void};
 use std::fmt::Display;
 use std::panic;
 use std::ptr;
+use std::sync::Mutex;
 
+mod benchmark;
 #[cfg(target_family = "wasm")]
 mod emscripten_h;
 mod interface;
 mod math;
 mod renderer;
+mod screenshot;
+mod settings;
 mod ship_game;
 
-use interface::Interface;
+use interface::{BuildTool, Interface, Tab};
 use renderer::Renderer;
-use ship_game::ShipGame;
+use settings::{GameOption, KeyAction, Settings};
+use ship_game::{ReplayEvent, ShipGame, AUTOSAVE_SLOT};
 
 fn main() {
     panic::set_hook(Box::new(|panic_info| {
@@ -34,11 +40,25 @@ fn main() {
 }
 
 fn _main() -> anyhow::Result<()> {
+    if std::env::args().any(|arg| arg == "--benchmark") {
+        // The simulation doesn't need anything from SDL or GL
+        benchmark::run();
+        return Ok(());
+    }
+    let replay = arg_value("--replay")
+        .map(|path| ship_game::load_replay(path.as_ref()))
+        .transpose()
+        .context("could not load the replay")?;
+
     let sdl_context = sdl2::init().map_err(SdlErr).context("sdl2::init failed")?;
     let timer = sdl_context
         .timer()
         .map_err(SdlErr)
         .context("sdl2 timer subsystem init failed")?;
+    let controller_subsystem = sdl_context
+        .game_controller()
+        .map_err(SdlErr)
+        .context("sdl2 game controller subsystem init failed")?;
     let video = sdl_context
         .video()
         .map_err(SdlErr)
@@ -49,6 +69,7 @@ fn _main() -> anyhow::Result<()> {
     gl_attr.set_multisample_buffers(1);
     gl_attr.set_multisample_samples(4);
     gl_attr.set_depth_size(24);
+    gl_attr.set_stencil_size(8);
     // Linear->SRGB conversion is done in shader, thanks to lacking WebGL support.
     gl_attr.set_framebuffer_srgb_compatible(false);
     let window = video
@@ -74,13 +95,14 @@ fn _main() -> anyhow::Result<()> {
         use renderer::gl;
 
         gl::load_with(|s| video.gl_get_proc_address(s) as *const core::ffi::c_void);
+        #[cfg(all(feature = "gl_debug_callback", not(target_family = "wasm")))]
+        gl::setup_debug_callback();
         video.gl_set_swap_interval(1).unwrap();
         let (w, h) = window.drawable_size();
         gl::call!(gl::Viewport(0, 0, w as i32, h as i32));
 
         gl::call!(gl::ClearColor(0.2, 0.4, 0.2, 1.0));
         gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT));
-        // TODO: Render a loading screen
         window.gl_swap_window();
     }
 
@@ -102,15 +124,34 @@ fn _main() -> anyhow::Result<()> {
                     let key_event = unsafe { event.key };
                     let keycode = key_event.keysym.sym;
                     // Here, we specifically "unignore"
-                    if keycode == SDL_KeyCode::SDLK_SPACE as i32 {
-                        ACCEPTED
-                    } else if keycode == SDL_KeyCode::SDLK_1 as i32 {
-                        ACCEPTED
-                    } else if keycode == SDL_KeyCode::SDLK_2 as i32 {
-                        ACCEPTED
-                    } else if keycode == SDL_KeyCode::SDLK_3 as i32 {
-                        ACCEPTED
-                    } else if keycode == SDL_KeyCode::SDLK_4 as i32 {
+                    let keycode = Keycode::from_i32(keycode);
+                    let unignored = matches!(
+                        keycode,
+                        Some(
+                            Keycode::Space
+                                | Keycode::Num1
+                                | Keycode::Num2
+                                | Keycode::Num3
+                                | Keycode::Num4
+                                | Keycode::Num5
+                                | Keycode::M
+                                | Keycode::O
+                                | Keycode::P
+                                | Keycode::W
+                                | Keycode::Backspace
+                                | Keycode::Left
+                                | Keycode::Right
+                                | Keycode::Escape
+                                | Keycode::Return
+                                | Keycode::Tab
+                                | Keycode::Up
+                                | Keycode::Down
+                                | Keycode::F3
+                                | Keycode::F11
+                                | Keycode::F12
+                        )
+                    );
+                    if unignored || is_bound_or_capturing(keycode) {
                         ACCEPTED
                     } else {
                         DROPPED
@@ -122,6 +163,13 @@ fn _main() -> anyhow::Result<()> {
             ACCEPTED
         }
     }
+    /// Returns true if the key is bound to something in the key bindings, or
+    /// if a new binding is being captured, so any key could be bound.
+    fn is_bound_or_capturing(keycode: Option<Keycode>) -> bool {
+        let filter_keys = FILTER_KEYS.lock().unwrap();
+        let is_bound = keycode.is_some_and(|keycode| filter_keys.bound.contains(&keycode));
+        is_bound || filter_keys.capturing
+    }
     unsafe { sdl2::sys::SDL_SetEventFilter(Some(event_filter), ptr::null_mut()) };
 
     #[cfg(target_family = "wasm")]
@@ -134,11 +182,56 @@ fn _main() -> anyhow::Result<()> {
         unsafe { emscripten_h::emscripten_sleep(100) };
     }
 
-    unsafe { STATE = Some(State::new(window, timer, event_pump)) };
+    let mut controllers = Vec::new();
+    for i in 0..controller_subsystem.num_joysticks().unwrap_or(0) {
+        if controllers.len() >= MAX_CONTROLLERS {
+            break;
+        }
+        if controller_subsystem.is_game_controller(i) {
+            match controller_subsystem.open(i) {
+                Ok(controller) => controllers.push(controller),
+                Err(err) => eprintln!("could not open game controller {i}: {err}"),
+            }
+        }
+    }
+
+    let mut loader = Renderer::begin_load();
+    for progress in loader.by_ref() {
+        let (w, h) = window.drawable_size();
+        renderer::render_loading_screen(&progress, w as i32, h as i32);
+        window.gl_swap_window();
+        #[cfg(target_family = "wasm")]
+        {
+            emscripten_h::run_javascript(&format!(
+                "document.getElementById('browser-support-warning').innerHTML = \"<p>Loading {}...</p>\"",
+                progress.label,
+            ));
+            // Let the browser show the frame
+            unsafe { emscripten_h::emscripten_sleep(0) };
+        }
+    }
+    let renderer = loader.finish();
+
+    unsafe {
+        STATE = Some(State::new(
+            window,
+            renderer,
+            timer,
+            event_pump,
+            controller_subsystem,
+            controllers,
+            replay,
+        ))
+    };
 
     #[cfg(target_family = "wasm")]
     {
         emscripten_h::run_javascript("document.getElementById('browser-support-warning').remove()");
+        // The canvas fills the page, so resize it along with the page when
+        // going fullscreen.
+        emscripten_h::run_javascript(
+            "document.addEventListener('fullscreenchange', () => window.dispatchEvent(new Event('resize')))",
+        );
         emscripten_h::set_main_loop(run_frame);
     }
     #[cfg(not(target_family = "wasm"))]
@@ -149,10 +242,37 @@ fn _main() -> anyhow::Result<()> {
 
 static mut STATE: Option<State> = None;
 
+/// The keys the event filter lets through on top of the always used ones.
+/// Kept apart from [STATE], since the filter runs while [STATE] is borrowed
+/// by [run_frame]. Updated with [update_filter_keys].
+static FILTER_KEYS: Mutex<FilterKeys> = Mutex::new(FilterKeys {
+    bound: Vec::new(),
+    capturing: false,
+});
+
+struct FilterKeys {
+    /// The keys in the key bindings.
+    bound: Vec<Keycode>,
+    /// Whether a new key binding is being captured, in which case any key
+    /// could be bound.
+    capturing: bool,
+}
+
+/// How many game controllers are listened to at once.
+const MAX_CONTROLLERS: usize = 4;
+/// How far the analog sticks need to be pushed, from 0.0 to 1.0, before it
+/// counts as input. Sticks rarely rest at exactly zero.
+const STICK_DEAD_ZONE: f32 = 0.15;
+
 struct State {
     window: Window,
     timer: TimerSubsystem,
     event_pump: EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    controllers: Vec<GameController>,
+    /// Zooming with the triggers, accumulated until it adds up to a whole
+    /// mouse wheel notch.
+    controller_zoom: f32,
     lmouse_pressed: bool,
     rmouse_pressed: bool,
     mouse_position: Point,
@@ -164,18 +284,82 @@ struct State {
     ship_game: ShipGame,
     interface: Interface,
     debug_time_speedup: bool,
+    /// Stops the game from updating, toggled with P. Rendering and the
+    /// interface keep going.
+    paused: bool,
+    settings: Settings,
+    /// Set when F12 is pressed, the screenshot is taken after rendering.
+    screenshot_requested: bool,
+    /// When the renderer's memory usage was last printed, in debug builds.
+    last_memory_stats_print: f32,
+    /// Time since `fps_display` was last updated, in seconds.
+    fps_timer: f32,
+    /// Frames rendered since `fps_display` was last updated.
+    fps_counter: u32,
+    fps_display: f32,
+    /// Shows the frame rate and rendering statistics, toggled with F3.
+    debug_overlay: bool,
+    /// The replay being played back, from `--replay <file>`, and how many of
+    /// its events have been applied. The game is just watched then.
+    replay: Option<(Vec<ReplayEvent>, usize)>,
 }
 
 impl State {
-    pub fn new(window: Window, timer: TimerSubsystem, event_pump: EventPump) -> State {
-        let renderer = Renderer::new();
-        let ship_game = ShipGame::new(&renderer);
+    pub fn new(
+        mut window: Window,
+        mut renderer: Renderer,
+        timer: TimerSubsystem,
+        event_pump: EventPump,
+        controller_subsystem: GameControllerSubsystem,
+        controllers: Vec<GameController>,
+        replay: Option<(u64, Vec<ReplayEvent>)>,
+    ) -> State {
+        // The world seed for new games, a saved game continues in its own world
+        let seed = std::env::args()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(42u64);
+        let ship_game = match ShipGame::load(&renderer.ship_models, AUTOSAVE_SLOT) {
+            // Replays start over in the world they were recorded in
+            _ if replay.is_some() => {
+                let mut ship_game =
+                    ShipGame::new(&renderer.ship_models, replay.as_ref().unwrap().0);
+                ship_game.replaying = true;
+                ship_game
+            }
+            Ok(Some(ship_game)) => ship_game,
+            Ok(None) => ShipGame::new(&renderer.ship_models, seed),
+            Err(err) => {
+                // Probably from an older version, which can't be loaded anymore
+                eprintln!("could not load the saved game, discarding it: {err:?}");
+                if let Err(err) = ShipGame::delete_save(AUTOSAVE_SLOT) {
+                    eprintln!("could not delete the saved game: {err:?}");
+                }
+                ShipGame::new(&renderer.ship_models, seed)
+            }
+        };
+        let settings = Settings::load().unwrap_or_else(|err| {
+            eprintln!("could not load the settings: {err:?}");
+            Settings::default()
+        });
+        set_vsync(&window, settings.vsync);
+        renderer.apply_camera_settings(&settings);
+        if settings.fullscreen {
+            // Browsers only allow going fullscreen in response to input, so
+            // this only works on desktop.
+            set_fullscreen(&mut window, true);
+        }
+        let interface = Interface::new();
+        update_filter_keys(&settings, &interface);
         let last_frame = timer.ticks();
         State {
             renderer,
             timer,
             window,
             event_pump,
+            controller_subsystem,
+            controllers,
+            controller_zoom: 0.0,
             lmouse_pressed: false,
             rmouse_pressed: false,
             mouse_position: Point::new(0, 0),
@@ -184,15 +368,34 @@ impl State {
             time: 0.0,
             last_frame,
             ship_game,
-            interface: Interface::new(),
-            debug_time_speedup: false,
+            interface,
+            debug_time_speedup: replay.is_some(),
+            paused: false,
+            settings,
+            screenshot_requested: false,
+            last_memory_stats_print: 0.0,
+            fps_timer: 0.0,
+            fps_counter: 0,
+            fps_display: 0.0,
+            debug_overlay: false,
+            replay: replay.map(|(_, events)| (events, 0)),
         }
     }
 }
 
+/// The value after the command line flag, e.g. the file in `--replay <file>`.
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args();
+    args.position(|arg| arg == flag)?;
+    args.next()
+}
+
 extern "C" fn run_frame() {
     let State {
         event_pump,
+        controller_subsystem,
+        controllers,
+        controller_zoom,
         lmouse_pressed,
         rmouse_pressed,
         mouse_position,
@@ -206,6 +409,15 @@ extern "C" fn run_frame() {
         ship_game,
         interface,
         debug_time_speedup,
+        paused,
+        settings,
+        screenshot_requested,
+        last_memory_stats_print,
+        fps_timer,
+        fps_counter,
+        fps_display,
+        debug_overlay,
+        replay,
         ..
     } = unsafe { &mut STATE }.as_mut().unwrap();
 
@@ -216,29 +428,62 @@ extern "C" fn run_frame() {
                 WindowEvent::Resized(w, h) => {
                     use renderer::gl;
                     gl::call!(gl::Viewport(0, 0, w, h));
+                    // The browser can leave fullscreen on its own
+                    settings.fullscreen = is_fullscreen(window);
                 }
+                // Browsers throttle hidden tabs anyway, so pause while the
+                // player is away instead of simulating in choppy steps.
+                WindowEvent::FocusLost if cfg!(target_family = "wasm") => *paused = true,
+                WindowEvent::FocusGained if cfg!(target_family = "wasm") => *paused = false,
                 _ => {}
             },
             Event::MouseButtonDown {
                 mouse_btn, x, y, ..
-            } => match mouse_btn {
-                MouseButton::Left => {
-                    *lmouse_pressed = true;
-                    let (w, h) = window.size();
-                    let mut clip_coords =
-                        Vec2::new(x as f32 / w as f32, y as f32 / h as f32) * 2.0 - Vec2::ONE;
-                    clip_coords.y *= -1.0;
-                    *ship_space_mouse_position =
-                        renderer.clip_to_ship_space(clip_coords, w as f32 / h as f32);
-
-                    interface.click(Point::new(x, y), ship_game, false);
+            } => {
+                interface.clear_tooltip();
+                match mouse_btn {
+                    MouseButton::Left => {
+                        *lmouse_pressed = true;
+                        let (clip_coords, aspect_ratio) = to_clip_coords(window, Point::new(x, y));
+                        *ship_space_mouse_position =
+                            renderer.clip_to_ship_space(clip_coords, aspect_ratio);
+                        if !interface.safe_area.contains_point(Point::new(x, y)) {
+                            let room =
+                                ship_game.room_at_screen_click(clip_coords, renderer, aspect_ratio);
+                            let building = interface.tab == Some(Tab::Build);
+                            let message = match interface.build_tool.filter(|_| building) {
+                                Some(BuildTool::Place(room_type)) => ship_game.build_room(
+                                    &renderer.ship_models,
+                                    room_type,
+                                    *ship_space_mouse_position,
+                                ),
+                                Some(BuildTool::Remove) => {
+                                    room.and_then(|room| ship_game.demolish_room(room))
+                                }
+                                None => {
+                                    ship_game.selected_room = room;
+                                    ship_game.selected_character =
+                                        ship_game.character_at(*ship_space_mouse_position);
+                                    None
+                                }
+                            };
+                            if let Some(message) = message {
+                                interface.push_notification(message, 3.0);
+                            }
+                        }
+
+                        interface.click(Point::new(x, y), ship_game, renderer, false);
+                    }
+                    MouseButton::Right => *rmouse_pressed = true,
+                    _ => {}
                 }
-                MouseButton::Right => *rmouse_pressed = true,
-                _ => {}
-            },
+            }
             Event::MouseButtonUp { mouse_btn, .. } => {
                 match mouse_btn {
-                    MouseButton::Left => *lmouse_pressed = false,
+                    MouseButton::Left => {
+                        *lmouse_pressed = false;
+                        interface.schedule_drag = None;
+                    }
                     MouseButton::Right => *rmouse_pressed = false,
                     _ => {}
                 }
@@ -252,11 +497,14 @@ extern "C" fn run_frame() {
                 ..
             } => {
                 *mouse_position = Point::new(x, y);
-                interface.hover(*mouse_position);
+                interface.hover(*mouse_position, ship_game);
                 if *rmouse_pressed {
-                    renderer.rotate_camera(xrel, yrel);
+                    renderer.rotate_camera(xrel, yrel, settings.camera_rotate_sensitivity);
                 }
-                if *lmouse_pressed {
+                if *lmouse_pressed && interface.schedule_drag.is_some() {
+                    // Painting the schedule, not moving the camera
+                    interface.click(Point::new(x, y), ship_game, renderer, true);
+                } else if *lmouse_pressed {
                     // Look movement
                     let threshold = 10i32.pow(2);
                     let (acc_x, acc_y) = accumulated_mouse_rel;
@@ -266,14 +514,15 @@ extern "C" fn run_frame() {
                             *acc_y += yrel;
                         } else {
                             // Not dragging the map around, but inside safe area with left btn held:
-                            interface.click(Point::new(x, y), ship_game, true);
+                            interface.click(Point::new(x, y), ship_game, renderer, true);
                         }
                         // Haven't moved enough yet, don't move.
                         xrel = 0;
                         yrel = 0;
                     }
                     let (_, h) = window.size();
-                    renderer.move_camera(xrel as f32 / h as f32, yrel as f32 / h as f32);
+                    let (dx, dy) = (xrel as f32 / h as f32, yrel as f32 / h as f32);
+                    renderer.move_camera(dx, dy, settings.camera_move_sensitivity);
                 }
             }
             Event::MouseWheel { y, direction, .. } => {
@@ -281,35 +530,378 @@ extern "C" fn run_frame() {
                     * (direction == MouseWheelDirection::Flipped)
                         .then_some(-1)
                         .unwrap_or(1);
-                renderer.zoom_camera(pixels);
+                let over_screen = interface.screen_area.contains_point(*mouse_position);
+                if !over_screen || !interface.scroll(-pixels as f32) {
+                    let (clip_coords, aspect_ratio) = to_clip_coords(window, *mouse_position);
+                    let sensitivity = settings.zoom_sensitivity;
+                    renderer.zoom_camera(pixels, clip_coords, aspect_ratio, sensitivity);
+                }
             }
-            Event::KeyDown { keycode, .. } => match keycode {
-                Some(Keycode::Space) => *debug_time_speedup = true,
-                Some(Keycode::Num1) => interface.open_tab(0),
-                Some(Keycode::Num2) => interface.open_tab(1),
-                Some(Keycode::Num3) => interface.open_tab(2),
-                Some(Keycode::Num4) => interface.open_tab(3),
-                _ => {}
+            Event::TextInput { text, .. } => {
+                if let Some(text_input) = interface.focused_text_input() {
+                    text_input.insert(&text);
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } if interface.capturing_key_binding.is_some() => {
+                let action = interface.capturing_key_binding.take().unwrap();
+                if keycode != Keycode::Escape {
+                    settings.key_bindings.set(action, keycode);
+                    save_settings(settings, interface);
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } if ship_game.is_sunk() => {
+                *ship_game = ShipGame::new(&renderer.ship_models, ship_game.seed);
+                interface.push_notification("A new voyage begins", 3.0);
+            }
+            Event::KeyDown { keycode, .. } if interface.focused_text_input().is_some() => {
+                let text_input = interface.focused_text_input().unwrap();
+                match keycode {
+                    Some(Keycode::Backspace) => text_input.backspace(),
+                    Some(Keycode::Left) => text_input.move_cursor_left(),
+                    Some(Keycode::Right) => text_input.move_cursor_right(),
+                    Some(Keycode::Escape | Keycode::Return) => text_input.focused = false,
+                    _ => {}
+                }
+            }
+            Event::KeyDown {
+                keycode, keymod, ..
+            } => match keycode.and_then(|keycode| settings.key_bindings.action_for(keycode)) {
+                Some(KeyAction::RotateView) => {
+                    renderer.rotate_camera(200, 0, settings.camera_rotate_sensitivity);
+                }
+                Some(KeyAction::SpeedUp) if !*paused => *debug_time_speedup = true,
+                Some(KeyAction::SpeedUp) => {}
+                Some(KeyAction::TabNavigation) => interface.open_tab(0),
+                Some(KeyAction::TabSchedule) => interface.open_tab(1),
+                Some(KeyAction::TabDeliveries) => interface.open_tab(2),
+                Some(KeyAction::TabSettings) => interface.open_tab(3),
+                None => match keycode {
+                    Some(Keycode::P) => {
+                        *paused = !*paused;
+                        *debug_time_speedup = false;
+                    }
+                    Some(Keycode::Escape) if interface.pending_confirm.is_some() => {
+                        interface.pending_confirm = None;
+                    }
+                    Some(Keycode::Escape) if settings.fullscreen => {
+                        toggle_fullscreen(window, settings, interface);
+                    }
+                    Some(Keycode::F11) => toggle_fullscreen(window, settings, interface),
+                    Some(Keycode::F12) => *screenshot_requested = true,
+                    Some(Keycode::F3) => *debug_overlay = !*debug_overlay,
+                    Some(Keycode::Tab) => {
+                        let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                        interface.focus_next_button(shift);
+                    }
+                    Some(Keycode::Return) => interface.click_focused_button(ship_game, renderer),
+                    Some(Keycode::Up) => interface.focus_button_towards(Vec2::new(0.0, -1.0)),
+                    Some(Keycode::Down) => interface.focus_button_towards(Vec2::new(0.0, 1.0)),
+                    Some(Keycode::Left) => interface.focus_button_towards(Vec2::new(-1.0, 0.0)),
+                    Some(Keycode::Right) => interface.focus_button_towards(Vec2::new(1.0, 0.0)),
+                    Some(Keycode::Num5) => interface.open_tab(4),
+                    Some(Keycode::M) => interface.open_tab(5),
+                    Some(Keycode::Num6) => interface.open_tab(6),
+                    Some(Keycode::Num7) => interface.open_tab(7),
+                    Some(Keycode::B) => interface.open_tab(8),
+                    Some(Keycode::O) => renderer.toggle_orthographic_camera(),
+                    Some(Keycode::N) => renderer.toggle_minimap(),
+                    Some(Keycode::W) if cfg!(debug_assertions) => renderer.toggle_wireframe_mode(),
+                    _ => {}
+                },
             },
-            Event::KeyUp { keycode, .. } => match keycode {
-                Some(Keycode::Space) => *debug_time_speedup = false,
+            Event::ControllerDeviceAdded { which, .. } if controllers.len() < MAX_CONTROLLERS => {
+                match controller_subsystem.open(which) {
+                    // Controllers connected at startup are announced too
+                    Ok(controller)
+                        if !controllers
+                            .iter()
+                            .any(|c| c.instance_id() == controller.instance_id()) =>
+                    {
+                        controllers.push(controller);
+                    }
+                    Ok(_) => {}
+                    Err(err) => eprintln!("could not open game controller {which}: {err}"),
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                controllers.retain(|controller| controller.instance_id() != which);
+            }
+            Event::ControllerButtonDown { button, .. } => match button {
+                ControllerButton::A => interface.open_tab(0),
+                ControllerButton::B => interface.open_tab(1),
+                ControllerButton::X => interface.open_tab(2),
+                ControllerButton::Y => interface.open_tab(3),
+                ControllerButton::DPadUp => interface.focus_button_towards(Vec2::new(0.0, -1.0)),
+                ControllerButton::DPadDown => interface.focus_button_towards(Vec2::new(0.0, 1.0)),
+                ControllerButton::DPadLeft => interface.focus_button_towards(Vec2::new(-1.0, 0.0)),
+                ControllerButton::DPadRight => interface.focus_button_towards(Vec2::new(1.0, 0.0)),
                 _ => {}
             },
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } if keycode == settings.key_bindings.speed_up => *debug_time_speedup = false,
+            _ => {}
+        }
+    }
+    // The key bindings and the capturing state can only change while
+    // handling events
+    update_filter_keys(settings, interface);
+
+    if interface.toggle_fullscreen {
+        interface.toggle_fullscreen = false;
+        toggle_fullscreen(window, settings, interface);
+    }
+    if interface.cycle_bloom {
+        interface.cycle_bloom = false;
+        settings.cycle_bloom_strength();
+        save_settings(settings, interface);
+    }
+    if interface.cycle_dof {
+        interface.cycle_dof = false;
+        settings.cycle_dof_aperture();
+        save_settings(settings, interface);
+    }
+    if let Some((option, step)) = interface.adjust_option.take() {
+        settings.adjust(option, step);
+        match option {
+            GameOption::Vsync => set_vsync(window, settings.vsync),
+            GameOption::MaxCameraDistance => renderer.apply_camera_settings(settings),
             _ => {}
         }
+        save_settings(settings, interface);
     }
 
     let now = timer.ticks();
     let dt = (now - *last_frame) as f32 / 1000.0;
-    *time += dt;
     *last_frame = now;
 
-    let speed_scale = if *debug_time_speedup { 12.0 } else { 1.0 };
-    ship_game.update(dt * speed_scale);
+    for controller in controllers.iter() {
+        // Like dragging the mouse across the window's height once a second.
+        // Dragging pulls the ship along, but the stick pushes the camera, so
+        // the direction is flipped.
+        let left_stick = stick_input(controller, Axis::LeftX, Axis::LeftY) * -dt;
+        renderer.move_camera(left_stick.x, left_stick.y, settings.camera_move_sensitivity);
+        let right_stick = stick_input(controller, Axis::RightX, Axis::RightY) * dt * 600.0;
+        let (x, y) = (right_stick.x.round() as i32, right_stick.y.round() as i32);
+        renderer.rotate_camera(x, y, settings.camera_rotate_sensitivity);
+        let zoom = trigger_input(controller, Axis::TriggerRight)
+            - trigger_input(controller, Axis::TriggerLeft);
+        *controller_zoom += zoom * dt * 4.0;
+    }
+    if controller_zoom.abs() >= 1.0 {
+        let notches = controller_zoom.trunc();
+        *controller_zoom -= notches;
+        let (w, h) = window.size();
+        let center = Point::new(w as i32 / 2, h as i32 / 2);
+        let (clip_coords, aspect_ratio) = to_clip_coords(window, center);
+        let sensitivity = settings.zoom_sensitivity;
+        renderer.zoom_camera(notches as i32, clip_coords, aspect_ratio, sensitivity);
+    }
+
+    renderer.update_camera(dt);
+    #[cfg(debug_assertions)]
+    renderer.reload_shaders_if_changed(dt);
+    interface.update(dt);
+
+    if !*paused && !ship_game.is_sunk() {
+        *time += dt;
+        // Replays are always sped up, there's nothing to do but watch
+        let speed_scale = if *debug_time_speedup || replay.is_some() {
+            12.0
+        } else {
+            1.0
+        };
+        ship_game.update(dt * speed_scale);
+        for (text, duration) in ship_game.notifications.drain(..) {
+            interface.push_notification(text, duration);
+        }
+        if let Some((events, applied)) = replay {
+            *applied +=
+                ship_game::play_replay(&events[*applied..], ship_game, ship_game.world_time);
+        }
+    }
+
+    *fps_counter += 1;
+    *fps_timer += dt;
+    if *fps_timer >= 1.0 {
+        *fps_display = *fps_counter as f32 / *fps_timer;
+        *fps_counter = 0;
+        *fps_timer = 0.0;
+    }
+    // The statistics are from the previous frame, since this one hasn't
+    // been drawn yet
+    let debug_overlay_text = debug_overlay.then(|| {
+        let stats = renderer.frame_stats();
+        format!(
+            "FPS: {fps_display:.0}\ndt: {:.1}ms\nDraw calls: {}\nTriangles: {}",
+            dt * 1000.0,
+            stats.draw_calls,
+            stats.triangles,
+        )
+    });
 
     let (w, h) = window.drawable_size();
-    renderer.render(w as f32, h as f32, *time, &ship_game, interface);
+    renderer.render(
+        w as f32,
+        h as f32,
+        *time,
+        *paused,
+        &ship_game,
+        interface,
+        settings,
+        debug_overlay_text.as_deref(),
+    );
+    if *screenshot_requested {
+        // Before swapping, since the contents of the window are undefined
+        // afterwards
+        *screenshot_requested = false;
+        take_screenshot(renderer, w, h, interface);
+    }
     window.gl_swap_window();
+
+    if cfg!(debug_assertions) && *time - *last_memory_stats_print >= 1.0 {
+        *last_memory_stats_print = *time;
+        eprintln!("Renderer memory usage: {}", renderer.memory_stats());
+    }
+}
+
+fn take_screenshot(renderer: &Renderer, width: u32, height: u32, interface: &mut Interface) {
+    #[cfg(target_family = "wasm")]
+    {
+        // The browser can encode the canvas itself, which is faster than
+        // reading the pixels back
+        let _ = (renderer, width, height);
+        screenshot::download_canvas();
+        interface.push_notification("Screenshot saved!", 2.0);
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        let pixels = renderer.read_window_pixels(width as i32, height as i32);
+        match screenshot::save(width, height, &pixels) {
+            Ok(path) => {
+                eprintln!("saved a screenshot to {path:?}");
+                interface.push_notification("Screenshot saved!", 2.0);
+            }
+            Err(err) => {
+                eprintln!("saving the screenshot failed: {err:?}");
+                interface.push_notification("Couldn't save the screenshot", 3.0);
+            }
+        }
+    }
+}
+
+fn is_fullscreen(window: &Window) -> bool {
+    #[cfg(target_family = "wasm")]
+    {
+        let _ = window;
+        emscripten_h::run_javascript_string("document.fullscreenElement ? 'yes' : ''") == "yes"
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        window.fullscreen_state() != FullscreenType::Off
+    }
+}
+
+fn set_fullscreen(window: &mut Window, fullscreen: bool) {
+    #[cfg(target_family = "wasm")]
+    {
+        let _ = window;
+        // The promises are rejected if the browser doesn't allow it right now
+        emscripten_h::run_javascript(if fullscreen {
+            "document.documentElement.requestFullscreen().catch(() => {})"
+        } else {
+            "document.exitFullscreen().catch(() => {})"
+        });
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        let fullscreen_type = if fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        if let Err(err) = window.set_fullscreen(fullscreen_type) {
+            eprintln!("could not change the fullscreen mode: {err}");
+        }
+    }
+}
+
+fn toggle_fullscreen(window: &mut Window, settings: &mut Settings, interface: &mut Interface) {
+    settings.fullscreen = !is_fullscreen(window);
+    set_fullscreen(window, settings.fullscreen);
+    save_settings(settings, interface);
+}
+
+fn set_vsync(window: &Window, vsync: bool) {
+    let interval = if vsync {
+        SwapInterval::VSync
+    } else {
+        SwapInterval::Immediate
+    };
+    if let Err(err) = window.subsystem().gl_set_swap_interval(interval) {
+        eprintln!(
+            "could not set vsync {}: {err}",
+            if vsync { "on" } else { "off" }
+        );
+    }
+}
+
+/// Copies the key bindings and whether a key binding is being captured for the
+/// event filter, see [FILTER_KEYS].
+fn update_filter_keys(settings: &Settings, interface: &Interface) {
+    let mut filter_keys = FILTER_KEYS.lock().unwrap();
+    filter_keys.bound.clear();
+    filter_keys.bound.extend(
+        KeyAction::ALL
+            .into_iter()
+            .map(|action| settings.key_bindings.get(action)),
+    );
+    filter_keys.capturing = interface.capturing_key_binding.is_some();
+}
+
+fn save_settings(settings: &Settings, interface: &mut Interface) {
+    if let Err(err) = settings.save() {
+        eprintln!("saving the settings failed: {err:?}");
+        interface.push_notification("Couldn't save the settings", 3.0);
+    }
+}
+
+/// Returns the position of the analog stick, from -1.0 to 1.0 on both axes,
+/// and zero inside the dead zone.
+fn stick_input(controller: &GameController, x_axis: Axis, y_axis: Axis) -> Vec2 {
+    let stick = Vec2::new(
+        controller.axis(x_axis) as f32 / i16::MAX as f32,
+        controller.axis(y_axis) as f32 / i16::MAX as f32,
+    );
+    let length = stick.length();
+    if length < STICK_DEAD_ZONE {
+        return Vec2::ZERO;
+    }
+    // Rescaled so that the input starts from zero at the edge of the dead zone
+    let scaled_length = ((length - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)).min(1.0);
+    stick / length * scaled_length
+}
+
+/// Returns how far the trigger is pressed, from 0.0 to 1.0.
+fn trigger_input(controller: &GameController, axis: Axis) -> f32 {
+    (controller.axis(axis) as f32 / i16::MAX as f32).max(0.0)
+}
+
+/// Returns the window-space position in clip space, and the aspect ratio of
+/// the window.
+fn to_clip_coords(window: &Window, position: Point) -> (Vec2, f32) {
+    let (w, h) = window.size();
+    let (x, y) = (position.x() as f32, position.y() as f32);
+    let mut clip_coords = Vec2::new(x / w as f32, y / h as f32) * 2.0 - Vec2::ONE;
+    clip_coords.y *= -1.0;
+    (clip_coords, w as f32 / h as f32)
 }
 
 fn display_error<D: Display>(err: D) {
@@ -326,7 +918,7 @@ fn display_error<D: Display>(err: D) {
         use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
 
         eprintln!("fatal error: {err}");
-        let window = unsafe { STATE.as_ref() }.map(|state| &state.window);
+        let window = unsafe { (*ptr::addr_of!(STATE)).as_ref() }.map(|state| &state.window);
         let _ = show_simple_message_box(
             MessageBoxFlag::ERROR,
             "Game crashed!",
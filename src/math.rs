@@ -1,6 +1,6 @@
 //! Structs and funcs glam doesn't provide.
 
-use glam::Vec2;
+use glam::{Mat4, Vec2, Vec3, Vec4};
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct Aabb2 {
@@ -24,6 +24,141 @@ impl Aabb2 {
     pub fn offset(&self, by: Vec2) -> Aabb2 {
         Aabb2::new(self.min + by, self.max + by)
     }
+
+    /// Returns true if the rectangles overlap. Rectangles that only touch at
+    /// their edges count as overlapping.
+    pub fn intersects(&self, other: &Aabb2) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// Returns the area where the rectangles overlap, see
+    /// [Aabb2::intersects].
+    pub fn intersection(&self, other: &Aabb2) -> Option<Aabb2> {
+        if self.intersects(other) {
+            Some(Aabb2::new(self.min.max(other.min), self.max.min(other.max)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Aabb2) -> Aabb2 {
+        Aabb2::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.max.x - self.min.x) * (self.max.y - self.min.y)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Circle2 {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle2 {
+    pub fn new(center: Vec2, radius: f32) -> Circle2 {
+        Circle2 { center, radius }
+    }
+
+    /// Returns true if the point is inside the circle, or on its edge.
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+
+    /// Returns true if the circles overlap. Touching circles count as
+    /// overlapping.
+    #[allow(dead_code)]
+    pub fn intersects_circle(&self, other: &Circle2) -> bool {
+        let radii = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= radii * radii
+    }
+
+    /// Returns true if the circle overlaps the rectangle, i.e. the closest
+    /// point of the rectangle is inside the circle.
+    #[allow(dead_code)]
+    pub fn intersects_aabb(&self, rect: &Aabb2) -> bool {
+        self.contains(self.nearest_point_on_aabb(rect))
+    }
+
+    /// Returns the point of the rectangle closest to the center of the
+    /// circle. That's the center itself, if it's inside the rectangle.
+    pub fn nearest_point_on_aabb(&self, rect: &Aabb2) -> Vec2 {
+        self.center.clamp(rect.min, rect.max)
+    }
+
+    /// Moves the point onto the edge of the circle, if it's outside of it.
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        if self.contains(point) {
+            point
+        } else {
+            self.center + (point - self.center).normalize_or_zero() * self.radius
+        }
+    }
+}
+
+/// A half-line in 2D, starting from `origin` and going towards `direction`
+/// forever. The direction doesn't need to be normalized, but the parametric
+/// distances (`t`) are in multiples of its length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray2 {
+    pub origin: Vec2,
+    pub direction: Vec2,
+}
+
+impl Ray2 {
+    pub fn at(&self, t: f32) -> Vec2 {
+        self.origin + self.direction * t
+    }
+
+    /// Returns the `t` where the ray first hits the rectangle, or None if it
+    /// misses. Rays starting inside the rectangle hit it at 0.
+    pub fn intersect_aabb(&self, aabb: &Aabb2) -> Option<f32> {
+        // The slab method: the ray is inside the rectangle where it's between
+        // both pairs of edges at the same time.
+        let t0 = (aabb.min - self.origin) / self.direction;
+        let t1 = (aabb.max - self.origin) / self.direction;
+        let t_enter = t0.min(t1).max_element().max(0.0);
+        let t_exit = t0.max(t1).min_element();
+        (t_enter <= t_exit).then_some(t_enter)
+    }
+
+    /// Returns the point on the ray closest to `point`.
+    #[allow(dead_code)]
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let length_squared = self.direction.length_squared();
+        if length_squared == 0.0 {
+            return self.origin;
+        }
+        let t = (point - self.origin).dot(self.direction) / length_squared;
+        self.at(t.max(0.0))
+    }
+}
+
+/// The volume visible to a camera, as six inward-facing planes.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Each plane is (normal, distance), where points p inside the frustum
+    /// satisfy `normal.dot(p) + distance >= 0`.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a projection matrix (or a view-projection
+    /// matrix, for world space planes).
+    pub fn from_matrix(m: Mat4) -> Frustum {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2]
+            .map(|plane| plane / plane.truncate().length());
+        Frustum { planes }
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
 }
 
 #[cfg(test)]
@@ -39,4 +174,114 @@ mod tests {
         assert!(!bounds.contains(Vec2::new(2.5, 0.5)));
         assert!(!bounds.contains(Vec2::new(1.5, 2.0)));
     }
+
+    #[test]
+    fn test_bounds_intersection() {
+        let bounds = Aabb2::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0));
+
+        // Touching edges
+        let right = Aabb2::new(Vec2::new(2.0, 0.0), Vec2::new(3.0, 2.0));
+        assert!(bounds.intersects(&right));
+        assert!(right.intersects(&bounds));
+        let edge = bounds.intersection(&right).unwrap();
+        assert_eq!(Vec2::new(2.0, 0.0), edge.min);
+        assert_eq!(Vec2::new(2.0, 2.0), edge.max);
+        assert_eq!(0.0, edge.area());
+
+        // One inside the other
+        let inner = Aabb2::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.5));
+        assert!(bounds.intersects(&inner));
+        assert!(inner == bounds.intersection(&inner).unwrap());
+        assert!(bounds == bounds.union(&inner));
+
+        // Partial overlap
+        let partial = Aabb2::new(Vec2::new(1.0, -1.0), Vec2::new(3.0, 1.0));
+        let overlap = bounds.intersection(&partial).unwrap();
+        assert_eq!(Vec2::new(1.0, 0.0), overlap.min);
+        assert_eq!(Vec2::new(2.0, 1.0), overlap.max);
+        assert_eq!(1.0, overlap.area());
+        let union = bounds.union(&partial);
+        assert_eq!(Vec2::new(0.0, -1.0), union.min);
+        assert_eq!(Vec2::new(3.0, 2.0), union.max);
+
+        // Separate
+        let far = Aabb2::new(Vec2::new(2.5, 2.5), Vec2::new(3.0, 3.0));
+        assert!(!bounds.intersects(&far));
+        assert!(bounds.intersection(&far).is_none());
+        assert_eq!(4.0, bounds.area());
+    }
+
+    #[test]
+    fn test_ray() {
+        let rect = Aabb2::new(Vec2::new(2.0, -1.0), Vec2::new(4.0, 1.0));
+        let ray = Ray2 {
+            origin: Vec2::ZERO,
+            direction: Vec2::X,
+        };
+        assert_eq!(Some(2.0), ray.intersect_aabb(&rect));
+        assert_eq!(Vec2::new(2.0, 0.0), ray.at(2.0));
+
+        let inside = Ray2 {
+            origin: Vec2::new(3.0, 0.0),
+            direction: Vec2::new(-1.0, 1.0),
+        };
+        assert_eq!(Some(0.0), inside.intersect_aabb(&rect));
+
+        let behind = Ray2 {
+            origin: Vec2::ZERO,
+            direction: -Vec2::X,
+        };
+        assert_eq!(None, behind.intersect_aabb(&rect));
+        let above = Ray2 {
+            origin: Vec2::new(0.0, 2.0),
+            direction: Vec2::X,
+        };
+        assert_eq!(None, above.intersect_aabb(&rect));
+
+        assert_eq!(Vec2::new(3.0, 0.0), ray.closest_point(Vec2::new(3.0, 5.0)));
+        assert_eq!(Vec2::ZERO, ray.closest_point(Vec2::new(-3.0, 5.0)));
+    }
+
+    #[test]
+    fn test_circle() {
+        let circle = Circle2::new(Vec2::new(1.0, 1.0), 2.0);
+        assert!(circle.contains(Vec2::new(1.0, 1.0)));
+        // On the circumference
+        assert!(circle.contains(Vec2::new(3.0, 1.0)));
+        assert!(!circle.contains(Vec2::new(3.0, 1.1)));
+
+        // Tangent circles
+        assert!(circle.intersects_circle(&Circle2::new(Vec2::new(1.0, 4.0), 1.0)));
+        assert!(!circle.intersects_circle(&Circle2::new(Vec2::new(1.0, 4.5), 1.0)));
+
+        // Touching the rectangle's edge
+        let rect = Aabb2::new(Vec2::new(3.0, -1.0), Vec2::new(4.0, 1.5));
+        assert_eq!(Vec2::new(3.0, 1.0), circle.nearest_point_on_aabb(&rect));
+        assert!(circle.intersects_aabb(&rect));
+        let below = Aabb2::new(Vec2::new(3.0, -1.0), Vec2::new(4.0, 0.5));
+        assert_eq!(Vec2::new(3.0, 0.5), circle.nearest_point_on_aabb(&below));
+        assert!(!circle.intersects_aabb(&below));
+        // The corner is closer than the radius on both axes, but not
+        // diagonally
+        let corner = Aabb2::new(Vec2::new(2.5, 2.5), Vec2::new(3.0, 3.0));
+        assert!(!circle.intersects_aabb(&corner));
+        let around = Aabb2::new(Vec2::new(-5.0, -5.0), Vec2::new(5.0, 5.0));
+        assert_eq!(circle.center, circle.nearest_point_on_aabb(&around));
+
+        assert_eq!(Vec2::new(3.0, 1.0), circle.clamp(Vec2::new(10.0, 1.0)));
+        assert_eq!(Vec2::new(2.0, 1.0), circle.clamp(Vec2::new(2.0, 1.0)));
+    }
+
+    #[test]
+    fn test_frustum() {
+        let proj = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let frustum = Frustum::from_matrix(proj * view);
+        assert!(frustum.intersects_sphere(Vec3::ZERO, 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, -200.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(20.0, 0.0, 0.0), 1.0));
+        // Partially inside, the center is just outside of the left plane
+        assert!(frustum.intersects_sphere(Vec3::new(-5.5, 0.0, 0.0), 1.0));
+    }
 }
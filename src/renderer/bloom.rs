@@ -0,0 +1,140 @@
+use crate::renderer::framebuffer::{create_fullscreen_program, Framebuffer};
+use crate::renderer::gl;
+
+/// Makes the brightest parts of the scene glow: they're picked out, blurred
+/// at half resolution, and added back on top of the scene.
+pub struct BloomPass {
+    threshold_fbo: Framebuffer,
+    blur_fbo_h: Framebuffer,
+    blur_fbo_v: Framebuffer,
+    threshold_shader: gl::types::GLuint,
+    blur_shader: gl::types::GLuint,
+    blend_shader: gl::types::GLuint,
+    blur_direction_location: gl::types::GLint,
+    bloom_strength_location: gl::types::GLint,
+    vao: gl::types::GLuint,
+    nearest_sampler: gl::types::GLuint,
+    linear_sampler: gl::types::GLuint,
+}
+
+impl BloomPass {
+    pub fn new() -> BloomPass {
+        let threshold_shader =
+            create_fullscreen_program(include_str!("bloom_threshold_fragment.glsl"));
+        let blur_shader = create_fullscreen_program(include_str!("bloom_blur_fragment.glsl"));
+        let blend_shader = create_fullscreen_program(include_str!("bloom_composite_fragment.glsl"));
+        for program in [threshold_shader, blur_shader, blend_shader] {
+            gl::call!(gl::UseProgram(program));
+            if let Some(location) = gl::get_uniform_location(program, "color_tex") {
+                gl::call!(gl::Uniform1i(location, 0));
+            }
+        }
+        if let Some(location) = gl::get_uniform_location(blend_shader, "bloom_tex") {
+            gl::call!(gl::Uniform1i(location, 1));
+        }
+        let blur_direction_location = gl::get_uniform_location(blur_shader, "direction").unwrap();
+        let bloom_strength_location =
+            gl::get_uniform_location(blend_shader, "bloom_strength").unwrap();
+
+        // The vertices are generated from gl_VertexID, like in FullscreenQuad
+        let mut vao = 0;
+        gl::call!(gl::GenVertexArrays(1, &mut vao));
+        let mut samplers = [0; 2];
+        gl::call!(gl::GenSamplers(2, samplers.as_mut_ptr()));
+        let [nearest_sampler, linear_sampler] = samplers;
+        gl::setup_nearest_sampler(nearest_sampler);
+        gl::setup_linear_sampler(linear_sampler, false);
+        // The blur shouldn't wrap around to the other side of the screen
+        for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T] {
+            gl::call!(gl::SamplerParameteri(
+                linear_sampler,
+                wrap,
+                gl::CLAMP_TO_EDGE as i32,
+            ));
+        }
+
+        BloomPass {
+            threshold_fbo: Framebuffer::new(1, 1),
+            blur_fbo_h: Framebuffer::new(1, 1),
+            blur_fbo_v: Framebuffer::new(1, 1),
+            threshold_shader,
+            blur_shader,
+            blend_shader,
+            blur_direction_location,
+            bloom_strength_location,
+            vao,
+            nearest_sampler,
+            linear_sampler,
+        }
+    }
+
+    /// Blurs the bright parts of the scene texture into `blur_fbo_v`. Leaves
+    /// `blur_fbo_v` bound, so the render target and viewport need to be
+    /// reset before [BloomPass::composite].
+    pub fn run(&mut self, scene_tex: gl::types::GLuint, width: i32, height: i32) {
+        let (width, height) = ((width / 2).max(1), (height / 2).max(1));
+        self.threshold_fbo.resize(width, height);
+        self.blur_fbo_h.resize(width, height);
+        self.blur_fbo_v.resize(width, height);
+
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::BindVertexArray(self.vao));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindSampler(0, self.linear_sampler));
+
+        self.threshold_fbo.bind();
+        gl::call!(gl::UseProgram(self.threshold_shader));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, scene_tex));
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+        gl::call!(gl::UseProgram(self.blur_shader));
+        for (source, target, direction) in [
+            (&self.threshold_fbo, &self.blur_fbo_h, (1.0, 0.0)),
+            (&self.blur_fbo_h, &self.blur_fbo_v, (0.0, 1.0)),
+        ] {
+            target.bind();
+            gl::call!(gl::Uniform2f(
+                self.blur_direction_location,
+                direction.0,
+                direction.1,
+            ));
+            gl::call!(gl::BindTexture(gl::TEXTURE_2D, source.color_tex));
+            gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+        }
+        gl::call!(gl::BindVertexArray(0));
+    }
+
+    /// Draws the scene texture with the bloom from the last
+    /// [BloomPass::run] added on top, over the whole render target.
+    pub fn composite(&self, scene_tex: gl::types::GLuint, bloom_strength: f32) {
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::UseProgram(self.blend_shader));
+        gl::call!(gl::Uniform1f(self.bloom_strength_location, bloom_strength));
+        gl::call!(gl::BindVertexArray(self.vao));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, scene_tex));
+        gl::call!(gl::BindSampler(0, self.nearest_sampler));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE1));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.blur_fbo_v.color_tex));
+        gl::call!(gl::BindSampler(1, self.linear_sampler));
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+        gl::call!(gl::BindSampler(1, 0));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindVertexArray(0));
+    }
+}
+
+impl Drop for BloomPass {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteProgram(self.threshold_shader));
+        gl::call!(gl::DeleteProgram(self.blur_shader));
+        gl::call!(gl::DeleteProgram(self.blend_shader));
+        gl::call!(gl::DeleteVertexArrays(1, &self.vao));
+        gl::call!(gl::DeleteSamplers(1, &self.nearest_sampler));
+        gl::call!(gl::DeleteSamplers(1, &self.linear_sampler));
+    }
+}
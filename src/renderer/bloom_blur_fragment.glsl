@@ -0,0 +1,24 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+// (1, 0) for the horizontal pass, (0, 1) for the vertical one.
+uniform vec2 direction;
+
+const float WEIGHTS[5] =
+    float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+  vec2 texel_step = direction / vec2(textureSize(color_tex, 0));
+  vec3 color = texture(color_tex, tex_coords).rgb * WEIGHTS[0];
+  for (int i = 1; i < 5; i++) {
+    vec2 offset = texel_step * float(i);
+    color += texture(color_tex, tex_coords + offset).rgb * WEIGHTS[i];
+    color += texture(color_tex, tex_coords - offset).rgb * WEIGHTS[i];
+  }
+  FRAG_COLOR = vec4(color, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+uniform sampler2D bloom_tex;
+uniform float bloom_strength;
+
+void main() {
+  vec3 color = texture(color_tex, tex_coords).rgb;
+  vec3 bloom = texture(bloom_tex, tex_coords).rgb;
+  FRAG_COLOR = vec4(color + bloom * bloom_strength, 1.0);
+}
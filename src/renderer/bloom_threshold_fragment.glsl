@@ -0,0 +1,18 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+
+// The scene is already tone mapped by the time it's in the framebuffer, so
+// this is as close to "brighter than 1.0" as it gets.
+#define THRESHOLD 0.8
+
+void main() {
+  vec3 color = texture(color_tex, tex_coords).rgb;
+  float brightness = max(color.r, max(color.g, color.b));
+  FRAG_COLOR = vec4(color * smoothstep(THRESHOLD, 1.0, brightness), 1.0);
+}
@@ -3,18 +3,53 @@ use std::ptr;
 
 use crate::renderer::gl;
 
+/// An alignment that satisfies GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT on all
+/// common implementations, so that it doesn't need to be queried at runtime.
+pub const UNIFORM_BUFFER_ALIGNMENT: usize = 256;
+
+/// How many backing buffers ring buffers cycle through. Emscripten's GL
+/// emulation doesn't benefit from multiple buffers, so it only orphans one.
+#[cfg(not(target_family = "wasm"))]
+const RING_BUFFER_COUNT: usize = 3;
+#[cfg(target_family = "wasm")]
+const RING_BUFFER_COUNT: usize = 1;
+
 pub struct BumpAllocatedBuffer {
     buffer: gl::types::GLuint,
     target: gl::types::GLenum,
     usage: gl::types::GLenum,
     offset: usize,
     size: usize,
+    /// The offsets returned by [BumpAllocatedBuffer::allocate_buffer] are
+    /// multiples of this.
+    alignment: usize,
+    /// The bytes skipped since the last clear to keep the offsets aligned.
+    padding: usize,
     data_copy: Vec<u8>,
     buffer_leaked: bool,
+    /// If true, the backing buffer is orphaned on every clear, and the
+    /// allocator cycles between multiple buffers, so that writing a new
+    /// frame's data doesn't need to wait for the GPU to finish with the old.
+    ring: bool,
+    /// The backing buffers (and their sizes) not currently written to, in the
+    /// order they are cycled to in [BumpAllocatedBuffer::clear].
+    spare_buffers: Vec<(gl::types::GLuint, usize)>,
 }
 
 impl BumpAllocatedBuffer {
     pub fn new(target: gl::types::GLenum, usage: gl::types::GLenum) -> BumpAllocatedBuffer {
+        BumpAllocatedBuffer::new_aligned(target, usage, 1)
+    }
+
+    /// Creates a bump allocator which only returns offsets that are multiples
+    /// of `alignment`. Uniform buffer offsets need to be multiples of
+    /// GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT, see [UNIFORM_BUFFER_ALIGNMENT].
+    pub fn new_aligned(
+        target: gl::types::GLenum,
+        usage: gl::types::GLenum,
+        alignment: usize,
+    ) -> BumpAllocatedBuffer {
+        assert!(alignment > 0);
         let mut buffer = 0;
         gl::call!(gl::GenBuffers(1, &mut buffer));
         BumpAllocatedBuffer {
@@ -23,14 +58,35 @@ impl BumpAllocatedBuffer {
             usage,
             offset: 0,
             size: 0,
+            alignment,
+            padding: 0,
             data_copy: Vec::new(),
             buffer_leaked: false,
+            ring: false,
+            spare_buffers: Vec::new(),
+        }
+    }
+
+    /// Creates a bump allocator for streaming data that's rewritten every
+    /// frame, see [BumpAllocatedBuffer::clear].
+    pub fn new_ring(target: gl::types::GLenum, usage: gl::types::GLenum) -> BumpAllocatedBuffer {
+        let mut allocator = BumpAllocatedBuffer::new(target, usage);
+        allocator.ring = true;
+        let mut spare_buffers = [0; RING_BUFFER_COUNT - 1];
+        if !spare_buffers.is_empty() {
+            gl::call!(gl::GenBuffers(
+                spare_buffers.len() as i32,
+                spare_buffers.as_mut_ptr(),
+            ));
         }
+        allocator.spare_buffers = spare_buffers.map(|buffer| (buffer, 0)).to_vec();
+        allocator
     }
 
     /// Returns the internal buffer of the bump allocator. If `leak` is true,
     /// the buffer is marked as "leaked" and not deleted when [Self] is dropped.
     pub fn get_buffer(&mut self, leak: bool) -> gl::types::GLuint {
+        assert!(!self.ring, "ring buffers switch their buffer on clear");
         self.buffer_leaked |= leak;
         self.buffer
     }
@@ -39,11 +95,16 @@ impl BumpAllocatedBuffer {
     /// returns the buffer object and offset into it, where the bytes were
     /// written.
     pub fn allocate_buffer(&mut self, bytes: &[u8]) -> (gl::types::GLuint, usize) {
-        if self.offset + bytes.len() >= self.size {
-            let additional = bytes.len() + self.size;
+        let unaligned_offset = self.offset;
+        let aligned_offset = align_offset(self.offset, self.alignment);
+        self.padding += aligned_offset - self.offset;
+        self.offset = aligned_offset;
+        self.data_copy.resize(self.offset, 0);
+        if self.offset + bytes.len() > self.size {
             let original_size = self.size;
-            self.size += additional;
-            self.data_copy.reserve_exact(additional);
+            self.size = grown_size(self.size, self.offset + bytes.len());
+            self.data_copy
+                .reserve_exact(self.size - self.data_copy.len());
             gl::call!(gl::BindBuffer(self.target, self.buffer));
             gl::call!(gl::BufferData(
                 self.target,
@@ -59,21 +120,96 @@ impl BumpAllocatedBuffer {
             ));
         }
         let upload_offset = self.offset;
+        self.data_copy.extend_from_slice(bytes);
+        self.offset += bytes.len();
+        // The padding is uploaded too, so that the buffer always matches
+        // data_copy, see [BumpAllocatedBuffer::validate_contents]
+        let uploaded = &self.data_copy[unaligned_offset..];
         gl::call!(gl::BindBuffer(self.target, self.buffer));
         gl::call!(gl::BufferSubData(
             self.target,
-            upload_offset as isize,
-            bytes.len() as isize,
-            bytes.as_ptr() as *const c_void,
+            unaligned_offset as isize,
+            uploaded.len() as isize,
+            uploaded.as_ptr() as *const c_void,
         ));
-        self.data_copy.extend_from_slice(bytes);
-        self.offset += bytes.len();
         (self.buffer, upload_offset)
     }
 
+    /// The amount of bytes written since the last clear, including alignment
+    /// padding.
+    pub fn allocated_bytes(&self) -> usize {
+        self.offset
+    }
+
+    /// The size of the backing buffers.
+    pub fn capacity_bytes(&self) -> usize {
+        let spare_size: usize = self.spare_buffers.iter().map(|(_, size)| size).sum();
+        self.size + spare_size
+    }
+
+    /// The bytes lost to alignment padding, relative to the size of the
+    /// backing buffers.
+    pub fn fragmentation_ratio(&self) -> f32 {
+        let capacity = self.capacity_bytes();
+        if capacity == 0 {
+            0.0
+        } else {
+            self.padding as f32 / capacity as f32
+        }
+    }
+
+    /// Reads `len` bytes starting from `offset` back from the backing buffer,
+    /// for debugging. Stalls until the GPU is done with the buffer. WebGL
+    /// can't map buffers for reading, so this isn't available there.
+    #[cfg(all(debug_assertions, not(target_family = "wasm")))]
+    pub fn read_back(&self, offset: usize, len: usize) -> Vec<u8> {
+        assert!(
+            offset + len <= self.size,
+            "reading past the end of the buffer"
+        );
+        if len == 0 {
+            return Vec::new();
+        }
+        gl::call!(gl::BindBuffer(self.target, self.buffer));
+        let mapped = gl::call!(gl::MapBufferRange(
+            self.target,
+            offset as isize,
+            len as isize,
+            gl::MAP_READ_BIT,
+        ));
+        assert!(!mapped.is_null(), "glMapBufferRange failed");
+        let bytes = unsafe { std::slice::from_raw_parts(mapped as *const u8, len) }.to_vec();
+        gl::call!(gl::UnmapBuffer(self.target));
+        bytes
+    }
+
+    /// Checks that the backing buffer contains what was written since the
+    /// last clear, for catching drivers that silently drop writes.
+    #[cfg(all(debug_assertions, not(target_family = "wasm")))]
+    pub fn validate_contents(&self) -> bool {
+        self.read_back(0, self.offset) == self.data_copy
+    }
+
     pub fn clear(&mut self) {
         self.offset = 0;
+        self.padding = 0;
         self.data_copy.clear();
+        if self.ring {
+            if !self.spare_buffers.is_empty() {
+                self.spare_buffers.push((self.buffer, self.size));
+                (self.buffer, self.size) = self.spare_buffers.remove(0);
+            }
+            if self.size > 0 {
+                // Orphan the old storage, the GPU might still be using it
+                gl::call!(gl::BindBuffer(self.target, self.buffer));
+                gl::call!(gl::BufferData(
+                    self.target,
+                    self.size as isize,
+                    ptr::null(),
+                    self.usage,
+                ));
+            }
+        }
     }
 }
 
@@ -82,5 +218,92 @@ impl Drop for BumpAllocatedBuffer {
         if !self.buffer_leaked {
             gl::call!(gl::DeleteBuffers(1, &self.buffer));
         }
+        for (buffer, _) in &self.spare_buffers {
+            gl::call!(gl::DeleteBuffers(1, buffer));
+        }
+    }
+}
+
+/// Rounds the offset up to the next multiple of `alignment`.
+fn align_offset(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+/// The size the backing buffer should grow to, from `size`, to fit
+/// `required` bytes.
+fn grown_size(size: usize, required: usize) -> usize {
+    (size * 2).max(required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{align_offset, grown_size};
+
+    #[test]
+    fn aligned_offsets() {
+        for alignment in [1, 4, 256] {
+            let mut offset = 0;
+            for size in [48, 1, 256, 300, 0, 7] {
+                offset = align_offset(offset, alignment);
+                assert_eq!(0, offset % alignment);
+                offset += size;
+            }
+        }
+        assert_eq!(0, align_offset(0, 256));
+        assert_eq!(256, align_offset(48, 256));
+        assert_eq!(256, align_offset(256, 256));
+        assert_eq!(49, align_offset(49, 1));
+    }
+
+    #[test]
+    fn grown_buffer_fits_aligned_allocations() {
+        // Like BumpAllocatedBuffer::allocate_buffer with a uniform buffer
+        let (mut offset, mut size, mut grow_count) = (0, 0, 0);
+        for _ in 0..10 {
+            offset = align_offset(offset, 256);
+            if offset + 64 > size {
+                size = grown_size(size, offset + 64);
+                grow_count += 1;
+            }
+            offset += 64;
+            assert!(offset <= size);
+        }
+        assert_eq!(9 * 256 + 64, offset);
+        // Doubling, so the buffer doesn't need to grow on every allocation
+        assert!(grow_count < 10);
+    }
+
+    /// Needs a display for the GL context, so it's only run with
+    /// `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    #[cfg(debug_assertions)]
+    fn read_back_written_data() {
+        use super::BumpAllocatedBuffer;
+        use crate::renderer::gl;
+
+        let sdl_context = sdl2::init().unwrap();
+        let video = sdl_context.video().unwrap();
+        let gl_attr = video.gl_attr();
+        gl_attr.set_context_profile(sdl2::video::GLProfile::GLES);
+        gl_attr.set_context_version(3, 0);
+        let window = video
+            .window("bumpalloc_buffer test", 16, 16)
+            .opengl()
+            .hidden()
+            .build()
+            .unwrap();
+        let _gl_context = window.gl_create_context().unwrap();
+        gl::load_with(|s| video.gl_get_proc_address(s) as *const core::ffi::c_void);
+
+        let mut allocator = BumpAllocatedBuffer::new_aligned(gl::ARRAY_BUFFER, gl::DYNAMIC_DRAW, 4);
+        let first = [1u8, 2, 3];
+        let second = (0..=255).collect::<Vec<u8>>();
+        let (_, first_offset) = allocator.allocate_buffer(&first);
+        // Big enough to grow the buffer, which copies the first allocation
+        let (_, second_offset) = allocator.allocate_buffer(&second);
+        assert_eq!(first, allocator.read_back(first_offset, first.len())[..]);
+        assert_eq!(second, allocator.read_back(second_offset, second.len()));
+        assert!(allocator.validate_contents());
     }
 }
@@ -1,6 +1,30 @@
 use std::f32::consts::{FRAC_PI_2, TAU};
 
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+/// The bounds the camera's controls are clamped to.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraLimits {
+    /// The minimum pitch, in radians.
+    pub min_pitch: f32,
+    /// The maximum pitch, in radians.
+    pub max_pitch: f32,
+    /// The minimum distance from the focus point.
+    pub min_distance: f32,
+    /// The maximum distance from the focus point.
+    pub max_distance: f32,
+}
+
+impl Default for CameraLimits {
+    fn default() -> Self {
+        CameraLimits {
+            min_pitch: 30.0 / 360.0 * TAU,
+            max_pitch: 90.0 / 360.0 * TAU,
+            min_distance: 10.0,
+            max_distance: 100.0,
+        }
+    }
+}
 
 pub struct Camera {
     /// Distance from the focus point.
@@ -11,6 +35,25 @@ pub struct Camera {
     pub pitch: f32,
     /// The point at the center of the screen.
     pub focus: Vec3,
+    /// How fast `focus` is moving, in world units per second.
+    pub velocity: Vec3,
+    /// How fast `yaw` (x) and `pitch` (y) are changing, in radians per second.
+    pub angular_velocity: Vec2,
+    /// How quickly the velocities slow down. Higher values stop the camera
+    /// faster.
+    pub damping: f32,
+    /// If set, `focus` moves smoothly towards this point until it gets there.
+    pub focus_target: Option<Vec3>,
+    /// How fast `focus` moves towards `focus_target`.
+    pub focus_blend_speed: f32,
+    /// If true, the camera uses an orthographic projection instead of a
+    /// perspective one, for a blueprint-like view.
+    pub orthographic: bool,
+    /// How far the camera is shaken from its position, in world units. Zero
+    /// for a steady camera.
+    pub shake: f32,
+    shake_time: f32,
+    limits: CameraLimits,
 }
 
 impl Camera {
@@ -20,17 +63,80 @@ impl Camera {
             yaw: TAU * 0.25,
             pitch: FRAC_PI_2 * 0.7,
             focus: Vec3::Y * 1.5,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec2::ZERO,
+            damping: 8.0,
+            focus_target: None,
+            focus_blend_speed: 5.0,
+            orthographic: false,
+            shake: 0.0,
+            shake_time: 0.0,
+            limits: CameraLimits::default(),
         }
     }
 
+    /// Sets the limits and clamps the current pitch and distance to them.
+    pub fn set_limits(&mut self, limits: CameraLimits) {
+        self.limits = limits;
+        self.pitch = self.pitch.clamp(limits.min_pitch, limits.max_pitch);
+        self.set_distance(self.distance);
+    }
+
+    /// Sets the distance from the focus point, clamped to the limits.
+    pub fn set_distance(&mut self, distance: f32) {
+        let CameraLimits {
+            min_distance,
+            max_distance,
+            ..
+        } = self.limits;
+        self.distance = distance.clamp(min_distance, max_distance);
+    }
+
+    /// Moves and rotates the camera based on its velocities, and slows them
+    /// down.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(target) = self.focus_target {
+            self.focus = self
+                .focus
+                .lerp(target, (self.focus_blend_speed * dt).min(1.0));
+            if self.focus.distance(target) < 0.01 {
+                self.focus = target;
+                self.focus_target = None;
+            }
+        }
+        // TODO: Use the bounds of all the rooms here
+        self.focus = (self.focus + self.velocity * dt).clamp(Vec3::ONE * -10.0, Vec3::ONE * 10.0);
+        self.yaw += self.angular_velocity.x * dt;
+        self.pitch = (self.pitch + self.angular_velocity.y * dt)
+            .clamp(self.limits.min_pitch, self.limits.max_pitch);
+        self.shake_time += dt;
+        let falloff = (1.0 - self.damping * dt).max(0.0);
+        self.velocity *= falloff;
+        self.angular_velocity *= falloff;
+    }
+
+    /// The direction from the focus towards the camera, normalized.
+    pub fn eye_direction(&self) -> Vec3 {
+        Quat::from_rotation_y(-self.yaw) * Quat::from_rotation_x(-self.pitch) * Vec3::Z
+    }
+
+    /// The position of the camera, without the shake.
+    pub fn eye_position(&self) -> Vec3 {
+        self.focus + self.eye_direction() * self.distance
+    }
+
     pub fn view_matrix(&self) -> Mat4 {
         let camera_rot =
             Quat::from_rotation_x(-self.pitch) * Quat::from_rotation_y(self.yaw + TAU / 2.0);
-        let camera_pos = self.focus
-            + Quat::from_rotation_y(-self.yaw)
-                * Quat::from_rotation_x(-self.pitch)
-                * Vec3::Z
-                * self.distance;
+        let camera_pos = self.eye_position();
+        // Incommensurate frequencies, so that the shake doesn't look periodic
+        let t = self.shake_time;
+        let shake = Vec3::new(
+            (t * 37.0).sin() + (t * 23.0).sin() * 0.5,
+            (t * 41.0).sin() + (t * 29.0).cos() * 0.5,
+            (t * 31.0).cos() + (t * 43.0).sin() * 0.5,
+        ) * self.shake;
+        let camera_pos = camera_pos + shake;
         Mat4::from_quat(-camera_rot) * Mat4::from_translation(-camera_pos)
     }
 }
@@ -0,0 +1,175 @@
+use crate::renderer::framebuffer::{create_fullscreen_program, Framebuffer};
+use crate::renderer::gl;
+use glam::Mat4;
+
+/// Blurs the parts of the scene that are out of focus, for a miniature-like
+/// look. The near and far out-of-focus layers are blurred separately, so that
+/// the things in focus don't bleed into the blur behind them.
+pub struct DofPass {
+    coc_fbo: Framebuffer,
+    blur_near_fbo: Framebuffer,
+    blur_far_fbo: Framebuffer,
+    /// The output of the first blur pass of each layer, and then the result
+    /// of the composite pass.
+    blur_temp_fbo: Framebuffer,
+    coc_shader: gl::types::GLuint,
+    blur_shader: gl::types::GLuint,
+    composite_shader: gl::types::GLuint,
+    view_from_clip_location: gl::types::GLint,
+    focus_distance_location: gl::types::GLint,
+    aperture_location: gl::types::GLint,
+    coc_channel_location: gl::types::GLint,
+    blur_direction_location: gl::types::GLint,
+    vao: gl::types::GLuint,
+    sampler: gl::types::GLuint,
+    /// The distance from the camera that's in focus, in world units.
+    pub focus_distance: f32,
+    /// How quickly things blur away from the focal plane. Zero means that
+    /// everything is in focus.
+    pub aperture: f32,
+}
+
+impl DofPass {
+    pub fn new() -> DofPass {
+        let coc_shader = create_fullscreen_program(include_str!("dof_coc_fragment.glsl"));
+        let blur_shader = create_fullscreen_program(include_str!("dof_blur_fragment.glsl"));
+        let composite_shader =
+            create_fullscreen_program(include_str!("dof_composite_fragment.glsl"));
+        for (program, textures) in [
+            (coc_shader, &["depth_tex"][..]),
+            (blur_shader, &["color_tex", "coc_tex"][..]),
+            (
+                composite_shader,
+                &["color_tex", "coc_tex", "blur_near_tex", "blur_far_tex"][..],
+            ),
+        ] {
+            gl::call!(gl::UseProgram(program));
+            for (unit, name) in textures.iter().enumerate() {
+                if let Some(location) = gl::get_uniform_location(program, name) {
+                    gl::call!(gl::Uniform1i(location, unit as i32));
+                }
+            }
+        }
+        let location = |program, name| gl::get_uniform_location(program, name).unwrap();
+
+        // The vertices are generated from gl_VertexID, like in FullscreenQuad
+        let mut vao = 0;
+        gl::call!(gl::GenVertexArrays(1, &mut vao));
+        let mut sampler = 0;
+        gl::call!(gl::GenSamplers(1, &mut sampler));
+        gl::setup_nearest_sampler(sampler);
+
+        DofPass {
+            coc_fbo: Framebuffer::new(1, 1),
+            blur_near_fbo: Framebuffer::new(1, 1),
+            blur_far_fbo: Framebuffer::new(1, 1),
+            blur_temp_fbo: Framebuffer::new(1, 1),
+            coc_shader,
+            blur_shader,
+            composite_shader,
+            view_from_clip_location: location(coc_shader, "view_from_clip"),
+            focus_distance_location: location(coc_shader, "focus_distance"),
+            aperture_location: location(coc_shader, "aperture"),
+            coc_channel_location: location(blur_shader, "coc_channel"),
+            blur_direction_location: location(blur_shader, "direction"),
+            vao,
+            sampler,
+            focus_distance: 30.0,
+            aperture: 0.0,
+        }
+    }
+
+    /// Blurs the out-of-focus parts of the scene color, with the depth from
+    /// the scene framebuffer, and returns the texture with the result. The
+    /// color may already be post-processed, so it's passed separately. Leaves
+    /// one of the pass's framebuffers bound, so the render target and viewport
+    /// need to be reset afterwards.
+    pub fn run(
+        &mut self,
+        scene_fbo: &Framebuffer,
+        color_tex: gl::types::GLuint,
+        proj_from_view: Mat4,
+    ) -> gl::types::GLuint {
+        let (width, height) = (scene_fbo.width, scene_fbo.height);
+        self.coc_fbo.resize(width, height);
+        self.blur_near_fbo.resize(width, height);
+        self.blur_far_fbo.resize(width, height);
+        self.blur_temp_fbo.resize(width, height);
+
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::BindVertexArray(self.vao));
+        for unit in 0..4 {
+            gl::call!(gl::BindSampler(unit, self.sampler));
+        }
+
+        self.coc_fbo.bind();
+        gl::call!(gl::UseProgram(self.coc_shader));
+        gl::call!(gl::UniformMatrix4fv(
+            self.view_from_clip_location,
+            1,
+            gl::FALSE,
+            proj_from_view.inverse().to_cols_array().as_ptr(),
+        ));
+        gl::call!(gl::Uniform1f(
+            self.focus_distance_location,
+            self.focus_distance
+        ));
+        gl::call!(gl::Uniform1f(self.aperture_location, self.aperture));
+        bind_textures(&[scene_fbo.depth_tex]);
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+        gl::call!(gl::UseProgram(self.blur_shader));
+        for (coc_channel, layer_fbo) in [(0, &self.blur_near_fbo), (1, &self.blur_far_fbo)] {
+            self.blur_temp_fbo.bind();
+            gl::call!(gl::Uniform1i(self.coc_channel_location, coc_channel));
+            gl::call!(gl::Uniform2f(self.blur_direction_location, 1.0, 0.0));
+            bind_textures(&[color_tex, self.coc_fbo.color_tex]);
+            gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+            // The first pass wrote its circle of confusion in the alpha
+            layer_fbo.bind();
+            gl::call!(gl::Uniform1i(self.coc_channel_location, 3));
+            gl::call!(gl::Uniform2f(self.blur_direction_location, 0.0, 1.0));
+            let temp_tex = self.blur_temp_fbo.color_tex;
+            bind_textures(&[temp_tex, temp_tex]);
+            gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+        }
+
+        self.blur_temp_fbo.bind();
+        gl::call!(gl::UseProgram(self.composite_shader));
+        bind_textures(&[
+            color_tex,
+            self.coc_fbo.color_tex,
+            self.blur_near_fbo.color_tex,
+            self.blur_far_fbo.color_tex,
+        ]);
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+        for unit in 0..4 {
+            gl::call!(gl::BindSampler(unit, 0));
+        }
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindVertexArray(0));
+        self.blur_temp_fbo.color_tex
+    }
+}
+
+impl Drop for DofPass {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteProgram(self.coc_shader));
+        gl::call!(gl::DeleteProgram(self.blur_shader));
+        gl::call!(gl::DeleteProgram(self.composite_shader));
+        gl::call!(gl::DeleteVertexArrays(1, &self.vao));
+        gl::call!(gl::DeleteSamplers(1, &self.sampler));
+    }
+}
+
+/// Binds the textures to the texture units starting from 0.
+fn bind_textures(textures: &[gl::types::GLuint]) {
+    for (unit, &texture) in textures.iter().enumerate() {
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0 + unit as u32));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+    }
+}
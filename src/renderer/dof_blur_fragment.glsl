@@ -0,0 +1,36 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+uniform sampler2D coc_tex;
+// Which channel of coc_tex has the circle of confusion: 0 for the near
+// layer, 1 for the far layer, and 3 for the output of the first blur pass.
+uniform int coc_channel;
+// (1, 0) for the horizontal pass, (0, 1) for the vertical one.
+uniform vec2 direction;
+
+// The blur radius at the maximum circle of confusion, in pixels.
+#define MAX_RADIUS 8.0
+
+const float WEIGHTS[5] =
+    float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+  vec2 texel_step =
+      direction * (MAX_RADIUS / 4.0) / vec2(textureSize(color_tex, 0));
+  vec3 color = vec3(0.0);
+  float coc_sum = 0.0;
+  for (int i = -4; i <= 4; i++) {
+    vec2 uv = tex_coords + texel_step * float(i);
+    // Each sample is weighted by its own circle of confusion, so that sharp
+    // pixels don't bleed into the blurred layer.
+    float weight = WEIGHTS[abs(i)] * texture(coc_tex, uv)[coc_channel];
+    color += texture(color_tex, uv).rgb * weight;
+    coc_sum += weight;
+  }
+  FRAG_COLOR = vec4(color / max(coc_sum, 0.0001), coc_sum);
+}
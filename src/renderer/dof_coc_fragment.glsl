@@ -0,0 +1,25 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D depth_tex;
+uniform mat4 view_from_clip;
+uniform float focus_distance;
+uniform float aperture;
+
+void main() {
+  float depth = texture(depth_tex, tex_coords).r;
+  vec4 clip_pos = vec4(vec3(tex_coords, depth) * 2.0 - 1.0, 1.0);
+  vec4 view_pos = view_from_clip * clip_pos;
+  float view_distance = length(view_pos.xyz / view_pos.w);
+
+  // Like with a real lens, the blur grows with the aperture and with the
+  // distance from the focal plane, relative to the distance from the camera.
+  float coc = aperture * (view_distance - focus_distance) / view_distance;
+  float near_coc = clamp(-coc, 0.0, 1.0);
+  float far_coc = clamp(coc, 0.0, 1.0);
+  FRAG_COLOR = vec4(near_coc, far_coc, 0.0, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+uniform sampler2D coc_tex;
+uniform sampler2D blur_near_tex;
+uniform sampler2D blur_far_tex;
+
+void main() {
+  vec3 color = texture(color_tex, tex_coords).rgb;
+  vec4 far = texture(blur_far_tex, tex_coords);
+  color = mix(color, far.rgb, texture(coc_tex, tex_coords).g);
+  // The near layer is spread over the sharp pixels behind it, so it's mixed
+  // in by its own blurred circle of confusion instead of the pixel's.
+  vec4 near = texture(blur_near_tex, tex_coords);
+  color = mix(color, near.rgb, clamp(near.a * 2.0, 0.0, 1.0));
+  FRAG_COLOR = vec4(color, 1.0);
+}
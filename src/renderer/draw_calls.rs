@@ -1,19 +1,24 @@
-use crate::renderer::bumpalloc_buffer::BumpAllocatedBuffer;
-use crate::renderer::{gl, gltf};
+use crate::math::Frustum;
+use crate::renderer::bumpalloc_buffer::{BumpAllocatedBuffer, UNIFORM_BUFFER_ALIGNMENT};
+use crate::renderer::{gl, gltf, RendererMemoryStats};
 use bytemuck::Zeroable;
-use glam::{Mat4, Vec4};
+use glam::{Mat4, Vec3, Vec4};
 use std::collections::HashMap;
 use std::ffi::c_void;
+use std::ops::AddAssign;
 use std::{mem, ptr};
 
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Uniforms {
     /// The OpenGL textures to bind at GL_TEXTURE0 + i where each element is
     /// of this array is `(i, texture_object, sampler_object)`.
-    pub textures: [Option<(u32, u32, u32)>; 5],
+    pub textures: [Option<(u32, u32, u32)>; 6],
     /// The OpenGL uniform buffers `buffer` to bind at indices `i`, where each
-    /// element of this array is `(i, buffer, offset, size)`.
-    pub ubos: [Option<(u32, u32, usize, usize)>; 1],
+    /// element of this array is `(i, buffer, offset, size)`. The first one is
+    /// the material, the second one is the skin of skinned meshes, and the
+    /// third one is the morph target weights of morphed meshes. If the skin or
+    /// the morph weights are None, defaults are bound instead.
+    pub ubos: [Option<(u32, u32, usize, usize)>; 3],
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -29,9 +34,44 @@ pub struct DrawCall {
     /// needs to be provided at draw-time, and can't be saved in the VAO. So
     /// this holds the location of the vertex color attribute, if it's disabled.
     pub disabled_all_ones_vertex_attribute: Option<gl::types::GLuint>,
+    /// Like `disabled_all_ones_vertex_attribute`, but for attributes that
+    /// should default to all zeroes, i.e. the joint weights of unskinned
+    /// meshes.
+    pub disabled_all_zeros_vertex_attribute: Option<gl::types::GLuint>,
     pub front_face: gl::types::GLenum,
 }
 
+/// Counts of what was drawn, for profiling.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+    pub triangles: u64,
+}
+
+impl FrameStats {
+    pub fn record_draw(
+        &mut self,
+        mode: gl::types::GLenum,
+        index_count: gl::types::GLint,
+        instances: gl::types::GLsizei,
+    ) {
+        self.draw_calls += 1;
+        self.instances += instances as u32;
+        if mode == gl::TRIANGLES {
+            self.triangles += index_count as u64 / 3 * instances as u64;
+        }
+    }
+}
+
+impl AddAssign for FrameStats {
+    fn add_assign(&mut self, other: FrameStats) {
+        self.draw_calls += other.draw_calls;
+        self.instances += other.instances;
+        self.triangles += other.triangles;
+    }
+}
+
 #[derive(Default)]
 struct InstanceData {
     transforms: Vec<Mat4>,
@@ -39,26 +79,68 @@ struct InstanceData {
     count: gl::types::GLsizei,
 }
 
+/// The instances to draw, grouped by their uniforms and draw calls, so that
+/// each group can be drawn with one instanced draw call.
+type Batches = HashMap<Uniforms, HashMap<DrawCall, InstanceData>>;
+
 /// Stores the required information for rendering a set of primitives with
 /// various materials, in a form that's optimized for minimum state changes
 /// during rendering.
 pub struct DrawCalls {
-    draws: HashMap<Uniforms, HashMap<DrawCall, InstanceData>>,
+    draws: Batches,
     temp_buffer: BumpAllocatedBuffer,
+    /// Per-frame uniform blocks, padded so that each one starts at an offset
+    /// that's aligned for binding.
+    uniform_buffer: BumpAllocatedBuffer,
     lights_ubo: gltf::UniformBlockLights,
     lights_count: usize,
+    /// If set, draws with bounding spheres outside of this frustum are
+    /// skipped in [DrawCalls::add].
+    frustum: Option<Frustum>,
+    /// The wireframe overlay drawn by [DrawCalls::draw_wireframe], if enabled.
+    wireframe: Option<Box<DrawCalls>>,
 }
 
 impl DrawCalls {
     pub fn new() -> DrawCalls {
         DrawCalls {
             draws: HashMap::new(),
-            temp_buffer: BumpAllocatedBuffer::new(gl::ARRAY_BUFFER, gl::STREAM_DRAW),
+            temp_buffer: BumpAllocatedBuffer::new_ring(gl::ARRAY_BUFFER, gl::STREAM_DRAW),
+            uniform_buffer: BumpAllocatedBuffer::new_aligned(
+                gl::UNIFORM_BUFFER,
+                gl::STREAM_DRAW,
+                UNIFORM_BUFFER_ALIGNMENT,
+            ),
             lights_ubo: gltf::UniformBlockLights::zeroed(),
             lights_count: 0,
+            frustum: None,
+            wireframe: None,
         }
     }
 
+    /// Enables or disables collecting the wireframe draw calls of the
+    /// primitives added to these draw calls.
+    pub fn set_wireframe(&mut self, enabled: bool) {
+        if enabled && self.wireframe.is_none() {
+            self.wireframe = Some(Box::new(DrawCalls::new()));
+        } else if !enabled {
+            self.wireframe = None;
+        }
+    }
+
+    /// Returns the draw calls where the wireframe draw calls should be added,
+    /// if the wireframe is enabled.
+    pub fn wireframe_draw_calls(&mut self) -> Option<&mut DrawCalls> {
+        self.wireframe.as_deref_mut()
+    }
+
+    /// Sets the frustum used for culling, in the same space as the model
+    /// transforms passed to [DrawCalls::add].
+    pub fn set_frustum(&mut self, frustum: Option<Frustum>) {
+        self.frustum = frustum;
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn add(
         &mut self,
         lights: Option<&gltf::UniformBlockLights>,
@@ -67,6 +149,7 @@ impl DrawCalls {
         model_transfrom: Mat4,
         primitive_transfrom: Mat4,
         texcoord_transform: Mat4,
+        bounding_sphere: Option<(Vec3, f32)>,
     ) {
         if let Some(lights) = lights {
             for i in 0..gltf::MAX_LIGHTS {
@@ -100,37 +183,44 @@ impl DrawCalls {
                 }
             }
         }
-        let draw = if let Some(draw) = self.draws.get_mut(uniforms) {
-            draw
-        } else {
-            self.draws.entry(uniforms.clone()).or_default()
-        };
-        let mut draw_call = if let Some(draw_call) = draw.get_mut(draw_call) {
-            draw_call
-        } else {
-            draw.entry(draw_call.clone()).or_default()
-        };
-        draw_call.count += 1;
-        draw_call.transforms.push(primitive_transfrom);
-        draw_call.texcoord_transforms.push(texcoord_transform);
+        // Lights shine outside of their model's bounds, so they're included
+        // even if the model itself is culled.
+        if let (Some(frustum), Some((center, radius))) = (&self.frustum, bounding_sphere) {
+            let center = model_transfrom.transform_point3(center);
+            let (scale, _, _) = model_transfrom.to_scale_rotation_translation();
+            if !frustum.intersects_sphere(center, radius * scale.max_element()) {
+                return;
+            }
+        }
+        add_instance(
+            &mut self.draws,
+            uniforms,
+            draw_call,
+            primitive_transfrom,
+            texcoord_transform,
+        );
+    }
+
+    /// Returns how many instanced draw calls [DrawCalls::draw] would make
+    /// with the currently added instances.
+    pub fn batch_count(&self) -> usize {
+        batch_count(&self.draws)
+    }
+
+    /// Uploads the uniform block for the current frame, returning the buffer
+    /// and offset it can be bound from. The block should be one of the
+    /// `UniformBlock*` structs.
+    pub fn allocate_uniform_block(&mut self, bytes: &[u8]) -> (gl::types::GLuint, usize) {
+        self.uniform_buffer.allocate_buffer(bytes)
     }
 
     pub fn draw(
         &mut self,
         model_transform_attrib_locations: [u32; 4],
         texcoord_transform_attrib_locations: [u32; 4],
-    ) {
-        let lights = [self.lights_ubo];
-        let lights = bytemuck::cast_slice(&lights);
-        let (lights_buf, lights_off) = self.temp_buffer.allocate_buffer(lights);
-        gl::call!(gl::BindBufferRange(
-            gl::UNIFORM_BUFFER,
-            gltf::UNIFORM_BLOCK_LIGHTS,
-            lights_buf,
-            lights_off as isize,
-            lights.len() as isize,
-        ));
-
+    ) -> FrameStats {
+        let stats = frame_stats(&self.draws);
+        let default_ubos = self.upload_shared_uniform_blocks();
         for (uniforms, draw_calls) in &self.draws {
             let empty_draw = draw_calls
                 .values()
@@ -138,85 +228,151 @@ impl DrawCalls {
             if empty_draw {
                 continue;
             }
-
-            for (binding, texture, sampler) in uniforms.textures.iter().flatten() {
-                gl::call!(gl::ActiveTexture(
-                    gl::TEXTURE0 + *binding as gl::types::GLenum
-                ));
-                gl::call!(gl::BindTexture(gl::TEXTURE_2D, *texture));
-                gl::call!(gl::BindSampler(*binding as u32, *sampler));
-            }
-
-            for &(index, buffer, offset, size) in uniforms.ubos.iter().flatten() {
-                gl::call!(gl::BindBufferRange(
-                    gl::UNIFORM_BUFFER,
-                    index,
-                    buffer,
-                    offset as isize,
-                    size as isize,
-                ));
+            bind_uniforms(uniforms, &default_ubos);
+            for (draw_call, instance_data) in draw_calls {
+                draw_instances(
+                    &mut self.temp_buffer,
+                    draw_call,
+                    &instance_data.transforms,
+                    &instance_data.texcoord_transforms,
+                    model_transform_attrib_locations,
+                    texcoord_transform_attrib_locations,
+                );
             }
+        }
+        stats
+    }
 
+    /// Like [DrawCalls::draw], but draws the instances back-to-front based on
+    /// the Z coordinate of their transforms, for blending translucent
+    /// primitives correctly. This assumes the view matrix is the identity
+    /// matrix, like it is for the UI. Consecutive instances with the same
+    /// uniforms and draw call are still batched together.
+    pub fn draw_sorted(
+        &mut self,
+        model_transform_attrib_locations: [u32; 4],
+        texcoord_transform_attrib_locations: [u32; 4],
+    ) -> FrameStats {
+        let mut stats = FrameStats::default();
+        let default_ubos = self.upload_shared_uniform_blocks();
+        let mut instances = Vec::new();
+        for (uniforms, draw_calls) in &self.draws {
             for (draw_call, instance_data) in draw_calls {
-                gl::call!(gl::BindVertexArray(draw_call.vao));
-                // Setup the transform vertex attribute
-                let transforms = bytemuck::cast_slice(&instance_data.transforms);
-                let (transforms_buffer, transforms_offset) =
-                    self.temp_buffer.allocate_buffer(transforms);
-                gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, transforms_buffer));
-                for i in 0..4 {
-                    let attrib_location = model_transform_attrib_locations[i];
-                    let offset = transforms_offset + mem::size_of::<Vec4>() * i;
-                    gl::call!(gl::EnableVertexAttribArray(attrib_location));
-                    gl::call!(gl::VertexAttribPointer(
-                        attrib_location,
-                        4,
-                        gl::FLOAT,
-                        gl::FALSE,
-                        mem::size_of::<Mat4>() as i32,
-                        ptr::null::<c_void>().add(offset)
-                    ));
-                    gl::call!(gl::VertexAttribDivisor(attrib_location, 1));
-                }
-                // Setup the texture coordinate transform vertex attribute
-                let tx_transforms = bytemuck::cast_slice(&instance_data.texcoord_transforms);
-                let (tx_transforms_buffer, tx_transforms_offset) =
-                    self.temp_buffer.allocate_buffer(tx_transforms);
-                gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, tx_transforms_buffer));
-                for i in 0..4 {
-                    let attrib_location = texcoord_transform_attrib_locations[i];
-                    let offset = tx_transforms_offset + mem::size_of::<Vec4>() * i;
-                    gl::call!(gl::EnableVertexAttribArray(attrib_location));
-                    gl::call!(gl::VertexAttribPointer(
-                        attrib_location,
-                        4,
-                        gl::FLOAT,
-                        gl::FALSE,
-                        mem::size_of::<Mat4>() as i32,
-                        ptr::null::<c_void>().add(offset)
-                    ));
-                    gl::call!(gl::VertexAttribDivisor(attrib_location, 1));
+                for (i, transform) in instance_data.transforms.iter().enumerate() {
+                    instances.push((transform.w_axis.z, uniforms, draw_call, instance_data, i));
                 }
-                // Set color vertex attribute default value
-                if let Some(location) = draw_call.disabled_all_ones_vertex_attribute {
-                    gl::call!(gl::VertexAttrib4f(location, 1.0, 1.0, 1.0, 1.0));
-                }
-                // Set the front face
-                gl::call!(gl::FrontFace(draw_call.front_face));
-                // Bind the index buffer
-                gl::call!(gl::BindBuffer(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    draw_call.index_buffer
-                ));
-                gl::call!(gl::DrawElementsInstanced(
-                    draw_call.mode,
-                    draw_call.index_count,
-                    draw_call.index_type,
-                    ptr::null::<c_void>().add(draw_call.index_byte_offset),
-                    instance_data.count
-                ));
             }
         }
+        // Stable, so instances at the same depth are drawn in the order they
+        // were added in
+        instances.sort_by(|(z_a, ..), (z_b, ..)| z_a.total_cmp(z_b));
+
+        let mut bound_uniforms = None;
+        let mut transforms = Vec::new();
+        let mut texcoord_transforms = Vec::new();
+        let mut start = 0;
+        while start < instances.len() {
+            let (_, uniforms, draw_call, ..) = instances[start];
+            let run_length = instances[start..]
+                .iter()
+                .take_while(|(_, u, d, ..)| *u == uniforms && *d == draw_call)
+                .count();
+            if bound_uniforms != Some(uniforms) {
+                bind_uniforms(uniforms, &default_ubos);
+                bound_uniforms = Some(uniforms);
+            }
+            transforms.clear();
+            texcoord_transforms.clear();
+            for &(_, _, _, instance_data, i) in &instances[start..start + run_length] {
+                transforms.push(instance_data.transforms[i]);
+                texcoord_transforms.push(instance_data.texcoord_transforms[i]);
+            }
+            stats.record_draw(draw_call.mode, draw_call.index_count, run_length as i32);
+            draw_instances(
+                &mut self.temp_buffer,
+                draw_call,
+                &transforms,
+                &texcoord_transforms,
+                model_transform_attrib_locations,
+                texcoord_transform_attrib_locations,
+            );
+            start += run_length;
+        }
+        stats
+    }
+
+    /// Draws the wireframe draw calls, if the wireframe is enabled. This
+    /// should be drawn after [DrawCalls::draw], which should have a polygon
+    /// offset pushing the triangles behind the lines.
+    pub fn draw_wireframe(
+        &mut self,
+        model_transform_attrib_locations: [u32; 4],
+        texcoord_transform_attrib_locations: [u32; 4],
+    ) -> FrameStats {
+        if let Some(wireframe) = &mut self.wireframe {
+            wireframe.draw(
+                model_transform_attrib_locations,
+                texcoord_transform_attrib_locations,
+            )
+        } else {
+            FrameStats::default()
+        }
+    }
+
+    /// Adds a light that shines everywhere from one direction, like the sun,
+    /// and returns its index in the lights uniform block. The direction is
+    /// the one the light travels in, in world space.
+    pub fn add_directional_light(&mut self, color: Vec3, intensity: f32, direction: Vec3) -> usize {
+        assert!(
+            self.lights_count < gltf::MAX_LIGHTS,
+            "scene lights overflowed"
+        );
+        let index = self.lights_count;
+        self.lights_ubo.color_and_kind[index] = color.extend(1.0);
+        self.lights_ubo.intensity_params[index] = Vec4::new(intensity, 0.0, 0.0, 0.0);
+        self.lights_ubo.position[index] = Vec4::W;
+        self.lights_ubo.direction[index] = direction.normalize().extend(0.0);
+        self.lights_count += 1;
+        index
+    }
+
+    /// Uploads and binds the lights, and returns the default uniform blocks
+    /// for [bind_uniforms].
+    fn upload_shared_uniform_blocks(&mut self) -> [Option<(u32, u32, usize, usize)>; 3] {
+        let lights = [self.lights_ubo];
+        let lights = bytemuck::cast_slice(&lights);
+        let (lights_buf, lights_off) = self.temp_buffer.allocate_buffer(lights);
+        gl::call!(gl::BindBufferRange(
+            gl::UNIFORM_BUFFER,
+            gltf::UNIFORM_BLOCK_LIGHTS,
+            lights_buf,
+            lights_off as isize,
+            lights.len() as isize,
+        ));
+
+        // Defaults for draws without skins or morph targets, since something
+        // needs to be bound to every uniform block.
+        let skin = [gltf::UniformBlockSkin {
+            joint_matrices: [Mat4::IDENTITY; gltf::MAX_JOINTS],
+        }];
+        let skin = bytemuck::cast_slice(&skin);
+        let (skin_buf, skin_off) = self.allocate_uniform_block(skin);
+        let morph = [gltf::UniformBlockMorph::zeroed()];
+        let morph = bytemuck::cast_slice(&morph);
+        let (morph_buf, morph_off) = self.allocate_uniform_block(morph);
+        [
+            None,
+            Some((gltf::UNIFORM_BLOCK_SKIN, skin_buf, skin_off, skin.len())),
+            Some((gltf::UNIFORM_BLOCK_MORPH, morph_buf, morph_off, morph.len())),
+        ]
+    }
+
+    pub fn add_memory_stats(&self, stats: &mut RendererMemoryStats) {
+        stats.add_buffer(&self.temp_buffer);
+        stats.add_buffer(&self.uniform_buffer);
+        if let Some(wireframe) = &self.wireframe {
+            wireframe.add_memory_stats(stats);
+        }
     }
 
     pub fn clear(&mut self) {
@@ -228,9 +384,266 @@ impl DrawCalls {
             }
         }
         self.temp_buffer.clear();
+        self.uniform_buffer.clear();
         for i in 0..self.lights_count {
             self.lights_ubo.color_and_kind[i].w = 0.0;
         }
         self.lights_count = 0;
+        if let Some(wireframe) = &mut self.wireframe {
+            wireframe.clear();
+        }
+    }
+}
+
+fn add_instance(
+    draws: &mut Batches,
+    uniforms: &Uniforms,
+    draw_call: &DrawCall,
+    primitive_transfrom: Mat4,
+    texcoord_transform: Mat4,
+) {
+    let draw = if let Some(draw) = draws.get_mut(uniforms) {
+        draw
+    } else {
+        draws.entry(uniforms.clone()).or_default()
+    };
+    let mut draw_call = if let Some(draw_call) = draw.get_mut(draw_call) {
+        draw_call
+    } else {
+        draw.entry(draw_call.clone()).or_default()
+    };
+    draw_call.count += 1;
+    draw_call.transforms.push(primitive_transfrom);
+    draw_call.texcoord_transforms.push(texcoord_transform);
+}
+
+fn batch_count(draws: &Batches) -> usize {
+    draws
+        .values()
+        .flat_map(|draw_calls| draw_calls.values())
+        .filter(|instance_data| instance_data.count > 0)
+        .count()
+}
+
+/// Counts what [DrawCalls::draw] draws for these batches: one draw call per
+/// batch, skipping the uniforms that have no instances at all.
+fn frame_stats(draws: &Batches) -> FrameStats {
+    let mut stats = FrameStats::default();
+    for draw_calls in draws.values() {
+        let empty_draw = draw_calls
+            .values()
+            .all(|instance| instance.transforms.is_empty());
+        if empty_draw {
+            continue;
+        }
+        for (draw_call, instance_data) in draw_calls {
+            stats.record_draw(draw_call.mode, draw_call.index_count, instance_data.count);
+        }
+    }
+    stats
+}
+
+fn bind_uniforms(uniforms: &Uniforms, default_ubos: &[Option<(u32, u32, usize, usize)>; 3]) {
+    for (binding, texture, sampler) in uniforms.textures.iter().flatten() {
+        gl::call!(gl::ActiveTexture(
+            gl::TEXTURE0 + *binding as gl::types::GLenum
+        ));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, *texture));
+        gl::call!(gl::BindSampler(*binding as u32, *sampler));
+    }
+
+    let ubos = uniforms.ubos.iter().zip(default_ubos);
+    for (index, buffer, offset, size) in ubos.flat_map(|(ubo, default)| ubo.or(*default)) {
+        gl::call!(gl::BindBufferRange(
+            gl::UNIFORM_BUFFER,
+            index,
+            buffer,
+            offset as isize,
+            size as isize,
+        ));
+    }
+}
+
+fn draw_instances(
+    temp_buffer: &mut BumpAllocatedBuffer,
+    draw_call: &DrawCall,
+    transforms: &[Mat4],
+    texcoord_transforms: &[Mat4],
+    model_transform_attrib_locations: [u32; 4],
+    texcoord_transform_attrib_locations: [u32; 4],
+) {
+    gl::call!(gl::BindVertexArray(draw_call.vao));
+    // Setup the transform vertex attribute
+    let (transforms_buffer, transforms_offset) =
+        temp_buffer.allocate_buffer(bytemuck::cast_slice(transforms));
+    gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, transforms_buffer));
+    for i in 0..4 {
+        let attrib_location = model_transform_attrib_locations[i];
+        let offset = transforms_offset + mem::size_of::<Vec4>() * i;
+        gl::call!(gl::EnableVertexAttribArray(attrib_location));
+        gl::call!(gl::VertexAttribPointer(
+            attrib_location,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            mem::size_of::<Mat4>() as i32,
+            ptr::null::<c_void>().add(offset)
+        ));
+        gl::call!(gl::VertexAttribDivisor(attrib_location, 1));
+    }
+    // Setup the texture coordinate transform vertex attribute
+    let (tx_transforms_buffer, tx_transforms_offset) =
+        temp_buffer.allocate_buffer(bytemuck::cast_slice(texcoord_transforms));
+    gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, tx_transforms_buffer));
+    for i in 0..4 {
+        let attrib_location = texcoord_transform_attrib_locations[i];
+        let offset = tx_transforms_offset + mem::size_of::<Vec4>() * i;
+        gl::call!(gl::EnableVertexAttribArray(attrib_location));
+        gl::call!(gl::VertexAttribPointer(
+            attrib_location,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            mem::size_of::<Mat4>() as i32,
+            ptr::null::<c_void>().add(offset)
+        ));
+        gl::call!(gl::VertexAttribDivisor(attrib_location, 1));
+    }
+    // Set color vertex attribute default value
+    if let Some(location) = draw_call.disabled_all_ones_vertex_attribute {
+        gl::call!(gl::VertexAttrib4f(location, 1.0, 1.0, 1.0, 1.0));
+    }
+    if let Some(location) = draw_call.disabled_all_zeros_vertex_attribute {
+        gl::call!(gl::VertexAttrib4f(location, 0.0, 0.0, 0.0, 0.0));
+    }
+    // Set the front face
+    gl::call!(gl::FrontFace(draw_call.front_face));
+    // Bind the index buffer
+    gl::call!(gl::BindBuffer(
+        gl::ELEMENT_ARRAY_BUFFER,
+        draw_call.index_buffer
+    ));
+    gl::call!(gl::DrawElementsInstanced(
+        draw_call.mode,
+        draw_call.index_count,
+        draw_call.index_type,
+        ptr::null::<c_void>().add(draw_call.index_byte_offset),
+        transforms.len() as gl::types::GLsizei
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_draw_call(vao: u32, mode: gl::types::GLenum, index_count: i32) -> DrawCall {
+        DrawCall {
+            vao,
+            mode,
+            index_buffer: 1,
+            index_type: gl::UNSIGNED_SHORT,
+            index_byte_offset: 0,
+            index_count,
+            disabled_all_ones_vertex_attribute: None,
+            disabled_all_zeros_vertex_attribute: None,
+            front_face: gl::CCW,
+        }
+    }
+
+    #[test]
+    fn test_frame_stats_triangles() {
+        // Ten quads and two cubes with one material, and four lines with
+        // another
+        let material = |offset| Uniforms {
+            textures: [None; 6],
+            ubos: [
+                Some((gltf::UNIFORM_BLOCK_MATERIAL, 1, offset, 64)),
+                None,
+                None,
+            ],
+        };
+        let quad = test_draw_call(1, gl::TRIANGLES, 6);
+        let cube = test_draw_call(2, gl::TRIANGLES, 36);
+        let line = test_draw_call(3, gl::LINES, 2);
+        let mut draws = Batches::new();
+        for _ in 0..10 {
+            add_instance(
+                &mut draws,
+                &material(0),
+                &quad,
+                Mat4::IDENTITY,
+                Mat4::IDENTITY,
+            );
+        }
+        for _ in 0..2 {
+            add_instance(
+                &mut draws,
+                &material(0),
+                &cube,
+                Mat4::IDENTITY,
+                Mat4::IDENTITY,
+            );
+        }
+        for _ in 0..4 {
+            add_instance(
+                &mut draws,
+                &material(64),
+                &line,
+                Mat4::IDENTITY,
+                Mat4::IDENTITY,
+            );
+        }
+        // Left over from an earlier frame, with no instances this frame
+        draws
+            .entry(material(128))
+            .or_default()
+            .insert(cube.clone(), InstanceData::default());
+        let expected = FrameStats {
+            draw_calls: 3,
+            instances: 16,
+            triangles: 10 * 2 + 2 * 12,
+        };
+        assert_eq!(expected, frame_stats(&draws));
+    }
+
+    #[test]
+    fn test_repeated_models_are_batched() {
+        // Six rooms of the same type, with five primitives each, all using
+        // the same material
+        let uniforms = Uniforms {
+            textures: [None; 6],
+            ubos: [Some((gltf::UNIFORM_BLOCK_MATERIAL, 1, 0, 64)), None, None],
+        };
+        let primitives = (0..5)
+            .map(|i| DrawCall {
+                vao: i + 1,
+                mode: gl::TRIANGLES,
+                index_buffer: 1,
+                index_type: gl::UNSIGNED_SHORT,
+                index_byte_offset: 0,
+                index_count: 36,
+                disabled_all_ones_vertex_attribute: None,
+                disabled_all_zeros_vertex_attribute: None,
+                front_face: gl::CCW,
+            })
+            .collect::<Vec<_>>();
+        let mut draws = Batches::new();
+        for room in 0..6 {
+            let room_transform = Mat4::from_translation(Vec3::X * room as f32 * 8.0);
+            for primitive in &primitives {
+                add_instance(
+                    &mut draws,
+                    &uniforms,
+                    primitive,
+                    room_transform,
+                    Mat4::IDENTITY,
+                );
+            }
+        }
+        // One instanced draw call per primitive, each with all the rooms
+        assert_eq!(5, batch_count(&draws));
+        for instance_data in draws[&uniforms].values() {
+            assert_eq!(6, instance_data.count);
+        }
     }
 }
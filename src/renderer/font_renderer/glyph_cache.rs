@@ -7,52 +7,83 @@ use fontdue::{
 };
 use glam::Vec4;
 
+/// The size of the texture at first, it's doubled whenever it fills up, up
+/// to [MAX_SIZE] or GL_MAX_TEXTURE_SIZE, whichever is smaller.
+const INITIAL_SIZE: u32 = 512;
+/// The biggest the texture grows to, even if the driver allows bigger
+/// textures. Past this, glyphs are evicted instead, which is cheaper than
+/// keeping hundreds of megabytes of mostly unused glyphs around.
+const MAX_SIZE: u32 = 2048;
+
 pub struct GlyphCache {
-    cache: HashMap<GlyphRasterConfig, (u32, u32, u32, u32)>,
+    /// The slots (x, y, width, height) of the glyphs in the texture, and the
+    /// value of `access_counter` when the glyph was last used. The slot can
+    /// be bigger than the glyph, if it was reused from an evicted glyph.
+    cache: HashMap<GlyphRasterConfig, (u32, u32, u32, u32, u64)>,
+    /// Incremented on every glyph lookup, for finding the least recently used
+    /// glyphs to evict when the texture is full.
+    access_counter: u64,
     cursor: (u32, u32),
     width: u32,
     height: u32,
+    /// The biggest the texture can grow, [MAX_SIZE] or GL_MAX_TEXTURE_SIZE.
+    max_size: u32,
     texture: u32,
+    /// Incremented when the texture is replaced with a bigger one. The glyphs
+    /// stay in the same place in the new texture, but the texture object and
+    /// the texture coordinates change.
+    pub atlas_generation: u64,
+    /// The textures replaced since the last
+    /// [GlyphCache::delete_replaced_textures], which might still be used by
+    /// the draw calls of the current frame.
+    replaced_textures: Vec<u32>,
     max_height_this_row: u32,
 }
 
 impl GlyphCache {
     pub fn new(texture: u32) -> GlyphCache {
-        let (width, height) = (4096, 4096);
-        let mut pixels = Vec::with_capacity((width * height) as usize);
-        for _ in 0..width * height {
-            pixels.push(0xFFu8);
-            pixels.push(0u8);
-            pixels.push(0xFFu8);
-            pixels.push(0u8);
-        }
-        gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
-        gl::call!(gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGBA as i32,
-            width as i32,
-            height as i32,
-            0,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
-            pixels.as_ptr() as *const c_void,
-        ));
+        let mut max_size = 0;
+        gl::call!(gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size));
+        let max_size = (max_size as u32).clamp(INITIAL_SIZE, MAX_SIZE);
+        allocate_texture(texture, INITIAL_SIZE, INITIAL_SIZE);
         GlyphCache {
             cache: HashMap::new(),
+            access_counter: 0,
             cursor: (0, 0),
-            width,
-            height,
+            width: INITIAL_SIZE,
+            height: INITIAL_SIZE,
+            max_size,
             texture,
+            atlas_generation: 0,
+            replaced_textures: Vec::new(),
             max_height_this_row: 0,
         }
     }
 
+    pub fn texture(&self) -> u32 {
+        self.texture
+    }
+
+    /// Deletes the textures replaced by bigger ones. Should be called when the
+    /// draw calls that might use them have been drawn.
+    pub fn delete_replaced_textures(&mut self) {
+        if !self.replaced_textures.is_empty() {
+            gl::call!(gl::DeleteTextures(
+                self.replaced_textures.len() as i32,
+                self.replaced_textures.as_ptr(),
+            ));
+            self.replaced_textures.clear();
+        }
+    }
+
     pub fn get_texcoord_transform(&mut self, glyph: &GlyphPosition<()>, fonts: &[Font]) -> Vec4 {
-        let (x, y, w, h) = if let Some(cached) = self.cache.get(&glyph.key) {
-            *cached
+        self.access_counter += 1;
+        let (w, h) = (glyph.width as u32, glyph.height as u32);
+        let (x, y) = if let Some(cached) = self.cache.get_mut(&glyph.key) {
+            cached.4 = self.access_counter;
+            (cached.0, cached.1)
         } else {
-            let (x, y, w, h) = self.reserve(glyph.width as u32, glyph.height as u32);
+            let (x, y, slot_w, slot_h) = self.reserve(w, h);
             let (_, pixels) = fonts[glyph.font_index].rasterize_config(glyph.key);
             let mut rgba_pixels = Vec::with_capacity(pixels.len() * 4);
             for pixel in pixels {
@@ -73,8 +104,9 @@ impl GlyphCache {
                 gl::UNSIGNED_BYTE,
                 rgba_pixels.as_ptr() as *const c_void,
             ));
-            self.cache.insert(glyph.key, (x, y, w, h));
-            (x, y, w, h)
+            let entry = (x, y, slot_w, slot_h, self.access_counter);
+            self.cache.insert(glyph.key, entry);
+            (x, y)
         };
 
         let (x, y, w, h) = (x as f32, y as f32, w as f32, h as f32);
@@ -82,18 +114,157 @@ impl GlyphCache {
         Vec4::new(x / tw, y / th, w / tw, h / th)
     }
 
+    /// Finds room for a glyph of the size, and returns the slot (x, y, width,
+    /// height) for it. Grows the texture if it's full, or evicts the least
+    /// recently used glyph if it can't grow anymore.
     fn reserve(&mut self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        if width == 0 || height == 0 {
+            // Whitespace doesn't need any room
+            return (0, 0, 0, 0);
+        }
+        if self.cursor.0 + width > self.width {
+            self.next_row();
+        }
+        if self.cursor.1 + height > self.height {
+            if self.width * 2 <= self.max_size {
+                self.grow();
+                return self.reserve(width, height);
+            }
+            return self.evict(width, height);
+        }
         let result = (self.cursor.0, self.cursor.1, width, height);
-        assert!(result.0 + result.2 <= self.width);
-        assert!(result.1 + result.3 <= self.height);
         if self.cursor.0 + width < self.width {
             self.cursor.0 += width + 1;
         } else {
-            self.cursor.0 = 0;
-            self.cursor.1 += self.max_height_this_row + 1;
-            self.max_height_this_row = 0;
+            self.next_row();
         }
         self.max_height_this_row = self.max_height_this_row.max(height);
         result
     }
+
+    /// Replaces the texture with one twice as big, with the glyphs copied to
+    /// the same places.
+    fn grow(&mut self) {
+        let (old_width, old_height) = (self.width, self.height);
+        self.width *= 2;
+        self.height *= 2;
+        let mut texture = 0;
+        gl::call!(gl::GenTextures(1, &mut texture));
+        allocate_texture(texture, self.width, self.height);
+
+        // glCopyTexSubImage2D copies from the read framebuffer, so the old
+        // texture needs to be attached to one
+        let mut previous_framebuffer = 0;
+        gl::call!(gl::GetIntegerv(
+            gl::READ_FRAMEBUFFER_BINDING,
+            &mut previous_framebuffer,
+        ));
+        let mut framebuffer = 0;
+        gl::call!(gl::GenFramebuffers(1, &mut framebuffer));
+        gl::call!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer));
+        gl::call!(gl::FramebufferTexture2D(
+            gl::READ_FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            self.texture,
+            0,
+        ));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+        gl::call!(gl::CopyTexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            0,
+            0,
+            0,
+            old_width as i32,
+            old_height as i32,
+        ));
+        gl::call!(gl::BindFramebuffer(
+            gl::READ_FRAMEBUFFER,
+            previous_framebuffer as u32,
+        ));
+        gl::call!(gl::DeleteFramebuffers(1, &framebuffer));
+
+        self.replaced_textures.push(self.texture);
+        self.texture = texture;
+        self.atlas_generation += 1;
+    }
+
+    fn next_row(&mut self) {
+        self.cursor.0 = 0;
+        self.cursor.1 += self.max_height_this_row + 1;
+        self.max_height_this_row = 0;
+    }
+
+    /// Frees up the slot of the least recently used glyph that's big enough
+    /// for the size, clears it, and returns it.
+    fn evict(&mut self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let lru = self
+            .cache
+            .iter()
+            .filter(|(_, &(_, _, w, h, _))| w >= width && h >= height)
+            .min_by_key(|(_, &(.., last_access))| last_access)
+            .map(|(key, _)| *key);
+        let Some(key) = lru else {
+            // Every glyph is smaller than this one, so start over with an
+            // empty texture. Glyphs already drawn this frame might look
+            // wrong, but only for this frame.
+            self.cache.clear();
+            self.clear(0, 0, self.width, self.height);
+            self.cursor = (0, 0);
+            self.max_height_this_row = 0;
+            assert!(
+                width <= self.width && height <= self.height,
+                "glyph too big"
+            );
+            return self.reserve(width, height);
+        };
+        let (x, y, w, h, _) = self.cache.remove(&key).unwrap();
+        self.clear(x, y, w, h);
+        (x, y, w, h)
+    }
+
+    /// Makes the area of the texture transparent, so that the leftovers of an
+    /// evicted glyph don't show up at the edges of a smaller one.
+    fn clear(&self, x: u32, y: u32, width: u32, height: u32) {
+        let pixels = vec![0u8; width as usize * height as usize * 4];
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.texture));
+        gl::call!(gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            x as i32,
+            y as i32,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_ptr() as *const c_void,
+        ));
+    }
+}
+
+/// (Re)creates the texture's storage at the size, filled with transparent
+/// magenta, which stands out if it's sampled by accident.
+fn allocate_texture(texture: u32, width: u32, height: u32) {
+    let pixel_count = width as usize * height as usize;
+    let mut pixels = Vec::with_capacity(pixel_count * 4);
+    for _ in 0..pixel_count {
+        pixels.push(0xFFu8);
+        pixels.push(0u8);
+        pixels.push(0xFFu8);
+        pixels.push(0u8);
+    }
+    gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+    gl::call!(gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RGBA as i32,
+        width as i32,
+        height as i32,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_ptr() as *const c_void,
+    ));
 }
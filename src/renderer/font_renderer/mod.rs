@@ -1,11 +1,10 @@
-use crate::renderer::bumpalloc_buffer::BumpAllocatedBuffer;
+use crate::renderer::bumpalloc_buffer::{BumpAllocatedBuffer, UNIFORM_BUFFER_ALIGNMENT};
 use crate::renderer::draw_calls::{DrawCall, Uniforms};
-use crate::renderer::{gl, gltf, DrawCalls};
-use fontdue::layout::{
-    CoordinateSystem, HorizontalAlign, Layout, LayoutSettings, TextStyle, VerticalAlign,
-};
+use crate::renderer::{gl, gltf, DrawCalls, RendererMemoryStats};
+use fontdue::layout::{CoordinateSystem, HorizontalAlign, Layout, LayoutSettings, VerticalAlign};
 use fontdue::{Font, FontSettings};
-use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use glam::{IVec4, Mat4, Quat, Vec2, Vec3, Vec4};
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::ptr;
 
@@ -16,11 +15,42 @@ use glyph_cache::GlyphCache;
 type IndexType = u16;
 const INDEX_COUNT: i32 = 6;
 const INDEX_TYPE: u32 = gl::UNSIGNED_SHORT;
+/// How much further back shadows and outlines are drawn than the text itself.
+const EFFECT_DEPTH_OFFSET: f32 = 0.01;
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub font_index: usize,
+    pub color: Vec4,
+    /// The offset and color of a drop-shadow drawn behind the text.
+    pub shadow: Option<(Vec2, Vec4)>,
+    /// The radius and color of an outline drawn around the text.
+    pub outline: Option<(f32, Vec4)>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            font_index: FontRenderer::FONT_DEFAULT,
+            color: Vec4::ONE,
+            shadow: None,
+            outline: None,
+        }
+    }
+}
 
 pub struct FontRenderer {
-    glyph_uniforms: Uniforms,
+    glyph_textures: [Option<(u32, u32, u32)>; 6],
+    /// The uniforms for each text color that's been drawn so far, keyed by the
+    /// bits of the color's components. The color is in the material's
+    /// emissive factor, so every color needs its own material.
+    glyph_uniforms: HashMap<[u32; 4], Uniforms>,
+    material_buffer: BumpAllocatedBuffer,
     glyph_draw_call: DrawCall,
     glyph_cache: GlyphCache,
+    /// The [GlyphCache::atlas_generation] the glyph texture in the uniforms
+    /// is from.
+    atlas_generation: u64,
     fonts: Vec<Font>,
     layout: Layout,
 
@@ -31,6 +61,12 @@ pub struct FontRenderer {
 }
 
 impl FontRenderer {
+    /// The index of Montserrat SemiBold, the default font.
+    pub const FONT_DEFAULT: usize = 0;
+    /// The index of DejaVu Sans Mono, for numbers that shouldn't jump around
+    /// when they change.
+    pub const FONT_MONO: usize = 1;
+
     pub fn new() -> FontRenderer {
         let mut allocator = BumpAllocatedBuffer::new(gl::ARRAY_BUFFER, gl::DYNAMIC_DRAW);
         let array_buffer = allocator.get_buffer(true);
@@ -79,6 +115,7 @@ impl FontRenderer {
             ptr::null::<c_void>().add(tex_offset),
         ));
         let disabled_all_ones_vertex_attribute = Some(gltf::ATTR_LOC_COLOR_0);
+        let disabled_all_zeros_vertex_attribute = Some(gltf::ATTR_LOC_WEIGHTS_0);
         let glyph_draw_call = DrawCall {
             vao: gl_vao,
             mode: gl::TRIANGLES,
@@ -87,6 +124,7 @@ impl FontRenderer {
             index_byte_offset: idx_offset,
             index_count: INDEX_COUNT,
             disabled_all_ones_vertex_attribute,
+            disabled_all_zeros_vertex_attribute,
             front_face: gl::CCW,
         };
 
@@ -102,59 +140,100 @@ impl FontRenderer {
         gl::write_1px_rgb_texture(glyph_tex, [0x99, 0x33, 0xBB]);
         gl::write_1px_rgb_texture(white, [0xFF, 0xFF, 0xFF]);
         gl::write_1px_rgb_texture(normal, [0x7F, 0x7F, 0xFF]);
-        let textures = [
+        let glyph_textures = [
             Some((gltf::TEX_UNIT_BASE_COLOR, glyph_tex, gl_sampler)),
             Some((gltf::TEX_UNIT_METALLIC_ROUGHNESS, white, gl_sampler)),
             Some((gltf::TEX_UNIT_NORMAL, normal, gl_sampler)),
             Some((gltf::TEX_UNIT_OCCLUSION, white, gl_sampler)),
             Some((gltf::TEX_UNIT_EMISSIVE, glyph_tex, gl_sampler)),
+            None,
         ];
-        let material = [gltf::UniformBlockMaterial {
-            base_color_factor: Vec4::new(0.0, 0.0, 0.0, 1.0),
-            metallic_factor: 0.0,
-            roughness_factor: 1.0,
-            normal_scale: 1.0,
-            occlusion_strength: 1.0,
-            emissive_factor: Vec4::new(1.0, 1.0, 1.0, 1.0),
-        }];
-        let mat_bytes = bytemuck::cast_slice(&material);
-        let mat_size = mat_bytes.len();
-        let (mat_buf, mat_off) = allocator.allocate_buffer(mat_bytes);
-        let ubos = [Some((
-            gltf::UNIFORM_BLOCK_MATERIAL,
-            mat_buf,
-            mat_off,
-            mat_size,
-        ))];
-        let glyph_uniforms = Uniforms { textures, ubos };
-
-        let montserrat =
-            Font::from_bytes(
-                &include_bytes!(
-                    "../../../resources/fonts/montserrat/static/Montserrat-SemiBold.ttf"
-                )[..],
-                FontSettings {
-                    collection_index: 0,
-                    scale: 36.0,
-                },
-            )
-            .unwrap();
+
         let layout = Layout::new(CoordinateSystem::PositiveYUp);
 
-        FontRenderer {
-            glyph_uniforms,
+        let mut font_renderer = FontRenderer {
+            glyph_textures,
+            glyph_uniforms: HashMap::new(),
+            material_buffer: BumpAllocatedBuffer::new_aligned(
+                gl::UNIFORM_BUFFER,
+                gl::STATIC_DRAW,
+                UNIFORM_BUFFER_ALIGNMENT,
+            ),
             glyph_draw_call,
             glyph_cache: GlyphCache::new(glyph_tex),
-            fonts: vec![montserrat],
+            atlas_generation: 0,
+            fonts: Vec::new(),
             layout,
             gl_vao,
             gl_buffers: [array_buffer, index_buffer],
             gl_textures,
             gl_sampler,
-        }
+        };
+        let montserrat = font_renderer.load_font(include_bytes!(
+            "../../../resources/fonts/montserrat/static/Montserrat-SemiBold.ttf"
+        ));
+        let mono = font_renderer.load_font(include_bytes!(
+            "../../../resources/fonts/dejavu-sans-mono/DejaVuSansMono.ttf"
+        ));
+        debug_assert_eq!(FontRenderer::FONT_DEFAULT, montserrat);
+        debug_assert_eq!(FontRenderer::FONT_MONO, mono);
+        font_renderer
+    }
+
+    /// Loads the font from a TrueType or OpenType file, returning the font
+    /// index that can be passed to [FontRenderer::draw_text_with_font].
+    pub fn load_font(&mut self, data: &[u8]) -> usize {
+        let settings = FontSettings {
+            collection_index: 0,
+            scale: 36.0,
+        };
+        self.fonts.push(Font::from_bytes(data, settings).unwrap());
+        self.fonts.len() - 1
     }
 
     pub fn draw_text(
+        &mut self,
+        draw_calls: &mut DrawCalls,
+        text: &str,
+        pos: Vec2,
+        depth: f32,
+        size: (f32, f32),
+        align: (HorizontalAlign, VerticalAlign),
+        max_width: Option<f32>,
+        color: Vec4,
+    ) {
+        let font_index = FontRenderer::FONT_DEFAULT;
+        self.draw_text_with_font(
+            draw_calls, text, font_index, pos, depth, size, align, max_width, color,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_with_font(
+        &mut self,
+        draw_calls: &mut DrawCalls,
+        text: &str,
+        font_index: usize,
+        pos: Vec2,
+        depth: f32,
+        size: (f32, f32),
+        align: (HorizontalAlign, VerticalAlign),
+        max_width: Option<f32>,
+        color: Vec4,
+    ) {
+        let style = TextStyle {
+            font_index,
+            color,
+            ..Default::default()
+        };
+        self.draw_text_styled(draw_calls, text, pos, depth, size, align, max_width, &style);
+    }
+
+    /// Draws the text like [FontRenderer::draw_text], with the font, color,
+    /// and effects from `style`. The shadow and outline are drawn with extra
+    /// copies of the glyphs, slightly behind the text.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_styled(
         &mut self,
         draw_calls: &mut DrawCalls,
         text: &str,
@@ -163,6 +242,7 @@ impl FontRenderer {
         (px, scale): (f32, f32),
         (h_align, v_align): (HorizontalAlign, VerticalAlign),
         max_width: Option<f32>,
+        style: &TextStyle,
     ) {
         self.layout.reset(&LayoutSettings {
             x: pos.x * scale,
@@ -172,35 +252,184 @@ impl FontRenderer {
             max_width: max_width.map(|f| f * scale),
             ..Default::default()
         });
-        let style = TextStyle {
+        let wrapped_text;
+        let text = if let Some(max_width) = max_width {
+            let max_width = max_width * scale;
+            wrapped_text = wrap_text(text, px * scale, &self.fonts, style.font_index, max_width);
+            &wrapped_text
+        } else {
+            text
+        };
+        let layout_style = fontdue::layout::TextStyle {
             text,
             px: px * scale,
-            font_index: 0,
+            font_index: style.font_index,
             user_data: (),
         };
-        self.layout.append(&self.fonts, &style);
-        for glyph in self.layout.glyphs() {
-            let texcoord = self.glyph_cache.get_texcoord_transform(glyph, &self.fonts);
+        self.layout.append(&self.fonts, &layout_style);
+        // Before getting the uniforms, since caching new glyphs can replace
+        // the texture the uniforms refer to
+        let glyphs = loop {
+            let generation = self.glyph_cache.atlas_generation;
+            let glyphs = (self.layout.glyphs().iter())
+                .map(|glyph| {
+                    let texcoord = self.glyph_cache.get_texcoord_transform(glyph, &self.fonts);
+                    (glyph.x, glyph.y, glyph.width, glyph.height, texcoord)
+                })
+                .collect::<Vec<_>>();
+            // If the texture grew midway, the first texture coordinates are
+            // for the old size, but the glyphs are all cached by now
+            if generation == self.glyph_cache.atlas_generation {
+                break glyphs;
+            }
+        };
+        self.update_glyph_texture();
+
+        let uniforms = self.get_glyph_uniforms(style.color).clone();
+        // Each copy of the glyphs is (offset, depth, uniforms)
+        let mut copies = Vec::with_capacity(10);
+        if let Some((offset, color)) = style.shadow {
+            let uniforms = self.get_glyph_uniforms(color).clone();
+            copies.push((offset, depth - EFFECT_DEPTH_OFFSET, uniforms));
+        }
+        if let Some((radius, color)) = style.outline {
+            let uniforms = self.get_glyph_uniforms(color).clone();
+            for i in 0..8 {
+                let angle = i as f32 / 8.0 * std::f32::consts::TAU;
+                let offset = Vec2::new(angle.cos(), angle.sin()) * radius;
+                copies.push((offset, depth - EFFECT_DEPTH_OFFSET, uniforms.clone()));
+            }
+        }
+        copies.push((Vec2::ZERO, depth, uniforms));
+
+        for (x, y, width, height, texcoord) in glyphs {
             let texcoord_transform = Mat4::from_scale_rotation_translation(
                 Vec3::new(texcoord.z, texcoord.w, 1.0),
                 Quat::IDENTITY,
                 Vec3::new(texcoord.x, texcoord.y, 0.0),
             );
-            let transform = Mat4::from_scale_rotation_translation(
-                Vec3::new(glyph.width as f32, glyph.height as f32, 1.0) / scale,
-                Quat::IDENTITY,
-                Vec3::new(glyph.x, glyph.y, depth) / scale,
-            );
-            draw_calls.add(
+            for (offset, depth, uniforms) in &copies {
+                let transform = Mat4::from_scale_rotation_translation(
+                    Vec3::new(width as f32, height as f32, 1.0) / scale,
+                    Quat::IDENTITY,
+                    Vec3::new(x, y, *depth) / scale + Vec3::from((*offset, 0.0)),
+                );
+                draw_calls.add(
+                    None,
+                    uniforms,
+                    &self.glyph_draw_call,
+                    transform,
+                    transform,
+                    texcoord_transform,
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Adds the glyph material buffer to the renderer's memory stats.
+    pub fn add_memory_stats(&self, stats: &mut RendererMemoryStats) {
+        stats.add_buffer(&self.material_buffer);
+    }
+
+    /// Returns the width of the text when drawn on a single line, including
+    /// trailing whitespace, in the same units as the `pos` of
+    /// [FontRenderer::draw_text].
+    pub fn text_width(&self, text: &str, font_index: usize, (px, scale): (f32, f32)) -> f32 {
+        let font = &self.fonts[font_index];
+        let advance = |c: char| font.metrics(c, px * scale).advance_width;
+        text.chars().map(advance).sum::<f32>() / scale
+    }
+
+    /// Switches the uniforms over to the glyph cache's texture, if it's been
+    /// replaced by a bigger one.
+    fn update_glyph_texture(&mut self) {
+        if self.atlas_generation == self.glyph_cache.atlas_generation {
+            return;
+        }
+        self.atlas_generation = self.glyph_cache.atlas_generation;
+        let (old_texture, new_texture) = (self.gl_textures[0], self.glyph_cache.texture());
+        self.gl_textures[0] = new_texture;
+        let textures = (self.glyph_textures.iter_mut()).chain(
+            self.glyph_uniforms
+                .values_mut()
+                .flat_map(|u| u.textures.iter_mut()),
+        );
+        for (_, texture, _) in textures.flatten() {
+            if *texture == old_texture {
+                *texture = new_texture;
+            }
+        }
+    }
+
+    /// Deletes the glyph textures replaced during the previous frame, which
+    /// has been drawn by now. Called at the start of each frame.
+    pub fn delete_replaced_textures(&mut self) {
+        self.glyph_cache.delete_replaced_textures();
+    }
+
+    fn get_glyph_uniforms(&mut self, color: Vec4) -> &Uniforms {
+        let key = color.to_array().map(f32::to_bits);
+        if !self.glyph_uniforms.contains_key(&key) {
+            let material = [gltf::UniformBlockMaterial {
+                base_color_factor: Vec4::new(0.0, 0.0, 0.0, color.w),
+                metallic_factor: 0.0,
+                roughness_factor: 1.0,
+                normal_scale: 1.0,
+                occlusion_strength: 1.0,
+                emissive_factor: color,
+                flags: IVec4::ZERO,
+            }];
+            let mat_bytes = bytemuck::cast_slice(&material);
+            let mat_size = mat_bytes.len();
+            let (mat_buf, mat_off) = self.material_buffer.allocate_buffer(mat_bytes);
+            let ubos = [
+                Some((gltf::UNIFORM_BLOCK_MATERIAL, mat_buf, mat_off, mat_size)),
                 None,
-                &self.glyph_uniforms,
-                &self.glyph_draw_call,
-                transform,
-                transform,
-                texcoord_transform,
-            );
+                None,
+            ];
+            let textures = self.glyph_textures;
+            self.glyph_uniforms.insert(key, Uniforms { textures, ubos });
+        }
+        &self.glyph_uniforms[&key]
+    }
+}
+
+/// Inserts line breaks between words so that none of the lines are wider than
+/// `max_width`. Words that don't fit on a line by themselves are left for the
+/// layout to break.
+pub fn wrap_text(text: &str, px: f32, fonts: &[Font], font_index: usize, max_width: f32) -> String {
+    let mut layout = Layout::new(CoordinateSystem::PositiveYUp);
+    let mut line_width = |line: &str| {
+        layout.reset(&LayoutSettings::default());
+        let style = fontdue::layout::TextStyle::new(line, px, font_index);
+        layout.append(fonts, &style);
+        layout
+            .glyphs()
+            .last()
+            .map_or(0.0, |glyph| glyph.x + glyph.width as f32)
+    };
+    let mut wrapped = String::with_capacity(text.len());
+    for (i, paragraph) in text.split('\n').enumerate() {
+        if i > 0 {
+            wrapped.push('\n');
+        }
+        // Splitting on single spaces keeps runs of spaces intact
+        let mut words = paragraph.split(' ');
+        let mut line = words.next().unwrap_or("").to_string();
+        for word in words {
+            let candidate = format!("{line} {word}");
+            if line_width(&candidate) > max_width {
+                wrapped.push_str(&line);
+                wrapped.push('\n');
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
         }
+        wrapped.push_str(&line);
     }
+    wrapped
 }
 
 impl Drop for FontRenderer {
@@ -215,5 +444,6 @@ impl Drop for FontRenderer {
             self.gl_textures.as_ptr(),
         ));
         gl::call!(gl::DeleteSamplers(1, &self.gl_sampler));
+        self.glyph_cache.delete_replaced_textures();
     }
 }
@@ -0,0 +1,310 @@
+use std::ptr;
+
+use crate::renderer::gl;
+
+/// An offscreen render target with a color texture and a depth and stencil
+/// texture.
+pub struct Framebuffer {
+    pub fbo: gl::types::GLuint,
+    pub color_tex: gl::types::GLuint,
+    pub depth_tex: gl::types::GLuint,
+    pub width: i32,
+    pub height: i32,
+    multisampled: Option<Multisampled>,
+}
+
+/// The multisampled renderbuffers that are rendered into instead of the
+/// textures, and then resolved into them with [Framebuffer::resolve], since
+/// multisampled textures can't be sampled in GLES 3.0.
+struct Multisampled {
+    fbo: gl::types::GLuint,
+    color_rb: gl::types::GLuint,
+    depth_rb: gl::types::GLuint,
+    samples: i32,
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32) -> Framebuffer {
+        let mut fbo = 0;
+        let mut color_tex = 0;
+        let mut depth_tex = 0;
+        gl::call!(gl::GenFramebuffers(1, &mut fbo));
+        gl::call!(gl::GenTextures(1, &mut color_tex));
+        gl::call!(gl::GenTextures(1, &mut depth_tex));
+        let mut framebuffer = Framebuffer {
+            fbo,
+            color_tex,
+            depth_tex,
+            width: 0,
+            height: 0,
+            multisampled: None,
+        };
+        framebuffer.resize(width, height);
+        framebuffer
+    }
+
+    /// Like [Framebuffer::new], but rendered into with the given amount of
+    /// samples per pixel (capped to what the driver supports). Call
+    /// [Framebuffer::resolve] before reading the textures.
+    pub fn new_multisampled(width: i32, height: i32, samples: i32) -> Framebuffer {
+        let mut max_samples = 0;
+        gl::call!(gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples));
+        let mut framebuffer = Framebuffer::new(1, 1);
+        let mut multisampled = Multisampled {
+            fbo: 0,
+            color_rb: 0,
+            depth_rb: 0,
+            samples: samples.min(max_samples),
+        };
+        gl::call!(gl::GenFramebuffers(1, &mut multisampled.fbo));
+        gl::call!(gl::GenRenderbuffers(1, &mut multisampled.color_rb));
+        gl::call!(gl::GenRenderbuffers(1, &mut multisampled.depth_rb));
+        framebuffer.multisampled = Some(multisampled);
+        // Force the renderbuffers to be allocated as well
+        framebuffer.width = 0;
+        framebuffer.resize(width, height);
+        framebuffer
+    }
+
+    /// Binds the framebuffer as the render target, and sets the viewport to
+    /// cover it.
+    pub fn bind(&self) {
+        let fbo = match &self.multisampled {
+            Some(multisampled) => multisampled.fbo,
+            None => self.fbo,
+        };
+        gl::call!(gl::BindFramebuffer(gl::FRAMEBUFFER, fbo));
+        gl::call!(gl::Viewport(0, 0, self.width, self.height));
+    }
+
+    /// Resolves the samples of a multisampled framebuffer into the color and
+    /// depth textures. Does nothing for framebuffers that aren't
+    /// multisampled. Leaves the default framebuffer bound.
+    pub fn resolve(&self) {
+        if let Some(multisampled) = &self.multisampled {
+            gl::call!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, multisampled.fbo));
+            gl::call!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.fbo));
+            // Depth and stencil can only be blitted with NEAREST, and the
+            // sizes match anyway
+            gl::call!(gl::BlitFramebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                self.width,
+                self.height,
+                gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+                gl::NEAREST,
+            ));
+            Framebuffer::unbind();
+        }
+    }
+
+    /// Binds the default framebuffer (the window) as the render target. Note
+    /// that the viewport is not reset.
+    pub fn unbind() {
+        gl::call!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+    }
+
+    /// Reallocates the color texture and the depth texture if the size has
+    /// changed. The contents of the framebuffer are undefined afterwards.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.color_tex));
+        gl::call!(gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as i32,
+            width,
+            height,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            ptr::null(),
+        ));
+        gl::call!(gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            gl::NEAREST as i32,
+        ));
+        gl::call!(gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            gl::NEAREST as i32,
+        ));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, 0));
+
+        // A texture instead of a renderbuffer, so that the depth can be read
+        // in post-processing
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.depth_tex));
+        gl::call!(gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH24_STENCIL8 as i32,
+            width,
+            height,
+            0,
+            gl::DEPTH_STENCIL,
+            gl::UNSIGNED_INT_24_8,
+            ptr::null(),
+        ));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, 0));
+
+        gl::call!(gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo));
+        gl::call!(gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            self.color_tex,
+            0,
+        ));
+        gl::call!(gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_STENCIL_ATTACHMENT,
+            gl::TEXTURE_2D,
+            self.depth_tex,
+            0,
+        ));
+        let status = gl::call!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER));
+        assert_eq!(
+            gl::FRAMEBUFFER_COMPLETE,
+            status,
+            "framebuffer of size {width}x{height} is incomplete",
+        );
+
+        if let Some(multisampled) = &self.multisampled {
+            let samples = multisampled.samples;
+            gl::call!(gl::BindRenderbuffer(
+                gl::RENDERBUFFER,
+                multisampled.color_rb
+            ));
+            gl::call!(gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples,
+                gl::RGBA8,
+                width,
+                height,
+            ));
+            // Same format as the depth texture, so that it can be blitted
+            gl::call!(gl::BindRenderbuffer(
+                gl::RENDERBUFFER,
+                multisampled.depth_rb
+            ));
+            gl::call!(gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples,
+                gl::DEPTH24_STENCIL8,
+                width,
+                height,
+            ));
+            gl::call!(gl::BindRenderbuffer(gl::RENDERBUFFER, 0));
+
+            gl::call!(gl::BindFramebuffer(gl::FRAMEBUFFER, multisampled.fbo));
+            gl::call!(gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                multisampled.color_rb,
+            ));
+            gl::call!(gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                multisampled.depth_rb,
+            ));
+            let status = gl::call!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER));
+            assert_eq!(
+                gl::FRAMEBUFFER_COMPLETE,
+                status,
+                "multisampled framebuffer of size {width}x{height} is incomplete",
+            );
+        }
+        Framebuffer::unbind();
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteFramebuffers(1, &self.fbo));
+        gl::call!(gl::DeleteTextures(1, &self.color_tex));
+        gl::call!(gl::DeleteTextures(1, &self.depth_tex));
+        if let Some(multisampled) = &self.multisampled {
+            gl::call!(gl::DeleteFramebuffers(1, &multisampled.fbo));
+            gl::call!(gl::DeleteRenderbuffers(1, &multisampled.color_rb));
+            gl::call!(gl::DeleteRenderbuffers(1, &multisampled.depth_rb));
+        }
+    }
+}
+
+/// Draws a texture over the whole render target, used to copy the contents of
+/// a [Framebuffer] to the window. Blitting isn't an option, since the window's
+/// framebuffer is multisampled.
+pub struct FullscreenQuad {
+    program: gl::types::GLuint,
+    vao: gl::types::GLuint,
+    sampler: gl::types::GLuint,
+}
+
+impl FullscreenQuad {
+    pub fn new() -> FullscreenQuad {
+        let program = create_fullscreen_program(include_str!("fullscreen_fragment.glsl"));
+        gl::call!(gl::UseProgram(program));
+        if let Some(location) = gl::get_uniform_location(program, "color_tex") {
+            gl::call!(gl::Uniform1i(location, 0));
+        }
+
+        // The vertices are generated from gl_VertexID, but a VAO needs to be
+        // bound for drawing anyway.
+        let mut vao = 0;
+        gl::call!(gl::GenVertexArrays(1, &mut vao));
+        let mut sampler = 0;
+        gl::call!(gl::GenSamplers(1, &mut sampler));
+        gl::setup_nearest_sampler(sampler);
+
+        FullscreenQuad {
+            program,
+            vao,
+            sampler,
+        }
+    }
+
+    pub fn draw(&self, texture: gl::types::GLuint) {
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::UseProgram(self.program));
+        gl::call!(gl::BindVertexArray(self.vao));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+        gl::call!(gl::BindSampler(0, self.sampler));
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+        gl::call!(gl::BindVertexArray(0));
+    }
+}
+
+impl Drop for FullscreenQuad {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteProgram(self.program));
+        gl::call!(gl::DeleteVertexArrays(1, &self.vao));
+        gl::call!(gl::DeleteSamplers(1, &self.sampler));
+    }
+}
+
+/// Creates a program out of the fragment shader and the fullscreen triangle
+/// vertex shader, for passes that cover the whole render target.
+pub fn create_fullscreen_program(fragment_source: &str) -> gl::types::GLuint {
+    let vertex_shader =
+        gl::create_shader(gl::VERTEX_SHADER, include_str!("fullscreen_vertex.glsl"));
+    let fragment_shader = gl::create_shader(gl::FRAGMENT_SHADER, fragment_source);
+    let program = gl::create_program(&[vertex_shader, fragment_shader]);
+    gl::call!(gl::DeleteShader(vertex_shader));
+    gl::call!(gl::DeleteShader(fragment_shader));
+    program
+}
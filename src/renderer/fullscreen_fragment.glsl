@@ -0,0 +1,12 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+
+void main() {
+  FRAG_COLOR = texture(color_tex, tex_coords);
+}
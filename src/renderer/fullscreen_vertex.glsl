@@ -0,0 +1,10 @@
+#version 300 es
+
+out vec2 tex_coords;
+
+void main() {
+  // A triangle covering the whole screen: (-1, -1), (3, -1), (-1, 3).
+  vec2 position = vec2(float((gl_VertexID & 1) * 4 - 1), float((gl_VertexID & 2) * 2 - 1));
+  tex_coords = position * 0.5 + 0.5;
+  gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -1,11 +1,13 @@
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 /// A wrapper for OpenGL calls, wrapping the call inside unsafe {} and possibly
-/// panicing based on glGetError in debug builds.
+/// panicing based on glGetError in debug builds. If the debug message callback
+/// is enabled (see [setup_debug_callback]), glGetError is not polled, as the
+/// errors are reported by the callback.
 macro_rules! call {
     ($expr:expr) => {{
         let result = unsafe { $expr };
-        if cfg!(debug_assertions) {
+        if cfg!(debug_assertions) && !crate::renderer::gl::debug_callback_enabled() {
             use crate::renderer::gl::*;
             let error = unsafe { GetError() };
             if error != NO_ERROR {
@@ -35,11 +37,97 @@ macro_rules! call {
 pub(crate) use call;
 
 use std::ffi::{c_void, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DEBUG_CALLBACK_ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn debug_callback_enabled() -> bool {
+    DEBUG_CALLBACK_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets up glDebugMessageCallback to print out errors and warnings, if
+/// GL_KHR_debug is available. Returns true if the callback was set up.
+#[cfg(all(feature = "gl_debug_callback", not(target_family = "wasm")))]
+pub fn setup_debug_callback() -> bool {
+    let mut extension_count = 0;
+    call!(GetIntegerv(NUM_EXTENSIONS, &mut extension_count));
+    let khr_debug_available = (0..extension_count as u32).any(|i| {
+        let name = call!(GetStringi(EXTENSIONS, i));
+        !name.is_null()
+            && unsafe { std::ffi::CStr::from_ptr(name.cast()) }.to_bytes() == b"GL_KHR_debug"
+    });
+    if !khr_debug_available || !DebugMessageCallbackKHR::is_loaded() {
+        return false;
+    }
+
+    call!(Enable(DEBUG_OUTPUT_KHR));
+    if cfg!(debug_assertions) {
+        // Makes the callback get called during the offending call, so it's
+        // visible in a stack trace.
+        call!(Enable(DEBUG_OUTPUT_SYNCHRONOUS_KHR));
+    }
+    call!(DebugMessageCallbackKHR(
+        Some(debug_callback),
+        std::ptr::null()
+    ));
+    DEBUG_CALLBACK_ENABLED.store(true, Ordering::Relaxed);
+    true
+}
+
+#[cfg(all(feature = "gl_debug_callback", not(target_family = "wasm")))]
+extern "system" fn debug_callback(
+    source: types::GLenum,
+    type_: types::GLenum,
+    _id: types::GLuint,
+    severity: types::GLenum,
+    length: types::GLsizei,
+    message: *const types::GLchar,
+    _user_param: *mut c_void,
+) {
+    let severity = match severity {
+        DEBUG_SEVERITY_HIGH_KHR => "high",
+        DEBUG_SEVERITY_MEDIUM_KHR => "medium",
+        _ => return,
+    };
+    let source = match source {
+        DEBUG_SOURCE_API_KHR => "API",
+        DEBUG_SOURCE_WINDOW_SYSTEM_KHR => "window system",
+        DEBUG_SOURCE_SHADER_COMPILER_KHR => "shader compiler",
+        DEBUG_SOURCE_THIRD_PARTY_KHR => "third party",
+        DEBUG_SOURCE_APPLICATION_KHR => "application",
+        _ => "other",
+    };
+    let type_ = match type_ {
+        DEBUG_TYPE_ERROR_KHR => "error",
+        DEBUG_TYPE_DEPRECATED_BEHAVIOR_KHR => "deprecated behavior",
+        DEBUG_TYPE_UNDEFINED_BEHAVIOR_KHR => "undefined behavior",
+        DEBUG_TYPE_PORTABILITY_KHR => "portability",
+        DEBUG_TYPE_PERFORMANCE_KHR => "performance",
+        DEBUG_TYPE_MARKER_KHR => "marker",
+        _ => "other",
+    };
+    let message = if message.is_null() {
+        std::borrow::Cow::Borrowed("")
+    } else if length < 0 {
+        unsafe { std::ffi::CStr::from_ptr(message) }.to_string_lossy()
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes)
+    };
+    eprintln!("OpenGL {type_} ({severity} severity, from {source}): {message}");
+}
 
 #[track_caller]
 pub fn create_shader(type_: types::GLenum, shader_source: &str) -> u32 {
+    try_create_shader(type_, shader_source).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Like [create_shader], but returns the compilation errors instead of
+/// panicking.
+#[track_caller]
+pub fn try_create_shader(type_: types::GLenum, shader_source: &str) -> Result<u32, String> {
     let shader = call!(CreateShader(type_));
-    let sources = [shader_source.as_bytes().as_ptr() as *const i8];
+    let sources = [shader_source.as_bytes().as_ptr().cast()];
     let source_lengths = [shader_source.len() as i32];
     call!(ShaderSource(
         shader,
@@ -57,7 +145,7 @@ pub fn create_shader(type_: types::GLenum, shader_source: &str) -> u32 {
             shader,
             4096,
             &mut length,
-            info_log.as_mut_ptr() as *mut i8,
+            info_log.as_mut_ptr().cast(),
         ));
         let info_log = std::str::from_utf8(&info_log[..length as usize]).unwrap();
         let shader_type = match type_ {
@@ -65,13 +153,23 @@ pub fn create_shader(type_: types::GLenum, shader_source: &str) -> u32 {
             FRAGMENT_SHADER => "Fragment ",
             _ => "",
         };
-        panic!("{shader_type}shader compilation failed: {info_log}",);
+        call!(DeleteShader(shader));
+        return Err(format!(
+            "{shader_type}shader compilation failed: {info_log}"
+        ));
     }
-    shader
+    Ok(shader)
 }
 
 #[track_caller]
 pub fn create_program(shaders: &[u32]) -> u32 {
+    try_create_program(shaders).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Like [create_program], but returns the linking errors instead of
+/// panicking.
+#[track_caller]
+pub fn try_create_program(shaders: &[u32]) -> Result<u32, String> {
     let program = call!(CreateProgram());
     for shader in shaders {
         call!(AttachShader(program, *shader));
@@ -86,12 +184,13 @@ pub fn create_program(shaders: &[u32]) -> u32 {
             program,
             4096,
             &mut length,
-            info_log.as_mut_ptr() as *mut i8,
+            info_log.as_mut_ptr().cast(),
         ));
         let info_log = std::str::from_utf8(&info_log[..length as usize]).unwrap();
-        panic!("Linking shader program failed: {info_log}");
+        call!(DeleteProgram(program));
+        return Err(format!("Linking shader program failed: {info_log}"));
     }
-    program
+    Ok(program)
 }
 
 pub fn get_uniform_location(program: u32, name: &str) -> Option<i32> {
@@ -137,6 +236,29 @@ pub fn setup_linear_sampler(sampler: u32, mipmaps: bool) {
     call!(SamplerParameteri(sampler, TEXTURE_WRAP_T, REPEAT as i32,));
 }
 
+pub fn setup_nearest_sampler(sampler: u32) {
+    call!(SamplerParameteri(
+        sampler,
+        TEXTURE_MAG_FILTER,
+        NEAREST as i32,
+    ));
+    call!(SamplerParameteri(
+        sampler,
+        TEXTURE_MIN_FILTER,
+        NEAREST as i32,
+    ));
+    call!(SamplerParameteri(
+        sampler,
+        TEXTURE_WRAP_S,
+        CLAMP_TO_EDGE as i32,
+    ));
+    call!(SamplerParameteri(
+        sampler,
+        TEXTURE_WRAP_T,
+        CLAMP_TO_EDGE as i32,
+    ));
+}
+
 pub fn write_1px_rgb_texture(tex: u32, color: [u8; 3]) {
     let target = TEXTURE_2D;
     let ifmt = RGB as i32;
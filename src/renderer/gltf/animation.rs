@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use crate::renderer::gltf::Gltf;
+use crate::renderer::gltf::{Gltf, UniformBlockSkin, MAX_JOINTS};
 use glam::{Mat4, Quat, Vec3};
 
 pub struct Animation {
@@ -22,6 +22,9 @@ pub enum Keyframes {
     Translation(Vec<Vec3>),
     Rotation(Vec<Quat>),
     Scale(Vec<Vec3>),
+    /// The morph target weights of the node's mesh, with all the weights of
+    /// each keyframe one after another.
+    Weights(Vec<f32>),
 }
 
 #[derive(Clone, Copy)]
@@ -31,6 +34,7 @@ pub enum Interpolation {
     CubicSpline,
 }
 
+#[derive(Clone)]
 pub struct NodeTransform<'a> {
     pub name: &'a str,
     pub transform: Mat4,
@@ -46,6 +50,80 @@ impl Gltf {
             })
             .collect::<Vec<_>>()
     }
+
+    /// Returns the node transforms blended between two of the model's
+    /// animations, see [Animation::blend_transforms].
+    pub fn get_blended_node_transforms(
+        &self,
+        animation_a: usize,
+        time_a: f32,
+        animation_b: usize,
+        time_b: f32,
+        blend: f32,
+    ) -> Vec<NodeTransform<'_>> {
+        let mut transforms = self.get_node_transforms();
+        Animation::blend_transforms(
+            &self.animations[animation_a],
+            &self.animations[animation_b],
+            &mut transforms,
+            time_a,
+            time_b,
+            blend,
+        );
+        transforms
+    }
+
+    /// Applies the morph target weight keyframes of the animation to the
+    /// meshes of the animated nodes.
+    pub fn animate_morph_weights(&mut self, animation_index: usize, time: f32) {
+        // Sampled first and set after, since the animation is borrowed from
+        // the model
+        let mut sampled_weights = Vec::new();
+        let animation = &self.animations[animation_index];
+        for (node_index, animations) in animation.nodes_animations.iter().enumerate() {
+            let Some(mesh_index) = self.nodes[node_index].mesh_index else {
+                continue;
+            };
+            for animation in animations {
+                if let Keyframes::Weights(keyframes) = &animation.keyframes {
+                    let mut weights = self.meshes[mesh_index].morph_weights.clone();
+                    sample_weight_keyframes(
+                        &animation.timestamps,
+                        keyframes,
+                        animation.interpolation,
+                        time,
+                        &mut weights,
+                    );
+                    sampled_weights.push((mesh_index, weights));
+                }
+            }
+        }
+        for (mesh_index, weights) in sampled_weights {
+            for (target, weight) in weights.into_iter().enumerate() {
+                self.set_morph_weight(mesh_index, target, weight);
+            }
+        }
+    }
+
+    /// Returns the joint matrices for the skin, based on the (animated) global
+    /// transforms of every node and the global transform of the skinned node.
+    pub fn get_joint_matrices(
+        &self,
+        skin_index: usize,
+        global_transforms: &[Mat4],
+        skinned_node_transform: Mat4,
+    ) -> UniformBlockSkin {
+        let skin = &self.skins[skin_index];
+        let node_from_global = skinned_node_transform.inverse();
+        let mut joints = UniformBlockSkin {
+            joint_matrices: [Mat4::IDENTITY; MAX_JOINTS],
+        };
+        for (i, &joint_node) in skin.joint_node_indices.iter().enumerate() {
+            joints.joint_matrices[i] =
+                node_from_global * global_transforms[joint_node] * skin.inverse_bind_matrices[i];
+        }
+        joints
+    }
 }
 
 impl Animation {
@@ -82,11 +160,37 @@ impl Animation {
                             time,
                         )
                     }
+                    Keyframes::Weights(_) => {}
                 }
             }
             transforms[i].transform = Mat4::from_scale_rotation_translation(s, r, t);
         }
     }
+
+    /// Samples both animations independently, and interpolates between the
+    /// results by `blend`: 0.0 is fully `a`, 1.0 is fully `b`.
+    pub fn blend_transforms(
+        a: &Animation,
+        b: &Animation,
+        transforms: &mut [NodeTransform],
+        time_a: f32,
+        time_b: f32,
+        blend: f32,
+    ) {
+        let mut transforms_a = transforms.to_vec();
+        let mut transforms_b = transforms.to_vec();
+        a.animate_transforms(&mut transforms_a, time_a);
+        b.animate_transforms(&mut transforms_b, time_b);
+        for (i, node_transform) in transforms.iter_mut().enumerate() {
+            let (s_a, r_a, t_a) = transforms_a[i].transform.to_scale_rotation_translation();
+            let (s_b, r_b, t_b) = transforms_b[i].transform.to_scale_rotation_translation();
+            node_transform.transform = Mat4::from_scale_rotation_translation(
+                s_a.lerp(s_b, blend),
+                r_a.slerp(r_b, blend),
+                t_a.lerp(t_b, blend),
+            );
+        }
+    }
 }
 
 fn sample_vec3_keyframes(
@@ -167,3 +271,46 @@ fn sample_quat_keyframes(
         }
     }
 }
+
+fn sample_weight_keyframes(
+    timestamps: &[f32],
+    keyframes: &[f32],
+    interpolation: Interpolation,
+    t: f32,
+    weights: &mut [f32],
+) {
+    let mut i = timestamps.len();
+    let t = t % timestamps[i - 1];
+    for (i_, timestamps) in timestamps.windows(2).enumerate() {
+        let start = timestamps[0];
+        let end = timestamps[1];
+        if start <= t && t < end {
+            i = i_;
+            break;
+        }
+    }
+    assert!(i < timestamps.len());
+    let t_k = timestamps[i];
+    let t_d = timestamps[i + 1] - t_k;
+    let t = (t - t_k) / t_d;
+    let n = weights.len();
+    for (j, weight) in weights.iter_mut().enumerate() {
+        *weight = match interpolation {
+            Interpolation::Step => keyframes[i * n + j],
+            Interpolation::Linear => {
+                (1.0 - t) * keyframes[i * n + j] + t * keyframes[(i + 1) * n + j]
+            }
+            Interpolation::CubicSpline => {
+                let v = |k: usize, element: usize| keyframes[(k * 3 + element) * n + j];
+                let a_k1 = v(i + 1, 0);
+                let v_k = v(i, 1);
+                let v_k1 = v(i + 1, 1);
+                let b_k = v(i, 2);
+                (2.0 * t.powi(3) - 3.0 * t.powi(2) + 1.0) * v_k
+                    + t_d * (t.powi(3) - 2.0 * t.powi(2) + t) * b_k
+                    + (-2.0 * t.powi(3) + 3.0 * t.powi(2)) * v_k1
+                    + t_d * (t.powi(3) - t.powi(2)) * a_k1
+            }
+        };
+    }
+}
@@ -0,0 +1,164 @@
+use crate::renderer::draw_calls::DrawCalls;
+use crate::renderer::gltf::{Gltf, NodeTransform};
+use glam::Mat4;
+
+/// Characters slower than this are considered to be standing still.
+const WALKING_VELOCITY_THRESHOLD: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterAnimState {
+    Idle,
+    Walk,
+    Work,
+    Sleep,
+}
+
+impl CharacterAnimState {
+    const ALL: [CharacterAnimState; 4] = [
+        CharacterAnimState::Idle,
+        CharacterAnimState::Walk,
+        CharacterAnimState::Work,
+        CharacterAnimState::Sleep,
+    ];
+
+    /// The name of the animation played in this state in the character models.
+    fn animation_name(self) -> &'static str {
+        match self {
+            CharacterAnimState::Idle => "Idle",
+            CharacterAnimState::Walk => "Walk",
+            CharacterAnimState::Work => "Work",
+            CharacterAnimState::Sleep => "Sleep",
+        }
+    }
+}
+
+/// Picks the animation of a character based on what it's doing, and blends
+/// between the animations when the state changes.
+#[derive(Clone)]
+pub struct AnimationStateMachine {
+    /// The animation indices for each state, in the order of
+    /// [CharacterAnimState::ALL]. States without an animation leave the nodes
+    /// in their static pose.
+    animations: [Option<usize>; 4],
+    pub current_state: CharacterAnimState,
+    pub next_state: Option<CharacterAnimState>,
+    /// How far along the blend from the current state to the next state is,
+    /// from 0.0 to 1.0.
+    pub blend: f32,
+    /// How much `blend` advances per second.
+    pub blend_speed: f32,
+    current_time: f32,
+    next_time: f32,
+}
+
+impl AnimationStateMachine {
+    /// Creates a state machine for a model with these animations (in the
+    /// model's order), finding the animations for each state by name.
+    pub fn new<'a>(
+        animation_names: impl Iterator<Item = &'a str> + Clone,
+    ) -> AnimationStateMachine {
+        let animations = CharacterAnimState::ALL.map(|state| {
+            let name = state.animation_name();
+            animation_names
+                .clone()
+                .position(|anim_name| anim_name == name)
+        });
+        AnimationStateMachine {
+            animations,
+            current_state: CharacterAnimState::Idle,
+            next_state: None,
+            blend: 0.0,
+            blend_speed: 4.0,
+            current_time: 0.0,
+            next_time: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, velocity: f32, is_working: bool, is_sleeping: bool) {
+        let target_state = if is_sleeping {
+            CharacterAnimState::Sleep
+        } else if velocity > WALKING_VELOCITY_THRESHOLD {
+            CharacterAnimState::Walk
+        } else if is_working {
+            CharacterAnimState::Work
+        } else {
+            CharacterAnimState::Idle
+        };
+        if target_state == self.current_state {
+            // Changed back before the blend finished, blend back
+            if let Some(next_state) = self.next_state.take() {
+                self.current_state = next_state;
+                self.blend = 1.0 - self.blend;
+                (self.current_time, self.next_time) = (self.next_time, self.current_time);
+                self.next_state = Some(target_state);
+            }
+        } else if self.next_state != Some(target_state) {
+            self.next_state = Some(target_state);
+            self.blend = 0.0;
+            self.next_time = 0.0;
+        }
+
+        self.current_time += dt;
+        self.next_time += dt;
+        if let Some(next_state) = self.next_state {
+            self.blend += self.blend_speed * dt;
+            if self.blend >= 1.0 {
+                self.current_state = next_state;
+                self.current_time = self.next_time;
+                self.next_state = None;
+                self.blend = 0.0;
+            }
+        }
+    }
+
+    /// The animation indices of the current and the next state.
+    fn current_and_next_animation(&self) -> (Option<usize>, Option<usize>) {
+        let next = self
+            .next_state
+            .and_then(|state| self.animations[state as usize]);
+        (self.animations[self.current_state as usize], next)
+    }
+
+    /// Returns the node transforms of the model, animated according to the
+    /// current state and blended with the next one.
+    pub fn get_node_transforms<'a>(&self, model: &'a Gltf) -> Vec<NodeTransform<'a>> {
+        let (current, next) = self.current_and_next_animation();
+        let (index, time) = match (current, next) {
+            (Some(current), Some(next)) => {
+                return model.get_blended_node_transforms(
+                    current,
+                    self.current_time,
+                    next,
+                    self.next_time,
+                    self.blend,
+                );
+            }
+            (Some(current), None) => (current, self.current_time),
+            (None, Some(next)) => (next, self.next_time),
+            (None, None) => return model.get_node_transforms(),
+        };
+        let mut transforms = model.get_node_transforms();
+        model.animations[index].animate_transforms(&mut transforms, time);
+        transforms
+    }
+
+    /// Draws the model animated according to the current state, blended with
+    /// the next one with [Gltf::draw_blended].
+    pub fn draw(&self, model: &Gltf, draw_calls: &mut DrawCalls, model_transform: Mat4) {
+        match self.current_and_next_animation() {
+            (Some(current), Some(next)) => model.draw_blended(
+                draw_calls,
+                model_transform,
+                current,
+                self.current_time,
+                next,
+                self.next_time,
+                self.blend,
+            ),
+            _ => {
+                let node_transforms = self.get_node_transforms(model);
+                model.draw_animated(draw_calls, model_transform, &node_transforms, None);
+            }
+        }
+    }
+}
@@ -5,6 +5,11 @@ precision highp int;
 #define PI 3.14159265
 #define MAX_LIGHTS 32
 #define VIEW_VECTOR vec3(0.0, 0.0, 1.0)
+#define TEX_UNIT_BASE_COLOR 0
+#define TEX_UNIT_METALLIC_ROUGHNESS 1
+#define TEX_UNIT_NORMAL 2
+#define TEX_UNIT_OCCLUSION 3
+#define TEX_UNIT_EMISSIVE 4
 
 out vec4 FRAG_COLOR;
 
@@ -13,8 +18,10 @@ in vec3 vertex_color;
 in vec3 vertex_normal;
 in vec4 vertex_tangent;
 in vec2 tex_coords;
+in vec2 tex_coords_1;
 
 uniform mat4 view_from_world;
+uniform float time;
 uniform sampler2D base_color_tex;
 uniform sampler2D metallic_roughness_tex;
 uniform sampler2D normal_tex;
@@ -26,6 +33,8 @@ layout(std140) uniform Material {
   // strength
   vec4 material_params;
   vec4 emissive_factor;
+  // x: bitmask of the texture units which use TEXCOORD_1, y: 1 if unlit
+  ivec4 material_flags;
 };
 layout(std140) uniform Lights {
   // w: 0.0 as the null terminator, 1.0: directional, 2.0: point, 3.0: spot,
@@ -46,6 +55,13 @@ vec3 aces_filmic(vec3 x) {
   return clamp(x * (a * x + b) / (x * (c * x + d) + e), vec3(0), vec3(1));
 }
 
+// Returns the UV channel the texture in the given texture unit should be
+// sampled with.
+vec2 uv(int tex_unit) {
+  return (material_flags.x & (1 << tex_unit)) != 0 ? tex_coords_1
+                                                     : tex_coords;
+}
+
 vec3 diffuse_brdf(vec3 color) { return color / PI; }
 
 // There's no basis or source for any of this, except the idea of dotting the
@@ -61,15 +77,25 @@ void get_incoming_light(inout vec3 out_diffuse, inout vec3 out_specular,
   // TODO: Calculate light contribution in a physically based way. Don't have
   // the time to do this before LD53 though.
 
-  // TODO: Handle spot and directional lights
+  // TODO: Handle spot lights
 
   vec3 color = light_color_and_kind[light_index].rgb;
-  vec3 to_light =
-      (view_from_world * light_position[light_index]).xyz - view_pos;
-  vec3 light_dir = normalize(to_light);
-  float distance_squared = dot(to_light, to_light);
-
-  float light_power = light_intensity_params[light_index].x / 2500.0;
+  vec3 light_dir;
+  float distance_squared;
+  float light_power;
+  if (kind == 1) {
+    // Directional lights don't fade with distance
+    vec4 direction = view_from_world * light_direction[light_index];
+    light_dir = -normalize(direction.xyz);
+    distance_squared = 1.0;
+    light_power = light_intensity_params[light_index].x;
+  } else {
+    vec3 to_light =
+        (view_from_world * light_position[light_index]).xyz - view_pos;
+    light_dir = normalize(to_light);
+    distance_squared = dot(to_light, to_light);
+    light_power = light_intensity_params[light_index].x / 2500.0;
+  }
   float k_diffuse = max(0.0, dot(normal, light_dir));
   float k_specular = 0.0;
   if (k_diffuse > 0.0) {
@@ -82,18 +108,23 @@ void get_incoming_light(inout vec3 out_diffuse, inout vec3 out_specular,
 }
 
 void main() {
-  vec4 texel_base_color = texture(base_color_tex, tex_coords);
+  vec4 texel_base_color = texture(base_color_tex, uv(TEX_UNIT_BASE_COLOR));
   vec2 texel_metallic_roughness =
-      texture(metallic_roughness_tex, tex_coords).rg;
-  vec3 texel_normal = texture(normal_tex, tex_coords).rgb * 2.0 - 1.0;
-  float texel_occlusion = texture(occlusion_tex, tex_coords).r;
-  vec4 texel_emissive = texture(emissive_tex, tex_coords);
+      texture(metallic_roughness_tex, uv(TEX_UNIT_METALLIC_ROUGHNESS)).rg;
+  vec3 texel_normal =
+      texture(normal_tex, uv(TEX_UNIT_NORMAL)).rgb * 2.0 - 1.0;
+  float texel_occlusion = texture(occlusion_tex, uv(TEX_UNIT_OCCLUSION)).r;
+  vec4 texel_emissive = texture(emissive_tex, uv(TEX_UNIT_EMISSIVE));
 
   float pixel_alpha = texel_base_color.a * base_color_factor.a;
   if (pixel_alpha < 0.01)
     discard;
   vec3 pixel_base_color =
       texel_base_color.rgb * vertex_color * base_color_factor.rgb;
+  if (material_flags.y != 0) {
+    FRAG_COLOR = vec4(pow(pixel_base_color, vec3(1.0 / 2.2)), pixel_alpha);
+    return;
+  }
   float pixel_metallic = texel_metallic_roughness.x * material_params.x;
   float pixel_roughness = texel_metallic_roughness.y * material_params.y;
 
@@ -105,6 +136,20 @@ void main() {
       normalize(mat3(vertex_tangent.xyz, vertex_bitangent, vertex_normal) *
                 tangent_space_normal);
   pixel_normal = vertex_normal;
+  if (material_flags.z != 0) {
+    // Two copies of the normal map, scrolling in different directions
+    vec2 normal_uv = uv(TEX_UNIT_NORMAL);
+    vec3 normal_a =
+        texture(normal_tex, normal_uv + time * 0.01).rgb * 2.0 - 1.0;
+    vec3 normal_b =
+        texture(normal_tex, normal_uv * 1.5 - time * 0.007).rgb * 2.0 - 1.0;
+    vec3 combined = normalize(
+        vec3((normal_a.xy + normal_b.xy) * material_params.z,
+             normal_a.z * normal_b.z));
+    pixel_normal =
+        normalize(mat3(vertex_tangent.xyz, vertex_bitangent, vertex_normal) *
+                  combined);
+  }
 
   float pixel_occlusion = 1.0 + material_params.w * (texel_occlusion - 1.0);
   vec3 light_emitted = texel_emissive.rgb * emissive_factor.rgb;
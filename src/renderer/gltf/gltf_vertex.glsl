@@ -1,4 +1,9 @@
 #version 300 es
+
+#define MAX_JOINTS 64
+#define MAX_MORPH_TARGETS 8
+#define MORPH_TEXTURE_WIDTH 1024
+
 layout(location = 0) in vec3 POSITION;
 layout(location = 1) in vec3 NORMAL;
 layout(location = 2) in vec4 TANGENT;
@@ -7,28 +12,63 @@ layout(location = 4) in vec2 TEXCOORD_1;
 layout(location = 5) in vec3 COLOR_0;
 layout(location = 6) in mat4 MODEL_TRANSFORM;
 layout(location = 10) in mat4 TEXCOORD_TRANSFORM;
+layout(location = 14) in uvec4 JOINTS_0;
+layout(location = 15) in vec4 WEIGHTS_0;
 
 out vec3 view_pos;
 out vec3 vertex_color;
 out vec3 vertex_normal;
 out vec4 vertex_tangent;
 out vec2 tex_coords;
+out vec2 tex_coords_1;
 
 uniform mat4 proj_from_view;
 uniform mat4 view_from_world;
+layout(std140) uniform Skin { mat4 joint_matrices[MAX_JOINTS]; };
+layout(std140) uniform Morph {
+  // x: weight
+  vec4 morph_weights[MAX_MORPH_TARGETS];
+  // x: morph target count, y: vertex count
+  ivec4 morph_params;
+};
+uniform highp sampler2D morph_targets_tex;
+
+vec3 get_morphed_position() {
+  vec3 position = POSITION;
+  for (int i = 0; i < MAX_MORPH_TARGETS; i++) {
+    if (i >= morph_params.x) {
+      break;
+    }
+    int delta_index = i * morph_params.y + gl_VertexID;
+    ivec2 texel = ivec2(delta_index % MORPH_TEXTURE_WIDTH,
+                        delta_index / MORPH_TEXTURE_WIDTH);
+    vec3 delta = texelFetch(morph_targets_tex, texel, 0).xyz;
+    position += morph_weights[i].x * delta;
+  }
+  return position;
+}
 
 void main() {
   // TODO: Move the inverse transpose of the model transfrom to the cpu
   // TODO: Move the inverse of view_from_world to the cpu
-  mat4 view_from_model = view_from_world * MODEL_TRANSFORM;
+  // Unskinned meshes have all-zero weights (see DrawCall in draw_calls.rs)
+  mat4 skin_transform = mat4(1.0);
+  if (dot(WEIGHTS_0, vec4(1.0)) > 0.0) {
+    skin_transform = WEIGHTS_0.x * joint_matrices[JOINTS_0.x] +
+                     WEIGHTS_0.y * joint_matrices[JOINTS_0.y] +
+                     WEIGHTS_0.z * joint_matrices[JOINTS_0.z] +
+                     WEIGHTS_0.w * joint_matrices[JOINTS_0.w];
+  }
+  mat4 view_from_model = view_from_world * MODEL_TRANSFORM * skin_transform;
   mat3 inverse_transpose_model_transfrom =
       transpose(inverse(mat3(view_from_model)));
-  vec4 view_pos_full = view_from_model * vec4(POSITION, 1.0);
+  vec4 view_pos_full = view_from_model * vec4(get_morphed_position(), 1.0);
   view_pos = view_pos_full.xyz;
   vertex_color = COLOR_0;
   vertex_normal = normalize(inverse_transpose_model_transfrom * NORMAL);
   vertex_tangent =
       vec4(normalize(mat3(view_from_model) * TANGENT.xyz), TANGENT.w);
   tex_coords = (TEXCOORD_TRANSFORM * vec4(TEXCOORD_0, 0.0, 1.0)).xy;
+  tex_coords_1 = TEXCOORD_1;
   gl_Position = proj_from_view * view_pos_full;
 }
@@ -1,17 +1,60 @@
-use crate::renderer::bumpalloc_buffer::BumpAllocatedBuffer;
+use crate::renderer::bumpalloc_buffer::{BumpAllocatedBuffer, UNIFORM_BUFFER_ALIGNMENT};
 use crate::renderer::draw_calls::{DrawCall, Uniforms};
 use crate::renderer::gltf::MAX_LIGHTS;
 use crate::renderer::{gl, gltf, FORWARD};
 use bytemuck::Zeroable;
-use glam::{Mat4, Quat, Vec3, Vec4};
+use glam::{IVec4, Mat4, Quat, Vec3, Vec4};
 use std::collections::HashMap;
 use std::f32::consts::FRAC_PI_4;
 use std::ffi::c_void;
-use std::ptr;
+use std::{mem, ptr};
 use tinyjson::JsonValue;
 
 #[track_caller]
 pub fn load_glb(glb: &[u8]) -> gltf::Gltf {
+    let (gltf, bin) = split_glb(glb);
+    load_gltf(gltf, &[("", bin)])
+}
+
+/// The parts of a glTF file that don't need OpenGL, see [load_glb_outline].
+pub struct GltfOutline {
+    /// The names and transforms of the nodes, like
+    /// [gltf::Gltf::get_node_transforms] before any animation.
+    pub nodes: Vec<(String, Mat4)>,
+    pub animation_names: Vec<String>,
+}
+
+/// Reads the nodes and the names of the animations from the glb, without
+/// uploading anything to OpenGL, so this works without a GL context.
+#[track_caller]
+pub fn load_glb_outline(glb: &[u8]) -> GltfOutline {
+    let (gltf, _) = split_glb(glb);
+    let gltf: JsonValue = gltf.parse().unwrap();
+    let gltf = gltf.get::<HashMap<_, _>>().unwrap();
+    let nodes = gltf["nodes"].get::<Vec<_>>().unwrap();
+    let nodes = nodes
+        .iter()
+        .map(|node| {
+            let node: &HashMap<_, _> = node.get().unwrap();
+            let name = node["name"].get::<String>().unwrap().clone();
+            (name, take_node_transform(node))
+        })
+        .collect();
+    let animation_names = match gltf.get("animations") {
+        Some(animations) => (animations.get::<Vec<_>>().unwrap().iter())
+            .map(|animation| animation["name"].get::<String>().unwrap().clone())
+            .collect(),
+        None => Vec::new(),
+    };
+    GltfOutline {
+        nodes,
+        animation_names,
+    }
+}
+
+/// Splits the glb into the glTF JSON and the binary buffer.
+#[track_caller]
+fn split_glb(glb: &[u8]) -> (&str, &[u8]) {
     fn read_chunk<'a>(expected_type: &[u8], bs: &'a [u8]) -> (&'a [u8], usize) {
         let len = u32::from_le_bytes([bs[0], bs[1], bs[2], bs[3]]) as usize;
         assert_eq!(expected_type, &bs[4..8]);
@@ -20,13 +63,12 @@ pub fn load_glb(glb: &[u8]) -> gltf::Gltf {
 
     let glb = &glb[12..];
 
-    let (gltf, next_start) = read_chunk(b"JSON", &glb);
+    let (gltf, next_start) = read_chunk(b"JSON", glb);
     let glb = &glb[next_start..];
 
     let gltf = std::str::from_utf8(gltf).unwrap();
-    let (bin, _) = read_chunk(b"BIN\0", &glb);
-
-    load_gltf(gltf, &[("", bin)])
+    let (bin, _) = read_chunk(b"BIN\0", glb);
+    (gltf, bin)
 }
 
 #[track_caller]
@@ -40,7 +82,7 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             .iter()
             .flat_map(
                 |ext_name: &JsonValue| match ext_name.get::<String>().unwrap().as_str() {
-                    "KHR_lights_punctual" => None,
+                    "KHR_lights_punctual" | "KHR_materials_unlit" => None,
                     ext_name => Some(ext_name),
                 },
             )
@@ -86,6 +128,35 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
         ));
         buffer_slices.push(*buffer_data);
     }
+
+    // Sparse accessors (and ones without a buffer view, which are all zeroes)
+    // are made dense here, and uploaded as additional buffers, so that the
+    // rest of the loader can treat them like any other accessor.
+    let accessors_json = gltf["accessors"].get::<Vec<_>>().unwrap();
+    let buffer_views_json = gltf["bufferViews"].get::<Vec<_>>().unwrap();
+    let mut dense_accessors = HashMap::new();
+    let mut dense_accessor_data = Vec::new();
+    for (i, accessor) in accessors_json.iter().enumerate() {
+        let accessor = accessor.get::<HashMap<_, _>>().unwrap();
+        if accessor.contains_key("sparse") || !accessor.contains_key("bufferView") {
+            let data = densify_accessor(accessor, buffer_views_json, &buffer_slices);
+            dense_accessors.insert(i, buffer_slices.len() + dense_accessor_data.len());
+            dense_accessor_data.push(data);
+        }
+    }
+    for data in &dense_accessor_data {
+        let mut gl_buffer = 0;
+        gl::call!(gl::GenBuffers(1, &mut gl_buffer));
+        gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, gl_buffer));
+        gl::call!(gl::BufferData(
+            gl::ARRAY_BUFFER,
+            data.len() as isize,
+            data.as_ptr() as *const c_void,
+            gl::STATIC_READ,
+        ));
+        gl_buffers.push(gl_buffer);
+        buffer_slices.push(data);
+    }
     gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, 0));
     let get_buffer_slice = |buffer: usize, offset: usize, length: usize| {
         &buffer_slices[buffer][offset..offset + length]
@@ -111,55 +182,39 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             Vec::new()
         };
         let mesh_index = node.get("mesh").map(take_usize);
-        let transform = if let Some(matrix_values) = node.get("matrix") {
-            let matrix_values = matrix_values.get::<Vec<_>>().unwrap();
-            let mut matrix: [f32; 16] = [0.0; 16];
-            assert_eq!(16, matrix_values.len());
-            for (i, value) in matrix_values.into_iter().enumerate() {
-                matrix[i] = take_f32(&value);
-            }
-            Mat4::from_cols_slice(&matrix)
-        } else {
-            let translation = node.get("translation").map(take_vec3).unwrap_or(Vec3::ZERO);
-            let scale = node.get("scale").map(take_vec3).unwrap_or(Vec3::ONE);
-            let rotation = node
-                .get("rotation")
-                .map(take_quat)
-                .unwrap_or(Quat::IDENTITY);
-            Mat4::from_scale_rotation_translation(scale, rotation, translation)
-        };
+        let skin_index = node.get("skin").map(take_usize);
+        let transform = take_node_transform(node);
         nodes.push(gltf::Node {
             name: node["name"].get::<String>().unwrap().clone(),
             mesh_index,
+            skin_index,
             child_node_indices,
             transform,
             original_transform: transform,
         });
     }
 
-    let accessors_json = gltf["accessors"].get::<Vec<_>>().unwrap();
-    let buffer_views_json = gltf["bufferViews"].get::<Vec<_>>().unwrap();
-    let unpack_accessor = |accessor: usize| {
-        let accessor = accessors_json[accessor].get::<HashMap<_, _>>().unwrap();
-        let buffer_view = buffer_views_json[take_usize(&accessor["bufferView"])]
+    let unpack_accessor = |accessor_index: usize| {
+        let accessor = accessors_json[accessor_index]
             .get::<HashMap<_, _>>()
             .unwrap();
+        let (buffer, byte_offset) = if let Some(&buffer) = dense_accessors.get(&accessor_index) {
+            (buffer, 0)
+        } else {
+            let buffer_view = buffer_views_json[take_usize(&accessor["bufferView"])]
+                .get::<HashMap<_, _>>()
+                .unwrap();
+            assert!(
+                !buffer_view.contains_key("byteStride"),
+                "byteStride is not supported for attributes"
+            );
+            let byte_offset = accessor.get("byteOffset").map(take_usize).unwrap_or(0)
+                + buffer_view.get("byteOffset").map(take_usize).unwrap_or(0);
+            (take_usize(&buffer_view["buffer"]), byte_offset)
+        };
 
-        let buffer = take_usize(&buffer_view["buffer"]);
-        let byte_offset = accessor.get("byteOffset").map(take_usize).unwrap_or(0)
-            + buffer_view.get("byteOffset").map(take_usize).unwrap_or(0);
         let count = take_usize(&accessor["count"]) as gl::types::GLint;
-        assert!(
-            !buffer_view.contains_key("byteStride"),
-            "byteStride is not supported for attributes"
-        );
-        let size = match accessor["type"].get::<String>().unwrap().as_ref() {
-            "SCALAR" => 1,
-            "VEC2" => 2,
-            "VEC3" => 3,
-            "VEC4" => 4,
-            type_ => panic!("unexpected vertex attribute accessor type \"{type_}\""),
-        };
+        let size = accessor_component_count(accessor) as gl::types::GLint;
         let type_ = take_usize(&accessor["componentType"]) as gl::types::GLuint;
         let normalized = accessor
             .get("normalized")
@@ -183,11 +238,20 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
     let mut index_buffer_allocator =
         BumpAllocatedBuffer::new(gl::ELEMENT_ARRAY_BUFFER, gl::DYNAMIC_DRAW);
     gl_buffers.push(index_buffer_allocator.get_buffer(true));
+    // Separate from the other indices to keep these u32's aligned
+    let mut wireframe_index_buffer_allocator =
+        BumpAllocatedBuffer::new(gl::ELEMENT_ARRAY_BUFFER, gl::DYNAMIC_DRAW);
+    gl_buffers.push(wireframe_index_buffer_allocator.get_buffer(true));
     let mut primitives = Vec::with_capacity(primitive_count);
     let mut meshes = Vec::with_capacity(meshes_json.len());
+    let mut morph_target_textures = Vec::new();
+    let mut morph_target_sampler = 0;
+    gl::call!(gl::GenSamplers(1, &mut morph_target_sampler));
+    gl::setup_nearest_sampler(morph_target_sampler);
     for mesh in meshes_json {
         let primitives_json = mesh["primitives"].get::<Vec<_>>().unwrap();
         let mut primitive_indices = Vec::with_capacity(primitives_json.len());
+        let mut morph_target_count = 0;
         for primitive_json in primitives_json {
             let primitive_json = primitive_json.get::<HashMap<_, _>>().unwrap();
 
@@ -196,6 +260,7 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             let mode = primitive_json.get("mode").map(take_usize).unwrap_or(4) as gl::types::GLuint;
             let vao = gl_vaos[primitive_index];
             let mut disabled_all_ones_vertex_attribute = Some(gltf::ATTR_LOC_COLOR_0);
+            let mut disabled_all_zeros_vertex_attribute = Some(gltf::ATTR_LOC_WEIGHTS_0);
             let attribute_accessors = primitive_json["attributes"].get::<HashMap<_, _>>().unwrap();
             gl::call!(gl::BindVertexArray(vao));
             for (attr_name, accessor) in attribute_accessors {
@@ -207,22 +272,38 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
                     "TEXCOORD_0" => gltf::ATTR_LOC_TEXCOORD_0,
                     "TEXCOORD_1" => gltf::ATTR_LOC_TEXCOORD_1,
                     "COLOR_0" => gltf::ATTR_LOC_COLOR_0,
+                    "JOINTS_0" => gltf::ATTR_LOC_JOINTS_0,
+                    "WEIGHTS_0" => gltf::ATTR_LOC_WEIGHTS_0,
                     attr => panic!("unsupported attribute semantic \"{attr}\""),
                 };
                 let (buffer, offset, _, size, type_, normalized) = unpack_accessor(accessor);
                 gl::call!(gl::EnableVertexAttribArray(location));
                 gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, gl_buffers[buffer]));
-                gl::call!(gl::VertexAttribPointer(
-                    location,
-                    size,
-                    type_,
-                    if normalized { gl::TRUE } else { gl::FALSE },
-                    0,
-                    ptr::null::<c_void>().add(offset),
-                ));
+                if location == gltf::ATTR_LOC_JOINTS_0 {
+                    // Joint indices are integers in the shader
+                    gl::call!(gl::VertexAttribIPointer(
+                        location,
+                        size,
+                        type_,
+                        0,
+                        ptr::null::<c_void>().add(offset),
+                    ));
+                } else {
+                    gl::call!(gl::VertexAttribPointer(
+                        location,
+                        size,
+                        type_,
+                        if normalized { gl::TRUE } else { gl::FALSE },
+                        0,
+                        ptr::null::<c_void>().add(offset),
+                    ));
+                }
                 if location == gltf::ATTR_LOC_COLOR_0 {
                     disabled_all_ones_vertex_attribute = None;
                 }
+                if location == gltf::ATTR_LOC_WEIGHTS_0 {
+                    disabled_all_zeros_vertex_attribute = None;
+                }
             }
 
             let indices_accessor = take_usize(&primitive_json["indices"]);
@@ -235,26 +316,123 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
                 type_ => panic!("invalid index buffer type {type_}"),
             };
             let index_byte_length = (index_count * size * index_type_byte_size) as usize;
-            let index_buffer = get_buffer_slice(index_buffer, index_byte_offset, index_byte_length);
+            let index_bytes = get_buffer_slice(index_buffer, index_byte_offset, index_byte_length);
             let (index_buffer, index_byte_offset) =
-                index_buffer_allocator.allocate_buffer(index_buffer);
+                index_buffer_allocator.allocate_buffer(index_bytes);
+
+            // For the debug wireframe mode. GLES 3.0 doesn't have
+            // glPolygonMode, so the edges of each triangle are drawn as lines.
+            let mut wireframe_indices = None;
+            if cfg!(debug_assertions) && mode == gl::TRIANGLES {
+                let indices: Vec<u32> = match index_type {
+                    gl::UNSIGNED_BYTE => index_bytes.iter().map(|&i| i as u32).collect(),
+                    gl::UNSIGNED_SHORT => bytemuck::pod_collect_to_vec::<u8, u16>(index_bytes)
+                        .into_iter()
+                        .map(|i| i as u32)
+                        .collect(),
+                    _ => bytemuck::pod_collect_to_vec(index_bytes),
+                };
+                let lines = indices
+                    .chunks_exact(3)
+                    .flat_map(|tri| [tri[0], tri[1], tri[1], tri[2], tri[2], tri[0]])
+                    .collect::<Vec<u32>>();
+                let (buffer, offset) =
+                    wireframe_index_buffer_allocator.allocate_buffer(bytemuck::cast_slice(&lines));
+                wireframe_indices = Some((buffer, offset, lines.len() as i32));
+            }
 
-            primitives.push(gltf::Primitive {
-                material_index,
-                draw_call: DrawCall {
-                    mode,
-                    vao,
-                    index_type,
+            // Morph targets are stored in a texture instead of vertex
+            // attributes, as all the attribute locations are already in use.
+            let mut morph_targets = None;
+            if let Some(targets) = primitive_json.get("targets") {
+                let targets = targets.get::<Vec<_>>().unwrap();
+                assert!(
+                    targets.len() <= gltf::MAX_MORPH_TARGETS,
+                    "this gltf renderer only supports a maximum of {} morph targets",
+                    gltf::MAX_MORPH_TARGETS,
+                );
+                let (_, _, vertex_count, ..) =
+                    unpack_accessor(take_usize(&attribute_accessors["POSITION"]));
+                let vertex_count = vertex_count as usize;
+                let mut deltas = Vec::with_capacity(targets.len() * vertex_count);
+                for target in targets {
+                    let target = target.get::<HashMap<_, _>>().unwrap();
+                    if let Some(accessor) = target.get("POSITION") {
+                        let (buffer, offset, count, ..) = unpack_accessor(take_usize(accessor));
+                        let length = count as usize * mem::size_of::<Vec3>();
+                        let position_deltas: Vec<Vec3> =
+                            bytemuck::pod_collect_to_vec(get_buffer_slice(buffer, offset, length));
+                        deltas.extend(position_deltas.into_iter().map(|d| Vec4::from((d, 0.0))));
+                    } else {
+                        deltas.resize(deltas.len() + vertex_count, Vec4::ZERO);
+                    }
+                }
+                let width = gltf::MORPH_TEXTURE_WIDTH;
+                let height = deltas.len().div_ceil(width);
+                deltas.resize(width * height, Vec4::ZERO);
+                let mut texture = 0;
+                gl::call!(gl::GenTextures(1, &mut texture));
+                gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+                gl::call!(gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA32F as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::FLOAT,
+                    deltas.as_ptr() as *const c_void,
+                ));
+                morph_target_textures.push(texture);
+                morph_target_count = morph_target_count.max(targets.len());
+                morph_targets = Some(gltf::MorphTargets {
+                    texture,
+                    sampler: morph_target_sampler,
+                    target_count: targets.len(),
+                    vertex_count,
+                });
+            }
+
+            let draw_call = DrawCall {
+                mode,
+                vao,
+                index_type,
+                index_buffer,
+                index_byte_offset,
+                index_count,
+                disabled_all_ones_vertex_attribute,
+                disabled_all_zeros_vertex_attribute,
+                front_face: gl::CCW,
+            };
+            let wireframe_draw_call =
+                wireframe_indices.map(|(index_buffer, index_byte_offset, index_count)| DrawCall {
+                    mode: gl::LINES,
+                    index_type: gl::UNSIGNED_INT,
                     index_buffer,
                     index_byte_offset,
                     index_count,
-                    disabled_all_ones_vertex_attribute,
-                    front_face: gl::CCW,
-                },
+                    ..draw_call.clone()
+                });
+            primitives.push(gltf::Primitive {
+                morph_targets,
+                material_index,
+                draw_call,
+                wireframe_draw_call,
             });
             primitive_indices.push(primitive_index);
         }
-        meshes.push(gltf::Mesh { primitive_indices });
+        let morph_weights =
+            if let Some(weights) = mesh.get::<HashMap<_, _>>().unwrap().get("weights") {
+                let weights = weights.get::<Vec<_>>().unwrap();
+                weights.iter().map(take_f32).collect::<Vec<_>>()
+            } else {
+                vec![0.0; morph_target_count]
+            };
+        meshes.push(gltf::Mesh {
+            primitive_indices,
+            morph_weights,
+        });
     }
 
     let materials_json = gltf["materials"].get::<Vec<_>>().unwrap();
@@ -390,8 +568,11 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
         ));
     }
 
-    let mut uniform_buffer_allocator =
-        BumpAllocatedBuffer::new(gl::UNIFORM_BUFFER, gl::DYNAMIC_DRAW);
+    let mut uniform_buffer_allocator = BumpAllocatedBuffer::new_aligned(
+        gl::UNIFORM_BUFFER,
+        gl::DYNAMIC_DRAW,
+        UNIFORM_BUFFER_ALIGNMENT,
+    );
     gl_buffers.push(uniform_buffer_allocator.get_buffer(true));
 
     // KHR_lights_punctual extension:
@@ -452,11 +633,11 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
     for material in materials_json {
         let unpack_texture_info = |texture_info: &JsonValue| {
             let texture_info = texture_info.get::<HashMap<_, _>>().unwrap();
-            // TODO: Support TEXCOORD_1
-            assert!(matches!(
-                texture_info.get("texCoord").map(take_usize),
-                None | Some(0)
-            ));
+            let tex_coord = texture_info.get("texCoord").map(take_usize).unwrap_or(0);
+            assert!(
+                tex_coord <= 1,
+                "only TEXCOORD_0 and TEXCOORD_1 are supported"
+            );
             let texture = &textures_json[take_usize(&texture_info["index"])];
             let texture = texture.get::<HashMap<_, _>>().unwrap();
             let sampler = texture
@@ -464,7 +645,7 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
                 .map(take_usize)
                 .unwrap_or(gl_samplers.len() - 1);
             let source = take_usize(&texture["source"]);
-            (gl_textures[source], gl_samplers[sampler])
+            (gl_textures[source], gl_samplers[sampler], tex_coord)
         };
 
         let material = material.get::<HashMap<_, _>>().unwrap();
@@ -475,19 +656,28 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             normal_scale: 1.0,
             occlusion_strength: 1.0,
             emissive_factor: Vec4::splat(0.0),
+            flags: IVec4::ZERO,
+        };
+        let mut tex_coord_1_mask = 0;
+        let mut use_tex_coord = |tex_unit: u32, tex_coord: usize| {
+            if tex_coord == 1 {
+                tex_coord_1_mask |= 1 << tex_unit;
+            }
         };
 
-        let mut textures = [None; 5];
+        let mut textures = [None; 6];
         if let Some(pbr) = material.get("pbrMetallicRoughness") {
             let pbr = pbr.get::<HashMap<_, _>>().unwrap();
             if let Some(texture_info) = pbr.get("baseColorTexture") {
-                let (texture, sampler) = unpack_texture_info(texture_info);
+                let (texture, sampler, tex_coord) = unpack_texture_info(texture_info);
+                use_tex_coord(gltf::TEX_UNIT_BASE_COLOR, tex_coord);
                 textures[0] = Some((gltf::TEX_UNIT_BASE_COLOR, texture, sampler));
             } else {
                 textures[0] = Some((gltf::TEX_UNIT_BASE_COLOR, white_tex, default_sampler));
             }
             if let Some(texture_info) = pbr.get("metallicRoughnessTexture") {
-                let (texture, sampler) = unpack_texture_info(texture_info);
+                let (texture, sampler, tex_coord) = unpack_texture_info(texture_info);
+                use_tex_coord(gltf::TEX_UNIT_METALLIC_ROUGHNESS, tex_coord);
                 textures[1] = Some((gltf::TEX_UNIT_METALLIC_ROUGHNESS, texture, sampler));
             } else {
                 textures[1] = Some((
@@ -512,7 +702,8 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             }
         }
         if let Some(texture_info) = material.get("normalTexture") {
-            let (texture, sampler) = unpack_texture_info(texture_info);
+            let (texture, sampler, tex_coord) = unpack_texture_info(texture_info);
+            use_tex_coord(gltf::TEX_UNIT_NORMAL, tex_coord);
             textures[2] = Some((gltf::TEX_UNIT_NORMAL, texture, sampler));
             let texture_info = texture_info.get::<HashMap<_, _>>().unwrap();
             if let Some(factor) = texture_info.get("scale") {
@@ -522,7 +713,8 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             textures[2] = Some((gltf::TEX_UNIT_NORMAL, normal_tex, default_sampler));
         }
         if let Some(texture_info) = material.get("occlusionTexture") {
-            let (texture, sampler) = unpack_texture_info(texture_info);
+            let (texture, sampler, tex_coord) = unpack_texture_info(texture_info);
+            use_tex_coord(gltf::TEX_UNIT_OCCLUSION, tex_coord);
             textures[3] = Some((gltf::TEX_UNIT_OCCLUSION, texture, sampler));
             let texture_info = texture_info.get::<HashMap<_, _>>().unwrap();
             if let Some(factor) = texture_info.get("strength") {
@@ -532,7 +724,8 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             textures[3] = Some((gltf::TEX_UNIT_OCCLUSION, white_tex, default_sampler));
         }
         if let Some(texture_info) = material.get("emissiveTexture") {
-            let (texture, sampler) = unpack_texture_info(texture_info);
+            let (texture, sampler, tex_coord) = unpack_texture_info(texture_info);
+            use_tex_coord(gltf::TEX_UNIT_EMISSIVE, tex_coord);
             textures[4] = Some((gltf::TEX_UNIT_EMISSIVE, texture, sampler));
         } else {
             textures[4] = Some((gltf::TEX_UNIT_EMISSIVE, white_tex, default_sampler));
@@ -545,24 +738,60 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             material_buffer.emissive_factor = Vec4::new(x, y, z, 1.0);
         }
 
+        material_buffer.flags.x = tex_coord_1_mask;
+        // KHR_materials_unlit extension:
+        if let Some(extensions) = material.get("extensions") {
+            let extensions = extensions.get::<HashMap<_, _>>().unwrap();
+            if extensions.contains_key("KHR_materials_unlit") {
+                material_buffer.flags.y = 1;
+            }
+        }
         let material_data = [material_buffer];
         let material_data = bytemuck::cast_slice(&material_data);
         let (ubo, ubo_offset) = uniform_buffer_allocator.allocate_buffer(material_data);
         let ubo_size = material_data.len();
-        let ubos = [Some((
-            gltf::UNIFORM_BLOCK_MATERIAL,
-            ubo,
-            ubo_offset,
-            ubo_size,
-        ))];
+        let ubos = [
+            Some((gltf::UNIFORM_BLOCK_MATERIAL, ubo, ubo_offset, ubo_size)),
+            None,
+            None,
+        ];
 
         materials.push(gltf::Material {
             name: material["name"].get::<String>().unwrap().clone(),
             uniforms: Uniforms { textures, ubos },
+            uniform_block: material_buffer,
             lights,
         });
     }
 
+    let skins_json_fallback = Vec::with_capacity(0);
+    let skins_json = gltf
+        .get("skins")
+        .map(|v| v.get::<Vec<_>>().unwrap())
+        .unwrap_or(&skins_json_fallback);
+    let mut skins = Vec::with_capacity(skins_json.len());
+    for skin in skins_json {
+        let skin = skin.get::<HashMap<_, _>>().unwrap();
+        let joints = skin["joints"].get::<Vec<_>>().unwrap();
+        let joint_node_indices = joints.iter().map(take_usize).collect::<Vec<_>>();
+        assert!(
+            joint_node_indices.len() <= gltf::MAX_JOINTS,
+            "this gltf renderer only supports a maximum of {} joints per skin",
+            gltf::MAX_JOINTS,
+        );
+        let inverse_bind_matrices = if let Some(accessor) = skin.get("inverseBindMatrices") {
+            let (buffer, offset, count, ..) = unpack_accessor(take_usize(accessor));
+            let length = count as usize * mem::size_of::<Mat4>();
+            bytemuck::pod_collect_to_vec(get_buffer_slice(buffer, offset, length))
+        } else {
+            vec![Mat4::IDENTITY; joint_node_indices.len()]
+        };
+        skins.push(gltf::Skin {
+            joint_node_indices,
+            inverse_bind_matrices,
+        });
+    }
+
     let animations_json_fallback = Vec::with_capacity(0);
     let animations_json = gltf
         .get("animations")
@@ -587,8 +816,15 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
             let path = channel["target"]["path"].get::<String>().unwrap().as_str();
             let input_accessor = take_usize(&sampler["input"]);
             let input = get_accessor_slice(input_accessor, 4);
-            let output_bpc = if path == "rotation" { 16 } else { 12 };
-            let output = get_accessor_slice(take_usize(&sampler["output"]), output_bpc);
+            let output_accessor = take_usize(&sampler["output"]);
+            let output = if path == "weights" {
+                // The output has a weight for each morph target for each keyframe
+                let (buffer, offset, count, ..) = unpack_accessor(output_accessor);
+                get_buffer_slice(buffer, offset, count as usize * 4)
+            } else {
+                let output_bpc = if path == "rotation" { 16 } else { 12 };
+                get_accessor_slice(output_accessor, output_bpc)
+            };
             let timestamps = bytemuck::pod_collect_to_vec(input);
             start = start.min(timestamps[0]);
             end = end.max(timestamps[timestamps.len() - 1]);
@@ -596,6 +832,7 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
                 "translation" => gltf::Keyframes::Translation(bytemuck::pod_collect_to_vec(output)),
                 "rotation" => gltf::Keyframes::Rotation(bytemuck::pod_collect_to_vec(output)),
                 "scale" => gltf::Keyframes::Scale(bytemuck::pod_collect_to_vec(output)),
+                "weights" => gltf::Keyframes::Weights(bytemuck::pod_collect_to_vec(output)),
                 target => panic!("unsupported animation target '{target}'"),
             };
             let interpolation = match sampler["interpolation"].get::<String>().unwrap().as_str() {
@@ -618,12 +855,16 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
         });
     }
 
+    gl_textures.extend(morph_target_textures);
+    gl_samplers.push(morph_target_sampler);
+
     gltf::Gltf {
         scene,
         animations,
         scenes,
         nodes,
         meshes,
+        skins,
         materials,
         primitives,
         gl_vaos,
@@ -633,6 +874,108 @@ pub fn load_gltf(gltf: &str, resources: &[(&str, &[u8])]) -> gltf::Gltf {
     }
 }
 
+/// Returns the contents of the accessor as a tightly packed buffer, with the
+/// values of its sparse storage (if any) written over the values in its
+/// buffer view (or zeroes, if it doesn't have one).
+fn densify_accessor(
+    accessor: &HashMap<String, JsonValue>,
+    buffer_views_json: &[JsonValue],
+    buffer_slices: &[&[u8]],
+) -> Vec<u8> {
+    let get_buffer_view_slice = |json: &HashMap<String, JsonValue>, length: usize| {
+        let buffer_view = buffer_views_json[take_usize(&json["bufferView"])]
+            .get::<HashMap<_, _>>()
+            .unwrap();
+        assert!(
+            !buffer_view.contains_key("byteStride"),
+            "byteStride is not supported for sparse accessors"
+        );
+        let buffer = take_usize(&buffer_view["buffer"]);
+        let offset = json.get("byteOffset").map(take_usize).unwrap_or(0)
+            + buffer_view.get("byteOffset").map(take_usize).unwrap_or(0);
+        &buffer_slices[buffer][offset..offset + length]
+    };
+
+    let count = take_usize(&accessor["count"]);
+    let component_type = take_usize(&accessor["componentType"]) as gl::types::GLuint;
+    let element_size = accessor_component_count(accessor) * component_byte_size(component_type);
+    let mut data = if accessor.contains_key("bufferView") {
+        get_buffer_view_slice(accessor, count * element_size).to_vec()
+    } else {
+        vec![0; count * element_size]
+    };
+
+    if let Some(sparse) = accessor.get("sparse") {
+        let sparse = sparse.get::<HashMap<_, _>>().unwrap();
+        let sparse_count = take_usize(&sparse["count"]);
+        let indices = sparse["indices"].get::<HashMap<_, _>>().unwrap();
+        let index_type = take_usize(&indices["componentType"]) as gl::types::GLuint;
+        let index_size = component_byte_size(index_type);
+        let index_bytes = get_buffer_view_slice(indices, sparse_count * index_size);
+        let values = sparse["values"].get::<HashMap<_, _>>().unwrap();
+        let value_bytes = get_buffer_view_slice(values, sparse_count * element_size);
+        for (index, value) in index_bytes
+            .chunks_exact(index_size)
+            .zip(value_bytes.chunks_exact(element_size))
+        {
+            let index = match index_type {
+                gl::UNSIGNED_BYTE => index[0] as usize,
+                gl::UNSIGNED_SHORT => u16::from_le_bytes([index[0], index[1]]) as usize,
+                gl::UNSIGNED_INT => u32::from_le_bytes(index.try_into().unwrap()) as usize,
+                type_ => panic!("invalid sparse accessor index type {type_}"),
+            };
+            data[index * element_size..(index + 1) * element_size].copy_from_slice(value);
+        }
+    }
+
+    data
+}
+
+/// Returns the amount of components in each element of the accessor, e.g. 3
+/// for VEC3.
+fn accessor_component_count(accessor: &HashMap<String, JsonValue>) -> usize {
+    match accessor["type"].get::<String>().unwrap().as_ref() {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" => 4,
+        "MAT4" => 16,
+        type_ => panic!("unexpected vertex attribute accessor type \"{type_}\""),
+    }
+}
+
+/// Returns the size of the accessor component type, in bytes.
+fn component_byte_size(component_type: gl::types::GLuint) -> usize {
+    match component_type {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT => 2,
+        gl::UNSIGNED_INT | gl::FLOAT => 4,
+        type_ => panic!("invalid accessor component type {type_}"),
+    }
+}
+
+/// Returns the node's local transform, from either its matrix or its
+/// translation, rotation and scale.
+fn take_node_transform(node: &HashMap<String, JsonValue>) -> Mat4 {
+    if let Some(matrix_values) = node.get("matrix") {
+        let matrix_values = matrix_values.get::<Vec<_>>().unwrap();
+        let mut matrix: [f32; 16] = [0.0; 16];
+        assert_eq!(16, matrix_values.len());
+        for (i, value) in matrix_values.into_iter().enumerate() {
+            matrix[i] = take_f32(&value);
+        }
+        Mat4::from_cols_slice(&matrix)
+    } else {
+        let translation = node.get("translation").map(take_vec3).unwrap_or(Vec3::ZERO);
+        let scale = node.get("scale").map(take_vec3).unwrap_or(Vec3::ONE);
+        let rotation = node
+            .get("rotation")
+            .map(take_quat)
+            .unwrap_or(Quat::IDENTITY);
+        Mat4::from_scale_rotation_translation(scale, rotation, translation)
+    }
+}
+
 /// Return usize if JsonValue is a number, otherwise panic.
 fn take_usize(json_value: &JsonValue) -> usize {
     let i: &f64 = json_value.get().unwrap();
@@ -0,0 +1,79 @@
+use crate::renderer::draw_calls::DrawCalls;
+use crate::renderer::gltf::Gltf;
+use glam::Mat4;
+
+/// A model with simpler versions of it for drawing further away from the
+/// camera. Generic over the model only so that the level selection can be
+/// tested without a GL context.
+pub struct LodGltf<M = Gltf> {
+    /// The levels of detail, as pairs of the camera distance from which on
+    /// the model is used, and the model. Sorted by the distance, starting
+    /// from the most detailed model at 0.0.
+    pub levels: Vec<(f32, M)>,
+}
+
+impl<M> LodGltf<M> {
+    pub fn new(levels: Vec<(f32, M)>) -> LodGltf<M> {
+        assert!(
+            levels.first().is_some_and(|(distance, _)| *distance == 0.0),
+            "the first level of detail should start at distance 0.0",
+        );
+        assert!(
+            levels.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "the levels of detail should be sorted by distance",
+        );
+        LodGltf { levels }
+    }
+
+    /// The most detailed version of the model.
+    pub fn full_detail(&self) -> &M {
+        &self.levels[0].1
+    }
+
+    /// Returns the model that should be drawn at the given distance from the
+    /// camera.
+    pub fn level(&self, camera_distance: f32) -> &M {
+        let (_, model) = self
+            .levels
+            .iter()
+            .rev()
+            .find(|(distance, _)| *distance <= camera_distance)
+            .unwrap_or(&self.levels[0]);
+        model
+    }
+}
+
+impl LodGltf {
+    #[allow(dead_code)]
+    pub fn draw_lod(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        camera_distance: f32,
+    ) {
+        self.level(camera_distance)
+            .draw(draw_calls, model_transform);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LodGltf;
+
+    #[test]
+    fn level_is_picked_by_distance() {
+        let lod = LodGltf::new(vec![(0.0, "full"), (20.0, "simple"), (50.0, "simplest")]);
+        assert_eq!(*lod.full_detail(), "full");
+        assert_eq!(*lod.level(0.0), "full");
+        assert_eq!(*lod.level(19.9), "full");
+        assert_eq!(*lod.level(20.0), "simple");
+        assert_eq!(*lod.level(49.9), "simple");
+        assert_eq!(*lod.level(500.0), "simplest");
+    }
+
+    #[test]
+    #[should_panic]
+    fn unsorted_levels_are_rejected() {
+        LodGltf::new(vec![(0.0, "full"), (50.0, "simplest"), (20.0, "simple")]);
+    }
+}
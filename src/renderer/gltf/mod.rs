@@ -1,13 +1,19 @@
 use crate::renderer::draw_calls::{DrawCall, DrawCalls, Uniforms};
 use crate::renderer::gl;
-use glam::Mat4;
+use bytemuck::Zeroable;
+use glam::{Mat4, Vec3, Vec4};
+use std::ffi::c_void;
 
 mod animation;
+mod animation_state_machine;
 mod loader;
+mod lod;
 mod program;
 
 pub use animation::*;
-pub use loader::{load_glb, load_gltf};
+pub use animation_state_machine::*;
+pub use loader::{load_glb, load_glb_outline, load_gltf, GltfOutline};
+pub use lod::*;
 pub use program::*;
 
 pub struct Gltf {
@@ -16,6 +22,7 @@ pub struct Gltf {
     scenes: Vec<Scene>,
     nodes: Vec<Node>,
     meshes: Vec<Mesh>,
+    skins: Vec<Skin>,
     materials: Vec<Material>,
     primitives: Vec<Primitive>,
 
@@ -34,27 +41,170 @@ pub struct Node {
     pub transform: Mat4,
     pub original_transform: Mat4,
     mesh_index: Option<usize>,
+    skin_index: Option<usize>,
     child_node_indices: Vec<usize>,
 }
 
 pub struct Mesh {
     primitive_indices: Vec<usize>,
+    /// The weights of the morph targets of this mesh's primitives.
+    morph_weights: Vec<f32>,
+}
+
+pub struct Skin {
+    joint_node_indices: Vec<usize>,
+    inverse_bind_matrices: Vec<Mat4>,
 }
 
 pub struct Primitive {
     pub draw_call: DrawCall,
+    /// The edges of the triangles as lines, for the debug wireframe mode. Only
+    /// generated in debug builds.
+    pub wireframe_draw_call: Option<DrawCall>,
     material_index: usize,
+    morph_targets: Option<MorphTargets>,
+}
+
+pub struct MorphTargets {
+    /// The position deltas of the targets, see [MORPH_TEXTURE_WIDTH].
+    texture: gl::types::GLuint,
+    sampler: gl::types::GLuint,
+    target_count: usize,
+    vertex_count: usize,
 }
 
 pub struct Material {
     pub name: String,
     pub uniforms: Uniforms,
+    /// The contents of the material's uniform block in `uniforms`, for
+    /// drawing with some of them overridden.
+    pub uniform_block: UniformBlockMaterial,
     pub lights: UniformBlockLights,
 }
 
 impl Gltf {
+    /// Sets the weight of a morph target of the mesh. Animations with morph
+    /// target weights overwrite these, see [Gltf::animate_morph_weights].
+    pub fn set_morph_weight(&mut self, mesh: usize, target: usize, weight: f32) {
+        self.meshes[mesh].morph_weights[target] = weight;
+    }
+
+    pub fn get_node_index_by_name(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn get_node_transform(&self, node_index: usize) -> Mat4 {
+        self.nodes[node_index].transform
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn get_material_index_by_name(&self, name: &str) -> Option<usize> {
+        self.materials
+            .iter()
+            .position(|material| material.name == name)
+    }
+
+    /// Returns the texture and sampler bound to the texture unit (one of the
+    /// TEX_UNIT_* constants) when drawing with the material.
+    pub fn get_texture(&self, material_index: usize, tex_unit: u32) -> Option<(u32, u32)> {
+        let textures = &self.materials[material_index].uniforms.textures;
+        let (_, texture, sampler) = textures[tex_unit as usize]?;
+        Some((texture, sampler))
+    }
+
+    /// Replaces the texture bound to the texture unit (one of the TEX_UNIT_*
+    /// constants) when drawing with the material. The caller is responsible
+    /// for deleting the new texture, and the old one stays owned by the model.
+    pub fn swap_texture(
+        &mut self,
+        material_index: usize,
+        tex_unit: u32,
+        new_texture: u32,
+        new_sampler: u32,
+    ) {
+        let textures = &mut self.materials[material_index].uniforms.textures;
+        textures[tex_unit as usize] = Some((tex_unit, new_texture, new_sampler));
+    }
+
+    /// Makes the material's normal map scroll around with the shader's `time`
+    /// uniform, for water.
+    pub fn set_animated_normals(&mut self, material_index: usize) {
+        let material = &mut self.materials[material_index];
+        material.uniform_block.flags.z = 1;
+        let block = [material.uniform_block];
+        let block: &[u8] = bytemuck::cast_slice(&block);
+        let (_, buffer, offset, size) = material.uniforms.ubos[0].unwrap();
+        assert_eq!(size, block.len());
+        gl::call!(gl::BindBuffer(gl::UNIFORM_BUFFER, buffer));
+        gl::call!(gl::BufferSubData(
+            gl::UNIFORM_BUFFER,
+            offset as isize,
+            size as isize,
+            block.as_ptr() as *const c_void,
+        ));
+        gl::call!(gl::BindBuffer(gl::UNIFORM_BUFFER, 0));
+    }
+
     pub fn draw(&self, draw_calls: &mut DrawCalls, model_transform: Mat4) {
-        self._draw(draw_calls, model_transform, |i| self.nodes[i].transform)
+        self._draw(draw_calls, model_transform, None, None, |i| {
+            self.nodes[i].transform
+        })
+    }
+
+    /// Draws the model, unless the bounding sphere (center and radius, in the
+    /// model's space) is outside of the frustum of the draw calls.
+    pub fn draw_culled(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        bounding_sphere: (Vec3, f32),
+    ) {
+        self._draw(
+            draw_calls,
+            model_transform,
+            Some(bounding_sphere),
+            None,
+            |i| self.nodes[i].transform,
+        )
+    }
+
+    /// Draws the model with some of the nodes' transforms replaced, given as
+    /// (node index, transform) pairs. Cheaper than [Gltf::draw_animated] when
+    /// only a few nodes need to move.
+    pub fn draw_with_overrides(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        overrides: &[(usize, Mat4)],
+    ) {
+        self._draw(draw_calls, model_transform, None, None, |i| match overrides
+            .iter()
+            .find(|(node_index, _)| *node_index == i)
+        {
+            Some((_, transform)) => *transform,
+            None => self.nodes[i].transform,
+        })
+    }
+
+    /// Draws the model with the base color factor of all of its materials
+    /// replaced with the given one, if any, e.g. for highlighting.
+    pub fn draw_with_material_override(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        bounding_sphere: Option<(Vec3, f32)>,
+        base_color_factor: Option<Vec4>,
+    ) {
+        self._draw(
+            draw_calls,
+            model_transform,
+            bounding_sphere,
+            base_color_factor,
+            |i| self.nodes[i].transform,
+        )
     }
 
     pub fn draw_animated(
@@ -62,20 +212,64 @@ impl Gltf {
         draw_calls: &mut DrawCalls,
         model_transform: Mat4,
         node_transforms: &[NodeTransform],
+        bounding_sphere: Option<(Vec3, f32)>,
     ) {
-        self._draw(draw_calls, model_transform, |i| {
+        self._draw(draw_calls, model_transform, bounding_sphere, None, |i| {
             node_transforms[i].transform
         })
     }
 
+    /// Like [Gltf::draw_animated], with the base color factor of all the
+    /// materials replaced, like in [Gltf::draw_with_material_override].
+    pub fn draw_animated_with_material_override(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        node_transforms: &[NodeTransform],
+        base_color_factor: Vec4,
+    ) {
+        self._draw(
+            draw_calls,
+            model_transform,
+            None,
+            Some(base_color_factor),
+            |i| node_transforms[i].transform,
+        )
+    }
+
+    /// Draws the model with the node transforms blended between two
+    /// animations, see [Gltf::get_blended_node_transforms].
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_blended(
+        &self,
+        draw_calls: &mut DrawCalls,
+        model_transform: Mat4,
+        animation_a: usize,
+        time_a: f32,
+        animation_b: usize,
+        time_b: f32,
+        blend: f32,
+    ) {
+        let node_transforms =
+            self.get_blended_node_transforms(animation_a, time_a, animation_b, time_b, blend);
+        self.draw_animated(draw_calls, model_transform, &node_transforms, None);
+    }
+
     #[inline]
     fn _draw<F: Fn(usize) -> Mat4>(
         &self,
         draw_calls: &mut DrawCalls,
         model_transform: Mat4,
+        bounding_sphere: Option<(Vec3, f32)>,
+        base_color_factor: Option<Vec4>,
         get_transform: F,
     ) {
         let scene = &self.scenes[self.scene];
+        let global_transforms = if self.skins.is_empty() {
+            Vec::new()
+        } else {
+            self.get_global_transforms(model_transform, &get_transform)
+        };
         let mut node_queue = scene
             .node_indices
             .iter()
@@ -83,8 +277,16 @@ impl Gltf {
             .collect::<Vec<_>>();
         while let Some((parent_transform, node_index)) = node_queue.pop() {
             let transform = parent_transform * get_transform(node_index);
-            if let Some(mesh_index) = self.nodes[node_index].mesh_index {
-                for &primitive_index in &self.meshes[mesh_index].primitive_indices {
+            let node = &self.nodes[node_index];
+            let skin_ubo = node.skin_index.map(|skin_index| {
+                let skin = [self.get_joint_matrices(skin_index, &global_transforms, transform)];
+                let skin = bytemuck::cast_slice(&skin);
+                let (buffer, offset) = draw_calls.allocate_uniform_block(skin);
+                (UNIFORM_BLOCK_SKIN, buffer, offset, skin.len())
+            });
+            if let Some(mesh_index) = node.mesh_index {
+                let mesh = &self.meshes[mesh_index];
+                for &primitive_index in &mesh.primitive_indices {
                     let primitive = &self.primitives[primitive_index];
                     let material = &self.materials[primitive.material_index];
                     let mut draw_call = primitive.draw_call.clone();
@@ -92,20 +294,97 @@ impl Gltf {
                     draw_call.front_face = (transform.determinant() > 0.0)
                         .then_some(gl::CCW)
                         .unwrap_or(gl::CW);
+                    let mut uniforms = material.uniforms.clone();
+                    if let Some(base_color_factor) = base_color_factor {
+                        let block = [UniformBlockMaterial {
+                            base_color_factor,
+                            ..material.uniform_block
+                        }];
+                        let block = bytemuck::cast_slice(&block);
+                        let (buffer, offset) = draw_calls.allocate_uniform_block(block);
+                        uniforms.ubos[0] =
+                            Some((UNIFORM_BLOCK_MATERIAL, buffer, offset, block.len()));
+                    }
+                    uniforms.ubos[1] = skin_ubo;
+                    if let Some(morph_targets) = &primitive.morph_targets {
+                        let morph = [morph_targets.get_uniform_block(&mesh.morph_weights)];
+                        let morph = bytemuck::cast_slice(&morph);
+                        let (buffer, offset) = draw_calls.allocate_uniform_block(morph);
+                        uniforms.ubos[2] = Some((UNIFORM_BLOCK_MORPH, buffer, offset, morph.len()));
+                        uniforms.textures[5] = Some((
+                            TEX_UNIT_MORPH_TARGETS,
+                            morph_targets.texture,
+                            morph_targets.sampler,
+                        ));
+                    }
                     draw_calls.add(
                         Some(&material.lights),
-                        &material.uniforms,
+                        &uniforms,
                         &draw_call,
                         model_transform,
                         transform,
                         Mat4::IDENTITY,
+                        bounding_sphere,
                     );
+                    let wireframe_draw_calls = draw_calls.wireframe_draw_calls();
+                    if let (Some(wireframe), Some(wireframe_draw_call)) =
+                        (wireframe_draw_calls, &primitive.wireframe_draw_call)
+                    {
+                        let mut wireframe_draw_call = wireframe_draw_call.clone();
+                        wireframe_draw_call.front_face = draw_call.front_face;
+                        // No lights, so that the lines are dark
+                        wireframe.add(
+                            None,
+                            &uniforms,
+                            &wireframe_draw_call,
+                            model_transform,
+                            transform,
+                            Mat4::IDENTITY,
+                            bounding_sphere,
+                        );
+                    }
                 }
             }
+            for &child_index in &node.child_node_indices {
+                node_queue.push((transform, child_index));
+            }
+        }
+    }
+
+    /// Returns the transforms of all the nodes in the scene, including their
+    /// parents' transforms.
+    fn get_global_transforms<F: Fn(usize) -> Mat4>(
+        &self,
+        model_transform: Mat4,
+        get_transform: &F,
+    ) -> Vec<Mat4> {
+        let mut global_transforms = vec![Mat4::IDENTITY; self.nodes.len()];
+        let scene = &self.scenes[self.scene];
+        let mut node_queue = scene
+            .node_indices
+            .iter()
+            .map(|&i| (model_transform, i))
+            .collect::<Vec<_>>();
+        while let Some((parent_transform, node_index)) = node_queue.pop() {
+            let transform = parent_transform * get_transform(node_index);
+            global_transforms[node_index] = transform;
             for &child_index in &self.nodes[node_index].child_node_indices {
                 node_queue.push((transform, child_index));
             }
         }
+        global_transforms
+    }
+}
+
+impl MorphTargets {
+    fn get_uniform_block(&self, weights: &[f32]) -> UniformBlockMorph {
+        let mut morph = UniformBlockMorph::zeroed();
+        for (i, &weight) in weights.iter().enumerate().take(self.target_count) {
+            morph.weights[i].x = weight;
+        }
+        morph.params.x = self.target_count as i32;
+        morph.params.y = self.vertex_count as i32;
+        morph
     }
 }
 
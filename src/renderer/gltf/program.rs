@@ -1,6 +1,6 @@
 use crate::renderer::gl;
 use bytemuck::{Pod, Zeroable};
-use glam::Vec4;
+use glam::{IVec4, Mat4, Vec4};
 
 pub const ATTR_LOC_POSITION: gl::types::GLuint = 0;
 pub const ATTR_LOC_NORMAL: gl::types::GLuint = 1;
@@ -10,17 +10,27 @@ pub const ATTR_LOC_TEXCOORD_1: gl::types::GLuint = 4;
 pub const ATTR_LOC_COLOR_0: gl::types::GLuint = 5;
 pub const ATTR_LOC_MODEL_TRANSFORM_COLUMNS: [gl::types::GLuint; 4] = [6, 7, 8, 9];
 pub const ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS: [gl::types::GLuint; 4] = [10, 11, 12, 13];
+pub const ATTR_LOC_JOINTS_0: gl::types::GLuint = 14;
+pub const ATTR_LOC_WEIGHTS_0: gl::types::GLuint = 15;
 
 pub const TEX_UNIT_BASE_COLOR: u32 = 0;
 pub const TEX_UNIT_METALLIC_ROUGHNESS: u32 = 1;
 pub const TEX_UNIT_NORMAL: u32 = 2;
 pub const TEX_UNIT_OCCLUSION: u32 = 3;
 pub const TEX_UNIT_EMISSIVE: u32 = 4;
+pub const TEX_UNIT_MORPH_TARGETS: u32 = 5;
 
 pub const UNIFORM_BLOCK_MATERIAL: u32 = 0;
 pub const UNIFORM_BLOCK_LIGHTS: u32 = 1;
+pub const UNIFORM_BLOCK_SKIN: u32 = 2;
+pub const UNIFORM_BLOCK_MORPH: u32 = 3;
 
 pub const MAX_LIGHTS: usize = 32;
+pub const MAX_JOINTS: usize = 64;
+pub const MAX_MORPH_TARGETS: usize = 8;
+/// The width of the morph target textures, which have the position deltas of
+/// each vertex of each target, one target after another.
+pub const MORPH_TEXTURE_WIDTH: usize = 1024;
 
 #[derive(Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
@@ -31,6 +41,11 @@ pub struct UniformBlockMaterial {
     pub normal_scale: f32,
     pub occlusion_strength: f32,
     pub emissive_factor: Vec4,
+    /// x: a bitmask of the texture units (1 << TEX_UNIT_*) whose textures are
+    /// sampled with TEXCOORD_1 instead of TEXCOORD_0, y: 1 if the material is
+    /// unlit (KHR_materials_unlit), z: 1 if the normal map is animated, see
+    /// [crate::renderer::gltf::Gltf::set_animated_normals], w: unused
+    pub flags: IVec4,
 }
 
 #[derive(Clone, Copy, PartialEq, Zeroable, Pod)]
@@ -45,24 +60,67 @@ pub struct UniformBlockLights {
     pub direction: [Vec4; MAX_LIGHTS],
 }
 
+#[derive(Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct UniformBlockSkin {
+    /// The transforms from each joint's bind pose to its current pose, relative
+    /// to the skinned mesh's node.
+    pub joint_matrices: [Mat4; MAX_JOINTS],
+}
+
+#[derive(Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct UniformBlockMorph {
+    /// x: the weight of the morph target, yzw: unused
+    pub weights: [Vec4; MAX_MORPH_TARGETS],
+    /// x: the amount of morph targets (0 if not morphed), y: vertex count
+    pub params: IVec4,
+}
+
 pub struct ShaderProgram {
     pub program: gl::types::GLuint,
     pub proj_from_view_location: gl::types::GLint,
     pub view_from_world_location: gl::types::GLint,
+    /// The time in seconds, for animating materials.
+    pub time_location: Option<gl::types::GLint>,
 }
 
 /// Compiles and returns the shader program which should be used to render the
 /// glTF models.
 pub fn create_program() -> ShaderProgram {
-    let vertex_shader = gl::create_shader(gl::VERTEX_SHADER, include_str!("gltf_vertex.glsl"));
-    let fragment_shader =
-        gl::create_shader(gl::FRAGMENT_SHADER, include_str!("gltf_fragment.glsl"));
-    let program = gl::create_program(&[vertex_shader, fragment_shader]);
+    let vertex_source = include_str!("gltf_vertex.glsl");
+    let fragment_source = include_str!("gltf_fragment.glsl");
+    create_program_from_sources(vertex_source, fragment_source)
+        .unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Like [create_program], but with the given shader sources, and returns
+/// the compilation errors instead of panicking. Used for reloading the
+/// shaders while the game is running.
+pub fn create_program_from_sources(
+    vertex_source: &str,
+    fragment_source: &str,
+) -> Result<ShaderProgram, String> {
+    let vertex_shader = gl::try_create_shader(gl::VERTEX_SHADER, vertex_source)?;
+    let fragment_shader = match gl::try_create_shader(gl::FRAGMENT_SHADER, fragment_source) {
+        Ok(shader) => shader,
+        Err(err) => {
+            gl::call!(gl::DeleteShader(vertex_shader));
+            return Err(err);
+        }
+    };
+    let program = gl::try_create_program(&[vertex_shader, fragment_shader]);
     gl::call!(gl::DeleteShader(vertex_shader));
     gl::call!(gl::DeleteShader(fragment_shader));
+    let program = program?;
     gl::call!(gl::UseProgram(program));
-    let proj_from_view_location = gl::get_uniform_location(program, "proj_from_view").unwrap();
-    let view_from_world_location = gl::get_uniform_location(program, "view_from_world").unwrap();
+    let (Some(proj_from_view_location), Some(view_from_world_location)) = (
+        gl::get_uniform_location(program, "proj_from_view"),
+        gl::get_uniform_location(program, "view_from_world"),
+    ) else {
+        gl::call!(gl::DeleteProgram(program));
+        return Err("the shader is missing the view or projection matrix uniform".to_string());
+    };
 
     if let Some(location) = gl::get_uniform_location(program, "base_color_tex") {
         gl::call!(gl::Uniform1i(location, TEX_UNIT_BASE_COLOR as i32));
@@ -79,6 +137,9 @@ pub fn create_program() -> ShaderProgram {
     if let Some(location) = gl::get_uniform_location(program, "emissive_tex") {
         gl::call!(gl::Uniform1i(location, TEX_UNIT_EMISSIVE as i32));
     }
+    if let Some(location) = gl::get_uniform_location(program, "morph_targets_tex") {
+        gl::call!(gl::Uniform1i(location, TEX_UNIT_MORPH_TARGETS as i32));
+    }
     if let Some(loc) = gl::get_uniform_block_index(program, "Material") {
         let binding = UNIFORM_BLOCK_MATERIAL;
         gl::call!(gl::UniformBlockBinding(program, loc, binding));
@@ -87,10 +148,19 @@ pub fn create_program() -> ShaderProgram {
         let binding = UNIFORM_BLOCK_LIGHTS;
         gl::call!(gl::UniformBlockBinding(program, loc, binding));
     }
+    if let Some(loc) = gl::get_uniform_block_index(program, "Skin") {
+        let binding = UNIFORM_BLOCK_SKIN;
+        gl::call!(gl::UniformBlockBinding(program, loc, binding));
+    }
+    if let Some(loc) = gl::get_uniform_block_index(program, "Morph") {
+        let binding = UNIFORM_BLOCK_MORPH;
+        gl::call!(gl::UniformBlockBinding(program, loc, binding));
+    }
 
-    ShaderProgram {
+    Ok(ShaderProgram {
         program,
         proj_from_view_location,
         view_from_world_location,
-    }
+        time_location: gl::get_uniform_location(program, "time"),
+    })
 }
@@ -0,0 +1,74 @@
+use crate::renderer::camera::Camera;
+use crate::renderer::framebuffer::Framebuffer;
+use crate::renderer::{gl, gltf, DrawCalls, FrameStats};
+use glam::{Mat4, Vec3};
+use std::f32::consts::TAU;
+
+/// The width and height of the minimap's framebuffer, in pixels.
+const MINIMAP_SIZE: i32 = 256;
+/// Half of the width of the area shown on the minimap, in world units.
+const MINIMAP_EXTENT: f32 = 20.0;
+
+/// Renders the ship from straight above into a small framebuffer, to be
+/// drawn in the corner of the screen.
+pub struct MinimapRenderer {
+    pub camera: Camera,
+    pub fbo: Framebuffer,
+    pub draw_calls: DrawCalls,
+}
+
+impl MinimapRenderer {
+    pub fn new() -> MinimapRenderer {
+        let mut camera = Camera::new();
+        camera.pitch = TAU / 4.0;
+        camera.distance = 60.0;
+        camera.focus = Vec3::ZERO;
+        camera.orthographic = true;
+        MinimapRenderer {
+            camera,
+            fbo: Framebuffer::new(MINIMAP_SIZE, MINIMAP_SIZE),
+            draw_calls: DrawCalls::new(),
+        }
+    }
+
+    /// Draws the draw calls into the framebuffer. Leaves the framebuffer
+    /// bound.
+    pub fn render(&mut self, shader: &gltf::ShaderProgram) -> FrameStats {
+        let view = self.camera.view_matrix();
+        let proj = Mat4::orthographic_rh_gl(
+            -MINIMAP_EXTENT,
+            MINIMAP_EXTENT,
+            -MINIMAP_EXTENT,
+            MINIMAP_EXTENT,
+            200.0,
+            0.3,
+        ) * super::to_opengl_basis();
+
+        self.fbo.bind();
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::ClearColor(0.1, 0.08, 0.06, 1.0));
+        gl::call!(gl::ClearDepthf(0.0));
+        gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+        gl::call!(gl::Enable(gl::CULL_FACE));
+        gl::call!(gl::Enable(gl::DEPTH_TEST));
+        gl::call!(gl::DepthFunc(gl::GREATER));
+
+        gl::call!(gl::UseProgram(shader.program));
+        gl::call!(gl::UniformMatrix4fv(
+            shader.proj_from_view_location,
+            1,
+            gl::FALSE,
+            proj.to_cols_array().as_ptr(),
+        ));
+        gl::call!(gl::UniformMatrix4fv(
+            shader.view_from_world_location,
+            1,
+            gl::FALSE,
+            view.to_cols_array().as_ptr(),
+        ));
+        self.draw_calls.draw(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        )
+    }
+}
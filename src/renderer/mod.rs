@@ -1,20 +1,46 @@
 use crate::{
-    interface::{Button, Interface, Tab},
-    ship_game::{Character, Job, RoomType, ShipGame, Task},
+    interface::{BuildTool, Button, Interface, PendingAction, Tab},
+    math::{Circle2, Frustum},
+    settings::{GameOption, KeyAction, Settings},
+    ship_game::{
+        splitmix64, Character, Delivery, Job, Location, LogCategory, RoomType, ShipGame,
+        ShipModels, Task, AUTOSAVE_SLOT, DAMAGED_HULL, MAX_CARGO_WEIGHT, MAX_SHIP_SPEED,
+        SCHEDULE_SLOTS,
+    },
 };
 use fontdue::layout::{HorizontalAlign, VerticalAlign};
 use glam::{IVec2, Mat4, Quat, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
-use sdl2::rect::Rect;
+use sdl2::rect::{Point, Rect};
 use std::f32::consts::TAU;
+use std::ffi::c_void;
+use std::fmt::{self, Display, Formatter};
+#[cfg(debug_assertions)]
+use std::{path::PathBuf, time::SystemTime};
 
+mod bloom;
 mod bumpalloc_buffer;
 mod camera;
+mod dof;
 mod draw_calls;
 mod font_renderer;
+mod framebuffer;
 pub mod gl;
 pub mod gltf;
+mod minimap;
+mod particles;
+mod skybox;
+mod ssao;
 
-pub use draw_calls::DrawCalls;
+use bloom::BloomPass;
+use bumpalloc_buffer::BumpAllocatedBuffer;
+use dof::DofPass;
+pub use draw_calls::{DrawCalls, FrameStats};
+use font_renderer::{FontRenderer, TextStyle};
+use framebuffer::{Framebuffer, FullscreenQuad};
+use minimap::MinimapRenderer;
+use particles::{Particle, ParticleEmitter};
+use skybox::Skybox;
+use ssao::SsaoPass;
 
 /// The "up" vector in world-space (which is in glTF's coordinate system, for
 /// now).
@@ -25,130 +51,951 @@ pub const RIGHT: Vec3 = Vec3::new(-1.0, 0.0, 0.0);
 /// The "forward" vector in world-space (which is in glTF's coordinate system,
 /// for now).
 pub const FORWARD: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+/// A sphere (center and radius) containing the character models.
+const CHARACTER_BOUNDING_SPHERE: (Vec3, f32) = (Vec3::new(0.0, 0.77, 0.0), 0.7);
+/// Where the ship's wake starts, at the stern.
+const WAKE_POSITION: Vec3 = Vec3::new(0.0, 0.0, -16.5);
+/// How many wake particles are spawned per second at full speed.
+const WAKE_MAX_EMISSION_RATE: f32 = 30.0;
+/// The height of the water surface, below the floors of the rooms.
+const WATER_LEVEL: f32 = -4.0;
+/// The width of each water tile, which is also how often the normal map
+/// repeats.
+const WATER_TILE_SIZE: f32 = 16.0;
+/// How many water tiles there are on each side of the ship.
+const WATER_TILE_RADIUS: i32 = 12;
+/// The warm amber color of the text printed on the dashboard itself, as
+/// opposed to the text on the screen.
+const DASHBOARD_TEXT_COLOR: Vec4 = Vec4::new(1.0, 0.55, 0.1, 1.0);
+/// The area [Renderer::clip_to_ship_space] returns points from.
+const SHIP_SPACE_BOUNDS: Circle2 = Circle2 {
+    center: Vec2::ZERO,
+    radius: 100.0,
+};
+/// The color of the docked status in the Navigation tab.
+const DOCKED_TEXT_COLOR: Vec4 = Vec4::new(0.3, 1.0, 0.4, 1.0);
+/// The colors of the ETA in the Navigation tab, for trips under a day, one
+/// to three days, and longer.
+const ETA_TEXT_COLORS: [Vec4; 3] = [
+    DOCKED_TEXT_COLOR,
+    Vec4::new(1.0, 0.9, 0.2, 1.0),
+    FAILED_TEXT_COLOR,
+];
+/// The color of failed deliveries in the Deliveries tab.
+const FAILED_TEXT_COLOR: Vec4 = Vec4::new(1.0, 0.25, 0.2, 1.0);
+/// The tab labels are outlined to stand out from the dark dashboard.
+const TAB_LABEL_STYLE: TextStyle = TextStyle {
+    font_index: FontRenderer::FONT_DEFAULT,
+    color: Vec4::ONE,
+    shadow: None,
+    outline: Some((1.0, Vec4::new(0.0, 0.0, 0.0, 1.0))),
+};
+
+/// The GPU memory used by the bump allocated buffers of the renderer, see
+/// [Renderer::memory_stats].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RendererMemoryStats {
+    pub allocated_bytes: usize,
+    pub capacity_bytes: usize,
+    /// Bytes lost to alignment padding.
+    pub wasted_bytes: usize,
+    /// Buffers whose contents on the GPU don't match what was written into
+    /// them, only checked in debug builds.
+    pub mismatched_buffers: usize,
+}
+
+impl RendererMemoryStats {
+    pub fn add_buffer(&mut self, buffer: &BumpAllocatedBuffer) {
+        self.allocated_bytes += buffer.allocated_bytes();
+        self.capacity_bytes += buffer.capacity_bytes();
+        self.wasted_bytes +=
+            (buffer.fragmentation_ratio() * buffer.capacity_bytes() as f32).round() as usize;
+        #[cfg(all(debug_assertions, not(target_family = "wasm")))]
+        if !buffer.validate_contents() {
+            self.mismatched_buffers += 1;
+        }
+    }
+
+    /// The bytes lost to alignment padding, relative to the total capacity.
+    pub fn fragmentation_ratio(&self) -> f32 {
+        if self.capacity_bytes == 0 {
+            0.0
+        } else {
+            self.wasted_bytes as f32 / self.capacity_bytes as f32
+        }
+    }
+}
+
+impl Display for RendererMemoryStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} KiB allocated out of {:.1} KiB, {:.1}% lost to alignment",
+            self.allocated_bytes as f32 / 1024.0,
+            self.capacity_bytes as f32 / 1024.0,
+            self.fragmentation_ratio() * 100.0,
+        )?;
+        if self.mismatched_buffers > 0 {
+            write!(
+                f,
+                ", {} buffers don't match what was written",
+                self.mismatched_buffers,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How brightly the sun lights the ship, at noon.
+const SUN_INTENSITY: f32 = 0.4;
+
+/// How often the shader files are checked for changes, in seconds.
+#[cfg(debug_assertions)]
+const SHADER_RELOAD_INTERVAL: f32 = 2.0;
 
 pub struct Renderer {
     gltf_shader: gltf::ShaderProgram,
+    /// The vertex and fragment shader source files, if they can be found,
+    /// for reloading [Renderer::gltf_shader] when they're edited.
+    #[cfg(debug_assertions)]
+    shader_files: Option<(PathBuf, PathBuf)>,
+    /// When the shader files were last loaded.
+    #[cfg(debug_assertions)]
+    shader_load_time: SystemTime,
+    /// Counts down to the next time the shader files are checked.
+    #[cfg(debug_assertions)]
+    shader_reload_timer: f32,
+    /// The world is rendered into this, and then drawn on the window, under
+    /// the UI.
+    scene_fbo: Framebuffer,
+    skybox: Skybox,
+    fullscreen_quad: FullscreenQuad,
+    bloom: BloomPass,
+    dof: DofPass,
+    ssao: SsaoPass,
+    /// Whether [SsaoPass] is used. Off on the web by default, since it's
+    /// expensive for integrated GPUs.
+    pub ssao_enabled: bool,
     draw_calls: DrawCalls,
+    /// The selected character, drawn after `draw_calls` while marking it in
+    /// the stencil buffer.
+    selected_draw_calls: DrawCalls,
+    /// A slightly larger, flat colored version of the selected character,
+    /// drawn where the stencil isn't marked, resulting in an outline.
+    outline_draw_calls: DrawCalls,
     ui_draw_calls: DrawCalls,
+    /// Translucent UI elements, i.e. text, which are drawn after
+    /// `ui_draw_calls`, back-to-front.
+    transparent_ui_draw_calls: DrawCalls,
+    /// The contents of the dashboard's screen, clipped to the screen area.
+    screen_draw_calls: DrawCalls,
+    /// Like `transparent_ui_draw_calls`, but clipped to the screen area.
+    transparent_screen_draw_calls: DrawCalls,
+    last_frame_stats: FrameStats,
+    /// Debug mode where the edges of the triangles in the world are drawn
+    /// on top of them.
+    wireframe_mode: bool,
     camera: camera::Camera,
     text: font_renderer::FontRenderer,
 
     debug_arrow: gltf::Gltf,
     ship: gltf::Gltf,
-    pub room_sailing: gltf::Gltf,
-    pub room_navigation: gltf::Gltf,
-    characters: [gltf::Gltf; Job::Count as usize],
+    room_sailing: gltf::Gltf,
+    room_navigation: gltf::Gltf,
+    room_kitchen: gltf::Gltf,
+    room_engine: gltf::Gltf,
+    /// The character models, which characters are drawn with depending on
+    /// their distance from the camera. The levels of detail of each model
+    /// need to have the same animations as [CHARACTER_MODELS], since the
+    /// characters' animation states are created from those.
+    pub lod_characters: [gltf::LodGltf; Job::Count as usize],
+    /// What the simulation needs from the room and character models.
+    pub ship_models: ShipModels,
     dashboard: gltf::Gltf,
     pixel_gray: gltf::Gltf,
     pixel_green: gltf::Gltf,
+    ocean_tile: gltf::Gltf,
+    /// A yellow texture which is swapped in as the base color texture of the
+    /// map table in `room_navigation` while the ship is sailing fast.
+    navigation_map_lit_texture: u32,
+    /// Shows the ship from above in the corner of the screen, if enabled.
+    minimap: Option<MinimapRenderer>,
+    /// The ocean tile, with an animated normal map, tiled under the ship.
+    water: gltf::Gltf,
+    /// The water's normal map texture and sampler.
+    water_normal_texture: (u32, u32),
+    /// The original texture and sampler of the map table, while the lit
+    /// texture is swapped in.
+    navigation_map_unlit_texture: Option<(u32, u32)>,
+    /// A soot-colored texture which is swapped in as the base color texture
+    /// of the ship's hull when it's badly damaged.
+    ship_damaged_texture: u32,
+    /// The original texture and sampler of the hull, while the damaged
+    /// texture is swapped in.
+    ship_undamaged_texture: Option<(u32, u32)>,
+    /// The foam behind the ship, which gets thicker the faster it sails.
+    wake: ParticleEmitter,
+    /// The `time` passed to the previous [Renderer::render], for animating
+    /// the particles.
+    last_render_time: f32,
 }
 
-impl Renderer {
-    pub fn new() -> Renderer {
-        let debug_arrow = gltf::load_glb(include_bytes!("../../resources/models/debug_arrow.glb"));
-        let ship = gltf::load_glb(include_bytes!("../../resources/models/ship.glb"));
-        let room_sailing =
-            gltf::load_glb(include_bytes!("../../resources/models/room_sailing.glb"));
-        let room_navigation =
-            gltf::load_glb(include_bytes!("../../resources/models/room_navigation.glb"));
-        let navigator = gltf::load_glb(include_bytes!("../../resources/models/navigator.glb"));
-        let sailor = gltf::load_glb(include_bytes!("../../resources/models/sailor.glb"));
-        let dashboard = gltf::load_glb(include_bytes!("../../resources/models/dashboard.glb"));
-        let pixel_gray = gltf::load_glb(include_bytes!("../../resources/models/pixel_gray.glb"));
-        let pixel_green = gltf::load_glb(include_bytes!("../../resources/models/pixel_green.glb"));
+/// The room models, in the order of [RoomType::ALL]. The simulation reads
+/// these too, see [ShipModels].
+pub const ROOM_MODELS: [&[u8]; 4] = [
+    include_bytes!("../../resources/models/room_navigation.glb"),
+    include_bytes!("../../resources/models/room_sailing.glb"),
+    // The kitchen and the engine room don't have models of their own yet
+    PLAIN_ROOM_MODEL,
+    PLAIN_ROOM_MODEL,
+];
+const PLAIN_ROOM_MODEL: &[u8] = include_bytes!("../../resources/models/room.glb");
+
+/// The character models of each [Job]. The simulation reads these too, see
+/// [ShipModels].
+pub const CHARACTER_MODELS: [&[u8]; Job::Count as usize] = [
+    include_bytes!("../../resources/models/navigator.glb"),
+    SAILOR_MODEL,
+    // The cook and the engineer don't have models of their own yet
+    SAILOR_MODEL,
+    SAILOR_MODEL,
+];
+const SAILOR_MODEL: &[u8] = include_bytes!("../../resources/models/sailor.glb");
+
+/// The models loaded by [RendererLoader], in the order they're loaded.
+const MODELS: [(&str, &[u8]); 15] = [
+    (
+        "debug arrow",
+        include_bytes!("../../resources/models/debug_arrow.glb"),
+    ),
+    ("ship", include_bytes!("../../resources/models/ship.glb")),
+    ("sails", ROOM_MODELS[RoomType::Sails as usize]),
+    (
+        "navigation room",
+        ROOM_MODELS[RoomType::Navigation as usize],
+    ),
+    ("kitchen", ROOM_MODELS[RoomType::Kitchen as usize]),
+    ("engine room", ROOM_MODELS[RoomType::Engine as usize]),
+    ("navigator", CHARACTER_MODELS[Job::Navigator as usize]),
+    ("sailor", CHARACTER_MODELS[Job::Sailor as usize]),
+    ("cook", CHARACTER_MODELS[Job::Cook as usize]),
+    ("engineer", CHARACTER_MODELS[Job::Engineer as usize]),
+    (
+        "dashboard",
+        include_bytes!("../../resources/models/dashboard.glb"),
+    ),
+    (
+        "pixel",
+        include_bytes!("../../resources/models/pixel_gray.glb"),
+    ),
+    (
+        "pixel",
+        include_bytes!("../../resources/models/pixel_green.glb"),
+    ),
+    (
+        "ocean",
+        include_bytes!("../../resources/models/ocean_tile.glb"),
+    ),
+    (
+        "water",
+        include_bytes!("../../resources/models/ocean_tile.glb"),
+    ),
+];
+
+pub struct LoadProgress {
+    /// How many steps have been loaded, including the one just loaded.
+    pub current: usize,
+    pub total: usize,
+    /// What was just loaded.
+    #[cfg_attr(not(target_family = "wasm"), allow(dead_code))]
+    pub label: &'static str,
+}
+
+/// Loads the renderer's resources one at a time, so that a loading screen
+/// can be drawn in between. Each call to `next` loads the next resource, and
+/// after it returns None, [RendererLoader::finish] creates the [Renderer].
+pub struct RendererLoader {
+    models: Vec<gltf::Gltf>,
+    text: Option<font_renderer::FontRenderer>,
+}
+
+impl Iterator for RendererLoader {
+    type Item = LoadProgress;
+
+    fn next(&mut self) -> Option<LoadProgress> {
+        // The models, and then the fonts
+        let total = MODELS.len() + 1;
+        let label = if let Some(&(label, glb)) = MODELS.get(self.models.len()) {
+            self.models.push(gltf::load_glb(glb));
+            label
+        } else if self.text.is_none() {
+            self.text = Some(font_renderer::FontRenderer::new());
+            "fonts"
+        } else {
+            return None;
+        };
+        Some(LoadProgress {
+            current: self.models.len() + self.text.is_some() as usize,
+            total,
+            label,
+        })
+    }
+}
+
+impl RendererLoader {
+    pub fn finish(mut self) -> Renderer {
+        assert!(
+            self.next().is_none(),
+            "the renderer should be fully loaded before finishing",
+        );
+        let text = self.text.unwrap();
+        // In the same order as in MODELS
+        let mut models = self.models.into_iter();
+        let mut next_model = || models.next().unwrap();
+        let mut navigation_map_lit_texture = 0;
+        gl::call!(gl::GenTextures(1, &mut navigation_map_lit_texture));
+        gl::write_1px_rgb_texture(navigation_map_lit_texture, [0xFF, 0xE0, 0x60]);
+        let mut ship_damaged_texture = 0;
+        gl::call!(gl::GenTextures(1, &mut ship_damaged_texture));
+        gl::write_1px_rgb_texture(ship_damaged_texture, [0x50, 0x44, 0x3C]);
+        let water_normal_texture = create_water_normal_texture();
         Renderer {
             gltf_shader: gltf::create_program(),
+            #[cfg(debug_assertions)]
+            shader_files: find_shader_files(),
+            #[cfg(debug_assertions)]
+            shader_load_time: SystemTime::now(),
+            #[cfg(debug_assertions)]
+            shader_reload_timer: SHADER_RELOAD_INTERVAL,
+            // Same amount of samples as the window
+            scene_fbo: Framebuffer::new_multisampled(1, 1, 4),
+            skybox: Skybox::new(),
+            fullscreen_quad: FullscreenQuad::new(),
+            bloom: BloomPass::new(),
+            dof: DofPass::new(),
+            ssao: SsaoPass::new(),
+            ssao_enabled: cfg!(not(target_family = "wasm")),
             draw_calls: DrawCalls::new(),
+            selected_draw_calls: DrawCalls::new(),
+            outline_draw_calls: DrawCalls::new(),
             ui_draw_calls: DrawCalls::new(),
+            transparent_ui_draw_calls: DrawCalls::new(),
+            screen_draw_calls: DrawCalls::new(),
+            transparent_screen_draw_calls: DrawCalls::new(),
+            last_frame_stats: FrameStats::default(),
+            wireframe_mode: false,
             camera: camera::Camera::new(),
-            text: font_renderer::FontRenderer::new(),
-            debug_arrow,
-            ship,
-            room_sailing,
-            room_navigation,
-            characters: [navigator, sailor],
-            dashboard,
-            pixel_gray,
-            pixel_green,
+            text,
+            debug_arrow: next_model(),
+            ship: next_model(),
+            room_sailing: next_model(),
+            room_navigation: next_model(),
+            room_kitchen: next_model(),
+            room_engine: next_model(),
+            ship_models: ShipModels::load(),
+            // TODO: Add lower detail levels once there are models for them
+            lod_characters: [(); Job::Count as usize]
+                .map(|_| gltf::LodGltf::new(vec![(0.0, next_model())])),
+            dashboard: next_model(),
+            pixel_gray: next_model(),
+            pixel_green: next_model(),
+            ocean_tile: next_model(),
+            minimap: Some(MinimapRenderer::new()),
+            water: create_water(next_model(), water_normal_texture),
+            water_normal_texture,
+            navigation_map_lit_texture,
+            navigation_map_unlit_texture: None,
+            ship_damaged_texture,
+            ship_undamaged_texture: None,
+            wake: create_wake_emitter(),
+            last_render_time: 0.0,
+        }
+    }
+}
+
+fn create_water(mut water: gltf::Gltf, normal_texture: (u32, u32)) -> gltf::Gltf {
+    let material = water.get_material_index_by_name("Ocean").unwrap();
+    let (texture, sampler) = normal_texture;
+    water.swap_texture(material, gltf::TEX_UNIT_NORMAL, texture, sampler);
+    water.set_animated_normals(material);
+    water
+}
+
+/// Creates a tiling normal map of overlapping waves, and a sampler for it.
+fn create_water_normal_texture() -> (u32, u32) {
+    const SIZE: usize = 64;
+    // (frequency in x, frequency in y, amplitude), with whole frequencies so
+    // that the waves tile
+    const WAVES: [(f32, f32, f32); 4] = [
+        (1.0, 2.0, 0.5),
+        (-3.0, 1.0, 0.25),
+        (4.0, 5.0, 0.1),
+        (-7.0, -6.0, 0.05),
+    ];
+    let mut pixels = Vec::with_capacity(SIZE * SIZE * 3);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let (u, v) = (x as f32 / SIZE as f32, y as f32 / SIZE as f32);
+            let mut slope = Vec2::ZERO;
+            for (fx, fy, amplitude) in WAVES {
+                let phase = TAU * (fx * u + fy * v);
+                slope += Vec2::new(fx, fy) * amplitude * phase.cos();
+            }
+            let normal = Vec3::new(-slope.x * 0.2, -slope.y * 0.2, 1.0).normalize();
+            let rgb = (normal * 0.5 + 0.5) * 255.0;
+            pixels.extend_from_slice(&[rgb.x as u8, rgb.y as u8, rgb.z as u8]);
+        }
+    }
+    let mut texture = 0;
+    gl::call!(gl::GenTextures(1, &mut texture));
+    gl::call!(gl::BindTexture(gl::TEXTURE_2D, texture));
+    gl::call!(gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RGB8 as i32,
+        SIZE as i32,
+        SIZE as i32,
+        0,
+        gl::RGB,
+        gl::UNSIGNED_BYTE,
+        pixels.as_ptr() as *const c_void,
+    ));
+    gl::call!(gl::GenerateMipmap(gl::TEXTURE_2D));
+    gl::call!(gl::BindTexture(gl::TEXTURE_2D, 0));
+    let mut sampler = 0;
+    gl::call!(gl::GenSamplers(1, &mut sampler));
+    gl::setup_linear_sampler(sampler, true);
+    (texture, sampler)
+}
+
+fn create_wake_emitter() -> ParticleEmitter {
+    let mut spawn_count = 0;
+    let spawn_fn = move || {
+        spawn_count += 1;
+        let random = splitmix64(spawn_count);
+        // Four random numbers between 0 and 1
+        let r = |i: u64| ((random >> (i * 16)) & 0xFFFF) as f32 / 0xFFFF as f32;
+        Particle {
+            position: WAKE_POSITION + Vec3::X * (r(0) * 4.0 - 2.0),
+            velocity: Vec3::new(r(1) * 2.0 - 1.0, 0.3 + r(2) * 0.3, -2.0),
+            lifetime: 2.0,
+            max_lifetime: 2.0,
+            scale: 0.4 + r(3) * 0.3,
+        }
+    };
+    ParticleEmitter::new(0.0, Box::new(spawn_fn))
+}
+
+/// Finds the glTF shaders' source files, which only exist when running from
+/// the source tree (e.g. with cargo run).
+#[cfg(debug_assertions)]
+fn find_shader_files() -> Option<(PathBuf, PathBuf)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/renderer/gltf");
+    let vertex = dir.join("gltf_vertex.glsl");
+    let fragment = dir.join("gltf_fragment.glsl");
+    (vertex.is_file() && fragment.is_file()).then_some((vertex, fragment))
+}
+
+/// Draws a progress bar on the window, with plain clears, since nothing
+/// needed for proper rendering has been loaded yet.
+pub fn render_loading_screen(progress: &LoadProgress, width: i32, height: i32) {
+    gl::call!(gl::Viewport(0, 0, width, height));
+    gl::call!(gl::ClearColor(0.2, 0.4, 0.2, 1.0));
+    gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT));
+
+    let (bar_width, bar_height) = (width / 2, (height / 40).max(4));
+    let (x, y) = ((width - bar_width) / 2, (height - bar_height) / 2);
+    let filled_width = bar_width * progress.current as i32 / progress.total as i32;
+    gl::call!(gl::Enable(gl::SCISSOR_TEST));
+    gl::call!(gl::Scissor(x, y, bar_width, bar_height));
+    gl::call!(gl::ClearColor(0.1, 0.2, 0.1, 1.0));
+    gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT));
+    gl::call!(gl::Scissor(x, y, filled_width, bar_height));
+    gl::call!(gl::ClearColor(0.6, 0.9, 0.5, 1.0));
+    gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT));
+    gl::call!(gl::Disable(gl::SCISSOR_TEST));
+}
+
+impl Renderer {
+    /// Starts loading the renderer, see [RendererLoader].
+    pub fn begin_load() -> RendererLoader {
+        RendererLoader {
+            models: Vec::with_capacity(MODELS.len()),
+            text: None,
         }
     }
 
+    /// Reads the window's pixels, as RGBA, starting from the bottom row. The
+    /// window is multisampled, so it's resolved into a temporary framebuffer
+    /// first, since multisampled framebuffers can't be read from directly.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn read_window_pixels(&self, width: i32, height: i32) -> Vec<u8> {
+        let resolved = Framebuffer::new(width, height);
+        gl::call!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0));
+        gl::call!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, resolved.fbo));
+        gl::call!(gl::BlitFramebuffer(
+            0,
+            0,
+            width,
+            height,
+            0,
+            0,
+            width,
+            height,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        ));
+        gl::call!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, resolved.fbo));
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        gl::call!(gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        ));
+        Framebuffer::unbind();
+        pixels
+    }
+
     pub fn clip_to_ship_space(&self, clip_coords: Vec2, aspect_ratio: f32) -> Vec2 {
         let clip_vec = Vec4::new(clip_coords.x, clip_coords.y, 1.0, 1.0);
         let (view, proj) = self.get_view_and_proj_matrices(aspect_ratio);
         let view_inv = view.inverse();
         let proj_inv = proj.inverse();
-        let mut view_point = proj_inv * clip_vec;
-        view_point /= view_point.w;
-        let view_point = Vec4::from((view_point.xyz().normalize(), 0.0));
-        let look_dir = (view_inv * view_point).xyz().normalize();
-        let ship_coord = if look_dir.dot(Vec3::Y) >= 0.0 {
-            Vec2::new(f32::INFINITY, f32::INFINITY)
+        let (origin, look_dir) = if self.camera.orthographic {
+            // All rays are parallel, starting from the near plane.
+            let near_point = proj_inv * clip_vec;
+            let far_point = proj_inv * Vec4::new(clip_coords.x, clip_coords.y, -1.0, 1.0);
+            let origin = (view_inv * near_point).xyz();
+            let look_dir = (view_inv * (far_point - near_point)).xyz().normalize();
+            (origin, look_dir)
         } else {
+            let mut view_point = proj_inv * clip_vec;
+            view_point /= view_point.w;
+            let view_point = Vec4::from((view_point.xyz().normalize(), 0.0));
+            let look_dir = (view_inv * view_point).xyz().normalize();
             let origin = (view_inv * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+            (origin, look_dir)
+        };
+        if look_dir.dot(Vec3::Y) >= 0.0 {
+            // Pointing at the sky, as far away as possible in the look
+            // direction
+            let direction = look_dir.xz().normalize_or_zero();
+            SHIP_SPACE_BOUNDS.center + direction * SHIP_SPACE_BOUNDS.radius
+        } else {
             let length = (origin.y / look_dir.y).abs();
             let floor_point = origin + look_dir * length;
-            floor_point.xz()
-        };
-        let maximum_distance = 100.0;
-        ship_coord.clamp(-Vec2::ONE * maximum_distance, Vec2::ONE * maximum_distance)
+            SHIP_SPACE_BOUNDS.clamp(floor_point.xz())
+        }
+    }
+
+    /// The direction from the ground towards the camera, normalized.
+    pub fn camera_eye_direction(&self) -> Vec3 {
+        self.camera.eye_direction()
     }
 
-    pub fn move_camera(&mut self, x: f32, y: f32) {
-        // TODO: Add camera move sensitivity
-        let sensitivity = Vec2::ONE * 0.4 * self.camera.distance;
+    /// Pushes the camera. The velocity is scaled by the damping so that the
+    /// camera ends up travelling the same distance as the mouse was dragged.
+    pub fn move_camera(&mut self, x: f32, y: f32, sensitivity: f32) {
+        let sensitivity = Vec2::ONE * 0.4 * sensitivity * self.camera.distance;
         let view_space_move = Vec3::new(x * sensitivity.x, 0.0, y * sensitivity.y);
         let world_space_move =
             Quat::from_rotation_y(-(self.camera.yaw + TAU / 2.0)) * view_space_move;
+        self.camera.velocity += world_space_move * self.camera.damping;
+        self.camera.focus_target = None;
+    }
+
+    pub fn toggle_minimap(&mut self) {
+        self.minimap = match self.minimap {
+            Some(_) => None,
+            None => Some(MinimapRenderer::new()),
+        };
+    }
+
+    /// Applies the camera's limits from the settings.
+    pub fn apply_camera_settings(&mut self, settings: &Settings) {
+        self.camera.set_limits(camera::CameraLimits {
+            max_distance: settings.max_camera_distance,
+            ..camera::CameraLimits::default()
+        });
+    }
+
+    pub fn rotate_camera(&mut self, x: i32, y: i32, sensitivity: f32) {
+        let sensitivity = Vec2::ONE * 0.004 * sensitivity;
+        let rotation = Vec2::new(x as f32, y as f32) * sensitivity;
+        self.camera.angular_velocity += rotation * self.camera.damping;
+    }
+
+    /// Smoothly moves the camera focus towards `world_pos`. The camera can't
+    /// leave the ship, so far away positions just pan the camera in their
+    /// direction.
+    pub fn animate_camera_to(&mut self, world_pos: Vec2) {
+        let target = Vec3::new(world_pos.x, self.camera.focus.y, world_pos.y);
         // TODO: Use the bounds of all the rooms here
-        self.camera.focus =
-            (self.camera.focus + world_space_move).clamp(Vec3::ONE * -10.0, Vec3::ONE * 10.0);
+        self.camera.focus_target = Some(target.clamp(Vec3::ONE * -10.0, Vec3::ONE * 10.0));
+    }
+
+    pub fn toggle_orthographic_camera(&mut self) {
+        self.camera.orthographic = !self.camera.orthographic;
+    }
+
+    pub fn toggle_wireframe_mode(&mut self) {
+        self.wireframe_mode = !self.wireframe_mode;
+        self.draw_calls.set_wireframe(self.wireframe_mode);
+    }
+
+    /// Lights up the map table in the navigation room when the ship is
+    /// sailing at almost full speed, by swapping its texture.
+    fn update_navigation_map(&mut self, ship_speed: f32) {
+        let lit = ship_speed > MAX_SHIP_SPEED * 0.75;
+        if lit == self.navigation_map_unlit_texture.is_some() {
+            return;
+        }
+        let map = self
+            .room_navigation
+            .get_material_index_by_name("Map")
+            .unwrap();
+        let current = self
+            .room_navigation
+            .get_texture(map, gltf::TEX_UNIT_BASE_COLOR)
+            .unwrap();
+        let (texture, sampler) = match self.navigation_map_unlit_texture.take() {
+            Some(unlit) => unlit,
+            None => {
+                self.navigation_map_unlit_texture = Some(current);
+                (self.navigation_map_lit_texture, current.1)
+            }
+        };
+        self.room_navigation
+            .swap_texture(map, gltf::TEX_UNIT_BASE_COLOR, texture, sampler);
+    }
+
+    /// Darkens the ship's hull when it's badly damaged, by swapping its
+    /// texture.
+    fn update_ship_damage(&mut self, hull_integrity: f32) {
+        let damaged = hull_integrity < DAMAGED_HULL;
+        if damaged == self.ship_undamaged_texture.is_some() {
+            return;
+        }
+        let hull = self.ship.get_material_index_by_name("Ship").unwrap();
+        let current = self
+            .ship
+            .get_texture(hull, gltf::TEX_UNIT_BASE_COLOR)
+            .unwrap();
+        let (texture, sampler) = match self.ship_undamaged_texture.take() {
+            Some(undamaged) => undamaged,
+            None => {
+                self.ship_undamaged_texture = Some(current);
+                (self.ship_damaged_texture, current.1)
+            }
+        };
+        self.ship
+            .swap_texture(hull, gltf::TEX_UNIT_BASE_COLOR, texture, sampler);
+    }
+
+    pub fn update_camera(&mut self, dt: f32) {
+        self.camera.update(dt);
     }
 
-    pub fn rotate_camera(&mut self, x: i32, y: i32) {
-        // TODO: Add camera rotation sensitivity
-        let sensitivity = Vec2::ONE * 0.004;
-        self.camera.yaw += x as f32 * sensitivity.x;
-        self.camera.pitch = (self.camera.pitch + y as f32 * sensitivity.y)
-            .clamp(30.0 / 360.0 * TAU, 90.0 / 360.0 * TAU);
+    /// Every few seconds, checks if the glTF shaders' source files have been
+    /// modified, and if so, recompiles the shader from them. If compiling
+    /// fails, the errors are printed out and the old shader is kept.
+    #[cfg(debug_assertions)]
+    pub fn reload_shaders_if_changed(&mut self, dt: f32) {
+        self.shader_reload_timer -= dt;
+        if self.shader_reload_timer > 0.0 {
+            return;
+        }
+        self.shader_reload_timer = SHADER_RELOAD_INTERVAL;
+        let Some((vertex_path, fragment_path)) = &self.shader_files else {
+            return;
+        };
+
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let changed = [vertex_path, fragment_path]
+            .into_iter()
+            .filter_map(modified)
+            .any(|time| time > self.shader_load_time);
+        if !changed {
+            return;
+        }
+        self.shader_load_time = SystemTime::now();
+
+        let sources = std::fs::read_to_string(vertex_path)
+            .and_then(|vertex| Ok((vertex, std::fs::read_to_string(fragment_path)?)));
+        let (vertex_source, fragment_source) = match sources {
+            Ok(sources) => sources,
+            Err(err) => {
+                eprintln!("Could not read the shader files for reloading: {err}");
+                return;
+            }
+        };
+        match gltf::create_program_from_sources(&vertex_source, &fragment_source) {
+            Ok(shader) => {
+                let old_shader = std::mem::replace(&mut self.gltf_shader, shader);
+                gl::call!(gl::DeleteProgram(old_shader.program));
+                eprintln!("Reloaded the glTF shaders.");
+            }
+            Err(err) => eprintln!("Could not reload the glTF shaders, keeping the old ones: {err}"),
+        }
     }
 
-    pub fn zoom_camera(&mut self, pixels: i32) {
-        // TODO: Add camera zoom sensitivity
-        self.camera.distance = (self.camera.distance - pixels as f32 * 10.0).clamp(10.0, 100.0);
+    /// Zooms the camera, keeping the point under the cursor (at `cursor_clip`
+    /// in clip space) in place.
+    pub fn zoom_camera(&mut self, pixels: i32, cursor_clip: Vec2, aspect: f32, sensitivity: f32) {
+        let pivot_before = self.clip_to_ship_space(cursor_clip, aspect);
+        self.camera
+            .set_distance(self.camera.distance - pixels as f32 * 10.0 * sensitivity);
+        let pivot_after = self.clip_to_ship_space(cursor_clip, aspect);
+        // Pointing at the sky or very far away, zoom towards the focus instead
+        let near_area = Circle2::new(SHIP_SPACE_BOUNDS.center, SHIP_SPACE_BOUNDS.radius - 0.1);
+        if !near_area.contains(pivot_before) || !near_area.contains(pivot_after) {
+            return;
+        }
+        let delta = pivot_before - pivot_after;
+        // TODO: Use the bounds of all the rooms here
+        self.camera.focus = (self.camera.focus + Vec3::new(delta.x, 0.0, delta.y))
+            .clamp(Vec3::ONE * -10.0, Vec3::ONE * 10.0);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         width: f32,
         height: f32,
-        _time: f32,
+        time: f32,
+        paused: bool,
         ship_game: &ShipGame,
         interface: &mut Interface,
+        settings: &Settings,
+        debug_overlay: Option<&str>,
     ) {
         // Render world:
 
+        self.update_navigation_map(ship_game.current_ship_speed);
+        self.update_ship_damage(ship_game.hull_integrity);
         self.draw_calls.clear();
+        self.text.delete_replaced_textures();
+        self.selected_draw_calls.clear();
+        self.outline_draw_calls.clear();
+        let (sun_direction, sun_color) = sun_direction_and_color(ship_game.world_time);
+        // The lights are collected anew every frame, so the sun is too
+        self.draw_calls
+            .add_directional_light(sun_color, SUN_INTENSITY, -sun_direction);
+        let storm_shake = if ship_game.is_stormy() {
+            ship_game.storm_intensity * 0.15
+        } else {
+            0.0
+        };
+        // A damaged hull creaks and rattles even in calm weather
+        self.camera.shake = storm_shake + (1.0 - ship_game.hull_integrity) * 0.1;
+        let (view, proj) = self.get_view_and_proj_matrices(width / height);
+        self.draw_calls
+            .set_frustum(Some(Frustum::from_matrix(proj * view)));
         for room in &ship_game.rooms {
             let position = Vec3::new(room.position.x, 0.0, room.position.y);
-            match room.room_type {
-                RoomType::Navigation => self
-                    .room_navigation
-                    .draw(&mut self.draw_calls, Mat4::from_translation(position)),
-                RoomType::Sails => self
-                    .room_sailing
-                    .draw(&mut self.draw_calls, Mat4::from_translation(position)),
+            let model = match room.room_type {
+                RoomType::Navigation => &self.room_navigation,
+                RoomType::Sails => &self.room_sailing,
+                RoomType::Kitchen => &self.room_kitchen,
+                RoomType::Engine => &self.room_engine,
+            };
+            model.draw_culled(
+                &mut self.draw_calls,
+                Mat4::from_translation(position),
+                room.bounding_sphere(),
+            );
+        }
+        // Rooms of the same type should be drawn with the same instanced draw
+        // calls, so the amount of draw calls shouldn't grow with the rooms.
+        if cfg!(debug_assertions) {
+            let mut room_types = Vec::new();
+            for room in &ship_game.rooms {
+                if !room_types.contains(&room.room_type) {
+                    room_types.push(room.room_type);
+                }
             }
+            let max_batches = room_types
+                .iter()
+                .map(|room_type| match room_type {
+                    RoomType::Navigation => self.room_navigation.primitive_count(),
+                    RoomType::Sails => self.room_sailing.primitive_count(),
+                    RoomType::Kitchen => self.room_kitchen.primitive_count(),
+                    RoomType::Engine => self.room_engine.primitive_count(),
+                })
+                .sum::<usize>();
+            debug_assert!(
+                self.draw_calls.batch_count() <= max_batches,
+                "rooms of the same type should be batched into the same draw calls",
+            );
         }
-        for character in &ship_game.characters {
+        // The dashboard is drawn over the world, so characters completely
+        // behind it can be skipped. The rooms aren't used as occluders, since
+        // they're seen from above and open at the top.
+        let occluders = [interface.safe_area];
+        for (i, character) in ship_game.characters.iter().enumerate() {
             let position = Vec3::new(character.position.x, 0.0, character.position.y);
+            let (center, radius) = CHARACTER_BOUNDING_SPHERE;
+            let screen_rect =
+                sphere_to_screen_rect(proj * view, position + center, radius, width, height);
+            if screen_rect.is_some_and(|rect| occluders.iter().any(|o| o.contains_rect(rect))) {
+                continue;
+            }
             let rot = character.look_dir.angle_between(Vec2::Y);
-            self.characters[character.job as usize].draw(
+            let camera_distance = self.camera.eye_position().distance(position);
+            let model = self.lod_characters[character.job as usize].level(camera_distance);
+            let mut transform =
+                Mat4::from_rotation_translation(Quat::from_rotation_y(rot), position);
+            if ship_game.is_working(i) {
+                // Offset per character, so the crew doesn't bob in unison
+                let t = character.working_animation_offset + i as f32 * 2.4;
+                let bob = Vec3::new(0.0, (t.sin() * 0.05).abs(), 0.0);
+                let sway = match character.job {
+                    Job::Navigator => Quat::from_rotation_x(0.1),
+                    Job::Sailor => Quat::from_rotation_z((t * 0.5).sin() * 0.1),
+                    _ => Quat::IDENTITY,
+                };
+                transform = Mat4::from_translation(bob) * transform * Mat4::from_quat(sway);
+            }
+            let node_transforms = character.animation.get_node_transforms(model);
+            if ship_game.selected_character == Some(i) {
+                model.draw_animated(
+                    &mut self.selected_draw_calls,
+                    transform,
+                    &node_transforms,
+                    None,
+                );
+                // Scaled up around the middle of the character
+                let outline_transform = transform
+                    * Mat4::from_translation(center)
+                    * Mat4::from_scale(Vec3::ONE * 1.05)
+                    * Mat4::from_translation(-center);
+                model.draw_animated_with_material_override(
+                    &mut self.outline_draw_calls,
+                    outline_transform,
+                    &node_transforms,
+                    Vec4::new(1.0, 0.9, 0.1, 1.0),
+                );
+            } else {
+                model.draw_animated(
+                    &mut self.draw_calls,
+                    transform,
+                    &node_transforms,
+                    Some(CHARACTER_BOUNDING_SPHERE),
+                );
+            }
+        }
+        self.ship.draw(&mut self.draw_calls, Mat4::IDENTITY);
+        // The tile is in the XY plane from 0 to 1, so it's turned to face up
+        let water_tile_rotation = Quat::from_rotation_x(-TAU / 4.0);
+        let water_tile_scale = Vec3::new(WATER_TILE_SIZE, WATER_TILE_SIZE, 1.0);
+        // Bounds the tile, in its own space where it's 1 by 1
+        let water_tile_bounds = (Vec3::new(0.5, 0.5, 0.0), 0.75);
+        for x in -WATER_TILE_RADIUS..WATER_TILE_RADIUS {
+            for z in -WATER_TILE_RADIUS..WATER_TILE_RADIUS {
+                let position = Vec3::new(x as f32, 0.0, z as f32) * WATER_TILE_SIZE;
+                self.water.draw_culled(
+                    &mut self.draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        water_tile_scale,
+                        water_tile_rotation,
+                        position + Vec3::Y * WATER_LEVEL,
+                    ),
+                    water_tile_bounds,
+                );
+            }
+        }
+
+        let dt = (time - self.last_render_time).clamp(0.0, 0.1);
+        self.last_render_time = time;
+        self.wake.emission_rate =
+            WAKE_MAX_EMISSION_RATE * ship_game.current_ship_speed / MAX_SHIP_SPEED;
+        self.wake.update(dt);
+        // Undoing the view's rotation makes the quads face the camera, and
+        // they're turned around since the quad's front is towards +Z
+        let (_, view_rotation, _) = view.to_scale_rotation_translation();
+        let billboard_rotation = view_rotation.inverse() * Quat::from_rotation_y(TAU / 2.0);
+        for particle in &self.wake.particles {
+            let transform = Mat4::from_rotation_translation(billboard_rotation, particle.position)
+                * Mat4::from_scale(Vec3::splat(particle.current_scale()))
+                * Mat4::from_translation(Vec3::new(-0.5, -0.5, 0.0));
+            self.pixel_green.draw_with_material_override(
                 &mut self.draw_calls,
-                Mat4::from_rotation_translation(Quat::from_rotation_y(rot), position),
+                transform,
+                None,
+                Some(Vec4::new(0.9, 0.95, 1.0, 1.0)),
             );
         }
-        self.ship.draw(&mut self.draw_calls, Mat4::IDENTITY);
+        if let Some(room) = ship_game.selected_room.and_then(|i| ship_game.rooms.get(i)) {
+            let bounds = room.room_bounds.offset(room.position);
+            let size = bounds.max - bounds.min;
+            let thickness = 0.15;
+            // The pixel is a unit quad on the XY plane, so laid down on the
+            // floor it covers x: 0..1 and z: -1..0
+            let floor_rotation = Quat::from_rotation_x(-TAU / 4.0);
+            let edges = [
+                (bounds.min, Vec2::new(size.x, thickness)),
+                (
+                    Vec2::new(bounds.min.x, bounds.max.y - thickness),
+                    Vec2::new(size.x, thickness),
+                ),
+                (bounds.min, Vec2::new(thickness, size.y)),
+                (
+                    Vec2::new(bounds.max.x - thickness, bounds.min.y),
+                    Vec2::new(thickness, size.y),
+                ),
+            ];
+            for (min, size) in edges {
+                self.pixel_green.draw(
+                    &mut self.draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        size.extend(1.0),
+                        floor_rotation,
+                        Vec3::new(min.x, 0.05, min.y + size.y),
+                    ),
+                );
+            }
+        }
+
+        if interface.tab == Some(Tab::Build) {
+            // A grid over the rooms and a room's width around them, to show
+            // where rooms snap to
+            let rooms = ship_game.rooms.iter();
+            let bounds = rooms
+                .map(|room| room.room_bounds.offset(room.position))
+                .reduce(|a, b| a.union(&b));
+            if let Some(bounds) = bounds {
+                let (min, max) = ((bounds.min - 8.0).floor(), (bounds.max + 8.0).ceil());
+                let size = max - min;
+                let thickness = 0.04;
+                let floor_rotation = Quat::from_rotation_x(-TAU / 4.0);
+                let columns = (0..=size.x as i32).map(|x| {
+                    (
+                        Vec2::new(min.x + x as f32, min.y),
+                        Vec2::new(thickness, size.y),
+                    )
+                });
+                let rows = (0..=size.y as i32).map(|y| {
+                    (
+                        Vec2::new(min.x, min.y + y as f32),
+                        Vec2::new(size.x, thickness),
+                    )
+                });
+                for (min, size) in columns.chain(rows) {
+                    self.pixel_gray.draw(
+                        &mut self.draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            size.extend(1.0),
+                            floor_rotation,
+                            Vec3::new(min.x, 0.03, min.y + size.y),
+                        ),
+                    );
+                }
+            }
+        }
 
         let pathfinding_debug_arrows = false;
         if cfg!(debug_assertions) && pathfinding_debug_arrows {
@@ -169,19 +1016,41 @@ impl Renderer {
                     );
                 }
             }
+            for character in &ship_game.characters {
+                for waypoint in &character.move_target_queue {
+                    let waypoint = Vec3::new(waypoint.x, 1.0, waypoint.y);
+                    self.debug_arrow
+                        .draw(&mut self.draw_calls, Mat4::from_translation(waypoint));
+                }
+            }
         }
 
+        self.scene_fbo.resize(width as i32, height as i32);
+        self.scene_fbo.bind();
         gl::call!(gl::Disable(gl::BLEND));
-        gl::call!(gl::ClearColor(0.6, 0.45, 0.3, 1.0));
+        // Darker at night, brightest at noon (half past each day)
+        let daylight = 0.6 - 0.4 * (ship_game.world_time * TAU).cos();
+        let horizon_color = Vec3::new(0.6, 0.45, 0.3) * daylight;
+        let zenith_color = Vec3::new(0.35, 0.3, 0.35) * daylight;
+        gl::call!(gl::ClearColor(
+            horizon_color.x,
+            horizon_color.y,
+            horizon_color.z,
+            1.0
+        ));
         gl::call!(gl::ClearDepthf(0.0));
-        gl::call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+        gl::call!(gl::ClearStencil(0));
+        gl::call!(gl::Clear(
+            gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT
+        ));
+        self.skybox.set_gradient(horizon_color, zenith_color);
+        self.skybox.draw(proj, view);
         gl::call!(gl::Enable(gl::CULL_FACE));
         gl::call!(gl::Enable(gl::DEPTH_TEST));
         gl::call!(gl::DepthFunc(gl::GREATER));
 
-        let (v, p) = self.get_view_and_proj_matrices(width / height);
-        let world_view_matrix = v.to_cols_array();
-        let world_proj_matrix = p.to_cols_array();
+        let world_view_matrix = view.to_cols_array();
+        let world_proj_matrix = proj.to_cols_array();
 
         gl::call!(gl::UseProgram(self.gltf_shader.program));
         gl::call!(gl::UniformMatrix4fv(
@@ -196,17 +1065,131 @@ impl Renderer {
             gl::FALSE,
             world_view_matrix.as_ptr(),
         ));
-        self.draw_calls.draw(
+        if let Some(time_location) = self.gltf_shader.time_location {
+            gl::call!(gl::Uniform1f(time_location, time));
+        }
+        if self.wireframe_mode {
+            // Push the triangles back so that the lines are drawn over them.
+            // Negative, since the depth buffer is reversed.
+            gl::call!(gl::PolygonOffset(-1.0, -1.0));
+            gl::call!(gl::Enable(gl::POLYGON_OFFSET_FILL));
+        }
+        let mut stats = self.draw_calls.draw(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        );
+        if self.wireframe_mode {
+            gl::call!(gl::Disable(gl::POLYGON_OFFSET_FILL));
+            stats += self.draw_calls.draw_wireframe(
+                gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+                gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+            );
+        }
+
+        // The selected character's outline: the character marks the stencil,
+        // and the larger version is only drawn around the marked area.
+        gl::call!(gl::Enable(gl::STENCIL_TEST));
+        gl::call!(gl::StencilFunc(gl::ALWAYS, 1, 0xFF));
+        gl::call!(gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE));
+        stats += self.selected_draw_calls.draw(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        );
+        gl::call!(gl::StencilFunc(gl::NOTEQUAL, 1, 0xFF));
+        gl::call!(gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP));
+        stats += self.outline_draw_calls.draw(
             gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
             gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
         );
+        gl::call!(gl::Disable(gl::STENCIL_TEST));
+
+        self.scene_fbo.resolve();
+        let mut scene_tex = self.scene_fbo.color_tex;
+        if self.ssao_enabled {
+            scene_tex = self.ssao.run(&self.scene_fbo, proj);
+        }
+        if settings.dof_aperture > 0.0 {
+            // Focused on the focus point, where the ship usually is
+            self.dof.focus_distance = self.camera.distance;
+            self.dof.aperture = settings.dof_aperture;
+            scene_tex = self.dof.run(&self.scene_fbo, scene_tex, proj);
+        }
+        if settings.bloom_strength > 0.0 {
+            self.bloom.run(scene_tex, width as i32, height as i32);
+        }
+        Framebuffer::unbind();
+        gl::call!(gl::Viewport(0, 0, width as i32, height as i32));
+        if settings.bloom_strength > 0.0 {
+            self.bloom.composite(scene_tex, settings.bloom_strength);
+        } else {
+            self.fullscreen_quad.draw(scene_tex);
+        }
+
+        if let Some(minimap) = &mut self.minimap {
+            minimap.draw_calls.clear();
+            minimap
+                .draw_calls
+                .add_directional_light(sun_color, SUN_INTENSITY, -sun_direction);
+            for room in &ship_game.rooms {
+                let model = match room.room_type {
+                    RoomType::Navigation => &self.room_navigation,
+                    RoomType::Sails => &self.room_sailing,
+                    RoomType::Kitchen => &self.room_kitchen,
+                    RoomType::Engine => &self.room_engine,
+                };
+                let position = Vec3::new(room.position.x, 0.0, room.position.y);
+                model.draw(&mut minimap.draw_calls, Mat4::from_translation(position));
+            }
+            for character in &ship_game.characters {
+                let position = Vec3::new(character.position.x, 0.0, character.position.y);
+                let rot = character.look_dir.angle_between(Vec2::Y);
+                // Twice as big, so that they're visible from this far away
+                let transform = Mat4::from_scale_rotation_translation(
+                    Vec3::ONE * 2.0,
+                    Quat::from_rotation_y(rot),
+                    position,
+                );
+                let model = self.lod_characters[character.job as usize].full_detail();
+                (character.animation).draw(model, &mut minimap.draw_calls, transform);
+            }
+            // A reticle at the main camera's focus point, above everything
+            let reticle_size = 1.5;
+            let reticle_position = self.camera.focus * Vec3::new(1.0, 0.0, 1.0)
+                + Vec3::new(-reticle_size / 2.0, 8.0, reticle_size / 2.0);
+            self.pixel_green.draw(
+                &mut minimap.draw_calls,
+                Mat4::from_scale_rotation_translation(
+                    Vec3::new(reticle_size, reticle_size, 1.0),
+                    Quat::from_rotation_x(-TAU / 4.0),
+                    reticle_position,
+                ),
+            );
+            stats += minimap.render(&self.gltf_shader);
+
+            Framebuffer::unbind();
+            let size = (width * 0.15) as i32;
+            let margin = (width * 0.01) as i32;
+            gl::call!(gl::Viewport(
+                width as i32 - size - margin,
+                height as i32 - size - margin,
+                size,
+                size,
+            ));
+            self.fullscreen_quad.draw(minimap.fbo.color_tex);
+            gl::call!(gl::Viewport(0, 0, width as i32, height as i32));
+        }
+        gl::call!(gl::Enable(gl::CULL_FACE));
+        gl::call!(gl::Enable(gl::DEPTH_TEST));
 
         // Render UI:
 
-        let scale = (width / 800.0).floor().max(1.0);
+        let scale = (width / 800.0).floor().max(1.0) * settings.ui_scale;
         let width = width / scale;
         let height = height / scale;
         self.ui_draw_calls.clear();
+        self.transparent_ui_draw_calls.clear();
+        self.screen_draw_calls.clear();
+        self.transparent_screen_draw_calls.clear();
 
         gl::call!(gl::Enable(gl::BLEND));
         gl::call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
@@ -215,27 +1198,28 @@ impl Renderer {
         gl::call!(gl::DepthFunc(gl::LESS));
 
         // Draw dashboard & clock
-        let mut dashboard_transforms = self.dashboard.get_node_transforms();
-        for node_transform in &mut dashboard_transforms {
-            if node_transform.name == "Clock Hand" {
-                let (s, mut r, t) = node_transform.transform.to_scale_rotation_translation();
-                r *= Quat::from_rotation_z(-TAU * (ship_game.world_time * 60.0).floor() / 60.0);
-                node_transform.transform = Mat4::from_scale_rotation_translation(s, r, t);
-            }
-        }
-        self.dashboard.draw_animated(
+        let clock_hand = self.dashboard.get_node_index_by_name("Clock Hand").unwrap();
+        let (s, mut r, t) = self
+            .dashboard
+            .get_node_transform(clock_hand)
+            .to_scale_rotation_translation();
+        r *= Quat::from_rotation_z(-TAU * (ship_game.world_time * 60.0).floor() / 60.0);
+        let clock_hand_transform = Mat4::from_scale_rotation_translation(s, r, t);
+        self.dashboard.draw_with_overrides(
             &mut self.ui_draw_calls,
             Mat4::IDENTITY,
-            &dashboard_transforms,
+            &[(clock_hand, clock_hand_transform)],
         );
-        self.text.draw_text(
-            &mut self.ui_draw_calls,
+        self.text.draw_text_with_font(
+            &mut self.transparent_ui_draw_calls,
             &format!("DAY {:.0}", ship_game.world_time.floor()),
+            FontRenderer::FONT_MONO,
             Vec2::new(-115.0, 68.0),
             9.0,
             (11.0, scale),
             (HorizontalAlign::Center, VerticalAlign::Top),
             Some(115.0 - 68.0),
+            DASHBOARD_TEXT_COLOR,
         );
 
         let interface_rect = |x: f32, y: f32, w: f32, h: f32| {
@@ -248,26 +1232,55 @@ impl Renderer {
         };
 
         // Tabs
-        interface.buttons.clear();
-        for (i, text) in ["NAVIGATION", "SCHEDULE", "DELIVERIES", "GAME SETTINGS"]
-            .iter()
-            .enumerate()
-        {
-            let y = 132.0 - i as f32 * 29.5;
-            self.text.draw_text(
-                &mut self.ui_draw_calls,
+        interface.clear_buttons();
+        let tabs = [
+            "NAVIGATION",
+            "SCHEDULE",
+            "DELIVERIES",
+            "GAME SETTINGS",
+            "CREW",
+            "MAP",
+            "TRADE",
+            "LOG",
+            "BUILD",
+        ];
+        let (tab_spacing, tab_font_size) = (14.0, 16.0);
+        for (i, text) in tabs.iter().enumerate() {
+            let y = 134.0 - i as f32 * tab_spacing;
+            self.text.draw_text_styled(
+                &mut self.transparent_ui_draw_calls,
                 text,
                 Vec2::new(-270.0, y),
                 9.0,
-                (20.0, scale),
+                (tab_font_size, scale),
                 (HorizontalAlign::Left, VerticalAlign::Top),
                 None,
+                &TAB_LABEL_STYLE,
             );
-            interface.buttons.insert(
+            interface.add_button(
                 Button::Tab(i),
-                interface_rect(-300.0, y - 2.0 - 28.0, 180.0, 28.0),
+                interface_rect(-300.0, y - 2.0 - tab_spacing, 180.0, tab_spacing),
             );
         }
+        // The autopilot indicator light, next to the navigation tab
+        let indicator_x = -270.0
+            + self
+                .text
+                .text_width(tabs[0], TAB_LABEL_STYLE.font_index, (tab_font_size, scale))
+            + 6.0;
+        let indicator = if ship_game.autopilot {
+            &self.pixel_green
+        } else {
+            &self.pixel_gray
+        };
+        indicator.draw(
+            &mut self.ui_draw_calls,
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(6.0, 6.0, 1.0),
+                Quat::IDENTITY,
+                Vec3::new(indicator_x, 134.0 - 11.0, 9.0),
+            ),
+        );
 
         // Interface
         let scr_x = -39.0;
@@ -275,123 +1288,561 @@ impl Renderer {
         let scr_h = 114.0;
         interface.screen_area = interface_rect(scr_x, scr_y, 336.0, scr_h);
         interface.safe_area = interface_rect(-322.0, 0.0, 644.0, 154.0);
+        let event_area = (scr_x, scr_y);
+        // The contents of the screen slide up from below when a tab is opened
+        let scr_y = scr_y - (1.0 - interface.tab_anim.powf(0.5)) * scr_h;
         match interface.tab {
             Some(Tab::Navigation) => {
-                let mut draw_location = |name: &str, location: Vec2, i: usize| {
+                // Location search field
+                let search = &interface.text_inputs[Interface::LOCATION_SEARCH];
+                let search_text = format!("Search: {}", search.text);
+                let search_size = (12.0, scale);
+                let x = scr_x + 10.0;
+                let y = scr_y + scr_h - 3.0;
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &search_text,
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(x, y),
+                    5.0,
+                    search_size,
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                if search.focused && time.fract() < 0.5 {
+                    let cursor_text = format!("Search: {}", &search.text[..search.cursor]);
+                    let mono = FontRenderer::FONT_MONO;
+                    let cursor_x = x + self.text.text_width(&cursor_text, mono, search_size);
+                    self.pixel_gray.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(1.0, search_size.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(cursor_x, y - search_size.0, 5.0),
+                        ),
+                    );
+                }
+                interface.add_button(
+                    Button::TextInput(Interface::LOCATION_SEARCH),
+                    interface_rect(x, y - 14.0, 270.0, 14.0),
+                );
+
+                let search = interface.text_inputs[Interface::LOCATION_SEARCH]
+                    .text
+                    .to_lowercase();
+                let location_list = interface.location_list;
+                let mut draw_location = |location: &Location, i: usize, row: usize| {
+                    let row_y = row as f32 * location_list.item_height;
+                    if !location_list.is_visible(row_y) {
+                        return;
+                    }
                     let x = scr_x + 10.0;
-                    let y = scr_y + scr_h - i as f32 * 17.0 - 3.0;
+                    let y = scr_y + scr_h - 17.0 - row_y + location_list.scroll_offset;
                     self.text.draw_text(
-                        &mut self.ui_draw_calls,
-                        name,
+                        &mut self.transparent_screen_draw_calls,
+                        &location.name,
                         Vec2::new(x, y),
                         5.0,
                         (14.0, scale),
                         (HorizontalAlign::Left, VerticalAlign::Top),
                         None,
+                        Vec4::ONE,
+                    );
+                    let danger = (0..3)
+                        .map(|level| {
+                            if level < location.danger_level {
+                                '★'
+                            } else {
+                                '☆'
+                            }
+                        })
+                        .collect::<String>();
+                    let fuel = if location.is_fuel_depot { '⚡' } else { ' ' };
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("{fuel} {danger}"),
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x + 215.0, y),
+                        5.0,
+                        (12.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
                     );
-                    if (location - ship_game.current_location).length() < 1.0 {
+                    if (location.position - ship_game.current_location).length() < 1.0 {
                         self.pixel_gray.draw(
-                            &mut self.ui_draw_calls,
+                            &mut self.screen_draw_calls,
                             Mat4::from_scale_rotation_translation(
-                                Vec3::new(315.0, 2.0, 2.0),
+                                Vec3::new(275.0, 2.0, 2.0),
                                 Quat::IDENTITY,
-                                Vec3::new(x, y - 18.0, 5.0),
+                                Vec3::new(x, y - 16.0, 5.0),
                             ),
                         )
                     }
-                    interface.buttons.insert(
+                    interface.add_button(
                         Button::LocationList(i),
-                        interface_rect(x, y - 16.0, 300.0, 16.0),
+                        interface_rect(x, y - 15.0, 250.0, 15.0),
                     );
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        "[+]",
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x + 255.0, y),
+                        5.0,
+                        (12.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    interface.add_button(
+                        Button::AddWaypoint(i),
+                        interface_rect(x + 253.0, y - 15.0, 24.0, 15.0),
+                    );
+                };
+                let found_locations = ship_game
+                    .locations
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, location)| location.name.to_lowercase().starts_with(&search));
+                let mut found_count = 0;
+                for (row, (i, location)) in found_locations.enumerate() {
+                    draw_location(location, i, row);
+                    found_count += 1;
+                }
+                interface.location_list.item_count = found_count;
+
+                // The route after the current target, in one line below the
+                // location list
+                let route_y = scr_y + scr_h - 19.0 - location_list.visible_height;
+                let route_size = (12.0, scale);
+                let mono = FontRenderer::FONT_MONO;
+                let autopilot_text = if ship_game.autopilot {
+                    "[AUTO: ON]"
+                } else {
+                    "[AUTO: OFF]"
                 };
-                for (i, location) in ship_game.locations.iter().enumerate() {
-                    draw_location(location.0, location.1, i)
+                let autopilot_x = scr_x + 206.0;
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    autopilot_text,
+                    mono,
+                    Vec2::new(autopilot_x, route_y),
+                    5.0,
+                    route_size,
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::ToggleAutopilot,
+                    interface_rect(autopilot_x - 2.0, route_y - 14.0, 84.0, 14.0),
+                );
+                let mut x = scr_x + 10.0;
+                if !ship_game.target_queue.is_empty() {
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        "Then:",
+                        mono,
+                        Vec2::new(x, route_y),
+                        5.0,
+                        route_size,
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    x += self.text.text_width("Then: ", mono, route_size);
+                }
+                for (i, &location) in ship_game.target_queue.iter().enumerate() {
+                    let name = &ship_game.locations[location].name;
+                    let waypoint = format!("{}. {name} ", i + 1);
+                    let width = self.text.text_width(&waypoint, mono, route_size);
+                    let remove_width = self.text.text_width("[-] ", mono, route_size);
+                    if x + width + remove_width > scr_x + 200.0 {
+                        // No room for the rest, they're still on the route
+                        self.text.draw_text_with_font(
+                            &mut self.transparent_screen_draw_calls,
+                            "…",
+                            mono,
+                            Vec2::new(x, route_y),
+                            5.0,
+                            route_size,
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            None,
+                            Vec4::ONE,
+                        );
+                        break;
+                    }
+                    for (text, x) in [(&*waypoint, x), ("[-]", x + width)] {
+                        self.text.draw_text_with_font(
+                            &mut self.transparent_screen_draw_calls,
+                            text,
+                            mono,
+                            Vec2::new(x, route_y),
+                            5.0,
+                            route_size,
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            None,
+                            Vec4::ONE,
+                        );
+                    }
+                    interface.add_button(
+                        Button::RemoveWaypoint(i),
+                        interface_rect(x + width - 2.0, route_y - 14.0, remove_width, 14.0),
+                    );
+                    x += width + remove_width;
+                }
+
+                // Compass, in the top-right corner next to the location list
+                let center = Vec2::new(scr_x + 312.0, scr_y + scr_h - 24.0);
+                let angle = ship_game
+                    .current_heading
+                    .y
+                    .atan2(ship_game.current_heading.x);
+                // Clockwise from north, like on a real compass
+                let bearing = TAU / 4.0 - angle;
+                let rotation = Quat::from_rotation_z(-bearing);
+                for (pixel, length, z) in
+                    [(&self.pixel_green, 16.0, 5.0), (&self.pixel_gray, 8.0, 5.5)]
+                {
+                    // The gray half is the tail, so it's clear which end is the tip
+                    let corner = rotation * Vec3::new(-1.0, -8.0, 0.0);
+                    pixel.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(2.0, length, 1.0),
+                            rotation,
+                            center.extend(z) + corner,
+                        ),
+                    );
+                }
+                for (label, offset) in [
+                    ("N", Vec2::new(0.0, 20.0)),
+                    ("E", Vec2::new(16.0, 4.5)),
+                    ("S", Vec2::new(0.0, -11.0)),
+                    ("W", Vec2::new(-16.0, 4.5)),
+                ] {
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        label,
+                        center + offset - Vec2::new(5.0, 0.0),
+                        5.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Center, VerticalAlign::Top),
+                        Some(10.0),
+                        Vec4::ONE,
+                    );
                 }
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("{:.0}°", bearing.to_degrees().rem_euclid(360.0)),
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(center.x - 20.0, center.y - 22.0),
+                    5.0,
+                    (9.0, scale),
+                    (HorizontalAlign::Center, VerticalAlign::Top),
+                    Some(40.0),
+                    Vec4::ONE,
+                );
                 let mut target = "";
-                for (name, location) in &ship_game.locations {
-                    if *location == ship_game.current_target {
-                        target = name;
+                for location in &ship_game.locations {
+                    if location.position == ship_game.current_target {
+                        target = &location.name;
                         break;
                     }
                 }
                 let spd = ship_game.current_ship_speed;
                 let d = (ship_game.current_target - ship_game.current_location).length() / 3.6;
                 self.text.draw_text(
-                    &mut self.ui_draw_calls,
-                    &format!("Heading: {target}\nSpeed: {spd:.1} m/s, distance: {d:.1} km"),
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("Heading: {target}"),
                     Vec2::new(scr_x + 10.0, scr_y + 38.0),
                     5.0,
                     (14.0, scale),
                     (HorizontalAlign::Left, VerticalAlign::Top),
                     None,
+                    Vec4::ONE,
                 );
-            }
-            Some(Tab::Schedule) => {
-                let l = 16.0;
-                let mut draw_legend = |pixel: &gltf::Gltf, name: &str, task: Task, x_off: f32| {
-                    let x = scr_x + 10.0 + x_off;
-                    let y = scr_y + scr_h - 10.0;
-                    pixel.draw(
-                        &mut self.ui_draw_calls,
-                        Mat4::from_scale_rotation_translation(
-                            Vec3::new(l, l, 1.0),
-                            Quat::IDENTITY,
-                            Vec3::new(x, y - l, 5.0),
-                        ),
-                    );
+                if ship_game.docked {
                     self.text.draw_text(
-                        &mut self.ui_draw_calls,
-                        name,
-                        Vec2::new(x + 20.0, y + 2.0),
+                        &mut self.transparent_screen_draw_calls,
+                        "DOCKED",
+                        Vec2::new(scr_x + 260.0, scr_y + 38.0),
                         5.0,
                         (14.0, scale),
                         (HorizontalAlign::Left, VerticalAlign::Top),
                         None,
+                        DOCKED_TEXT_COLOR,
+                    );
+                }
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("Speed: {spd:4.1} m/s, distance: {d:5.1} km"),
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(scr_x + 10.0, scr_y + 21.0),
+                    5.0,
+                    (12.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+
+                // The ship's speed doesn't depend on the debug speedup, so
+                // this is the ETA at the normal pace of one day per minute
+                let remaining = (ship_game.current_target - ship_game.current_location).length();
+                let (eta_text, eta_color) = if spd > 0.0 && remaining > 0.0 {
+                    let eta_minutes = remaining / spd / 60.0;
+                    // A day passes in a minute, see ShipGame::update
+                    let eta_days = eta_minutes;
+                    let color = if eta_days < 1.0 {
+                        ETA_TEXT_COLORS[0]
+                    } else if eta_days <= 3.0 {
+                        ETA_TEXT_COLORS[1]
+                    } else {
+                        ETA_TEXT_COLORS[2]
+                    };
+                    (
+                        format!("ETA: {eta_days:.1} days ({eta_minutes:.0} min)"),
+                        color,
+                    )
+                } else {
+                    (String::from("ETA: —"), Vec4::ONE)
+                };
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &eta_text,
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(scr_x + 146.0, scr_y + 9.0),
+                    5.0,
+                    (9.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    eta_color,
+                );
+
+                // Fuel gauge
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    "Fuel",
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(scr_x + 10.0, scr_y + 9.0),
+                    5.0,
+                    (9.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                let gauge_width = 100.0;
+                self.pixel_gray.draw(
+                    &mut self.screen_draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        Vec3::new(gauge_width, 5.0, 1.0),
+                        Quat::IDENTITY,
+                        Vec3::new(scr_x + 36.0, scr_y + 2.0, 5.0),
+                    ),
+                );
+                self.pixel_green.draw(
+                    &mut self.screen_draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        Vec3::new(gauge_width * ship_game.fuel / ship_game.max_fuel, 5.0, 1.0),
+                        Quat::IDENTITY,
+                        Vec3::new(scr_x + 36.0, scr_y + 2.0, 5.5),
+                    ),
+                );
+
+                // Wind arrow, pointing where the wind blows
+                let center = Vec2::new(scr_x + 316.0, scr_y + 26.0);
+                let dir = ship_game.wind_direction;
+                let tip = center + dir * 10.0;
+                let mut draw_line = |from: Vec2, to: Vec2| {
+                    let pixel = &self.pixel_gray;
+                    draw_ui_line(pixel, &mut self.screen_draw_calls, from, to, 2.0, 5.0);
+                };
+                draw_line(center - dir * 10.0, tip);
+                draw_line(tip, tip - Vec2::from_angle(0.6).rotate(dir) * 5.0);
+                draw_line(tip, tip - Vec2::from_angle(-0.6).rotate(dir) * 5.0);
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("{:4.1} m/s", ship_game.wind_speed),
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(center.x - 20.0, scr_y + 12.0),
+                    5.0,
+                    (9.0, scale),
+                    (HorizontalAlign::Center, VerticalAlign::Top),
+                    Some(40.0),
+                    Vec4::ONE,
+                );
+            }
+            Some(Tab::Schedule) => {
+                let l = 16.0;
+                let mut draw_legend = |pixel: &gltf::Gltf, name: &str, task: Task, x_off: f32| {
+                    let x = scr_x + 10.0 + x_off;
+                    let y = scr_y + scr_h - 10.0;
+                    pixel.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(l, l, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - l, 5.0),
+                        ),
+                    );
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        name,
+                        Vec2::new(x + 20.0, y + 2.0),
+                        5.0,
+                        (14.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    if interface.selected_task == task {
+                        pixel.draw(
+                            &mut self.screen_draw_calls,
+                            Mat4::from_scale_rotation_translation(
+                                Vec3::new(50.0, 2.0, 2.0),
+                                Quat::IDENTITY,
+                                Vec3::new(x + 18.0, y - l, 5.0),
+                            ),
+                        )
+                    }
+                    interface.add_button(
+                        Button::TaskPicker(task),
+                        interface_rect(x, y - l - 5.0, 70.0, l + 10.0),
                     );
-                    if interface.selected_task == task {
-                        pixel.draw(
-                            &mut self.ui_draw_calls,
-                            Mat4::from_scale_rotation_translation(
-                                Vec3::new(50.0, 2.0, 2.0),
-                                Quat::IDENTITY,
-                                Vec3::new(x + 18.0, y - l, 5.0),
-                            ),
-                        )
-                    }
-                    interface.buttons.insert(
-                        Button::TaskPicker(task),
-                        interface_rect(x, y - l - 5.0, 70.0, l + 10.0),
-                    );
                 };
                 draw_legend(&self.pixel_gray, "Sleep", Task::Sleep, 0.0);
                 draw_legend(&self.pixel_green, "Work", Task::Work, 85.0);
 
+                // Time slot labels, one hour per slot
+                let row_spacing = 20.0;
+                interface.schedule_grid.item_count = SCHEDULE_SLOTS;
+                let schedule_grid = interface.schedule_grid;
+                let grid_x = scr_x + 40.0 + 16.0;
+                // Just below the bottommost schedule row, there's no room
+                // between the topmost row and the legend
+                let labels_y = scr_y + 9.0;
+                for i in 0..SCHEDULE_SLOTS {
+                    let slot_x = 20.0 * i as f32;
+                    if !schedule_grid.is_visible(slot_x) {
+                        continue;
+                    }
+                    let x = grid_x + slot_x - schedule_grid.scroll_offset + l / 2.0;
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("{:02}", i),
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x, labels_y),
+                        5.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Center, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                }
+                for (text, x, slots) in [
+                    ("<", grid_x - 12.0, -6),
+                    (">", grid_x + schedule_grid.visible_height, 6),
+                ] {
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        text,
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x + 2.0, labels_y),
+                        5.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    interface.add_button(
+                        Button::ScrollSchedule(slots),
+                        interface_rect(x, labels_y - 10.0, 10.0, 10.0),
+                    );
+                }
+
                 let mut draw_schedule = |char_idx: usize, character: &Character, y_offset: f32| {
-                    let x = scr_x + 18.0;
-                    let y = scr_y + 32.0 + y_offset;
-                    self.characters[character.job as usize].draw(
-                        &mut self.ui_draw_calls,
+                    let y = scr_y + 26.0 + y_offset;
+
+                    // Clear button
+                    let x = scr_x + 4.0;
+                    self.pixel_gray.draw(
+                        &mut self.screen_draw_calls,
                         Mat4::from_scale_rotation_translation(
-                            Vec3::ONE * 16.0,
+                            Vec3::new(20.0, 12.0, 1.0),
                             Quat::IDENTITY,
-                            Vec3::new(x, y - 22.0, 5.0),
+                            Vec3::new(x, y - l + 2.0, 5.0),
                         ),
                     );
-                    for i in 0..12 {
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        "CLR",
+                        Vec2::new(x + 2.0, y - 1.0),
+                        6.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    interface.add_button(
+                        Button::ClearSchedule(char_idx),
+                        interface_rect(x, y - l, 20.0, l),
+                    );
+
+                    let x = scr_x + 40.0;
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &character.name,
+                        Vec2::new(x - 5.0, y + 5.0),
+                        6.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    // Skill, as one to five stars after the name
+                    let font = FontRenderer::FONT_DEFAULT;
+                    let name_width = self.text.text_width(&character.name, font, (10.0, scale));
+                    let stars = 1 + (character.skill * 4.0).round() as usize;
+                    for i in 0..stars {
+                        self.pixel_green.draw(
+                            &mut self.screen_draw_calls,
+                            Mat4::from_scale_rotation_translation(
+                                Vec3::new(3.0, 3.0, 1.0),
+                                Quat::IDENTITY,
+                                Vec3::new(x + name_width + i as f32 * 4.0, y - 2.0, 5.0),
+                            ),
+                        );
+                    }
+                    self.lod_characters[character.job as usize]
+                        .full_detail()
+                        .draw(
+                            &mut self.screen_draw_calls,
+                            Mat4::from_scale_rotation_translation(
+                                Vec3::ONE * 16.0,
+                                Quat::IDENTITY,
+                                Vec3::new(x, y - 22.0, 5.0),
+                            ),
+                        );
+                    for i in 0..SCHEDULE_SLOTS {
+                        let slot_x = 20.0 * i as f32;
+                        if !schedule_grid.is_visible(slot_x) {
+                            continue;
+                        }
                         let pixel = match character.schedule[i] {
                             Task::Sleep => &self.pixel_gray,
                             Task::Work => &self.pixel_green,
                         };
-                        let x = x + 16.0 + 20.0 * i as f32;
+                        let x = x + 16.0 + slot_x - schedule_grid.scroll_offset;
                         pixel.draw(
-                            &mut self.ui_draw_calls,
+                            &mut self.screen_draw_calls,
                             Mat4::from_scale_rotation_translation(
                                 Vec3::new(l, l, 1.0),
                                 Quat::IDENTITY,
                                 Vec3::new(x, y - l, 5.0),
                             ),
                         );
-                        interface.buttons.insert(
+                        interface.add_button(
                             Button::TaskAssigner {
                                 character: char_idx,
                                 time: i,
@@ -399,49 +1850,986 @@ impl Renderer {
                             interface_rect(x, y - l - 5.0, l, l + 10.0),
                         );
                     }
+
+                    // Morale bar
+                    let x = x + 16.0 + schedule_grid.visible_height;
+                    let bar_width = 30.0;
+                    self.pixel_gray.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width, 4.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 4.0, 5.0),
+                        ),
+                    );
+                    self.pixel_green.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width * character.morale, 4.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 4.0, 5.5),
+                        ),
+                    );
+
+                    // Energy bar, under the morale bar
+                    self.pixel_gray.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width, 4.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 10.0, 5.0),
+                        ),
+                    );
+                    self.pixel_green.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width * character.energy, 4.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 10.0, 5.5),
+                        ),
+                    );
                 };
                 for (i, character) in ship_game.characters.iter().enumerate() {
-                    draw_schedule(i, character, i as f32 * 40.0);
+                    draw_schedule(i, character, i as f32 * row_spacing);
                 }
             }
             Some(Tab::Deliveries) => {
-                let mut draw_delivery = |name: &str, done: bool, i: usize| {
+                let delivery_list = &interface.delivery_list;
+                let world_time = ship_game.world_time;
+                let mut draw_delivery = |delivery: &Delivery, i: usize| {
+                    let row_y = i as f32 * delivery_list.item_height;
+                    if !delivery_list.is_visible(row_y) {
+                        return;
+                    }
                     let x = scr_x + 10.0;
-                    let y = scr_y + scr_h - i as f32 * 25.0 - 10.0;
-                    let check = if done { "x" } else { "  " };
+                    let y = scr_y + scr_h - 10.0 - row_y + delivery_list.scroll_offset;
+                    let name = delivery.name;
+                    let (text, color) = if delivery.failed {
+                        (format!("[-] {name}"), FAILED_TEXT_COLOR)
+                    } else if delivery.completed {
+                        (format!("[x] {name}"), Vec4::ONE)
+                    } else if let Some(days_left) = delivery.days_left(world_time) {
+                        (format!("[  ] {name} ({days_left:.1} days)"), Vec4::ONE)
+                    } else {
+                        (format!("[  ] {name}"), Vec4::ONE)
+                    };
                     self.text.draw_text(
-                        &mut self.ui_draw_calls,
-                        &format!("[{check}] {name}"),
+                        &mut self.transparent_screen_draw_calls,
+                        &text,
                         Vec2::new(x, y),
                         5.0,
                         (20.0, scale),
                         (HorizontalAlign::Left, VerticalAlign::Top),
-                        None,
+                        Some(316.0),
+                        color,
+                    );
+                    let route = if delivery.picked_up {
+                        format!("{} → {}", delivery.origin_name, delivery.destination_name)
+                    } else {
+                        format!("Pick up at {}", delivery.origin_name)
+                    };
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        &route,
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x + 30.0, y - 20.0),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        Some(286.0),
+                        color,
                     );
                 };
                 let mut checks = 0;
                 for (i, delivery) in ship_game.deliveries.iter().enumerate() {
-                    draw_delivery(delivery.0, delivery.2, i);
-                    if delivery.2 {
+                    draw_delivery(delivery, i);
+                    if delivery.completed && !delivery.failed {
                         checks += 1;
                     }
                 }
+                interface.delivery_list.item_count = ship_game.deliveries.len();
                 if checks == ship_game.deliveries.len() {
                     self.text.draw_text(
-                        &mut self.ui_draw_calls,
+                        &mut self.transparent_screen_draw_calls,
                         "Well done, you delivered all the packages!",
                         Vec2::new(scr_x + 7.0, scr_y + 30.0),
                         5.0,
                         (14.0, scale),
                         (HorizontalAlign::Left, VerticalAlign::Top),
                         None,
+                        Vec4::ONE,
+                    );
+                }
+            }
+            Some(Tab::GameSettings) => {
+                let (x, y) = (scr_x + 10.0, scr_y + scr_h - 10.0);
+                // The rigging and the hull, side by side
+                let bar_width = 95.0;
+                for (label, integrity, color, x) in [
+                    ("Integrity", ship_game.ship_integrity, None, x),
+                    (
+                        "Hull",
+                        ship_game.hull_integrity,
+                        Some(FAILED_TEXT_COLOR),
+                        x + 105.0,
+                    ),
+                ] {
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("{label}: {:3.0}%", integrity * 100.0),
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x, y),
+                        5.0,
+                        (11.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    self.pixel_gray.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width, 8.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 26.0, 5.0),
+                        ),
+                    );
+                    self.pixel_green.draw_with_material_override(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(bar_width * integrity, 8.0, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x, y - 26.0, 5.5),
+                        ),
+                        None,
+                        color,
+                    );
+                }
+
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    "[Save / Load]",
+                    Vec2::new(x, y - 40.0),
+                    5.0,
+                    (14.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::OpenSaveLoad,
+                    interface_rect(x - 4.0, y - 58.0, 96.0, 20.0),
+                );
+                let fullscreen_text = if settings.fullscreen {
+                    "[Fullscreen: on]"
+                } else {
+                    "[Fullscreen: off]"
+                };
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    fullscreen_text,
+                    Vec2::new(x, y - 60.0),
+                    5.0,
+                    (14.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::ToggleFullscreen,
+                    interface_rect(x - 4.0, y - 78.0, 140.0, 20.0),
+                );
+                // Shown so that players can share their worlds
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("World seed: {}", ship_game.seed),
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(x + 150.0, y - 62.0),
+                    5.0,
+                    (12.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    Some(166.0),
+                    Vec4::ONE,
+                );
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("[Bloom: {:.0}%]", settings.bloom_strength * 100.0),
+                    Vec2::new(x, y - 80.0),
+                    5.0,
+                    (14.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::CycleBloom,
+                    interface_rect(x - 4.0, y - 98.0, 108.0, 20.0),
+                );
+                let dof_text = if settings.dof_aperture > 0.0 {
+                    format!("[DoF: {:.1}]", settings.dof_aperture)
+                } else {
+                    "[DoF: off]".to_string()
+                };
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    &dof_text,
+                    Vec2::new(x + 112.0, y - 80.0),
+                    5.0,
+                    (14.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::CycleDof,
+                    interface_rect(x + 108.0, y - 98.0, 88.0, 20.0),
+                );
+
+                let (x, width, row_height) = (scr_x + 214.0, 116.0, 15.0);
+                let options_page = interface.options_page;
+                let page_text = if options_page {
+                    "[< Key bindings]"
+                } else {
+                    "[More options >]"
+                };
+                let page_y = y - 6.0 * row_height;
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    page_text,
+                    Vec2::new(x, page_y),
+                    5.0,
+                    (10.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::SettingsPage,
+                    interface_rect(x - 2.0, page_y - row_height, width + 4.0, row_height),
+                );
+                let key_bindings = KeyAction::ALL.into_iter();
+                let options = GameOption::ALL.into_iter();
+                for (i, option) in options.enumerate().filter(|_| options_page) {
+                    let y = y - i as f32 * row_height;
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("{}:", option.name()),
+                        Vec2::new(x, y),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &settings.option_text(option),
+                        Vec2::new(x + 78.0, y),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Center, VerticalAlign::Top),
+                        Some(28.0),
+                        DASHBOARD_TEXT_COLOR,
+                    );
+                    for (text, step, arrow_x) in [("<", -1, x + 66.0), (">", 1, x + 108.0)] {
+                        self.text.draw_text(
+                            &mut self.transparent_screen_draw_calls,
+                            text,
+                            Vec2::new(arrow_x, y),
+                            5.0,
+                            (10.0, scale),
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            None,
+                            DASHBOARD_TEXT_COLOR,
+                        );
+                        interface.add_button(
+                            Button::AdjustOption(option, step),
+                            interface_rect(arrow_x - 3.0, y - row_height, 12.0, row_height),
+                        );
+                    }
+                }
+                if options_page {
+                    let y = y - 5.0 * row_height;
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        "[Wipe progress]",
+                        Vec2::new(x, y),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        FAILED_TEXT_COLOR,
+                    );
+                    interface.add_button(
+                        Button::WipeProgress,
+                        interface_rect(x - 2.0, y - row_height, width + 4.0, row_height),
+                    );
+                }
+                for (i, action) in key_bindings.enumerate().filter(|_| !options_page) {
+                    let y = y - i as f32 * row_height;
+                    let key_name = if interface.capturing_key_binding == Some(action) {
+                        "[press a key]".to_string()
+                    } else {
+                        format!("[{}]", settings.key_bindings.get(action).name())
+                    };
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("{}:", action.name()),
+                        Vec2::new(x, y),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &key_name,
+                        Vec2::new(x, y),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Right, VerticalAlign::Top),
+                        Some(width),
+                        DASHBOARD_TEXT_COLOR,
+                    );
+                    interface.add_button(
+                        Button::KeyBinding(action),
+                        interface_rect(x - 2.0, y - row_height, width + 4.0, row_height),
+                    );
+                }
+            }
+            Some(Tab::Map) => {
+                let (map_width, map_height) = (320.0, 100.0);
+                let map_center = Vec2::new(scr_x + 168.0, scr_y + scr_h / 2.0);
+                self.ocean_tile.draw(
+                    &mut self.screen_draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        Vec3::new(map_width, map_height, 1.0),
+                        Quat::IDENTITY,
+                        (map_center - Vec2::new(map_width, map_height) / 2.0).extend(4.0),
+                    ),
+                );
+                // Leave some margin around the outermost locations
+                let world_scale = ship_game
+                    .locations
+                    .iter()
+                    .map(|location| location.position.abs().max_element())
+                    .fold(1.0, f32::max)
+                    * 1.1;
+                let to_map = |position: Vec2| {
+                    let half_size = Vec2::new(map_width, map_height) / 2.0;
+                    map_center + position / world_scale * half_size
+                };
+
+                // Delivery routes, as dotted arcs from the ship
+                for delivery in ship_game.deliveries.iter().filter(|d| !d.completed) {
+                    let destination = ship_game
+                        .locations
+                        .iter()
+                        .find(|location| location.name == delivery.next_stop_name());
+                    let Some(destination) = destination else {
+                        continue;
+                    };
+                    let from = to_map(ship_game.current_location);
+                    let to = to_map(destination.position);
+                    let bulge = (to - from).perp() * 0.2;
+                    let dots = ((to - from).length() / 5.0) as usize;
+                    for i in 1..dots {
+                        let t = i as f32 / dots as f32;
+                        let point = from.lerp(to, t) + bulge * 4.0 * t * (1.0 - t);
+                        self.pixel_gray.draw(
+                            &mut self.screen_draw_calls,
+                            Mat4::from_scale_rotation_translation(
+                                Vec3::new(1.5, 1.5, 1.0),
+                                Quat::IDENTITY,
+                                point.extend(5.0),
+                            ),
+                        );
+                    }
+                }
+
+                // Dashed line to the current target
+                let from = to_map(ship_game.current_location);
+                let to = to_map(ship_game.current_target);
+                let dash_count = ((to - from).length() / 6.0) as usize;
+                for i in 0..dash_count {
+                    let dash_start = from.lerp(to, i as f32 / dash_count as f32);
+                    let dash_end = from.lerp(to, (i as f32 + 0.5) / dash_count as f32);
+                    let pixel = &self.pixel_green;
+                    draw_ui_line(
+                        pixel,
+                        &mut self.screen_draw_calls,
+                        dash_start,
+                        dash_end,
+                        1.0,
+                        5.5,
+                    );
+                }
+
+                for location in &ship_game.locations {
+                    let pixel = if location.is_fuel_depot {
+                        &self.pixel_green
+                    } else {
+                        &self.pixel_gray
+                    };
+                    let position = to_map(location.position);
+                    pixel.draw(
+                        &mut self.screen_draw_calls,
+                        Mat4::from_scale_rotation_translation(
+                            Vec3::new(4.0, 4.0, 1.0),
+                            Quat::IDENTITY,
+                            (position - Vec2::ONE * 2.0).extend(6.0),
+                        ),
+                    );
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &location.name,
+                        position + Vec2::new(4.0, 11.0),
+                        6.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                }
+
+                // The ship, as an arrowhead pointing where it's heading
+                let ship = to_map(ship_game.current_location);
+                let heading = ship_game.current_heading.normalize_or_zero();
+                let tip = ship + heading * 5.0;
+                for side in [2.5f32, -2.5] {
+                    let back = ship - heading * 3.0 + heading.perp() * side;
+                    let pixel = &self.pixel_green;
+                    draw_ui_line(pixel, &mut self.screen_draw_calls, back, tip, 2.0, 7.0);
+                }
+            }
+            Some(Tab::Crew) => {
+                let row_height = 16.0;
+                let mut draw_column = |x: f32, title: &str, rows: Vec<(String, Button)>| {
+                    let y = scr_y + scr_h - 4.0;
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        title,
+                        Vec2::new(x, y),
+                        5.0,
+                        (14.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    for (i, (name, button)) in rows.into_iter().enumerate() {
+                        let y = y - 20.0 - i as f32 * row_height;
+                        let label = match button {
+                            Button::HireCharacter(_) => "[HIRE]",
+                            _ => "[DISMISS]",
+                        };
+                        self.text.draw_text(
+                            &mut self.transparent_screen_draw_calls,
+                            &format!("{name} {label}"),
+                            Vec2::new(x, y),
+                            5.0,
+                            (12.0, scale),
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            None,
+                            Vec4::ONE,
+                        );
+                        interface.add_button(
+                            button,
+                            interface_rect(x - 2.0, y - row_height + 2.0, 150.0, row_height),
+                        );
+                    }
+                };
+                let crew = ship_game.characters.iter().enumerate();
+                let crew = crew
+                    .map(|(i, c)| {
+                        let name = format!("{}, {}", c.name, c.job.name());
+                        (name, Button::DismissCharacter(i))
+                    })
+                    .collect();
+                draw_column(scr_x + 10.0, "Crew", crew);
+                let recruits = ship_game.available_recruits.iter().enumerate();
+                let recruits = recruits
+                    .map(|(i, r)| {
+                        let name = format!("{}, {}", r.name, r.job.name());
+                        (name, Button::HireCharacter(i))
+                    })
+                    .collect();
+                draw_column(scr_x + 176.0, "Recruits", recruits);
+            }
+            Some(Tab::Build) => {
+                let (x, y) = (scr_x + 10.0, scr_y + scr_h - 4.0);
+                let row_height = 16.0;
+                let tools = RoomType::ALL
+                    .iter()
+                    .map(|&room_type| {
+                        let text = format!("[{} room]", room_type.name());
+                        (
+                            text,
+                            BuildTool::Place(room_type),
+                            Button::PlaceRoom(room_type),
+                        )
+                    })
+                    .chain([(
+                        "[Tear down]".to_string(),
+                        BuildTool::Remove,
+                        Button::RemoveRoom,
+                    )]);
+                for (i, (text, tool, button)) in tools.enumerate() {
+                    let y = y - i as f32 * row_height;
+                    let color = if interface.build_tool == Some(tool) {
+                        DOCKED_TEXT_COLOR
+                    } else {
+                        Vec4::ONE
+                    };
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &text,
+                        Vec2::new(x, y),
+                        5.0,
+                        (12.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        color,
+                    );
+                    interface.add_button(
+                        button,
+                        interface_rect(x - 2.0, y - row_height + 2.0, 130.0, row_height),
+                    );
+                }
+                let help = match interface.build_tool {
+                    Some(BuildTool::Place(room_type)) => format!(
+                        "Click on the ship to build a {} room next to the others.",
+                        room_type.name().to_lowercase(),
+                    ),
+                    Some(BuildTool::Remove) => "Click on a room to tear it down. The ship \
+                        needs at least one navigation and one sails room."
+                        .to_string(),
+                    None => "Pick a room to build, or pick tearing rooms down.".to_string(),
+                };
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    &help,
+                    Vec2::new(x + 140.0, y),
+                    5.0,
+                    (12.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    Some(176.0),
+                    Vec4::ONE,
+                );
+            }
+            Some(Tab::Log) => {
+                let log_list = &interface.log_list;
+                let (x, y) = (scr_x + 10.0, scr_y + scr_h - 10.0);
+                for (i, (time, category, text)) in ship_game.log.iter().rev().enumerate() {
+                    let row_y = i as f32 * log_list.item_height;
+                    if !log_list.is_visible(row_y) {
+                        continue;
+                    }
+                    let color = match category {
+                        LogCategory::Travel => DOCKED_TEXT_COLOR,
+                        LogCategory::Delivery => Vec4::ONE,
+                        LogCategory::Event => ETA_TEXT_COLORS[1],
+                        LogCategory::Crew => Vec4::new(0.6, 0.8, 1.0, 1.0),
+                        LogCategory::Danger => FAILED_TEXT_COLOR,
+                    };
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        &format!("Day {time:.1}: {text}"),
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x, y - row_y + log_list.scroll_offset),
+                        5.0,
+                        (10.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        color,
+                    );
+                }
+                interface.log_list.item_count = ship_game.log.len();
+                if ship_game.log.is_empty() {
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        "Nothing's happened yet.",
+                        Vec2::new(x, y),
+                        5.0,
+                        (14.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                }
+            }
+            Some(Tab::SaveLoad) => {
+                let (x, y) = (scr_x + 10.0, scr_y + scr_h - 10.0);
+                let save_slots = interface.save_slots.clone();
+                for (slot, description) in save_slots.iter().enumerate() {
+                    let slot_list = &interface.save_slot_list;
+                    let row_y = slot as f32 * slot_list.item_height;
+                    if !slot_list.is_visible(row_y) {
+                        continue;
+                    }
+                    let y = y - row_y + slot_list.scroll_offset;
+                    let name = if slot == AUTOSAVE_SLOT {
+                        "Autosave".to_string()
+                    } else {
+                        format!("Slot {slot}")
+                    };
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        &name,
+                        Vec2::new(x, y),
+                        5.0,
+                        (12.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    self.text.draw_text_with_font(
+                        &mut self.transparent_screen_draw_calls,
+                        description.as_deref().unwrap_or("Empty"),
+                        FontRenderer::FONT_MONO,
+                        Vec2::new(x + 60.0, y - 2.0),
+                        5.0,
+                        (9.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
+                    );
+                    let mut buttons = vec![(Button::SaveToSlot(slot), "[Save]", 214.0)];
+                    if description.is_some() {
+                        buttons.push((Button::LoadFromSlot(slot), "[Load]", 250.0));
+                        buttons.push((Button::DeleteSlot(slot), "[Del]", 286.0));
+                    }
+                    for (button, text, x_offset) in buttons {
+                        let x = x + x_offset;
+                        self.text.draw_text(
+                            &mut self.transparent_screen_draw_calls,
+                            text,
+                            Vec2::new(x, y),
+                            5.0,
+                            (12.0, scale),
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            None,
+                            Vec4::ONE,
+                        );
+                        interface.add_button(button, interface_rect(x - 2.0, y - 15.0, 34.0, 16.0));
+                    }
+                }
+                interface.save_slot_list.item_count = save_slots.len();
+            }
+            Some(Tab::Trade) => {
+                let (x, y) = (scr_x + 10.0, scr_y + scr_h - 4.0);
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!("Credits: {:.0}", ship_game.credits),
+                    Vec2::new(x, y),
+                    5.0,
+                    (20.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    DOCKED_TEXT_COLOR,
+                );
+                self.text.draw_text_with_font(
+                    &mut self.transparent_screen_draw_calls,
+                    &format!(
+                        "Cargo: {:3.0}/{:.0} t",
+                        ship_game.cargo_weight(),
+                        MAX_CARGO_WEIGHT,
+                    ),
+                    FontRenderer::FONT_MONO,
+                    Vec2::new(x + 166.0, y - 4.0),
+                    5.0,
+                    (12.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                if let Some(port) = ship_game.port_here() {
+                    // Only as many rows as fit on the screen
+                    let (row_height, max_rows) = (11.0, 6);
+                    let mut draw_column = |x: f32, title: &str, rows: Vec<(String, Button)>| {
+                        let y = y - 26.0;
+                        self.text.draw_text(
+                            &mut self.transparent_screen_draw_calls,
+                            title,
+                            Vec2::new(x, y),
+                            5.0,
+                            (12.0, scale),
+                            (HorizontalAlign::Left, VerticalAlign::Top),
+                            Some(150.0),
+                            Vec4::ONE,
+                        );
+                        for (i, (text, button)) in rows.into_iter().take(max_rows).enumerate() {
+                            let y = y - 15.0 - i as f32 * row_height;
+                            self.text.draw_text_with_font(
+                                &mut self.transparent_screen_draw_calls,
+                                &text,
+                                FontRenderer::FONT_MONO,
+                                Vec2::new(x, y),
+                                5.0,
+                                (9.0, scale),
+                                (HorizontalAlign::Left, VerticalAlign::Top),
+                                None,
+                                Vec4::ONE,
+                            );
+                            interface.add_button(
+                                button,
+                                interface_rect(x - 2.0, y - row_height + 1.0, 150.0, row_height),
+                            );
+                        }
+                    };
+                    let hold = ship_game.cargo_hold.iter().enumerate();
+                    let hold = hold
+                        .map(|(i, (name, quantity, _))| {
+                            let is_delivery = ship_game.deliveries.iter().any(|d| d.name == name);
+                            let label = if is_delivery { "[DELIVER]" } else { "[SELL]" };
+                            (format!("{name} x{quantity} {label}"), Button::SellItem(i))
+                        })
+                        .collect();
+                    draw_column(x, "Cargo hold", hold);
+                    let location = &ship_game.locations[port];
+                    let inventory = location.port_inventory.iter().enumerate();
+                    let inventory = inventory
+                        .map(|(i, (name, quantity, price))| {
+                            let text = format!("{name} x{quantity} {price:.0}c [BUY]");
+                            (text, Button::BuyItem(i))
+                        })
+                        .collect();
+                    draw_column(x + 166.0, &location.name, inventory);
+                } else {
+                    self.text.draw_text(
+                        &mut self.transparent_screen_draw_calls,
+                        "Stop at a port to trade",
+                        Vec2::new(x, y - 40.0),
+                        5.0,
+                        (14.0, scale),
+                        (HorizontalAlign::Left, VerticalAlign::Top),
+                        None,
+                        Vec4::ONE,
                     );
                 }
             }
-            Some(Tab::GameSettings) => {}
             _ => {}
         }
 
+        if let Some(action) = interface.pending_confirm {
+            let question = match action {
+                PendingAction::ClearSchedule(_) => "Clear schedule?",
+                PendingAction::WipeProgress => "Wipe all progress?",
+                PendingAction::DeleteSlot(_) => "Delete save?",
+            };
+            let (w, h) = (200.0, 60.0);
+            let x = scr_x + (336.0 - w) / 2.0;
+            let y = scr_y + (scr_h - h) / 2.0;
+            self.pixel_gray.draw(
+                &mut self.screen_draw_calls,
+                Mat4::from_scale_rotation_translation(
+                    Vec3::new(w, h, 1.0),
+                    Quat::IDENTITY,
+                    Vec3::new(x, y, 10.0),
+                ),
+            );
+            self.text.draw_text(
+                &mut self.transparent_screen_draw_calls,
+                question,
+                Vec2::new(x + 10.0, y + h - 6.0),
+                11.0,
+                (14.0, scale),
+                (HorizontalAlign::Left, VerticalAlign::Top),
+                None,
+                Vec4::ONE,
+            );
+            for (button, text, x_offset) in [
+                (Button::ConfirmYes, "[Yes]", 40.0),
+                (Button::ConfirmNo, "[No]", 120.0),
+            ] {
+                let x = x + x_offset;
+                let y = y + 24.0;
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    text,
+                    Vec2::new(x, y),
+                    11.0,
+                    (14.0, scale),
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(button, interface_rect(x - 4.0, y - 18.0, 44.0, 20.0));
+            }
+        }
+
+        // Events are shown regardless of the tab, so they don't slide
+        if let Some(event) = &ship_game.pending_event {
+            let (w, h) = (300.0, 70.0);
+            let x = event_area.0 + (336.0 - w) / 2.0;
+            let y = event_area.1 + (scr_h - h) / 2.0;
+            self.pixel_gray.draw(
+                &mut self.screen_draw_calls,
+                Mat4::from_scale_rotation_translation(
+                    Vec3::new(w, h, 1.0),
+                    Quat::IDENTITY,
+                    Vec3::new(x, y, 12.0),
+                ),
+            );
+            self.text.draw_text(
+                &mut self.transparent_screen_draw_calls,
+                &format!("{} ({:.0}s)", event.description, event.timer.ceil()),
+                Vec2::new(x + 10.0, y + h - 6.0),
+                13.0,
+                (14.0, scale),
+                (HorizontalAlign::Left, VerticalAlign::Top),
+                Some(w - 20.0),
+                Vec4::ONE,
+            );
+            let mut option_x = x + 10.0;
+            for (i, (text, _)) in event.options.iter().enumerate() {
+                let text = format!("[{text}]");
+                let size = (14.0, scale);
+                let text_width = self
+                    .text
+                    .text_width(&text, FontRenderer::FONT_DEFAULT, size);
+                let y = y + 24.0;
+                self.text.draw_text(
+                    &mut self.transparent_screen_draw_calls,
+                    &text,
+                    Vec2::new(option_x, y),
+                    13.0,
+                    size,
+                    (HorizontalAlign::Left, VerticalAlign::Top),
+                    None,
+                    Vec4::ONE,
+                );
+                interface.add_button(
+                    Button::EventOption(i),
+                    interface_rect(option_x - 4.0, y - 18.0, text_width + 8.0, 20.0),
+                );
+                option_x += text_width + 20.0;
+            }
+        }
+
+        // Keyboard focus indicator
+        if let Some(rect) = interface.focused_button_rect() {
+            let x = rect.x() as f32 / scale - width / 2.0;
+            let y = height + 2.0 - rect.bottom() as f32 / scale;
+            let w = rect.width() as f32 / scale;
+            let h = rect.height() as f32 / scale;
+            let border = 2.0;
+            for (x, y, w, h) in [
+                (x - border, y - border, w + border * 2.0, border),
+                (x - border, y + h, w + border * 2.0, border),
+                (x - border, y, border, h),
+                (x + w, y, border, h),
+            ] {
+                self.pixel_green.draw(
+                    &mut self.ui_draw_calls,
+                    Mat4::from_scale_rotation_translation(
+                        Vec3::new(w, h, 1.0),
+                        Quat::IDENTITY,
+                        Vec3::new(x, y, 6.0),
+                    ),
+                );
+            }
+        }
+
+        if let Some(notification) = interface.notifications.front() {
+            let age = notification.duration - notification.ttl;
+            let slide_in = (1.0 - age / 0.25).max(0.0);
+            let y = height - 8.0 + slide_in * 30.0;
+            // Quantized, since each text color gets its own material
+            let alpha = (notification.ttl.min(0.5) / 0.5 * 16.0).round() / 16.0;
+            let size = (16.0, scale);
+            let text_width =
+                self.text
+                    .text_width(&notification.text, FontRenderer::FONT_DEFAULT, size);
+            self.text.draw_text(
+                &mut self.transparent_ui_draw_calls,
+                &notification.text,
+                Vec2::new(-text_width / 2.0, y),
+                30.0,
+                size,
+                (HorizontalAlign::Left, VerticalAlign::Top),
+                None,
+                Vec4::new(1.0, 1.0, 1.0, alpha),
+            );
+        }
+
+        if paused {
+            let size = (32.0, scale);
+            let text_width = self
+                .text
+                .text_width("PAUSED", FontRenderer::FONT_DEFAULT, size);
+            self.text.draw_text(
+                &mut self.transparent_ui_draw_calls,
+                "PAUSED",
+                Vec2::new(-text_width / 2.0, height / 2.0 + size.0 / 2.0),
+                30.0,
+                size,
+                (HorizontalAlign::Left, VerticalAlign::Top),
+                None,
+                Vec4::ONE,
+            );
+        }
+
+        if let Some(character) = ship_game
+            .selected_character
+            .and_then(|i| ship_game.characters.get(i))
+        {
+            let task = if character.exhausted {
+                "Resting"
+            } else {
+                match character.schedule[ship_game.current_hour()] {
+                    Task::Work => "Working",
+                    Task::Sleep => "Sleeping",
+                }
+            };
+            self.character_info_overlay(character, task, width, scale);
+        }
+
+        if let Some((text, position)) = &interface.active_tooltip {
+            self.draw_tooltip(text, *position, width, height, scale);
+        }
+
+        if ship_game.is_sunk() {
+            self.pixel_gray.draw(
+                &mut self.ui_draw_calls,
+                Mat4::from_scale_rotation_translation(
+                    Vec3::new(width, height, 1.0),
+                    Quat::IDENTITY,
+                    Vec3::new(-width / 2.0, 0.0, 40.0),
+                ),
+            );
+            for (text, size, y) in [
+                ("You sank!", 48.0, height / 2.0 + 30.0),
+                ("Press Enter to start over", 16.0, height / 2.0 - 20.0),
+            ] {
+                self.text.draw_text(
+                    &mut self.transparent_ui_draw_calls,
+                    text,
+                    Vec2::new(0.0, y),
+                    41.0,
+                    (size, scale),
+                    (HorizontalAlign::Center, VerticalAlign::Middle),
+                    None,
+                    Vec4::ONE,
+                );
+            }
+        }
+
+        if let Some(text) = debug_overlay {
+            // In front of everything else, including tooltips
+            let max_width = 150.0;
+            self.text.draw_text_with_font(
+                &mut self.transparent_ui_draw_calls,
+                text,
+                FontRenderer::FONT_MONO,
+                Vec2::new(width / 2.0 - 8.0 - max_width, height - 8.0),
+                90.0,
+                (12.0, scale),
+                (HorizontalAlign::Right, VerticalAlign::Top),
+                Some(max_width),
+                Vec4::ONE,
+            );
+        }
+
         let ui_proj_matrix =
             Mat4::orthographic_rh_gl(-width / 2.0, width / 2.0, 0.0, height, -100.0, 100.0)
                 .to_cols_array();
@@ -459,25 +2847,260 @@ impl Renderer {
             gl::FALSE,
             ui_view_matrix.as_ptr(),
         ));
-        self.ui_draw_calls.draw(
+        let screen_area = interface.screen_area;
+        let set_screen_scissor = |enabled: bool| {
+            if enabled {
+                gl::call!(gl::Scissor(
+                    screen_area.x(),
+                    (height * scale) as i32 - screen_area.bottom(),
+                    screen_area.width() as i32,
+                    screen_area.height() as i32,
+                ));
+                gl::call!(gl::Enable(gl::SCISSOR_TEST));
+            } else {
+                gl::call!(gl::Disable(gl::SCISSOR_TEST));
+            }
+        };
+        gl::call!(gl::DepthMask(gl::TRUE));
+        stats += self.ui_draw_calls.draw(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        );
+        set_screen_scissor(true);
+        stats += self.screen_draw_calls.draw(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        );
+        // The translucent elements are sorted, so they don't need to occlude
+        // each other, just be occluded by the opaque ones.
+        gl::call!(gl::DepthMask(gl::FALSE));
+        stats += self.transparent_screen_draw_calls.draw_sorted(
+            gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
+            gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
+        );
+        set_screen_scissor(false);
+        stats += self.transparent_ui_draw_calls.draw_sorted(
             gltf::ATTR_LOC_MODEL_TRANSFORM_COLUMNS,
             gltf::ATTR_LOC_TEXCOORD_TRANSFORM_COLUMNS,
         );
+        gl::call!(gl::DepthMask(gl::TRUE));
+        self.last_frame_stats = stats;
     }
 
-    fn get_view_and_proj_matrices(&self, aspect_ratio: f32) -> (Mat4, Mat4) {
-        // OpenGL clip space: right-handed, +X right, +Y up, +Z backward (out of screen).
-        // GLTF:              right-handed, +X left, +Y up, +Z forward (into the screen).
-        let to_opengl_basis = Mat4::from_cols(
-            (RIGHT, 0.0).into(),    // +X is right in OpenGL clip space
-            (UP, 0.0).into(),       // +Y is up in OpenGL clip space
-            (-FORWARD, 0.0).into(), // +Z is backward in OpenGL clip space
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
+    /// Returns the statistics of the draw calls of the last rendered frame.
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.last_frame_stats
+    }
+
+    /// Draws the text in a box next to the cursor (in window coordinates),
+    /// moved to stay inside the UI area of the given size.
+    fn draw_tooltip(&mut self, text: &str, cursor: Point, width: f32, height: f32, scale: f32) {
+        let size = (12.0, scale);
+        let padding = 3.0;
+        let w = self.text.text_width(text, FontRenderer::FONT_DEFAULT, size) + padding * 2.0;
+        let h = size.0 + padding * 2.0;
+        // Below and to the right of the cursor, like most tooltips
+        let x = cursor.x() as f32 / scale - width / 2.0 + 12.0;
+        let y = height - cursor.y() as f32 / scale - 16.0;
+        let x = x.min(width / 2.0 - w).max(-width / 2.0);
+        let y = y.max(h).min(height);
+        self.pixel_gray.draw(
+            &mut self.ui_draw_calls,
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(w, h, 1.0),
+                Quat::IDENTITY,
+                Vec3::new(x, y - h, 20.0),
+            ),
         );
+        self.text.draw_text(
+            &mut self.transparent_ui_draw_calls,
+            text,
+            Vec2::new(x + padding, y - padding),
+            21.0,
+            size,
+            (HorizontalAlign::Left, VerticalAlign::Top),
+            None,
+            Vec4::ONE,
+        );
+    }
 
+    /// Draws a panel with the character's details in the bottom-left corner
+    /// of the screen.
+    fn character_info_overlay(
+        &mut self,
+        character: &Character,
+        task: &str,
+        width: f32,
+        scale: f32,
+    ) {
+        let padding = 4.0;
+        let (w, h) = (120.0, 70.0);
+        let (x, y) = (-width / 2.0 + 8.0, 8.0);
+        self.pixel_gray.draw(
+            &mut self.ui_draw_calls,
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(w, h, 1.0),
+                Quat::IDENTITY,
+                Vec3::new(x, y, 20.0),
+            ),
+        );
+        self.text.draw_text(
+            &mut self.transparent_ui_draw_calls,
+            &character.name,
+            Vec2::new(x + padding, y + h - padding),
+            21.0,
+            (14.0, scale),
+            (HorizontalAlign::Left, VerticalAlign::Top),
+            Some(w - padding * 2.0),
+            Vec4::ONE,
+        );
+        let details = format!(
+            "{}\n{task}\nEnergy: {:3.0}%\nMorale: {:3.0}%",
+            character.job.name(),
+            character.energy * 100.0,
+            character.morale * 100.0,
+        );
+        self.text.draw_text_with_font(
+            &mut self.transparent_ui_draw_calls,
+            &details,
+            FontRenderer::FONT_MONO,
+            Vec2::new(x + padding, y + h - padding - 17.0),
+            21.0,
+            (10.0, scale),
+            (HorizontalAlign::Left, VerticalAlign::Top),
+            Some(w - padding * 2.0),
+            Vec4::ONE,
+        );
+    }
+
+    /// Returns the memory used by the buffers that are written to during
+    /// rendering.
+    pub fn memory_stats(&self) -> RendererMemoryStats {
+        let mut stats = RendererMemoryStats::default();
+        self.draw_calls.add_memory_stats(&mut stats);
+        self.selected_draw_calls.add_memory_stats(&mut stats);
+        self.outline_draw_calls.add_memory_stats(&mut stats);
+        self.ui_draw_calls.add_memory_stats(&mut stats);
+        self.transparent_ui_draw_calls.add_memory_stats(&mut stats);
+        self.screen_draw_calls.add_memory_stats(&mut stats);
+        if let Some(minimap) = &self.minimap {
+            minimap.draw_calls.add_memory_stats(&mut stats);
+        }
+        self.transparent_screen_draw_calls
+            .add_memory_stats(&mut stats);
+        self.text.add_memory_stats(&mut stats);
+        stats
+    }
+
+    fn get_view_and_proj_matrices(&self, aspect_ratio: f32) -> (Mat4, Mat4) {
         let view_matrix = self.camera.view_matrix();
-        let proj_matrix =
-            Mat4::perspective_rh_gl(20f32.to_radians(), aspect_ratio, 200.0, 0.3) * to_opengl_basis;
-        (view_matrix, proj_matrix)
+        let fov = 20f32.to_radians();
+        let proj_matrix = if self.camera.orthographic {
+            // Match the size of the perspective view at the focus point, so
+            // that zooming works the same way in both modes.
+            let h = self.camera.distance * (fov / 2.0).tan();
+            let w = h * aspect_ratio;
+            Mat4::orthographic_rh_gl(-w, w, -h, h, 200.0, 0.3)
+        } else {
+            Mat4::perspective_rh_gl(fov, aspect_ratio, 200.0, 0.3)
+        };
+        (view_matrix, proj_matrix * to_opengl_basis())
+    }
+}
+
+/// Converts from the glTF basis to the OpenGL clip space basis, to be
+/// multiplied after the projection matrix.
+fn to_opengl_basis() -> Mat4 {
+    // OpenGL clip space: right-handed, +X right, +Y up, +Z backward (out of screen).
+    // GLTF:              right-handed, +X left, +Y up, +Z forward (into the screen).
+    Mat4::from_cols(
+        (RIGHT, 0.0).into(),    // +X is right in OpenGL clip space
+        (UP, 0.0).into(),       // +Y is up in OpenGL clip space
+        (-FORWARD, 0.0).into(), // +Z is backward in OpenGL clip space
+        Vec4::new(0.0, 0.0, 0.0, 1.0),
+    )
+}
+
+/// Returns the window-space rectangle (in pixels, from the top left) which
+/// covers the sphere, or None if the sphere is partly behind the camera.
+/// Returns the direction towards the sun and its color, at the given time
+/// in days. The sun rises at 6:00, is overhead at noon, and sets at 18:00.
+/// At night, the same light stands in for the moon, dim and orange.
+fn sun_direction_and_color(world_time: f32) -> (Vec3, Vec3) {
+    let sun_angle = (world_time.fract() - 0.25) * TAU;
+    let sun_direction = Vec3::new(sun_angle.cos(), sun_angle.sin().abs(), sun_angle.sin());
+    let low_sun_color = Vec3::new(1.0, 0.5, 0.2);
+    let sun_height = sun_angle.sin().clamp(0.0, 1.0);
+    let sun_color = low_sun_color.lerp(Vec3::ONE, sun_height) * sun_height.max(0.1);
+    (sun_direction, sun_color)
+}
+
+fn sphere_to_screen_rect(
+    proj_from_world: Mat4,
+    center: Vec3,
+    radius: f32,
+    width: f32,
+    height: f32,
+) -> Option<Rect> {
+    // The corners of the cube around the sphere
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    for i in 0..8 {
+        let corner_dir = Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        let clip = proj_from_world * (center + corner_dir * radius).extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.xy() / clip.w;
+        min = min.min(ndc);
+        max = max.max(ndc);
+    }
+    let to_window =
+        |ndc: Vec2| Vec2::new((ndc.x * 0.5 + 0.5) * width, (0.5 - ndc.y * 0.5) * height);
+    // The y axis flips, so the top left corner comes from (min.x, max.y)
+    let top_left = to_window(Vec2::new(min.x, max.y)).floor();
+    let bottom_right = to_window(Vec2::new(max.x, min.y)).ceil();
+    let size = (bottom_right - top_left).max(Vec2::ONE);
+    Some(Rect::new(
+        top_left.x as i32,
+        top_left.y as i32,
+        size.x as u32,
+        size.y as u32,
+    ))
+}
+
+/// Draws a line between two points in interface coordinates, out of a
+/// stretched and rotated pixel model.
+fn draw_ui_line(
+    pixel: &gltf::Gltf,
+    draw_calls: &mut DrawCalls,
+    from: Vec2,
+    to: Vec2,
+    thickness: f32,
+    z: f32,
+) {
+    let delta = to - from;
+    let rotation = Quat::from_rotation_z(delta.y.atan2(delta.x));
+    let start = from.extend(z) - rotation * Vec3::new(0.0, thickness / 2.0, 0.0);
+    pixel.draw(
+        draw_calls,
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(delta.length(), thickness, 1.0),
+            rotation,
+            start,
+        ),
+    );
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteTextures(1, &self.navigation_map_lit_texture));
+        gl::call!(gl::DeleteTextures(1, &self.ship_damaged_texture));
+        gl::call!(gl::DeleteTextures(1, &self.water_normal_texture.0));
+        gl::call!(gl::DeleteSamplers(1, &self.water_normal_texture.1));
     }
 }
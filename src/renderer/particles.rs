@@ -0,0 +1,91 @@
+use glam::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: Vec3,
+    /// How fast the particle moves, in world units per second.
+    pub velocity: Vec3,
+    /// How many seconds the particle has left before it disappears.
+    pub lifetime: f32,
+    /// The lifetime the particle started with.
+    pub max_lifetime: f32,
+    /// The size of the particle at the start of its life. Particles shrink
+    /// away as they age, see [Particle::current_scale].
+    pub scale: f32,
+}
+
+impl Particle {
+    pub fn current_scale(&self) -> f32 {
+        self.scale * (self.lifetime / self.max_lifetime).clamp(0.0, 1.0)
+    }
+}
+
+/// Spawns particles at a steady rate, and moves them until they die.
+pub struct ParticleEmitter {
+    pub particles: Vec<Particle>,
+    /// How many particles are spawned per second.
+    pub emission_rate: f32,
+    /// Counts up to the next particle spawn, in seconds.
+    emit_timer: f32,
+    /// Creates each new particle, in world space.
+    spawn_fn: Box<dyn FnMut() -> Particle>,
+}
+
+impl ParticleEmitter {
+    pub fn new(emission_rate: f32, spawn_fn: Box<dyn FnMut() -> Particle>) -> ParticleEmitter {
+        ParticleEmitter {
+            particles: Vec::new(),
+            emission_rate,
+            emit_timer: 0.0,
+            spawn_fn,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.position += particle.velocity * dt;
+            particle.lifetime -= dt;
+        }
+        self.particles.retain(|particle| particle.lifetime > 0.0);
+
+        if self.emission_rate <= 0.0 {
+            self.emit_timer = 0.0;
+            return;
+        }
+        self.emit_timer += dt;
+        let spawn_interval = 1.0 / self.emission_rate;
+        while self.emit_timer >= spawn_interval {
+            self.emit_timer -= spawn_interval;
+            self.particles.push((self.spawn_fn)());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_particle_emitter() {
+        let spawn = || Particle {
+            position: Vec3::ZERO,
+            velocity: Vec3::X,
+            lifetime: 1.0,
+            max_lifetime: 1.0,
+            scale: 1.0,
+        };
+        let mut emitter = ParticleEmitter::new(4.0, Box::new(spawn));
+        emitter.update(0.5);
+        assert_eq!(2, emitter.particles.len());
+
+        // The first two have moved and aged, and one more was spawned
+        emitter.update(0.25);
+        assert_eq!(3, emitter.particles.len());
+        assert_eq!(Vec3::X * 0.25, emitter.particles[0].position);
+        assert_eq!(0.75, emitter.particles[0].lifetime);
+
+        emitter.emission_rate = 0.0;
+        emitter.update(1.0);
+        assert!(emitter.particles.is_empty());
+    }
+}
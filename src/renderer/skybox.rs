@@ -0,0 +1,154 @@
+use crate::renderer::gl;
+use glam::{Mat4, Vec3};
+use std::ffi::c_void;
+use std::{mem, ptr};
+
+/// Draws a vertical gradient from the horizon to the zenith behind
+/// everything, on the inside of a cube around the camera.
+pub struct Skybox {
+    program: gl::types::GLuint,
+    vao: gl::types::GLuint,
+    vbo: gl::types::GLuint,
+    ibo: gl::types::GLuint,
+    proj_from_view_location: gl::types::GLint,
+    view_rotation_location: gl::types::GLint,
+    horizon_color_location: gl::types::GLint,
+    zenith_color_location: gl::types::GLint,
+    horizon_color: Vec3,
+    zenith_color: Vec3,
+}
+
+/// The corners of a cube around the origin. The positions double as the
+/// directions from the camera.
+const CUBE_VERTICES: [Vec3; 8] = [
+    Vec3::new(-1.0, -1.0, -1.0),
+    Vec3::new(1.0, -1.0, -1.0),
+    Vec3::new(-1.0, 1.0, -1.0),
+    Vec3::new(1.0, 1.0, -1.0),
+    Vec3::new(-1.0, -1.0, 1.0),
+    Vec3::new(1.0, -1.0, 1.0),
+    Vec3::new(-1.0, 1.0, 1.0),
+    Vec3::new(1.0, 1.0, 1.0),
+];
+
+/// Two triangles for each side of the cube. Face culling is disabled while
+/// drawing the skybox, so the winding doesn't matter.
+const CUBE_INDICES: [u16; 36] = [
+    0, 1, 2, 2, 1, 3, // -Z
+    4, 6, 5, 5, 6, 7, // +Z
+    0, 2, 4, 4, 2, 6, // -X
+    1, 5, 3, 3, 5, 7, // +X
+    0, 4, 1, 1, 4, 5, // -Y
+    2, 3, 6, 6, 3, 7, // +Y
+];
+
+impl Skybox {
+    pub fn new() -> Skybox {
+        let vertex_shader =
+            gl::create_shader(gl::VERTEX_SHADER, include_str!("skybox_vertex.glsl"));
+        let fragment_shader =
+            gl::create_shader(gl::FRAGMENT_SHADER, include_str!("skybox_fragment.glsl"));
+        let program = gl::create_program(&[vertex_shader, fragment_shader]);
+        gl::call!(gl::DeleteShader(vertex_shader));
+        gl::call!(gl::DeleteShader(fragment_shader));
+        let location = |name: &str| gl::get_uniform_location(program, name).unwrap();
+
+        let mut vao = 0;
+        let mut buffers = [0; 2];
+        gl::call!(gl::GenVertexArrays(1, &mut vao));
+        gl::call!(gl::GenBuffers(2, buffers.as_mut_ptr()));
+        let [vbo, ibo] = buffers;
+        gl::call!(gl::BindVertexArray(vao));
+        gl::call!(gl::BindBuffer(gl::ARRAY_BUFFER, vbo));
+        gl::call!(gl::BufferData(
+            gl::ARRAY_BUFFER,
+            mem::size_of_val(&CUBE_VERTICES) as isize,
+            CUBE_VERTICES.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        ));
+        gl::call!(gl::EnableVertexAttribArray(0));
+        gl::call!(gl::VertexAttribPointer(
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            0,
+            ptr::null(),
+        ));
+        gl::call!(gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo));
+        gl::call!(gl::BufferData(
+            gl::ELEMENT_ARRAY_BUFFER,
+            mem::size_of_val(&CUBE_INDICES) as isize,
+            CUBE_INDICES.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        ));
+        gl::call!(gl::BindVertexArray(0));
+
+        Skybox {
+            program,
+            vao,
+            vbo,
+            ibo,
+            proj_from_view_location: location("proj_from_view"),
+            view_rotation_location: location("view_rotation"),
+            horizon_color_location: location("horizon_color"),
+            zenith_color_location: location("zenith_color"),
+            horizon_color: Vec3::new(0.6, 0.45, 0.3),
+            zenith_color: Vec3::new(0.3, 0.2, 0.15),
+        }
+    }
+
+    /// Sets the colors of the sky at the horizon and straight up, which are
+    /// blended between in the sky. The colors are in the framebuffer's
+    /// color space, not linear.
+    pub fn set_gradient(&mut self, horizon: Vec3, zenith: Vec3) {
+        self.horizon_color = horizon;
+        self.zenith_color = zenith;
+    }
+
+    /// Draws the skybox over the whole render target, without touching the
+    /// depth buffer, so it should be drawn before everything else.
+    pub fn draw(&self, proj_from_view: Mat4, view_from_world: Mat4) {
+        let (_, rotation, _) = view_from_world.to_scale_rotation_translation();
+        let view_rotation = Mat4::from_quat(rotation);
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::DepthMask(gl::FALSE));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::UseProgram(self.program));
+        gl::call!(gl::UniformMatrix4fv(
+            self.proj_from_view_location,
+            1,
+            gl::FALSE,
+            proj_from_view.to_cols_array().as_ptr(),
+        ));
+        gl::call!(gl::UniformMatrix4fv(
+            self.view_rotation_location,
+            1,
+            gl::FALSE,
+            view_rotation.to_cols_array().as_ptr(),
+        ));
+        let [r, g, b] = self.horizon_color.to_array();
+        gl::call!(gl::Uniform3f(self.horizon_color_location, r, g, b));
+        let [r, g, b] = self.zenith_color.to_array();
+        gl::call!(gl::Uniform3f(self.zenith_color_location, r, g, b));
+        gl::call!(gl::BindVertexArray(self.vao));
+        gl::call!(gl::DrawElements(
+            gl::TRIANGLES,
+            CUBE_INDICES.len() as i32,
+            gl::UNSIGNED_SHORT,
+            ptr::null(),
+        ));
+        gl::call!(gl::BindVertexArray(0));
+        gl::call!(gl::DepthMask(gl::TRUE));
+        gl::call!(gl::Enable(gl::CULL_FACE));
+        gl::call!(gl::Enable(gl::DEPTH_TEST));
+    }
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteProgram(self.program));
+        gl::call!(gl::DeleteVertexArrays(1, &self.vao));
+        gl::call!(gl::DeleteBuffers(2, [self.vbo, self.ibo].as_ptr()));
+    }
+}
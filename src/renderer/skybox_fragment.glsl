@@ -0,0 +1,14 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec3 view_dir;
+
+uniform vec3 horizon_color;
+uniform vec3 zenith_color;
+
+void main() {
+  float height = max(0.0, normalize(view_dir).y);
+  FRAG_COLOR = vec4(mix(horizon_color, zenith_color, height), 1.0);
+}
@@ -0,0 +1,17 @@
+#version 300 es
+
+layout(location = 0) in vec3 POSITION;
+
+out vec3 view_dir;
+
+uniform mat4 proj_from_view;
+// Only the rotation of the view matrix, so that the sky stays put when the
+// camera moves.
+uniform mat4 view_rotation;
+
+void main() {
+  view_dir = POSITION;
+  // Scaled up to stay between the near and far planes, which matters for the
+  // orthographic projection.
+  gl_Position = proj_from_view * view_rotation * vec4(POSITION * 100.0, 1.0);
+}
@@ -0,0 +1,182 @@
+use crate::renderer::framebuffer::{create_fullscreen_program, Framebuffer};
+use crate::renderer::gl;
+use crate::ship_game::splitmix64;
+use glam::{Mat4, Vec3};
+use std::ffi::c_void;
+
+/// The amount of samples per pixel, needs to match KERNEL_SIZE in the shader.
+const KERNEL_SIZE: usize = 64;
+
+/// Darkens the scene where the geometry is close to other geometry, like in
+/// corners and where characters stand next to walls.
+pub struct SsaoPass {
+    /// A 4x4 texture of random vectors for rotating the kernel.
+    noise_tex: gl::types::GLuint,
+    ssao_fbo: Framebuffer,
+    /// The blurred occlusion multiplied with the scene, the result of the
+    /// pass.
+    blur_fbo: Framebuffer,
+    ssao_shader: gl::types::GLuint,
+    blur_shader: gl::types::GLuint,
+    view_from_clip_location: gl::types::GLint,
+    clip_from_view_location: gl::types::GLint,
+    vao: gl::types::GLuint,
+    sampler: gl::types::GLuint,
+}
+
+impl SsaoPass {
+    pub fn new() -> SsaoPass {
+        let ssao_shader = create_fullscreen_program(include_str!("ssao_fragment.glsl"));
+        let blur_shader = create_fullscreen_program(include_str!("ssao_blur_fragment.glsl"));
+        let kernel = create_kernel();
+        for (program, textures) in [
+            (ssao_shader, ["depth_tex", "noise_tex"]),
+            (blur_shader, ["color_tex", "ssao_tex"]),
+        ] {
+            gl::call!(gl::UseProgram(program));
+            for (unit, name) in textures.iter().enumerate() {
+                if let Some(location) = gl::get_uniform_location(program, name) {
+                    gl::call!(gl::Uniform1i(location, unit as i32));
+                }
+            }
+        }
+        // The kernel never changes, so it's only uploaded once
+        gl::call!(gl::UseProgram(ssao_shader));
+        let kernel_location = gl::get_uniform_location(ssao_shader, "kernel").unwrap();
+        gl::call!(gl::Uniform3fv(
+            kernel_location,
+            KERNEL_SIZE as i32,
+            kernel.as_ptr() as *const f32,
+        ));
+        let location = |name| gl::get_uniform_location(ssao_shader, name).unwrap();
+
+        let mut noise = [0u8; 4 * 4 * 4];
+        for (i, pixel) in noise.chunks_exact_mut(4).enumerate() {
+            let random = splitmix64(KERNEL_SIZE as u64 + i as u64);
+            pixel[0] = random as u8;
+            pixel[1] = (random >> 8) as u8;
+            pixel[2] = 128;
+            pixel[3] = 255;
+        }
+        let mut noise_tex = 0;
+        gl::call!(gl::GenTextures(1, &mut noise_tex));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, noise_tex));
+        gl::call!(gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as i32,
+            4,
+            4,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            noise.as_ptr() as *const c_void,
+        ));
+        // Not drawn with a sampler object, so that these apply
+        for (param, value) in [
+            (gl::TEXTURE_MIN_FILTER, gl::NEAREST),
+            (gl::TEXTURE_MAG_FILTER, gl::NEAREST),
+            (gl::TEXTURE_WRAP_S, gl::REPEAT),
+            (gl::TEXTURE_WRAP_T, gl::REPEAT),
+        ] {
+            gl::call!(gl::TexParameteri(gl::TEXTURE_2D, param, value as i32));
+        }
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, 0));
+
+        // The vertices are generated from gl_VertexID, like in FullscreenQuad
+        let mut vao = 0;
+        gl::call!(gl::GenVertexArrays(1, &mut vao));
+        let mut sampler = 0;
+        gl::call!(gl::GenSamplers(1, &mut sampler));
+        gl::setup_nearest_sampler(sampler);
+
+        SsaoPass {
+            noise_tex,
+            ssao_fbo: Framebuffer::new(1, 1),
+            blur_fbo: Framebuffer::new(1, 1),
+            ssao_shader,
+            blur_shader,
+            view_from_clip_location: location("view_from_clip"),
+            clip_from_view_location: location("clip_from_view"),
+            vao,
+            sampler,
+        }
+    }
+
+    /// Darkens the occluded parts of the scene, and returns the texture with
+    /// the result. Leaves one of the pass's framebuffers bound, so the render
+    /// target and viewport need to be reset afterwards.
+    pub fn run(&mut self, scene_fbo: &Framebuffer, proj_from_view: Mat4) -> gl::types::GLuint {
+        self.ssao_fbo.resize(scene_fbo.width, scene_fbo.height);
+        self.blur_fbo.resize(scene_fbo.width, scene_fbo.height);
+
+        gl::call!(gl::Disable(gl::DEPTH_TEST));
+        gl::call!(gl::Disable(gl::CULL_FACE));
+        gl::call!(gl::Disable(gl::BLEND));
+        gl::call!(gl::BindVertexArray(self.vao));
+
+        self.ssao_fbo.bind();
+        gl::call!(gl::UseProgram(self.ssao_shader));
+        gl::call!(gl::UniformMatrix4fv(
+            self.view_from_clip_location,
+            1,
+            gl::FALSE,
+            proj_from_view.inverse().to_cols_array().as_ptr(),
+        ));
+        gl::call!(gl::UniformMatrix4fv(
+            self.clip_from_view_location,
+            1,
+            gl::FALSE,
+            proj_from_view.to_cols_array().as_ptr(),
+        ));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, scene_fbo.depth_tex));
+        gl::call!(gl::BindSampler(0, self.sampler));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE1));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.noise_tex));
+        gl::call!(gl::BindSampler(1, 0));
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+        self.blur_fbo.bind();
+        gl::call!(gl::UseProgram(self.blur_shader));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, scene_fbo.color_tex));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE1));
+        gl::call!(gl::BindTexture(gl::TEXTURE_2D, self.ssao_fbo.color_tex));
+        gl::call!(gl::BindSampler(1, self.sampler));
+        gl::call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+
+        gl::call!(gl::BindSampler(0, 0));
+        gl::call!(gl::BindSampler(1, 0));
+        gl::call!(gl::ActiveTexture(gl::TEXTURE0));
+        gl::call!(gl::BindVertexArray(0));
+        self.blur_fbo.color_tex
+    }
+}
+
+impl Drop for SsaoPass {
+    fn drop(&mut self) {
+        gl::call!(gl::DeleteTextures(1, &self.noise_tex));
+        gl::call!(gl::DeleteProgram(self.ssao_shader));
+        gl::call!(gl::DeleteProgram(self.blur_shader));
+        gl::call!(gl::DeleteVertexArrays(1, &self.vao));
+        gl::call!(gl::DeleteSamplers(1, &self.sampler));
+    }
+}
+
+/// Returns points in a hemisphere pointing towards +Z, more of them close to
+/// the center.
+fn create_kernel() -> [Vec3; KERNEL_SIZE] {
+    let mut kernel = [Vec3::ZERO; KERNEL_SIZE];
+    for (i, sample) in kernel.iter_mut().enumerate() {
+        let random = splitmix64(i as u64);
+        // Four random numbers between 0 and 1
+        let r = |i: u64| ((random >> (i * 16)) & 0xFFFF) as f32 / 0xFFFF as f32;
+        let direction = Vec3::new(r(0) * 2.0 - 1.0, r(1) * 2.0 - 1.0, r(2)).normalize_or_zero();
+        // Samples closer to the pixel matter more, so there's more of them
+        let t = i as f32 / KERNEL_SIZE as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        *sample = direction * r(3) * scale;
+    }
+    kernel
+}
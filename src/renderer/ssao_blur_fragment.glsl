@@ -0,0 +1,24 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+uniform sampler2D color_tex;
+uniform sampler2D ssao_tex;
+
+void main() {
+  // A 3x3 box blur, to hide the noise from the rotated kernels
+  vec2 texel_size = 1.0 / vec2(textureSize(ssao_tex, 0));
+  float ambient_occlusion = 0.0;
+  for (int y = -1; y <= 1; y++) {
+    for (int x = -1; x <= 1; x++) {
+      vec2 offset = vec2(float(x), float(y)) * texel_size;
+      ambient_occlusion += texture(ssao_tex, tex_coords + offset).r;
+    }
+  }
+  ambient_occlusion /= 9.0;
+  vec3 color = texture(color_tex, tex_coords).rgb;
+  FRAG_COLOR = vec4(color * ambient_occlusion, 1.0);
+}
@@ -0,0 +1,64 @@
+#version 300 es
+precision highp float;
+
+out vec4 FRAG_COLOR;
+
+in vec2 tex_coords;
+
+#define KERNEL_SIZE 64
+// The radius of the sampled hemisphere, in world units.
+#define RADIUS 0.5
+#define BIAS 0.025
+
+uniform sampler2D depth_tex;
+uniform sampler2D noise_tex;
+uniform mat4 view_from_clip;
+uniform mat4 clip_from_view;
+uniform vec3 kernel[KERNEL_SIZE];
+
+vec3 view_position(vec2 uv) {
+  float depth = texture(depth_tex, uv).r;
+  vec4 view_pos = view_from_clip * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+  return view_pos.xyz / view_pos.w;
+}
+
+void main() {
+  // The depth is reversed, so the cleared depth of 0 is the far plane, where
+  // there's only the sky.
+  if (texture(depth_tex, tex_coords).r == 0.0) {
+    FRAG_COLOR = vec4(1.0);
+    return;
+  }
+
+  vec3 position = view_position(tex_coords);
+  // The normals are reconstructed from the depth, which is good enough for
+  // the flat surfaces of the ship.
+  vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+  if (dot(normal, position) > 0.0) {
+    normal = -normal;
+  }
+
+  // The kernel is rotated randomly around the normal, with the rotation
+  // repeating every 4x4 pixels, to be smoothed out by the blur.
+  vec2 noise_scale = vec2(textureSize(depth_tex, 0)) / 4.0;
+  vec3 random = texture(noise_tex, tex_coords * noise_scale).xyz * 2.0 - 1.0;
+  vec3 tangent = normalize(random - normal * dot(random, normal));
+  vec3 bitangent = cross(normal, tangent);
+  mat3 tbn = mat3(tangent, bitangent, normal);
+
+  float occlusion = 0.0;
+  for (int i = 0; i < KERNEL_SIZE; i++) {
+    vec3 sample_pos = position + tbn * kernel[i] * RADIUS;
+    vec4 clip_pos = clip_from_view * vec4(sample_pos, 1.0);
+    vec2 uv = clip_pos.xy / clip_pos.w * 0.5 + 0.5;
+    float sample_distance = length(sample_pos);
+    float scene_distance = length(view_position(uv));
+    // Occluders far in front of the sample are probably not nearby at all
+    float range_check =
+        smoothstep(0.0, 1.0, RADIUS / abs(length(position) - scene_distance));
+    if (scene_distance < sample_distance - BIAS) {
+      occlusion += range_check;
+    }
+  }
+  FRAG_COLOR = vec4(vec3(1.0 - occlusion / float(KERNEL_SIZE)), 1.0);
+}
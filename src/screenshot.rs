@@ -0,0 +1,153 @@
+//! Saving screenshots as PNG files.
+
+/// Saves the pixels as a PNG file in the pictures directory, and returns
+/// the path it was saved to. The pixels are RGBA, starting from the bottom
+/// row, like they are returned by glReadPixels.
+#[cfg(not(target_family = "wasm"))]
+pub fn save(width: u32, height: u32, pixels: &[u8]) -> anyhow::Result<std::path::PathBuf> {
+    use anyhow::{bail, Context};
+    use std::fs;
+
+    if width == 0 || height == 0 {
+        // E.g. a minimized window, there's nothing to save
+        bail!("the screenshot is empty ({width}x{height})");
+    }
+    let dir = pictures_dir().context("could not find a directory for the screenshot")?;
+    fs::create_dir_all(&dir).with_context(|| format!("could not create {dir:?}"))?;
+    let path = dir.join(format!("sailing-venus-{}.png", timestamp()));
+    let png = encode_png(width, height, pixels);
+    fs::write(&path, png).with_context(|| format!("could not write {path:?}"))?;
+    Ok(path)
+}
+
+/// Makes the browser download the current contents of the canvas as a PNG.
+/// Needs to be called before the frame is over, since the canvas is cleared
+/// after it's shown.
+#[cfg(target_family = "wasm")]
+pub fn download_canvas() {
+    crate::emscripten_h::run_javascript(&format!(
+        "{{ const link = document.createElement('a');\
+        link.href = Module.canvas.toDataURL('image/png');\
+        link.download = 'sailing-venus-{}.png';\
+        link.click(); }}",
+        timestamp(),
+    ));
+}
+
+fn timestamp() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn pictures_dir() -> Option<std::path::PathBuf> {
+    use std::{env, path::PathBuf};
+    let home = if cfg!(target_os = "windows") {
+        env::var_os("USERPROFILE")?
+    } else {
+        env::var_os("HOME")?
+    };
+    Some(PathBuf::from(home).join("Pictures"))
+}
+
+/// Encodes the pixels (RGBA, bottom row first) as an uncompressed PNG.
+/// Screenshots are rare enough that the size doesn't matter, and this
+/// avoids pulling in an image library just for this.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(width as usize * height as usize * 4, pixels.len());
+    let mut png = b"\x89PNG\r\n\x1A\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, and the only compression, filter and
+    // interlace methods there are
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // Each row starts with its filter type, 0 for none. The rows are flipped
+    // here, since PNGs start from the top.
+    let row_length = width as usize * 4;
+    let mut image_data = Vec::with_capacity((row_length + 1) * height as usize);
+    for row in pixels.chunks_exact(row_length).rev() {
+        image_data.push(0);
+        image_data.extend_from_slice(row);
+    }
+
+    // A zlib stream made of uncompressed deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = image_data.chunks(0xFFFF).peekable();
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        zlib.push(is_final as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&image_data).to_be_bytes());
+    write_chunk(&mut png, b"IDAT", &zlib);
+
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[crc_start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_checksums() {
+        assert_eq!(0xCBF43926, crc32(b"123456789"));
+        assert_eq!(0x11E60398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn png_structure() {
+        // Bottom row red, top row blue
+        let pixels = [[255, 0, 0, 255], [0, 0, 255, 255]].concat();
+        let png = encode_png(1, 2, &pixels);
+        assert_eq!(b"\x89PNG\r\n\x1A\n", &png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        assert_eq!(b"IEND", &png[png.len() - 8..png.len() - 4]);
+        // The top row is stored first, after the zlib header and the block
+        // header, and its filter type byte
+        let idat = 8 + 12 + 13;
+        assert_eq!(b"IDAT", &png[idat + 4..idat + 8]);
+        let first_row = idat + 8 + 2 + 5 + 1;
+        assert_eq!(&[0, 0, 255, 255], &png[first_row..first_row + 4]);
+    }
+}
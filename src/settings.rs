@@ -0,0 +1,469 @@
+//! The player's preferences, like the rebindable keyboard controls, stored
+//! in a small TOML file.
+
+use anyhow::{bail, Context};
+use sdl2::keyboard::Keycode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    RotateView,
+    SpeedUp,
+    TabNavigation,
+    TabSchedule,
+    TabDeliveries,
+    TabSettings,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 6] = [
+        KeyAction::RotateView,
+        KeyAction::SpeedUp,
+        KeyAction::TabNavigation,
+        KeyAction::TabSchedule,
+        KeyAction::TabDeliveries,
+        KeyAction::TabSettings,
+    ];
+
+    /// The name shown in the settings. The tab actions are named after
+    /// their tabs.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyAction::RotateView => "Rotate view",
+            KeyAction::SpeedUp => "Speed up",
+            KeyAction::TabNavigation => "Navigation",
+            KeyAction::TabSchedule => "Schedule",
+            KeyAction::TabDeliveries => "Deliveries",
+            KeyAction::TabSettings => "Settings",
+        }
+    }
+
+    /// The key used in the settings file.
+    fn config_key(self) -> &'static str {
+        match self {
+            KeyAction::RotateView => "rotate_view",
+            KeyAction::SpeedUp => "speed_up",
+            KeyAction::TabNavigation => "tab_navigation",
+            KeyAction::TabSchedule => "tab_schedule",
+            KeyAction::TabDeliveries => "tab_deliveries",
+            KeyAction::TabSettings => "tab_settings",
+        }
+    }
+}
+
+/// The settings adjusted in steps with the arrow buttons in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameOption {
+    CameraRotateSensitivity,
+    CameraMoveSensitivity,
+    ZoomSensitivity,
+    MaxCameraDistance,
+    UiScale,
+    Vsync,
+}
+
+impl GameOption {
+    pub const ALL: [GameOption; 6] = [
+        GameOption::CameraRotateSensitivity,
+        GameOption::CameraMoveSensitivity,
+        GameOption::ZoomSensitivity,
+        GameOption::MaxCameraDistance,
+        GameOption::UiScale,
+        GameOption::Vsync,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameOption::CameraRotateSensitivity => "Rotate speed",
+            GameOption::CameraMoveSensitivity => "Pan speed",
+            GameOption::ZoomSensitivity => "Zoom speed",
+            GameOption::MaxCameraDistance => "Max zoom out",
+            GameOption::UiScale => "UI scale",
+            GameOption::Vsync => "Vsync",
+        }
+    }
+}
+
+/// The keys for each [KeyAction]. The defaults are the keys the game has
+/// always used, Space for speeding up and 1-4 for the tabs, except for
+/// rotating the view: that used to be only possible by dragging with the
+/// right mouse button, and R is a new binding for turning it a step at a
+/// time.
+pub struct KeyBindings {
+    pub rotate_view: Keycode,
+    pub speed_up: Keycode,
+    pub tab_navigation: Keycode,
+    pub tab_schedule: Keycode,
+    pub tab_deliveries: Keycode,
+    pub tab_settings: Keycode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            rotate_view: Keycode::R,
+            speed_up: Keycode::Space,
+            tab_navigation: Keycode::Num1,
+            tab_schedule: Keycode::Num2,
+            tab_deliveries: Keycode::Num3,
+            tab_settings: Keycode::Num4,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: KeyAction) -> Keycode {
+        match action {
+            KeyAction::RotateView => self.rotate_view,
+            KeyAction::SpeedUp => self.speed_up,
+            KeyAction::TabNavigation => self.tab_navigation,
+            KeyAction::TabSchedule => self.tab_schedule,
+            KeyAction::TabDeliveries => self.tab_deliveries,
+            KeyAction::TabSettings => self.tab_settings,
+        }
+    }
+
+    fn get_mut(&mut self, action: KeyAction) -> &mut Keycode {
+        match action {
+            KeyAction::RotateView => &mut self.rotate_view,
+            KeyAction::SpeedUp => &mut self.speed_up,
+            KeyAction::TabNavigation => &mut self.tab_navigation,
+            KeyAction::TabSchedule => &mut self.tab_schedule,
+            KeyAction::TabDeliveries => &mut self.tab_deliveries,
+            KeyAction::TabSettings => &mut self.tab_settings,
+        }
+    }
+
+    /// Binds the key to the action. If the key was already bound to another
+    /// action, that action gets this action's old key, so that every action
+    /// still has a key of its own.
+    pub fn set(&mut self, action: KeyAction, keycode: Keycode) {
+        let old_keycode = self.get(action);
+        if let Some(other) = self.action_for(keycode) {
+            *self.get_mut(other) = old_keycode;
+        }
+        *self.get_mut(action) = keycode;
+    }
+
+    /// Returns the action bound to the key, if any.
+    pub fn action_for(&self, keycode: Keycode) -> Option<KeyAction> {
+        KeyAction::ALL
+            .into_iter()
+            .find(|&action| self.get(action) == keycode)
+    }
+}
+
+/// The bloom strengths the settings button cycles through.
+const BLOOM_STRENGTHS: [f32; 3] = [0.0, 0.5, 1.0];
+/// The depth of field apertures the settings button cycles through.
+const DOF_APERTURES: [f32; 3] = [0.0, 1.0, 2.0];
+/// The camera sensitivity multipliers the arrow buttons step through.
+const SENSITIVITIES: [f32; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0];
+/// The furthest camera distances the arrow buttons step through.
+const MAX_CAMERA_DISTANCES: [f32; 4] = [50.0, 100.0, 150.0, 200.0];
+/// The interface scales the arrow buttons step through.
+const UI_SCALES: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+
+pub struct Settings {
+    pub key_bindings: KeyBindings,
+    /// Whether the game should start in fullscreen. Kept up to date when
+    /// fullscreen is toggled.
+    pub fullscreen: bool,
+    /// How much of the blurred bright parts of the scene are added on top of
+    /// it. Zero skips the bloom passes entirely.
+    pub bloom_strength: f32,
+    /// How quickly things blur when they're away from the camera's focus
+    /// point. Zero skips the depth of field passes entirely.
+    pub dof_aperture: f32,
+    /// Multiplies how fast the camera turns when dragged with the right mouse
+    /// button, or with the right stick.
+    pub camera_rotate_sensitivity: f32,
+    /// Multiplies how fast the camera moves when dragged with the left mouse
+    /// button, or with the left stick.
+    pub camera_move_sensitivity: f32,
+    /// Multiplies how much the camera zooms per mouse wheel notch.
+    pub zoom_sensitivity: f32,
+    /// How far from the ship the camera can zoom out, see
+    /// [crate::renderer::Renderer::apply_camera_settings].
+    pub max_camera_distance: f32,
+    /// Multiplies the size of the interface, on top of the scaling based on
+    /// the window's width.
+    pub ui_scale: f32,
+    pub vsync: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            key_bindings: KeyBindings::default(),
+            fullscreen: false,
+            bloom_strength: 0.5,
+            dof_aperture: 1.0,
+            camera_rotate_sensitivity: 1.0,
+            camera_move_sensitivity: 1.0,
+            zoom_sensitivity: 1.0,
+            max_camera_distance: 100.0,
+            ui_scale: 1.0,
+            vsync: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Switches to the next bloom strength, going back to off after the
+    /// strongest one.
+    pub fn cycle_bloom_strength(&mut self) {
+        self.bloom_strength = next_value(&BLOOM_STRENGTHS, self.bloom_strength);
+    }
+
+    /// Switches to the next depth of field aperture, going back to off after
+    /// the strongest one.
+    pub fn cycle_dof_aperture(&mut self) {
+        self.dof_aperture = next_value(&DOF_APERTURES, self.dof_aperture);
+    }
+
+    /// Returns the option's current value, as shown in the settings.
+    pub fn option_text(&self, option: GameOption) -> String {
+        match option {
+            GameOption::CameraRotateSensitivity => format!("{}x", self.camera_rotate_sensitivity),
+            GameOption::CameraMoveSensitivity => format!("{}x", self.camera_move_sensitivity),
+            GameOption::ZoomSensitivity => format!("{}x", self.zoom_sensitivity),
+            GameOption::MaxCameraDistance => format!("{}", self.max_camera_distance),
+            GameOption::UiScale => format!("{}x", self.ui_scale),
+            GameOption::Vsync if self.vsync => "on".to_string(),
+            GameOption::Vsync => "off".to_string(),
+        }
+    }
+
+    /// Steps the option to the next value, or to the previous one if `step`
+    /// is negative. Stops at the smallest and largest values.
+    pub fn adjust(&mut self, option: GameOption, step: i32) {
+        let (values, value) = match option {
+            GameOption::CameraRotateSensitivity => {
+                (&SENSITIVITIES[..], &mut self.camera_rotate_sensitivity)
+            }
+            GameOption::CameraMoveSensitivity => {
+                (&SENSITIVITIES[..], &mut self.camera_move_sensitivity)
+            }
+            GameOption::ZoomSensitivity => (&SENSITIVITIES[..], &mut self.zoom_sensitivity),
+            GameOption::MaxCameraDistance => {
+                (&MAX_CAMERA_DISTANCES[..], &mut self.max_camera_distance)
+            }
+            GameOption::UiScale => (&UI_SCALES[..], &mut self.ui_scale),
+            GameOption::Vsync => {
+                self.vsync = !self.vsync;
+                return;
+            }
+        };
+        // The closest one, since the settings file can have any value
+        let current = (0..values.len())
+            .min_by(|&a, &b| {
+                (values[a] - *value)
+                    .abs()
+                    .total_cmp(&(values[b] - *value).abs())
+            })
+            .unwrap();
+        let next = (current as i32 + step).clamp(0, values.len() as i32 - 1);
+        *value = values[next as usize];
+    }
+
+    /// Loads the settings from the settings file. Settings missing from the
+    /// file, or with invalid values, keep their default values.
+    pub fn load() -> anyhow::Result<Settings> {
+        let mut settings = Settings::default();
+        let Some(config) = storage::read()? else {
+            return Ok(settings);
+        };
+        let table = config
+            .parse::<toml::Table>()
+            .context("the settings file is not valid TOML")?;
+        for action in KeyAction::ALL {
+            if let Some(keycode) = skip_invalid(get_keycode(&table, action.config_key())) {
+                *settings.key_bindings.get_mut(action) = keycode;
+            }
+        }
+        if let Some(fullscreen) = skip_invalid(get_bool(&table, "fullscreen")) {
+            settings.fullscreen = fullscreen;
+        }
+        if let Some(bloom_strength) = skip_invalid(get_number(&table, "bloom_strength")) {
+            settings.bloom_strength = bloom_strength.max(0.0);
+        }
+        if let Some(dof_aperture) = skip_invalid(get_number(&table, "dof_aperture")) {
+            settings.dof_aperture = dof_aperture.max(0.0);
+        }
+        for (key, value) in [
+            (
+                "camera_rotate_sensitivity",
+                &mut settings.camera_rotate_sensitivity,
+            ),
+            (
+                "camera_move_sensitivity",
+                &mut settings.camera_move_sensitivity,
+            ),
+            ("zoom_sensitivity", &mut settings.zoom_sensitivity),
+            ("max_camera_distance", &mut settings.max_camera_distance),
+            ("ui_scale", &mut settings.ui_scale),
+        ] {
+            if let Some(number) = skip_invalid(get_positive_number(&table, key)) {
+                *value = number;
+            }
+        }
+        if let Some(vsync) = skip_invalid(get_bool(&table, "vsync")) {
+            settings.vsync = vsync;
+        }
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut table = toml::Table::new();
+        let fullscreen = toml::Value::Boolean(self.fullscreen);
+        table.insert("fullscreen".to_string(), fullscreen);
+        let bloom_strength = toml::Value::Float(self.bloom_strength as f64);
+        table.insert("bloom_strength".to_string(), bloom_strength);
+        let dof_aperture = toml::Value::Float(self.dof_aperture as f64);
+        table.insert("dof_aperture".to_string(), dof_aperture);
+        for (key, value) in [
+            ("camera_rotate_sensitivity", self.camera_rotate_sensitivity),
+            ("camera_move_sensitivity", self.camera_move_sensitivity),
+            ("zoom_sensitivity", self.zoom_sensitivity),
+            ("max_camera_distance", self.max_camera_distance),
+            ("ui_scale", self.ui_scale),
+        ] {
+            table.insert(key.to_string(), toml::Value::Float(value as f64));
+        }
+        table.insert("vsync".to_string(), toml::Value::Boolean(self.vsync));
+        for action in KeyAction::ALL {
+            let name = toml::Value::String(self.key_bindings.get(action).name());
+            table.insert(action.config_key().to_string(), name);
+        }
+        storage::write(&table.to_string())
+    }
+}
+
+/// Returns the first value that's larger than the current one, or the first
+/// value if there's none.
+fn next_value(values: &[f32], current: f32) -> f32 {
+    let next = values.iter().position(|&value| value > current);
+    values[next.unwrap_or(0)]
+}
+
+/// Prints out the error of a setting that couldn't be loaded, so that the
+/// setting can keep its default value.
+fn skip_invalid<T>(setting: anyhow::Result<Option<T>>) -> Option<T> {
+    setting.unwrap_or_else(|err| {
+        eprintln!("ignoring an invalid setting: {err:?}");
+        None
+    })
+}
+
+fn get_keycode(table: &toml::Table, key: &str) -> anyhow::Result<Option<Keycode>> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let name = value
+        .as_str()
+        .with_context(|| format!("{key} should be a key name"))?;
+    let keycode =
+        Keycode::from_name(name).with_context(|| format!("{name:?} is not a key name"))?;
+    Ok(Some(keycode))
+}
+
+fn get_bool(table: &toml::Table, key: &str) -> anyhow::Result<Option<bool>> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let value = value
+        .as_bool()
+        .with_context(|| format!("{key} should be true or false"))?;
+    Ok(Some(value))
+}
+
+/// Like [get_number], but the number must be finite and larger than zero.
+fn get_positive_number(table: &toml::Table, key: &str) -> anyhow::Result<Option<f32>> {
+    let number = get_number(table, key)?;
+    if number.is_some_and(|number| !number.is_finite() || number <= 0.0) {
+        bail!("{key} should be larger than zero");
+    }
+    Ok(number)
+}
+
+/// Reads a number from the settings, accepting integers too, since TOML
+/// doesn't consider `1` a float.
+fn get_number(table: &toml::Table, key: &str) -> anyhow::Result<Option<f32>> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let number = value
+        .as_float()
+        .or_else(|| value.as_integer().map(|number| number as f64))
+        .with_context(|| format!("{key} should be a number"))?;
+    Ok(Some(number as f32))
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod storage {
+    use anyhow::Context;
+    use std::{env, fs, io, path::PathBuf};
+
+    /// The platform's directory for configuration files.
+    fn config_dir() -> Option<PathBuf> {
+        if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            let home = PathBuf::from(env::var_os("HOME")?);
+            Some(home.join("Library/Application Support"))
+        } else if let Some(config_home) = env::var_os("XDG_CONFIG_HOME") {
+            Some(PathBuf::from(config_home))
+        } else {
+            let home = PathBuf::from(env::var_os("HOME")?);
+            Some(home.join(".config"))
+        }
+    }
+
+    fn config_path() -> anyhow::Result<PathBuf> {
+        let config_dir = config_dir().context("could not find a directory for the settings")?;
+        Ok(config_dir.join("sailing-venus").join("settings.toml"))
+    }
+
+    pub fn write(config: &str) -> anyhow::Result<()> {
+        let path = config_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("could not create {dir:?}"))?;
+        }
+        fs::write(&path, config).with_context(|| format!("could not write {path:?}"))
+    }
+
+    pub fn read() -> anyhow::Result<Option<String>> {
+        let path = config_path()?;
+        match fs::read_to_string(&path) {
+            Ok(config) => Ok(Some(config)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("could not read {path:?}")),
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod storage {
+    use crate::emscripten_h;
+
+    const STORAGE_KEY: &str = "sailing-venus-settings";
+
+    pub fn write(config: &str) -> anyhow::Result<()> {
+        // The TOML goes into a JS string literal
+        let escaped = config
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace('\n', "\\n");
+        emscripten_h::run_javascript(&format!(
+            "localStorage.setItem('{STORAGE_KEY}', '{escaped}')"
+        ));
+        Ok(())
+    }
+
+    pub fn read() -> anyhow::Result<Option<String>> {
+        let config = emscripten_h::run_javascript_string(&format!(
+            "localStorage.getItem('{STORAGE_KEY}') || ''"
+        ));
+        Ok((!config.is_empty()).then_some(config))
+    }
+}
@@ -0,0 +1,117 @@
+//! Building and tearing down rooms, from the Build tab.
+
+use super::{PathfindingMap, Room, RoomType, ShipGame, ShipModels, SLEEPING_COORDS};
+use crate::math::Aabb2;
+use glam::{IVec2, Vec2};
+use std::collections::HashSet;
+
+impl ShipGame {
+    /// Builds a room at `position` in ship space, snapped to the grid. Returns
+    /// the reason if it can't be built there.
+    pub fn build_room(
+        &mut self,
+        models: &ShipModels,
+        room_type: RoomType,
+        position: Vec2,
+    ) -> Option<String> {
+        let position = position.round();
+        // Built on a copy first, to see how it would connect to the others
+        let mut pf_map = self.pf_map.clone();
+        let room = Room::new(models, room_type, position, &mut pf_map);
+        let bounds = room.room_bounds.offset(position);
+        let overlaps = |other: &Room| {
+            let other_bounds = other.room_bounds.offset(other.position);
+            (bounds.intersection(&other_bounds)).is_some_and(|overlap| overlap.area() > 0.0)
+        };
+        if self.rooms.iter().any(overlaps) {
+            return Some("There's another room in the way".to_string());
+        }
+        let connected = pf_map.iter().any(|(node, neighbors)| {
+            !self.pf_map.contains_key(node) && neighbors.iter().any(|n| self.pf_map.contains_key(n))
+        });
+        if !connected {
+            return Some("Rooms need to be built next to other rooms".to_string());
+        }
+        ShipGame::add_room(
+            &mut self.rooms,
+            &mut self.pf_map,
+            models,
+            room_type,
+            position,
+        );
+        None
+    }
+
+    /// Tears down the room (by index). Returns the reason if it can't be torn
+    /// down.
+    pub fn demolish_room(&mut self, index: usize) -> Option<String> {
+        let room = &self.rooms[index];
+        let room_type = room.room_type;
+        let bounds = room.room_bounds.offset(room.position);
+        let required = matches!(room_type, RoomType::Navigation | RoomType::Sails);
+        let same_type_rooms = self.rooms.iter().filter(|r| r.room_type == room_type);
+        if required && same_type_rooms.count() <= 1 {
+            let name = room_type.name().to_lowercase();
+            return Some(format!("The ship needs at least one {name} room"));
+        } else if bounds.contains(SLEEPING_COORDS) {
+            return Some("The crew sleeps in this room".to_string());
+        } else if (self.characters.iter()).any(|c| bounds.contains(c.position)) {
+            return Some("Someone is in the room".to_string());
+        } else if !stays_connected(&self.pf_map, bounds) {
+            return Some("The other rooms would be cut off".to_string());
+        }
+        ShipGame::remove_room(&mut self.rooms, &mut self.pf_map, index);
+        self.selected_room = None;
+        None
+    }
+}
+
+/// Returns true if every node that can be walked to from the sleeping
+/// quarters still can, after the nodes inside `removed` are gone.
+fn stays_connected(pf_map: &PathfindingMap, removed: Aabb2) -> bool {
+    let is_removed = |node: &IVec2| removed.contains(node.as_vec2() + Vec2::ONE * 0.5);
+    let reachable = |skip_removed: bool| {
+        let start = SLEEPING_COORDS.floor().as_ivec2();
+        let mut visited = HashSet::from([start]);
+        let mut queue = vec![start];
+        while let Some(node) = queue.pop() {
+            for neighbor in pf_map.get(&node).into_iter().flatten() {
+                if !(skip_removed && is_removed(neighbor)) && visited.insert(*neighbor) {
+                    queue.push(*neighbor);
+                }
+            }
+        }
+        visited
+    };
+    let before = reachable(false).iter().filter(|n| !is_removed(n)).count();
+    before == reachable(true).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A corridor from the sleeping quarters towards +x, `length` nodes long.
+    fn corridor(length: i32) -> PathfindingMap {
+        let start = SLEEPING_COORDS.floor().as_ivec2();
+        let node = |x: i32| start + IVec2::new(x, 0);
+        (0..length)
+            .map(|x| {
+                let neighbors = [x - 1, x + 1]
+                    .into_iter()
+                    .filter(|n| (0..length).contains(n));
+                (node(x), neighbors.map(node).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn removing_the_middle_disconnects() {
+        let map = corridor(10);
+        let start = SLEEPING_COORDS.floor();
+        let middle = Aabb2::new(start + Vec2::new(4.0, 0.0), start + Vec2::new(6.0, 1.0));
+        assert!(!stays_connected(&map, middle));
+        let end = Aabb2::new(start + Vec2::new(7.0, 0.0), start + Vec2::new(10.0, 1.0));
+        assert!(stays_connected(&map, end));
+    }
+}
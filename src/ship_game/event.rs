@@ -0,0 +1,89 @@
+//! Random encounters during the voyage, which the player responds to.
+
+/// How long the player has to respond to an event, in seconds, before the
+/// last option is picked for them.
+const RESPONSE_TIME: f32 = 20.0;
+/// How much of the ship's integrity an unrepaired malfunction takes.
+pub const MALFUNCTION_DAMAGE: f32 = 0.25;
+/// How many hours of an engineer's time repairing a malfunction takes.
+const MALFUNCTION_REPAIR_HOURS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventEffect {
+    /// Nothing happens.
+    None,
+    /// One of the undelivered packages on board is lost.
+    LoseCargo,
+    /// Everyone's morale goes up.
+    GainMorale,
+    /// The ship's speed is increased by this much, in meters per second.
+    SpeedBonus(f32),
+    /// The ship's integrity is reduced by this much.
+    DamageShip(f32),
+    /// An engineer works for this many hours, starting from the current one,
+    /// whatever their schedule says. Without an engineer on board, the ship
+    /// takes [MALFUNCTION_DAMAGE] instead.
+    EngineerWork(usize),
+}
+
+pub struct GameEvent {
+    pub description: String,
+    /// The responses the player can pick from, and what happens after each.
+    pub options: Vec<(String, EventEffect)>,
+    /// How long is left to respond, in seconds.
+    pub timer: f32,
+}
+
+impl GameEvent {
+    /// Picks a random event (or none) based on the seed. The same seed always
+    /// results in the same event.
+    pub fn roll(seed: u64) -> Option<GameEvent> {
+        let random = splitmix64(seed);
+        let (description, options) = match random % 100 {
+            0..=19 => {
+                // The vessel is friendly about half the time
+                let investigate = if (random >> 32) & 1 == 0 {
+                    EventEffect::GainMorale
+                } else {
+                    EventEffect::LoseCargo
+                };
+                (
+                    "Strange vessel spotted",
+                    vec![("Investigate", investigate), ("Ignore", EventEffect::None)],
+                )
+            }
+            20..=34 => (
+                "Favorable current",
+                vec![("Ride it", EventEffect::SpeedBonus(5.0))],
+            ),
+            35..=49 => (
+                "Equipment malfunction",
+                vec![
+                    (
+                        "Repair it",
+                        EventEffect::EngineerWork(MALFUNCTION_REPAIR_HOURS),
+                    ),
+                    ("Keep going", EventEffect::DamageShip(MALFUNCTION_DAMAGE)),
+                ],
+            ),
+            _ => return None,
+        };
+        Some(GameEvent {
+            description: description.to_string(),
+            options: options
+                .into_iter()
+                .map(|(text, effect)| (text.to_string(), effect))
+                .collect(),
+            timer: RESPONSE_TIME,
+        })
+    }
+}
+
+/// A simple hash for turning seeds into random-looking numbers, from
+/// <https://prng.di.unimi.it/splitmix64.c>.
+pub(crate) fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
@@ -1,17 +1,105 @@
 //! The part of the game that happens inside the ship.
 
+use crate::math::{Circle2, Ray2};
+use crate::renderer::gltf::AnimationStateMachine;
 use crate::renderer::Renderer;
-use glam::{IVec2, Vec2};
-use std::collections::{HashMap, VecDeque};
+use glam::{IVec2, Vec2, Vec3Swizzles};
+use std::collections::{HashMap, HashSet, VecDeque};
 
+mod build;
+mod event;
+mod models;
 mod pathfinding;
+mod replay;
 mod room;
+mod save_game;
+mod trade;
+mod world_gen;
 
+pub use event::*;
+pub use models::ShipModels;
+pub use pathfinding::find_path;
+pub use replay::*;
 pub use room::*;
+pub use save_game::{AUTOSAVE_SLOT, SAVE_SLOTS};
+pub use trade::*;
 
 pub type PathfindingMap = HashMap<IVec2, Vec<IVec2>>;
+/// How many slots there are in a day's schedule, one per hour.
+pub const SCHEDULE_SLOTS: usize = 24;
 const SLEEPING_COORDS: Vec2 = Vec2::new(-2.5, -9.5);
-const MAX_SHIP_SPEED: f32 = 10.0;
+pub const MAX_SHIP_SPEED: f32 = 10.0;
+/// How long the ship stays docked after arriving at a location, in seconds.
+const DOCKING_DURATION: f32 = 2.0;
+/// How much fuel is used to sail one unit of distance.
+const FUEL_PER_UNIT_DISTANCE: f32 = 0.01;
+/// The fastest the ship can sail without fuel, with just the sails, so that it
+/// can still make it to a fuel depot.
+const OUT_OF_FUEL_SPEED: f32 = 1.0;
+/// How strongly characters sidestep each other, relative to their movement
+/// direction.
+const SEPARATION_STRENGTH: f32 = 0.5;
+/// Below this morale, characters move and work at half speed.
+const LOW_MORALE: f32 = 0.2;
+/// How much morale is lost per second of scheduled work.
+const MORALE_WORK_DRAIN: f32 = 0.001;
+/// How much morale is gained per second of scheduled sleep.
+const MORALE_SLEEP_GAIN: f32 = 0.002;
+/// How much morale everyone else gains per second of a cook working in the
+/// kitchen.
+const MORALE_COOKING_GAIN: f32 = 0.0005;
+/// How much energy is lost per second of working in a working area.
+const ENERGY_WORK_DRAIN: f32 = 0.002;
+/// How much energy is gained per second of sleep.
+const ENERGY_SLEEP_GAIN: f32 = 0.003;
+/// Below this energy, characters stop to rest wherever they are.
+const EXHAUSTED_ENERGY: f32 = 0.1;
+/// Sleeping uninterrupted for this long, in seconds (two hours of the
+/// schedule), restores all energy.
+const FULL_REST_DURATION: f32 = 5.0;
+/// How much ship integrity is lost per second.
+const INTEGRITY_DECAY: f32 = 0.0001;
+/// How much ship integrity each engineer working in the engine room repairs
+/// per second.
+const INTEGRITY_REPAIR: f32 = 0.0005;
+/// Below this integrity, the sails only accelerate at half strength.
+const DAMAGED_INTEGRITY: f32 = 0.5;
+/// Below this integrity, the ship doesn't move at all.
+const BROKEN_INTEGRITY: f32 = 0.1;
+/// How much hull integrity is lost per second in a storm.
+const HULL_STORM_DAMAGE: f32 = 0.001;
+/// Storms stronger than this damage the hull.
+const HULL_DAMAGING_STORM: f32 = 0.5;
+/// How much hull integrity each engineer working in the engine room repairs
+/// per second.
+const HULL_REPAIR: f32 = 0.0002;
+/// Below this hull integrity, the ship is visibly damaged.
+pub const DAMAGED_HULL: f32 = 0.5;
+/// Below this hull integrity, the ship only sails at a quarter of its speed.
+const CRITICAL_HULL: f32 = 0.2;
+/// The most characters there can be on the ship, as many as fit in the
+/// schedule.
+pub const MAX_CREW_SIZE: usize = 4;
+/// How many credits a new game starts with.
+const STARTING_CREDITS: f32 = 100.0;
+/// How fast characters get better at their job while working, per second.
+const SKILL_GAIN: f32 = 0.00001;
+/// How close to a character a click needs to be to select them.
+const CHARACTER_CLICK_RADIUS: f32 = 0.7;
+/// How many recruits are available at each port.
+const RECRUITS_PER_PORT: usize = 3;
+/// The names characters can have. Each name is only used once per session.
+const NAMES: &[&str] = &[
+    "Ada", "Bruno", "Cass", "Dmitri", "Elif", "Farah", "Gus", "Hilde", "Ines", "Jun", "Kofi",
+    "Lumi", "Mateo", "Noor", "Otto", "Priya", "Quinn", "Rosa", "Sami", "Tove", "Umar", "Vesna",
+    "Wren", "Yara", "Zeno",
+];
+/// How fast the wind turns, in radians per in-game day.
+const WIND_TURN_SPEED: f32 = 0.5;
+/// Above this storm intensity, the weather is considered stormy.
+const STORM_THRESHOLD: f32 = 0.7;
+/// How many entries the ship's log keeps, the oldest ones are dropped.
+const MAX_LOG_ENTRIES: usize = 200;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Task {
@@ -19,115 +107,736 @@ pub enum Task {
     Work,
 }
 
+/// What a [ShipGame::log] entry is about, for color-coding the log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogCategory {
+    Travel,
+    Delivery,
+    Event,
+    Crew,
+    Danger,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 5] = [
+        LogCategory::Travel,
+        LogCategory::Delivery,
+        LogCategory::Event,
+        LogCategory::Crew,
+        LogCategory::Danger,
+    ];
+}
+
 pub struct ShipGame {
+    /// The seed the locations and deliveries were generated from, see
+    /// [world_gen::generate_world].
+    pub seed: u64,
     /// The time in the in-game world, counted in days. One day is a minute in real-time.
     pub world_time: f32,
     pub rooms: Vec<Room>,
     pub characters: Vec<Character>,
     pub selected_character: Option<usize>,
+    /// The room clicked in the 3D view, see [ShipGame::room_at_screen_click].
+    pub selected_room: Option<usize>,
     /// Coordinate -> neighbor coordinates
     pub pf_map: PathfindingMap,
-    pub locations: Vec<(&'static str, Vec2)>,
+    pub locations: Vec<Location>,
     pub current_location: Vec2,
     pub current_target: Vec2,
+    /// The locations (indices into [ShipGame::locations]) to sail to next,
+    /// in order, after reaching the current target.
+    pub target_queue: VecDeque<usize>,
+    /// When set, the ship sets off to the next location in
+    /// [ShipGame::target_queue] as soon as it arrives somewhere. Otherwise it
+    /// waits there for the player.
+    pub autopilot: bool,
     pub current_heading: Vec2,
     pub current_ship_speed: f32,
-    pub deliveries: Vec<(&'static str, Vec2, bool)>,
+    pub deliveries: Vec<Delivery>,
+    /// The goods on board, as (name, quantity, average buy price). Deliveries
+    /// are in here too while they're on board, see [trade::item_weight].
+    pub cargo_hold: Vec<(String, u32, f32)>,
+    pub credits: f32,
+    /// The condition of the ship, from 0.0 to 1.0. Slowly wears down, and is
+    /// repaired by engineers working in the engine room.
+    pub ship_integrity: f32,
+    /// The condition of the hull, from 0.0 (sunk) to 1.0. Damaged by storms,
+    /// and repaired by engineers working in the engine room.
+    pub hull_integrity: f32,
+    /// In meters per second, like the ship's speed.
+    pub wind_speed: f32,
+    /// The direction the wind blows towards, normalized.
+    pub wind_direction: Vec2,
+    /// How stormy the weather is, from 0.0 to 1.0.
+    pub storm_intensity: f32,
+    /// An event waiting for the player's response.
+    pub pending_event: Option<GameEvent>,
+    /// The last day an event was rolled for, see [GameEvent::roll].
+    last_event_day: u64,
+    /// The characters that can be hired at the current port.
+    pub available_recruits: Vec<CharacterTemplate>,
+    /// The names from [NAMES] that haven't been given to anyone yet.
+    name_pool: Vec<&'static str>,
+    pub fuel: f32,
+    pub max_fuel: f32,
+    /// Set when the ship arrives at a location, and unset after
+    /// [ShipGame::dock_timer] runs out. The ship doesn't move while docked.
+    pub docked: bool,
+    /// How long the ship still stays docked, in seconds.
+    pub dock_timer: f32,
+    /// The ship's log, as (world time, category, message), oldest first.
+    pub log: Vec<(f32, LogCategory, String)>,
+    /// The player's decisions so far, for playing the voyage back, see
+    /// [replay::play_replay].
+    pub replay: Vec<ReplayEvent>,
+    /// Set when this game is playing back a replay. Nothing is recorded or
+    /// autosaved then.
+    pub replaying: bool,
+    /// Messages for the player from [ShipGame::update], as (text, how many
+    /// seconds to show it). The interface takes them from here.
+    pub notifications: Vec<(String, f32)>,
+}
+
+pub struct Delivery {
+    pub name: &'static str,
+    /// The name of the [Location] the delivery is picked up from.
+    pub origin_name: String,
+    /// The name of the [Location] the delivery goes to.
+    pub destination_name: String,
+    /// How many days the delivery has to be delivered in, if there's a rush.
+    pub deadline_days: Option<f32>,
+    /// Set when the delivery is delivered, or when it fails.
+    pub completed: bool,
+    /// Set when the deadline passed before the delivery was delivered.
+    pub failed: bool,
+    /// The in-game time the delivery was taken on, in days.
+    pub start_time: f32,
+    /// Set when the ship has docked at the origin and loaded the delivery.
+    pub picked_up: bool,
+}
+
+impl Delivery {
+    fn new(
+        name: &'static str,
+        origin_name: String,
+        destination_name: String,
+        deadline_days: Option<f32>,
+    ) -> Delivery {
+        Delivery {
+            name,
+            origin_name,
+            destination_name,
+            deadline_days,
+            completed: false,
+            failed: false,
+            start_time: 0.0,
+            picked_up: false,
+        }
+    }
+
+    /// The name of the location the ship needs to go to next for this
+    /// delivery: the origin until it's picked up, and then the destination.
+    pub fn next_stop_name(&self) -> &str {
+        if self.picked_up {
+            &self.destination_name
+        } else {
+            &self.origin_name
+        }
+    }
+
+    /// How many days are left until the deadline, if there is one.
+    pub fn days_left(&self, world_time: f32) -> Option<f32> {
+        let deadline = self.start_time + self.deadline_days?;
+        Some(deadline - world_time)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Faction {
+    PostUnion,
+    Terraformers,
+    Independent,
+}
+
+impl Faction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Faction::PostUnion => "Post Union",
+            Faction::Terraformers => "Terraformers",
+            Faction::Independent => "Independent",
+        }
+    }
+}
+
+pub struct Location {
+    pub name: String,
+    pub position: Vec2,
+    /// If true, the ship's fuel is topped up when it arrives here.
+    pub is_fuel_depot: bool,
+    pub faction: Faction,
+    /// How dangerous the location is to sail to, from 0 to 3.
+    pub danger_level: u8,
+    /// The goods sold here, as (name, quantity, price).
+    pub port_inventory: Vec<(String, u32, f32)>,
+}
+
+/// A character that could join the crew.
+#[derive(Debug, Clone)]
+pub struct CharacterTemplate {
+    pub name: String,
+    pub job: Job,
 }
 
 pub struct Character {
+    pub name: String,
     pub position: Vec2,
     pub move_target_queue: VecDeque<Vec2>,
     pub move_speed: f32,
     pub look_dir: Vec2,
     pub current_room: usize,
-    pub schedule: [Task; 12],
+    pub schedule: [Task; SCHEDULE_SLOTS],
     pub job: Job,
+    /// Other characters closer than this push this character aside while it's
+    /// moving.
+    pub separation_radius: f32,
+    pub animation: AnimationStateMachine,
+    /// How content the character is, from 0.0 to 1.0. Goes down while
+    /// working, and up while sleeping.
+    pub morale: f32,
+    /// How much stamina the character has left, from 0.0 to 1.0. Goes down
+    /// while working, and up while sleeping.
+    pub energy: f32,
+    /// Set when energy runs out, after which the character rests in place
+    /// until fully recovered.
+    pub exhausted: bool,
+    /// How long the character has been sleeping (or resting) uninterrupted.
+    time_slept: f32,
+    /// How good the character is at their job, from 0.0 to 1.0. Grows while
+    /// working in their job's room.
+    pub skill: f32,
+    /// Drives the bobbing while the character is working, advances only
+    /// while they're in a room's working area.
+    pub working_animation_offset: f32,
 }
 
 impl Character {
+    fn new(models: &ShipModels, job: Job, name: String) -> Character {
+        Character {
+            name,
+            position: SLEEPING_COORDS,
+            move_target_queue: VecDeque::new(),
+            move_speed: 5.0,
+            look_dir: Vec2::new(1.0, 0.0),
+            current_room: 0,
+            schedule: [Task::Sleep; SCHEDULE_SLOTS],
+            job,
+            separation_radius: 0.8,
+            morale: 0.5,
+            energy: 1.0,
+            exhausted: false,
+            time_slept: 0.0,
+            skill: 0.0,
+            working_animation_offset: 0.0,
+            animation: models.character_animation(job),
+        }
+    }
+
+    pub fn has_low_morale(&self) -> bool {
+        self.morale < LOW_MORALE
+    }
+
+    /// How much of a full worker this character counts as, based on morale.
+    pub fn work_efficiency(&self) -> f32 {
+        if self.has_low_morale() {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    pub fn effective_move_speed(&self) -> f32 {
+        self.move_speed * self.work_efficiency()
+    }
+
     fn pathfind_to(&mut self, map: &PathfindingMap, to: Vec2) {
         if let Some(path) = pathfinding::find_path(map, self.position, to) {
-            self.move_target_queue.extend(path);
+            self.move_target_queue
+                .extend(pathfinding::smooth_path(map, path));
         } else {
             debug_assert!(false, "{:?} can't find path to {:?}", &self.job, to);
         }
     }
+
+    /// Returns true if the queued path goes through nodes that are not on the
+    /// map anymore.
+    fn is_path_blocked(&self, map: &PathfindingMap) -> bool {
+        let queue = &self.move_target_queue;
+        let mut segments = queue.iter().zip(queue.iter().skip(1));
+        segments.any(|(&from, &to)| !pathfinding::is_walkable(map, from, to))
+            || queue
+                .back()
+                .is_some_and(|to| !map.contains_key(&to.floor().as_ivec2()))
+    }
+
+    /// Returns a sideways nudge (perpendicular to `move_dir`) away from the
+    /// nearby characters in `positions`. `index` is this character's index in
+    /// `positions`.
+    fn separation_deflection(&self, index: usize, positions: &[Vec2], move_dir: Vec2) -> Vec2 {
+        let mut repulsion = Vec2::ZERO;
+        let personal_space = Circle2::new(self.position, self.separation_radius);
+        for (i, &other) in positions.iter().enumerate() {
+            if i == index {
+                continue;
+            }
+            if personal_space.contains(other) {
+                let away = self.position - other;
+                let distance = away.length();
+                let away_dir = away.normalize_or_zero();
+                repulsion += away_dir * (1.0 - distance / self.separation_radius);
+            }
+        }
+        let side = move_dir.perp();
+        side * repulsion.dot(side) * SEPARATION_STRENGTH
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum Job {
     Navigator,
     Sailor,
+    Cook,
+    Engineer,
     #[doc(hidden)]
     Count,
 }
 
+impl Job {
+    pub const ALL: [Job; Job::Count as usize] =
+        [Job::Navigator, Job::Sailor, Job::Cook, Job::Engineer];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Job::Navigator => "Navigator",
+            Job::Sailor => "Sailor",
+            Job::Cook => "Cook",
+            Job::Engineer => "Engineer",
+            Job::Count => unreachable!(),
+        }
+    }
+
+    /// The room where characters with this job work.
+    pub fn job_room(self) -> RoomType {
+        match self {
+            Job::Navigator => RoomType::Navigation,
+            Job::Sailor => RoomType::Sails,
+            Job::Cook => RoomType::Kitchen,
+            Job::Engineer => RoomType::Engine,
+            Job::Count => unreachable!(),
+        }
+    }
+}
+
 impl ShipGame {
-    pub fn new(renderer: &Renderer) -> ShipGame {
-        let mut pathfinding_neighbors = HashMap::new();
-        let rooms = vec![
-            Room::new(
-                &renderer,
-                RoomType::Navigation,
-                Vec2::new(0.0, -4.0),
-                &mut pathfinding_neighbors,
-            ),
-            Room::new(
-                &renderer,
-                RoomType::Sails,
-                Vec2::new(0.0, 5.0),
-                &mut pathfinding_neighbors,
-            ),
-        ];
+    pub fn new(models: &ShipModels, seed: u64) -> ShipGame {
+        let mut rooms = Vec::new();
+        let mut pf_map = HashMap::new();
+        let mut add_room = |room_type: RoomType, position: Vec2| {
+            ShipGame::add_room(&mut rooms, &mut pf_map, models, room_type, position)
+        };
+        add_room(RoomType::Navigation, Vec2::new(0.0, -4.0));
+        add_room(RoomType::Sails, Vec2::new(0.0, 5.0));
+        add_room(RoomType::Kitchen, Vec2::new(0.0, -13.0));
+        add_room(RoomType::Engine, Vec2::new(0.0, -22.0));
+        let mut name_pool = NAMES.to_vec();
+        let characters = [Job::Navigator, Job::Sailor, Job::Cook, Job::Engineer]
+            .into_iter()
+            .map(|job| Character::new(models, job, name_pool.remove(0).to_string()))
+            .collect();
+        let (locations, deliveries) = world_gen::generate_world(seed);
+        let start = locations[0].position;
+        let cargo_hold = deliveries
+            .iter()
+            .filter(|delivery| delivery.picked_up)
+            .map(|delivery| (delivery.name.to_string(), 1, 0.0))
+            .collect();
         ShipGame {
+            seed,
             world_time: 0.0,
             rooms,
-            pf_map: pathfinding_neighbors,
-            characters: vec![
-                Character {
-                    position: SLEEPING_COORDS,
-                    move_target_queue: VecDeque::new(),
-                    move_speed: 5.0,
-                    look_dir: Vec2::new(1.0, 0.0),
-                    current_room: 0,
-                    schedule: [Task::Sleep; 12],
-                    job: Job::Navigator,
-                },
-                Character {
-                    position: SLEEPING_COORDS,
-                    move_target_queue: VecDeque::new(),
-                    move_speed: 5.0,
-                    look_dir: Vec2::new(1.0, 0.0),
-                    current_room: 0,
-                    schedule: [Task::Sleep; 12],
-                    job: Job::Sailor,
-                },
-            ],
+            pf_map,
+            characters,
             selected_character: Some(0),
-            locations: vec![
-                ("Morning Star Post Union", Vec2::new(0.0, 0.0)),
-                ("Terraforming Station Alpha-Beta", Vec2::new(100.0, 50.0)),
-                ("Firefly Space Station", Vec2::new(75.0, 75.0)),
-                ("A Mineral Miner's Shack", Vec2::new(-25.0, -50.0)),
-            ],
-            current_location: Vec2::ZERO,
-            current_target: Vec2::ZERO,
+            selected_room: None,
+            locations,
+            current_location: start,
+            current_target: start,
+            target_queue: VecDeque::new(),
+            autopilot: false,
             current_heading: Vec2::new(1.0, 0.0),
             current_ship_speed: 0.0,
-            deliveries: vec![
-                ("Mineral refiner", Vec2::new(-25.0, -50.0), false),
-                ("Rocket fuel", Vec2::new(75.0, 75.0), false),
-            ],
+            deliveries,
+            cargo_hold,
+            credits: STARTING_CREDITS,
+            ship_integrity: 1.0,
+            hull_integrity: 1.0,
+            wind_speed: 2.0,
+            wind_direction: Vec2::new(1.0, 0.0),
+            storm_intensity: 0.0,
+            pending_event: None,
+            last_event_day: 0,
+            available_recruits: Vec::new(),
+            name_pool,
+            fuel: 2.5,
+            max_fuel: 2.5,
+            docked: false,
+            dock_timer: 0.0,
+            log: Vec::new(),
+            replay: Vec::new(),
+            replaying: false,
+            notifications: Vec::new(),
+        }
+    }
+
+    /// The places the characters walk to: the working areas of the rooms,
+    /// and where they sleep.
+    pub fn pathfinding_destinations(&self) -> Vec<Vec2> {
+        let mut destinations = vec![SLEEPING_COORDS];
+        destinations.extend(self.rooms.iter().map(Room::working_area_center));
+        destinations
+    }
+
+    /// Writes an entry in the ship's log, timestamped with the current
+    /// in-game time.
+    pub fn log_event(&mut self, category: LogCategory, text: impl Into<String>) {
+        if self.log.len() >= MAX_LOG_ENTRIES {
+            self.log.remove(0);
+        }
+        self.log.push((self.world_time, category, text.into()));
+    }
+
+    /// How strongly the sails can accelerate the ship, based on its integrity.
+    pub fn integrity_multiplier(&self) -> f32 {
+        if self.ship_integrity < BROKEN_INTEGRITY {
+            0.0
+        } else if self.ship_integrity < DAMAGED_INTEGRITY {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    /// Moves the recruit (by index) into the crew, if there's room for them.
+    pub fn hire(&mut self, models: &ShipModels, recruit: usize) {
+        if self.characters.len() >= MAX_CREW_SIZE || recruit >= self.available_recruits.len() {
+            return;
+        }
+        let template = self.available_recruits.remove(recruit);
+        let character = Character::new(models, template.job, template.name);
+        let text = format!("{} joined the crew", character.name);
+        self.characters.push(character);
+        self.log_event(LogCategory::Crew, text);
+    }
+
+    /// Removes the character (by index) from the crew, unless they're the
+    /// last one. Returns true if the character was removed.
+    pub fn dismiss(&mut self, character: usize) -> bool {
+        if self.characters.len() <= 1 || character >= self.characters.len() {
+            return false;
+        }
+        self.characters.remove(character);
+        self.selected_character = match self.selected_character {
+            Some(i) if i == character => None,
+            Some(i) if i > character => Some(i - 1),
+            selected => selected,
+        };
+        for room in &mut self.rooms {
+            room.currently_working_characters.clear();
+        }
+        true
+    }
+
+    /// Adds the location (by index) to the end of the route. If the ship is
+    /// already at its target, it sets off to the location right away.
+    pub fn add_waypoint(&mut self, location: usize) {
+        if self.current_location == self.current_target && self.target_queue.is_empty() {
+            self.set_target(self.locations[location].position);
+        } else {
+            self.target_queue.push_back(location);
+        }
+    }
+
+    /// Turns the autopilot on or off. If it's turned on while the ship is
+    /// waiting at its target, it continues along the route right away.
+    pub fn toggle_autopilot(&mut self) {
+        self.autopilot = !self.autopilot;
+        if self.autopilot && self.current_location == self.current_target {
+            self.sail_to_next_waypoint();
+        }
+    }
+
+    fn sail_to_next_waypoint(&mut self) {
+        if let Some(next) = self.target_queue.pop_front() {
+            self.set_target(self.locations[next].position);
+        }
+    }
+
+    /// Sets off towards the position, recording it for the replay.
+    pub fn set_target(&mut self, target: Vec2) {
+        self.current_target = target;
+        self.record(ReplayEventKind::SetTarget(target));
+    }
+
+    /// Changes the character's task for the hour, recording it for the
+    /// replay.
+    pub fn set_schedule(&mut self, character: usize, time: usize, task: Task) {
+        self.characters[character].schedule[time] = task;
+        self.record(ReplayEventKind::SetSchedule {
+            character,
+            time,
+            task,
+        });
+    }
+
+    /// Refuels the ship and loads deliveries, if the ship is at the right
+    /// kind of location for them. Deliveries are unloaded by selling them,
+    /// see [ShipGame::sell].
+    fn dock(&mut self) {
+        let current_location = self.current_location;
+        let is_here = |location: &Location| location.position.distance(current_location) < 1.0;
+        let depot = self
+            .locations
+            .iter()
+            .find(|location| location.is_fuel_depot && is_here(location));
+        if let Some(depot) = depot {
+            if self.fuel < self.max_fuel {
+                self.fuel = self.max_fuel;
+                let text = format!("Refueled at {}", depot.name);
+                self.notifications.push((text.clone(), 3.0));
+                self.log_event(LogCategory::Travel, text);
+            }
+        }
+        let mut loaded = Vec::new();
+        for delivery in &mut self.deliveries {
+            let origin = self
+                .locations
+                .iter()
+                .find(|location| location.name == delivery.origin_name);
+            if !origin.is_some_and(is_here) || delivery.picked_up {
+                continue;
+            }
+            delivery.picked_up = true;
+            self.cargo_hold.push((delivery.name.to_string(), 1, 0.0));
+            let name = delivery.name.to_lowercase();
+            self.notifications.push((format!("Loaded: {name}"), 4.0));
+            loaded.push(format!("Loaded {name} at {}", delivery.origin_name));
+        }
+        for text in loaded {
+            self.log_event(LogCategory::Delivery, text);
+        }
+    }
+
+    pub fn apply_event_effect(&mut self, effect: EventEffect) {
+        match effect {
+            EventEffect::None => {}
+            EventEffect::LoseCargo => {
+                let lost = self
+                    .deliveries
+                    .iter()
+                    .rposition(|delivery| delivery.picked_up && !delivery.completed);
+                if let Some(i) = lost {
+                    let delivery = self.deliveries.remove(i);
+                    self.unload_delivery(delivery.name);
+                }
+            }
+            EventEffect::GainMorale => {
+                for character in &mut self.characters {
+                    character.morale = (character.morale + 0.2).min(1.0);
+                }
+            }
+            EventEffect::SpeedBonus(bonus) => {
+                let max_speed = self.max_ship_speed();
+                self.current_ship_speed = (self.current_ship_speed + bonus).min(max_speed);
+            }
+            EventEffect::DamageShip(damage) => {
+                self.ship_integrity = (self.ship_integrity - damage).max(0.0);
+            }
+            EventEffect::EngineerWork(hours) => {
+                // The most rested engineer takes it on
+                let engineer = (self.characters.iter().enumerate())
+                    .filter(|(_, character)| matches!(character.job, Job::Engineer))
+                    .max_by(|(_, a), (_, b)| a.energy.total_cmp(&b.energy))
+                    .map(|(i, _)| i);
+                let Some(engineer) = engineer else {
+                    self.apply_event_effect(EventEffect::DamageShip(MALFUNCTION_DAMAGE));
+                    return;
+                };
+                let current_hour = self.current_hour();
+                for hour in current_hour..current_hour + hours {
+                    self.set_schedule(engineer, hour % SCHEDULE_SLOTS, Task::Work);
+                }
+                let name = &self.characters[engineer].name;
+                self.notifications
+                    .push((format!("{name} is repairing the equipment"), 4.0));
+            }
+        }
+    }
+
+    /// Returns the index of the room under the cursor, at `clip` in clip
+    /// space. The cursor is projected onto the floor, and if that's not inside
+    /// a room, a ray is cast from there back towards the camera, to find the
+    /// walls that might be in front of the floor.
+    pub fn room_at_screen_click(
+        &self,
+        clip: Vec2,
+        renderer: &Renderer,
+        aspect: f32,
+    ) -> Option<usize> {
+        let floor_point = renderer.clip_to_ship_space(clip, aspect);
+        let to_camera = renderer.camera_eye_direction();
+        // The cursor can only be on top of walls this close to the floor point
+        let max_distance = ROOM_HEIGHT * to_camera.xz().length() / to_camera.y.max(0.001);
+        let ray = Ray2 {
+            origin: floor_point,
+            direction: to_camera.xz().normalize_or_zero(),
+        };
+        self.rooms
+            .iter()
+            .enumerate()
+            .filter_map(|(i, room)| {
+                let t = ray.intersect_aabb(&room.room_bounds.offset(room.position))?;
+                (t <= max_distance).then_some((i, t))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the index of the character closest to `position` in ship
+    /// space, if there's one close enough to count as clicked.
+    pub fn character_at(&self, position: Vec2) -> Option<usize> {
+        self.characters
+            .iter()
+            .enumerate()
+            .map(|(i, character)| (i, character.position.distance(position)))
+            .filter(|&(_, distance)| distance < CHARACTER_CLICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    /// The schedule slot for the current time of day.
+    pub fn current_hour(&self) -> usize {
+        (self.world_time * 24.0).floor() as usize % SCHEDULE_SLOTS
+    }
+
+    /// Returns true if the character (by index) is in the working area of a
+    /// room, and not too exhausted to work.
+    pub fn is_working(&self, character: usize) -> bool {
+        !self.characters[character].exhausted
+            && self
+                .rooms
+                .iter()
+                .any(|room| room.currently_working_characters.contains(&character))
+    }
+
+    /// The game is over once the hull breaks completely.
+    pub fn is_sunk(&self) -> bool {
+        self.hull_integrity <= 0.0
+    }
+
+    pub fn is_stormy(&self) -> bool {
+        self.storm_intensity > STORM_THRESHOLD
+    }
+
+    /// Creates a new room and connects its pathfinding nodes to the nodes of
+    /// the existing rooms.
+    pub fn add_room(
+        rooms: &mut Vec<Room>,
+        pf_map: &mut PathfindingMap,
+        models: &ShipModels,
+        room_type: RoomType,
+        position: Vec2,
+    ) {
+        rooms.push(Room::new(models, room_type, position, pf_map));
+        // Room::new connects the new nodes to the existing ones, but not the
+        // other way around, so add the missing edges.
+        let edges = pf_map
+            .iter()
+            .flat_map(|(&node, neighbors)| neighbors.iter().map(move |&n| (n, node)))
+            .collect::<Vec<_>>();
+        for (node, neighbor) in edges {
+            if let Some(neighbors) = pf_map.get_mut(&node) {
+                if !neighbors.contains(&neighbor) {
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+    }
+
+    /// Removes the room and all the pathfinding nodes inside it. Characters
+    /// walking towards the removed nodes stop in [ShipGame::update].
+    pub fn remove_room(rooms: &mut Vec<Room>, pf_map: &mut PathfindingMap, index: usize) {
+        let room = rooms.remove(index);
+        let bounds = room.room_bounds.offset(room.position);
+        pf_map.retain(|node, _| !bounds.contains(node.as_vec2() + Vec2::ONE * 0.5));
+        let remaining_nodes = pf_map.keys().copied().collect::<HashSet<_>>();
+        for neighbors in pf_map.values_mut() {
+            neighbors.retain(|neighbor| remaining_nodes.contains(neighbor));
         }
     }
 
     pub fn update(&mut self, dt: f32) {
         let dt = dt.min(1.0 / 30.0);
+        let was_docked = self.docked;
         self.world_time += dt / 60.0;
-        let current_hour = (self.world_time * 12.0).floor() as usize % 12;
-        for character in &mut self.characters {
-            if character.move_target_queue.is_empty() {
+        let current_hour = self.current_hour();
+        self.wind_speed = (self.world_time * 0.3).sin().abs() * MAX_SHIP_SPEED * 0.5 + 2.0;
+        self.wind_direction = Vec2::from_angle(self.world_time * WIND_TURN_SPEED);
+        let was_stormy = self.is_stormy();
+        self.storm_intensity = (self.world_time * 0.7).sin().max(0.0).powi(2);
+        if self.is_stormy() != was_stormy {
+            self.record(ReplayEventKind::WeatherChange(self.storm_intensity));
+        }
+
+        let day = self.world_time.floor() as u64;
+        if day != self.last_event_day {
+            self.last_event_day = day;
+            if self.pending_event.is_none() {
+                self.pending_event = GameEvent::roll(day);
+                if let Some(event) = &self.pending_event {
+                    let text = event.description.clone();
+                    self.log_event(LogCategory::Event, text);
+                }
+            }
+        }
+        if let Some(event) = &mut self.pending_event {
+            event.timer -= dt;
+            if event.timer <= 0.0 {
+                // No response, go with the last option
+                let effect = event.options.last().map(|(_, effect)| *effect);
+                self.pending_event = None;
+                if let Some(effect) = effect {
+                    self.apply_event_effect(effect);
+                }
+            }
+        }
+        let positions = self
+            .characters
+            .iter()
+            .map(|character| character.position)
+            .collect::<Vec<_>>();
+        for (i, character) in self.characters.iter_mut().enumerate() {
+            if character.is_path_blocked(&self.pf_map) {
+                // The path goes through a removed room, find a new one
+                character.move_target_queue.clear();
+            }
+            character.morale = match character.schedule[current_hour] {
+                Task::Work => character.morale - MORALE_WORK_DRAIN * dt,
+                Task::Sleep => character.morale + MORALE_SLEEP_GAIN * dt,
+            }
+            .clamp(0.0, 1.0);
+            if character.exhausted {
+                // Resting in place until recovered
+                character.move_target_queue.clear();
+            } else if character.move_target_queue.is_empty() {
                 // Not doing anything, queue something to do
                 match character.schedule[current_hour] {
                     Task::Sleep => {
@@ -136,20 +845,15 @@ impl ShipGame {
                         }
                     }
                     Task::Work => {
-                        let room = self.rooms.iter().find(|room| match character.job {
-                            Job::Sailor => room.room_type == RoomType::Sails,
-                            Job::Navigator => room.room_type == RoomType::Navigation,
-                            _ => false,
-                        });
+                        let job_room = character.job.job_room();
+                        let room = self.rooms.iter().find(|room| room.room_type == job_room);
                         if let Some(room) = room {
                             if !room
                                 .working_area_bounds
                                 .offset(room.position)
                                 .contains(character.position)
                             {
-                                let target = room.position
-                                    + (room.working_area_bounds.min + room.working_area_bounds.max)
-                                        / 2.0;
+                                let target = room.working_area_center();
                                 character.pathfind_to(&self.pf_map, target);
                             }
                         } else {
@@ -162,12 +866,23 @@ impl ShipGame {
                 let delta = next_move - character.position;
                 let delta_length = delta.length();
                 let delta_dir = delta.normalize_or_zero();
-                let step_length = character.move_speed * dt;
+                let step_length = character.effective_move_speed() * dt;
                 if step_length >= delta_length {
                     character.move_target_queue.pop_front();
                     character.position = next_move;
                 } else {
-                    character.position += delta_dir * step_length;
+                    let deflection = character.separation_deflection(i, &positions, delta_dir);
+                    let deflected_dir = (delta_dir + deflection).normalize_or_zero();
+                    let deflected_position = character.position + deflected_dir * step_length;
+                    // Only sidestep if there's room for it, e.g. not in narrow corridors
+                    if self
+                        .pf_map
+                        .contains_key(&deflected_position.floor().as_ivec2())
+                    {
+                        character.position = deflected_position;
+                    } else {
+                        character.position += delta_dir * step_length;
+                    }
                 }
                 if delta_dir.length() > 0.0 {
                     character.look_dir = character.look_dir.lerp(delta_dir, 20.0 * dt);
@@ -175,6 +890,14 @@ impl ShipGame {
             }
         }
 
+        // Logged and recorded at the end, since the rooms and characters are
+        // borrowed while these happen
+        let mut log_entries = Vec::new();
+        let mut next_target = None;
+        let mut cooking_characters = Vec::new();
+        let mut repairing_engineers = 0;
+        let integrity_multiplier = self.integrity_multiplier();
+        let max_speed = self.max_ship_speed();
         for room in &mut self.rooms {
             room.currently_working_characters.clear();
             let bounds = room.room_bounds.offset(room.position);
@@ -182,40 +905,248 @@ impl ShipGame {
             for (i, c) in self.characters.iter_mut().enumerate() {
                 if working_bounds.contains(c.position) {
                     room.currently_working_characters.push(i);
+                    c.working_animation_offset += dt * 2.0;
+                    if c.job.job_room() == room.room_type {
+                        c.skill = (c.skill + SKILL_GAIN * dt).min(1.0);
+                    }
                 }
                 if bounds.contains(c.position) {
                     c.current_room = i;
                 }
             }
+            if room.room_type == RoomType::Kitchen {
+                let cooks = room
+                    .currently_working_characters
+                    .iter()
+                    .filter(|&&i| matches!(self.characters[i].job, Job::Cook));
+                cooking_characters.extend(cooks);
+            }
+            if room.room_type == RoomType::Engine {
+                repairing_engineers += room
+                    .currently_working_characters
+                    .iter()
+                    .filter(|&&i| matches!(self.characters[i].job, Job::Engineer))
+                    .count();
+            }
             let ship_loc_delta = self.current_target - self.current_location;
             if room.room_type == RoomType::Navigation {
+                // The most skilled navigator at work turns the ship faster
+                let navigator_skill = room
+                    .currently_working_characters
+                    .iter()
+                    .map(|&i| &self.characters[i])
+                    .filter(|character| matches!(character.job, Job::Navigator))
+                    .map(|character| character.skill)
+                    .fold(0.0, f32::max);
+                let turn_speed = 10.0 + navigator_skill * 30.0;
                 let direction = ship_loc_delta.normalize_or_zero();
                 if direction.length_squared() > 0.0 {
-                    self.current_heading = self.current_heading.lerp(direction, 10.0 * dt);
+                    self.current_heading = self.current_heading.lerp(direction, turn_speed * dt);
                 }
             }
             if room.room_type == RoomType::Sails {
+                let workers = room
+                    .currently_working_characters
+                    .iter()
+                    .map(|&i| &self.characters[i])
+                    .map(|character| {
+                        // Skilled sailors count for up to two workers
+                        let skill = if matches!(character.job, Job::Sailor) {
+                            character.skill
+                        } else {
+                            0.0
+                        };
+                        character.work_efficiency() * (1.0 + skill)
+                    })
+                    .sum::<f32>();
+                // Sailing with the wind is faster, and against it slower
+                let tailwind = self.current_heading.dot(self.wind_direction);
+                let wind_multiplier =
+                    (1.0 + self.wind_speed * tailwind / MAX_SHIP_SPEED).clamp(0.5, 2.0);
                 let acceleration =
-                    room.currently_working_characters.len() as f32 / 20.0 * MAX_SHIP_SPEED;
-                if acceleration > 0.0 {
+                    workers / 20.0 * MAX_SHIP_SPEED * integrity_multiplier * wind_multiplier;
+                if self.docked || self.ship_integrity < BROKEN_INTEGRITY {
+                    self.current_ship_speed = 0.0;
+                } else if acceleration > 0.0 {
                     self.current_ship_speed =
-                        (self.current_ship_speed + acceleration * dt).min(MAX_SHIP_SPEED);
+                        (self.current_ship_speed + acceleration * dt).min(max_speed);
                 } else {
                     self.current_ship_speed =
                         (self.current_ship_speed - MAX_SHIP_SPEED / 10.0 * dt).max(0.0);
                 }
                 let step = self.current_ship_speed * self.current_heading * dt;
-                if step.length_squared() >= ship_loc_delta.length_squared() {
+                if ship_loc_delta == Vec2::ZERO {
+                    // Already there
+                } else if step.length_squared() >= ship_loc_delta.length_squared() {
                     self.current_location = self.current_target;
+                    self.current_ship_speed = 0.0;
+                    self.docked = true;
+                    self.dock_timer = DOCKING_DURATION;
+                    let arrived_at = self
+                        .locations
+                        .iter()
+                        .find(|location| location.position == self.current_location);
+                    if let Some(Location { name, .. }) = arrived_at {
+                        self.notifications
+                            .push((format!("Arrived at {name}!"), 5.0));
+                        log_entries.push((LogCategory::Travel, format!("Arrived at {name}")));
+                        let seed = self.world_time.to_bits() as u64;
+                        let name_pool = &mut self.name_pool;
+                        self.available_recruits = (0..RECRUITS_PER_PORT as u64)
+                            .map(|i| {
+                                let random = splitmix64(seed + i) as usize;
+                                let job = Job::ALL[random % Job::ALL.len()];
+                                let name = if name_pool.is_empty() {
+                                    // Everyone's been named, ran out of names
+                                    job.name().to_string()
+                                } else {
+                                    name_pool.remove(random % name_pool.len()).to_string()
+                                };
+                                CharacterTemplate { name, job }
+                            })
+                            .collect();
+                    }
+                    if self.autopilot {
+                        if let Some(next) = self.target_queue.pop_front() {
+                            next_target = Some(self.locations[next].position);
+                        }
+                    }
                 } else {
                     self.current_location += step;
                 }
-                for delivery in &mut self.deliveries {
-                    if delivery.1 == self.current_location {
-                        delivery.2 = true;
+                if ship_loc_delta != Vec2::ZERO && self.fuel > 0.0 {
+                    let used_fuel = step.length() * FUEL_PER_UNIT_DISTANCE;
+                    self.fuel = (self.fuel - used_fuel).max(0.0);
+                    if self.fuel <= 0.0 {
+                        self.current_ship_speed = self.current_ship_speed.min(OUT_OF_FUEL_SPEED);
+                        self.notifications
+                            .push(("Out of fuel! Sailing at a crawl".to_string(), 4.0));
+                        log_entries.push((LogCategory::Danger, "Ran out of fuel".to_string()));
                     }
                 }
             }
         }
+
+        // Before docking, so that arriving is logged before loading
+        for (category, text) in log_entries.drain(..) {
+            self.log_event(category, text);
+        }
+        if let Some(target) = next_target {
+            self.set_target(target);
+        }
+
+        if self.docked {
+            self.dock();
+            if !was_docked && !self.replaying {
+                // Autosave after unloading the deliveries
+                if let Err(err) = self.save(AUTOSAVE_SLOT) {
+                    eprintln!("autosave failed: {err:?}");
+                    self.notifications
+                        .push(("Couldn't save the game".to_string(), 3.0));
+                }
+            }
+            self.dock_timer -= dt;
+            if self.dock_timer <= 0.0 {
+                self.docked = false;
+            }
+        }
+
+        for delivery in &mut self.deliveries {
+            let days_left = delivery.days_left(self.world_time);
+            if !delivery.completed && days_left.is_some_and(|days| days < 0.0) {
+                delivery.completed = true;
+                delivery.failed = true;
+                // Not wanted anymore, so it's thrown overboard
+                self.cargo_hold.retain(|(name, ..)| name != delivery.name);
+                let name = delivery.name.to_lowercase();
+                self.notifications
+                    .push((format!("Delivery failed: {name}"), 4.0));
+                let text = format!("Failed to deliver {name} in time");
+                log_entries.push((LogCategory::Danger, text));
+            }
+        }
+
+        let previous_integrity = self.ship_integrity;
+        self.ship_integrity +=
+            (repairing_engineers as f32 * INTEGRITY_REPAIR - INTEGRITY_DECAY) * dt;
+        self.ship_integrity = self.ship_integrity.clamp(0.0, 1.0);
+        if previous_integrity >= DAMAGED_INTEGRITY && self.ship_integrity < DAMAGED_INTEGRITY {
+            self.notifications
+                .push(("The ship is falling apart!".to_string(), 4.0));
+            self.log_event(LogCategory::Danger, "The ship started falling apart");
+        } else if previous_integrity <= DAMAGED_INTEGRITY && self.ship_integrity > DAMAGED_INTEGRITY
+        {
+            self.notifications
+                .push(("The ship has been repaired".to_string(), 3.0));
+        }
+
+        let previous_hull = self.hull_integrity;
+        if self.storm_intensity > HULL_DAMAGING_STORM {
+            self.hull_integrity -= HULL_STORM_DAMAGE * dt;
+        }
+        self.hull_integrity += repairing_engineers as f32 * HULL_REPAIR * dt;
+        self.hull_integrity = self.hull_integrity.clamp(0.0, 1.0);
+        if previous_hull >= CRITICAL_HULL && self.hull_integrity < CRITICAL_HULL {
+            self.notifications
+                .push(("SHIP CRITICALLY DAMAGED".to_string(), 5.0));
+            self.log_event(LogCategory::Danger, "The hull was critically damaged");
+        }
+        if previous_hull > 0.0 && self.is_sunk() {
+            self.log_event(LogCategory::Danger, "The ship sank");
+        }
+
+        for cook in cooking_characters {
+            for (i, character) in self.characters.iter_mut().enumerate() {
+                if i != cook {
+                    character.morale = (character.morale + MORALE_COOKING_GAIN * dt).min(1.0);
+                }
+            }
+        }
+
+        for (i, character) in self.characters.iter_mut().enumerate() {
+            let velocity = if dt > 0.0 {
+                character.position.distance(positions[i]) / dt
+            } else {
+                0.0
+            };
+            let is_working = self
+                .rooms
+                .iter()
+                .any(|room| room.currently_working_characters.contains(&i));
+            let task = character.schedule[current_hour];
+
+            if task == Task::Work && is_working && !character.exhausted {
+                character.energy = (character.energy - ENERGY_WORK_DRAIN * dt).max(0.0);
+            }
+            if task == Task::Sleep || character.exhausted {
+                character.time_slept += dt;
+                character.energy = (character.energy + ENERGY_SLEEP_GAIN * dt).min(1.0);
+                if character.time_slept >= FULL_REST_DURATION {
+                    character.energy = 1.0;
+                }
+            } else {
+                character.time_slept = 0.0;
+            }
+            if !character.exhausted && character.energy < EXHAUSTED_ENERGY {
+                character.exhausted = true;
+                character.time_slept = 0.0;
+                character.move_target_queue.clear();
+                self.notifications
+                    .push((format!("{} is exhausted!", character.name), 4.0));
+                let text = format!("{} collapsed from exhaustion", character.name);
+                log_entries.push((LogCategory::Crew, text));
+            } else if character.exhausted && character.energy >= 1.0 {
+                character.exhausted = false;
+            }
+
+            let is_sleeping = (task == Task::Sleep && character.position == SLEEPING_COORDS)
+                || character.exhausted;
+            character
+                .animation
+                .update(dt, velocity, is_working, is_sleeping);
+        }
+        for (category, text) in log_entries {
+            self.log_event(category, text);
+        }
     }
 }
@@ -0,0 +1,48 @@
+//! The parts of the models that the simulation depends on: where characters
+//! can walk in each room, and which animations the characters have. Read
+//! straight from the glTF files, so the simulation doesn't need a GL context,
+//! see [crate::benchmark].
+
+use super::{Job, RoomType};
+use crate::renderer::{self, gltf, gltf::AnimationStateMachine};
+use glam::{IVec2, Vec4, Vec4Swizzles};
+
+pub struct ShipModels {
+    /// The pathfinding nodes of each room type, in the order of
+    /// [RoomType::ALL], relative to the room's position.
+    room_nodes: [Vec<IVec2>; 4],
+    /// The animation state machine each job's characters start with.
+    character_animations: [AnimationStateMachine; Job::Count as usize],
+}
+
+impl ShipModels {
+    pub fn load() -> ShipModels {
+        ShipModels {
+            room_nodes: renderer::ROOM_MODELS
+                .map(|glb| get_pathfinding_nodes(&gltf::load_glb_outline(glb))),
+            character_animations: renderer::CHARACTER_MODELS.map(|glb| {
+                let outline = gltf::load_glb_outline(glb);
+                AnimationStateMachine::new(outline.animation_names.iter().map(String::as_str))
+            }),
+        }
+    }
+
+    pub fn room_nodes(&self, room_type: RoomType) -> &[IVec2] {
+        &self.room_nodes[room_type as usize]
+    }
+
+    pub fn character_animation(&self, job: Job) -> AnimationStateMachine {
+        self.character_animations[job as usize].clone()
+    }
+}
+
+fn get_pathfinding_nodes(outline: &gltf::GltfOutline) -> Vec<IVec2> {
+    let mut nodes = Vec::new();
+    for (name, transform) in &outline.nodes {
+        if name.starts_with("Empty") {
+            let pos = (*transform * Vec4::new(0.0, 0.0, 0.0, 1.0)).xz();
+            nodes.push(pos.floor().as_ivec2());
+        }
+    }
+    nodes
+}
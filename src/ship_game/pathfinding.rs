@@ -1,3 +1,4 @@
+use super::PathfindingMap;
 use glam::{IVec2, Vec2};
 use std::{
     cmp::Ordering,
@@ -30,48 +31,51 @@ pub fn find_path(map: &HashMap<IVec2, Vec<IVec2>>, from: Vec2, to: Vec2) -> Opti
         from = closest;
         path.push(from);
     }
+    let heuristic = |pos: IVec2| (to.as_vec2() - pos.as_vec2()).length();
+    // Coordinate -> (cost of the cheapest known path from the start, previous coordinate)
     let mut prev = HashMap::with_capacity(map.keys().len());
     prev.insert(from, (0.0, from));
     let mut queue = BinaryHeap::new();
     queue.push(DistSortedCoord {
         pos: from,
-        from,
-        to,
+        g: 0.0,
+        h: heuristic(from),
     });
     let mut processed: HashSet<IVec2> = HashSet::new();
-    processed.insert(from);
 
     while let Some(current) = queue.pop() {
-        let curr_dist = prev[&current.pos].0;
+        if !processed.insert(current.pos) {
+            // Already processed via a shorter path, this is a stale entry
+            continue;
+        }
+        let curr_dist = current.g;
         if current.pos == to {
             let mut path = vec![current.pos.as_vec2() + Vec2::ONE * 0.5];
-            loop {
-                let (prev_dist, prev_pos) = prev[&path[path.len() - 1].floor().as_ivec2()];
-                if prev_dist == 0.0 {
-                    break;
-                }
-                path.push(prev_pos.as_vec2() + Vec2::ONE * 0.5);
+            let mut pos = current.pos;
+            while pos != from {
+                pos = prev[&pos].1;
+                path.push(pos.as_vec2() + Vec2::ONE * 0.5);
             }
             path.reverse();
             return Some(path);
         }
         for &neighbor in &map[&current.pos] {
+            if processed.contains(&neighbor) {
+                continue;
+            }
             let dist_to_neighbor =
                 curr_dist + (neighbor.as_vec2() - current.pos.as_vec2()).length();
-            if !processed.contains(&neighbor) {
-                processed.insert(neighbor);
+            let shorter = match prev.get(&neighbor) {
+                Some(&(prev_dist, _)) => dist_to_neighbor < prev_dist,
+                None => true,
+            };
+            if shorter {
                 prev.insert(neighbor, (dist_to_neighbor, current.pos));
                 queue.push(DistSortedCoord {
                     pos: neighbor,
-                    from,
-                    to,
+                    g: dist_to_neighbor,
+                    h: heuristic(neighbor),
                 });
-                continue;
-            }
-            let prev_dist = prev[&neighbor].0;
-            if prev_dist > dist_to_neighbor {
-                // Shorter path to this neighbor found, replace
-                prev.insert(neighbor, (dist_to_neighbor, current.pos));
             }
         }
     }
@@ -79,29 +83,158 @@ pub fn find_path(map: &HashMap<IVec2, Vec<IVec2>>, from: Vec2, to: Vec2) -> Opti
     None
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+/// Removes waypoints from `path` where the straight line between the
+/// surrounding waypoints stays on walkable nodes, so that characters don't
+/// zig-zag along tile centers.
+pub fn smooth_path(map: &PathfindingMap, path: Vec<Vec2>) -> Vec<Vec2> {
+    if path.len() <= 2 {
+        return path;
+    }
+    let mut smoothed = vec![path[0]];
+    let mut i = 0;
+    while i < path.len() - 1 {
+        let mut j = path.len() - 1;
+        while j > i + 1 && !is_walkable(map, path[i], path[j]) {
+            j -= 1;
+        }
+        smoothed.push(path[j]);
+        i = j;
+    }
+    smoothed
+}
+
+/// Returns true if every node the line from `from` to `to` passes through is
+/// on the map, and each step from one node to the next is along a map edge.
+pub fn is_walkable(map: &PathfindingMap, from: Vec2, to: Vec2) -> bool {
+    const STEP_LENGTH: f32 = 0.1;
+    let steps = ((to - from).length() / STEP_LENGTH).ceil().max(1.0) as usize;
+    let mut prev_node = from.floor().as_ivec2();
+    if !map.contains_key(&prev_node) {
+        return false;
+    }
+    for i in 1..=steps {
+        let node = from.lerp(to, i as f32 / steps as f32).floor().as_ivec2();
+        if node == prev_node {
+            continue;
+        }
+        if !map[&prev_node].contains(&node) {
+            return false;
+        }
+        prev_node = node;
+    }
+    true
+}
+
+/// A queued node for the A* search, ordered so that the [BinaryHeap] pops the
+/// node with the lowest `g + h` first.
+#[derive(Clone, Copy, PartialEq)]
 struct DistSortedCoord {
     pos: IVec2,
-    from: IVec2,
-    to: IVec2,
+    /// The cost of the path from the start to `pos`.
+    g: f32,
+    /// The estimated cost from `pos` to the goal (straight-line distance).
+    h: f32,
 }
 
+impl Eq for DistSortedCoord {}
+
 impl PartialOrd for DistSortedCoord {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let get_h = |x: &DistSortedCoord| {
-            let diff_to_start = x.pos - x.from;
-            let dist_to_start =
-                diff_to_start.x * diff_to_start.x + diff_to_start.y * diff_to_start.y;
-            let diff_to_end = x.pos - x.to;
-            let dist_to_end = diff_to_end.x * diff_to_end.x + diff_to_end.y * diff_to_end.y;
-            -(dist_to_start + dist_to_end)
-        };
-        Some(get_h(self).cmp(&get_h(other)))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for DistSortedCoord {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        (-(self.g + self.h)).total_cmp(&-(other.g + other.h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::SQRT_2;
+
+    /// Creates an 8-connected map out of the '.' characters in `rows`. The
+    /// first row is y = 0.
+    fn map_from_rows(rows: &[&str]) -> HashMap<IVec2, Vec<IVec2>> {
+        let mut nodes = HashSet::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '.' {
+                    nodes.insert(IVec2::new(x as i32, y as i32));
+                }
+            }
+        }
+        let mut map = HashMap::new();
+        for &node in &nodes {
+            let mut neighbors = Vec::new();
+            for yo in -1..=1 {
+                for xo in -1..=1 {
+                    let neighbor = node + IVec2::new(xo, yo);
+                    if (xo != 0 || yo != 0) && nodes.contains(&neighbor) {
+                        neighbors.push(neighbor);
+                    }
+                }
+            }
+            map.insert(node, neighbors);
+        }
+        map
+    }
+
+    fn path_length(path: &[Vec2]) -> f32 {
+        path.windows(2).map(|w| (w[1] - w[0]).length()).sum()
+    }
+
+    fn center(x: i32, y: i32) -> Vec2 {
+        Vec2::new(x as f32, y as f32) + Vec2::ONE * 0.5
+    }
+
+    #[test]
+    fn test_open_grid_is_optimal() {
+        let map = map_from_rows(&[".....", ".....", ".....", ".....", "....."]);
+        let path = find_path(&map, center(0, 0), center(4, 2)).unwrap();
+        assert_eq!(center(0, 0), path[0]);
+        assert_eq!(center(4, 2), path[path.len() - 1]);
+        assert!((path_length(&path) - (2.0 * SQRT_2 + 2.0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_detour_around_wall_is_optimal() {
+        let map = map_from_rows(&[".....", ".###.", ".#.#.", ".#.#.", "....."]);
+        let path = find_path(&map, center(0, 2), center(4, 2)).unwrap();
+        // Around the wall: two straight steps, two diagonal steps and two
+        // more straight steps, either over or under the wall.
+        let expected = 4.0 + 2.0 * SQRT_2;
+        assert!((path_length(&path) - expected).abs() < 0.001);
+        for point in &path {
+            assert!(map.contains_key(&point.floor().as_ivec2()));
+        }
+    }
+
+    #[test]
+    fn test_smoothing_removes_unnecessary_waypoints() {
+        let map = map_from_rows(&[".....", ".....", ".....", ".....", "....."]);
+        let path = find_path(&map, center(0, 0), center(4, 2)).unwrap();
+        assert_eq!(vec![center(0, 0), center(4, 2)], smooth_path(&map, path));
+    }
+
+    #[test]
+    fn test_smoothing_keeps_path_on_the_map() {
+        let map = map_from_rows(&[".....", ".###.", ".#.#.", ".#.#.", "....."]);
+        let path = find_path(&map, center(0, 2), center(4, 2)).unwrap();
+        let smoothed = smooth_path(&map, path.clone());
+        assert!(smoothed.len() < path.len());
+        assert!(smoothed.len() > 2);
+        for segment in smoothed.windows(2) {
+            assert!(is_walkable(&map, segment[0], segment[1]));
+        }
+    }
+
+    #[test]
+    fn test_same_start_and_end() {
+        let map = map_from_rows(&["..", ".."]);
+        let path = find_path(&map, center(1, 1), center(1, 1)).unwrap();
+        assert_eq!(vec![center(1, 1)], path);
     }
 }
@@ -0,0 +1,158 @@
+//! Recording the player's decisions during a voyage, and playing them back on
+//! top of a new game from the same seed.
+//!
+//! Only the events in [ReplayEventKind] are recorded, everything else is
+//! either generated from the seed or follows from the world time. Hiring,
+//! trading and building aren't recorded, so the schedule events of crew that
+//! wasn't hired in the replay are skipped.
+
+use super::save_game::{SaveReader, SaveWriter};
+use super::{ShipGame, Task, SCHEDULE_SLOTS};
+use anyhow::{bail, Context};
+use glam::Vec2;
+use std::path::Path;
+
+/// The first bytes of every replay file.
+const MAGIC: &[u8] = b"SVRP";
+/// Bumped whenever the format changes, old replays are not loaded.
+const VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayEvent {
+    /// The in-game time the event happened at, in days.
+    pub world_time: f32,
+    pub event: ReplayEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayEventKind {
+    /// The ship set off towards the position.
+    SetTarget(Vec2),
+    SetSchedule {
+        character: usize,
+        time: usize,
+        task: Task,
+    },
+    /// The weather turned stormy or calm, with this storm intensity.
+    WeatherChange(f32),
+}
+
+impl ShipGame {
+    /// Adds the event to [ShipGame::replay], unless this game is a replay
+    /// itself.
+    pub fn record(&mut self, event: ReplayEventKind) {
+        if !self.replaying {
+            self.replay.push(ReplayEvent {
+                world_time: self.world_time,
+                event,
+            });
+        }
+    }
+}
+
+/// Writes the replay to the file, along with the world seed it was recorded
+/// in.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+pub fn save_replay(seed: u64, replay: &[ReplayEvent], path: &Path) -> anyhow::Result<()> {
+    let mut w = SaveWriter(MAGIC.to_vec());
+    w.u32(VERSION);
+    w.u64(seed);
+    w.u32(replay.len() as u32);
+    for event in replay {
+        write_event(&mut w, event);
+    }
+    std::fs::write(path, w.0).with_context(|| format!("could not write {path:?}"))
+}
+
+/// Reads a replay written by [save_replay], returning the world seed and the
+/// events.
+pub fn load_replay(path: &Path) -> anyhow::Result<(u64, Vec<ReplayEvent>)> {
+    let data = std::fs::read(path).with_context(|| format!("could not read {path:?}"))?;
+    let mut r = SaveReader::new(&data);
+    if r.bytes(MAGIC.len())? != MAGIC {
+        bail!("not a replay file");
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        bail!("unsupported replay file version {version}, expected {VERSION}");
+    }
+    let seed = r.u64()?;
+    let mut replay = Vec::new();
+    for _ in 0..r.u32()? {
+        replay.push(read_event(&mut r)?);
+    }
+    Ok((seed, replay))
+}
+
+/// Applies the events that have happened by `world_time`, in order. The
+/// events are expected to be sorted by time, as they are when recorded.
+/// Returns how many events were applied, the caller should pass the rest
+/// next time.
+pub fn play_replay(replay: &[ReplayEvent], ship_game: &mut ShipGame, world_time: f32) -> usize {
+    let applied = replay.partition_point(|event| event.world_time <= world_time);
+    for event in &replay[..applied] {
+        match event.event {
+            ReplayEventKind::SetTarget(target) => ship_game.current_target = target,
+            ReplayEventKind::SetSchedule {
+                character,
+                time,
+                task,
+            } => {
+                if let Some(character) = ship_game.characters.get_mut(character) {
+                    character.schedule[time] = task;
+                }
+            }
+            ReplayEventKind::WeatherChange(storm_intensity) => {
+                ship_game.storm_intensity = storm_intensity;
+            }
+        }
+    }
+    applied
+}
+
+fn write_event(w: &mut SaveWriter, event: &ReplayEvent) {
+    w.f32(event.world_time);
+    match event.event {
+        ReplayEventKind::SetTarget(target) => {
+            w.u32(0);
+            w.vec2(target);
+        }
+        ReplayEventKind::SetSchedule {
+            character,
+            time,
+            task,
+        } => {
+            w.u32(1);
+            w.u32(character as u32);
+            w.u32(time as u32);
+            w.bool(task == Task::Work);
+        }
+        ReplayEventKind::WeatherChange(storm_intensity) => {
+            w.u32(2);
+            w.f32(storm_intensity);
+        }
+    }
+}
+
+fn read_event(r: &mut SaveReader) -> anyhow::Result<ReplayEvent> {
+    let world_time = r.f32()?;
+    let event = match r.u32()? {
+        0 => ReplayEventKind::SetTarget(r.vec2()?),
+        1 => {
+            let character = r.u32()? as usize;
+            let time = r.u32()? as usize;
+            if time >= SCHEDULE_SLOTS {
+                bail!("replay has an invalid schedule slot: {time}");
+            }
+            let task = if r.bool()? { Task::Work } else { Task::Sleep };
+            ReplayEventKind::SetSchedule {
+                character,
+                time,
+                task,
+            }
+        }
+        2 => ReplayEventKind::WeatherChange(r.f32()?),
+        kind => bail!("replay has an invalid event: {kind}"),
+    };
+    Ok(ReplayEvent { world_time, event })
+}
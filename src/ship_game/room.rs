@@ -1,8 +1,6 @@
-use crate::{
-    math::Aabb2,
-    renderer::{gltf, Renderer},
-};
-use glam::{IVec2, Vec2, Vec4, Vec4Swizzles};
+use super::ShipModels;
+use crate::math::{Aabb2, Circle2};
+use glam::{IVec2, Vec2, Vec3};
 use std::collections::{HashMap, HashSet};
 
 pub struct Room {
@@ -13,35 +11,63 @@ pub struct Room {
     /// The area where characters are considered to be working in
     /// this room (unless they're moving through). Relative to `position`.
     pub working_area_bounds: Aabb2,
+    /// A circle containing the whole room model from above, relative to
+    /// `position`. Used for frustum culling, see [Room::bounding_sphere].
+    pub bounding_circle: Circle2,
     pub currently_working_characters: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How tall the room models are, for their bounding spheres.
+pub const ROOM_HEIGHT: f32 = 4.0;
+/// How far the room models can extend outside of `room_bounds`, e.g. the
+/// consoles in the navigation room.
+const ROOM_MODEL_MARGIN: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RoomType {
     Navigation,
     Sails,
+    Kitchen,
+    Engine,
+}
+
+impl RoomType {
+    pub const ALL: [RoomType; 4] = [
+        RoomType::Navigation,
+        RoomType::Sails,
+        RoomType::Kitchen,
+        RoomType::Engine,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoomType::Navigation => "Navigation",
+            RoomType::Sails => "Sails",
+            RoomType::Kitchen => "Kitchen",
+            RoomType::Engine => "Engine",
+        }
+    }
 }
 
 impl Room {
     pub fn new(
-        renderer: &Renderer,
+        models: &ShipModels,
         room_type: RoomType,
         position: Vec2,
         // TODO(opt): replace inner Vec with a u8 ("neighbor exists" bits)
         pathfinding_neighbors: &mut HashMap<IVec2, Vec<IVec2>>,
     ) -> Room {
-        let pathfinding_nodes = match room_type {
-            RoomType::Navigation => get_pathfinding_nodes(&renderer.room_navigation),
-            RoomType::Sails => get_pathfinding_nodes(&renderer.room_sailing),
-        };
+        let pathfinding_nodes = models.room_nodes(room_type);
         let working_area_bounds = match room_type {
             RoomType::Navigation => Aabb2::new(Vec2::new(2.0, 0.0), Vec2::new(3.0, 1.0)),
             RoomType::Sails => Aabb2::new(Vec2::new(-2.0, 0.0), Vec2::new(-1.0, 1.0)),
+            RoomType::Kitchen => Aabb2::new(Vec2::new(1.0, -1.0), Vec2::new(2.0, 0.0)),
+            RoomType::Engine => Aabb2::new(Vec2::new(1.0, -1.0), Vec2::new(2.0, 0.0)),
         };
         let ipos = position.floor().as_ivec2();
         let nodes_set: HashSet<IVec2> =
             HashSet::from_iter(pathfinding_nodes.iter().map(|n| *n + ipos));
-        for node in &pathfinding_nodes {
+        for node in pathfinding_nodes {
             let node = ipos + *node;
             let mut neighbors = Vec::with_capacity(8);
             for yo in -1..=1 {
@@ -67,23 +93,34 @@ impl Room {
                 pathfinding_neighbors.insert(node, neighbors);
             }
         }
+        let room_bounds = Aabb2::new(Vec2::ONE * -4.0, Vec2::ONE * 4.0);
+        let half_diagonal = (room_bounds.max - room_bounds.min).length() / 2.0;
+        let bounding_circle = Circle2::new(
+            (room_bounds.min + room_bounds.max) / 2.0,
+            half_diagonal + ROOM_MODEL_MARGIN,
+        );
         Room {
             room_type,
             position,
-            room_bounds: Aabb2::new(Vec2::ONE * -4.0, Vec2::ONE * 4.0),
+            room_bounds,
             working_area_bounds,
+            bounding_circle,
             currently_working_characters: Vec::new(),
         }
     }
-}
 
-fn get_pathfinding_nodes(gltf: &gltf::Gltf) -> Vec<IVec2> {
-    let mut nodes = Vec::new();
-    for node in gltf.get_node_transforms() {
-        if node.name.starts_with("Empty") {
-            let pos = (node.transform * Vec4::new(0.0, 0.0, 0.0, 1.0)).xz();
-            nodes.push(pos.floor().as_ivec2());
-        }
+    /// The middle of the working area, where characters walk to to work.
+    /// Relative to the ship, not `position`.
+    pub fn working_area_center(&self) -> Vec2 {
+        self.position + (self.working_area_bounds.min + self.working_area_bounds.max) / 2.0
+    }
+
+    /// Returns a sphere (center and radius) that contains the whole room
+    /// model, relative to `position`.
+    pub fn bounding_sphere(&self) -> (Vec3, f32) {
+        let Circle2 { center, radius } = self.bounding_circle;
+        let center = Vec3::new(center.x, ROOM_HEIGHT / 2.0, center.y);
+        // The sphere around the cylinder extruded from the circle
+        (center, Vec2::new(radius, ROOM_HEIGHT / 2.0).length())
     }
-    nodes
 }
@@ -0,0 +1,596 @@
+//! Saving and loading the game, in a small hand-written binary format.
+//!
+//! Only the state that changes during play is saved. The locations and
+//! deliveries are generated from the saved seed, so they're recreated from
+//! there and the saved state is applied on top. The rooms are saved as their
+//! types and positions, and rebuilt from those.
+//!
+//! There are [SAVE_SLOTS] save slots. The autosave goes into the first one,
+//! and that's the one continued from at startup.
+//!
+//! The replay of the voyage grows for as long as the voyage goes on, so it's
+//! not part of the save, but written next to it as a replay file (which can
+//! be played back with `--replay <file>`). On the web, there are no files to
+//! play back, so the replay isn't saved at all.
+
+use super::{
+    Character, CharacterTemplate, Job, LogCategory, RoomType, ShipGame, ShipModels, Task, NAMES,
+};
+use anyhow::{bail, Context};
+use glam::Vec2;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The first bytes of every save file.
+const MAGIC: &[u8] = b"SVEN";
+/// Bumped whenever the format changes, old saves are not loaded.
+const VERSION: u32 = 12;
+/// How many save slots there are.
+pub const SAVE_SLOTS: usize = 10;
+/// The save slot the game is autosaved in.
+pub const AUTOSAVE_SLOT: usize = 0;
+
+impl ShipGame {
+    pub fn save_to_bytes(&self) -> Vec<u8> {
+        let mut w = SaveWriter(MAGIC.to_vec());
+        w.u32(VERSION);
+        // Just for showing when the save was made, a wrong clock is fine
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        w.u64(now.map_or(0, |now| now.as_secs()));
+        w.u64(self.seed);
+        w.f32(self.world_time);
+        w.vec2(self.current_location);
+        w.vec2(self.current_target);
+        w.u32(self.target_queue.len() as u32);
+        for &location in &self.target_queue {
+            w.u32(location as u32);
+        }
+        w.bool(self.autopilot);
+        w.vec2(self.current_heading);
+        w.f32(self.current_ship_speed);
+        w.f32(self.ship_integrity);
+        w.f32(self.hull_integrity);
+        w.f32(self.fuel);
+        w.f32(self.max_fuel);
+        w.u32(self.last_event_day as u32);
+
+        w.u32(self.rooms.len() as u32);
+        for room in &self.rooms {
+            w.u32(room.room_type as u32);
+            w.vec2(room.position);
+        }
+
+        w.u32(self.characters.len() as u32);
+        for character in &self.characters {
+            w.str(&character.name);
+            w.u32(character.job as u32);
+            w.vec2(character.position);
+            for task in character.schedule {
+                w.bool(task == Task::Work);
+            }
+            w.f32(character.morale);
+            w.f32(character.energy);
+            w.bool(character.exhausted);
+            w.f32(character.time_slept);
+            w.f32(character.skill);
+        }
+
+        w.u32(self.deliveries.len() as u32);
+        for delivery in &self.deliveries {
+            w.str(delivery.name);
+            w.bool(delivery.completed);
+            w.bool(delivery.failed);
+            w.f32(delivery.start_time);
+            w.bool(delivery.picked_up);
+        }
+
+        w.u32(self.available_recruits.len() as u32);
+        for recruit in &self.available_recruits {
+            w.str(&recruit.name);
+            w.u32(recruit.job as u32);
+        }
+
+        w.u32(self.name_pool.len() as u32);
+        for name in &self.name_pool {
+            w.str(name);
+        }
+
+        w.f32(self.credits);
+        w.u32(self.cargo_hold.len() as u32);
+        for (name, quantity, price) in &self.cargo_hold {
+            w.str(name);
+            w.u32(*quantity);
+            w.f32(*price);
+        }
+        // The locations are the same as the generated ones, so they're
+        // matched up by index
+        for location in &self.locations {
+            w.u32(location.port_inventory.len() as u32);
+            for (name, quantity, price) in &location.port_inventory {
+                w.str(name);
+                w.u32(*quantity);
+                w.f32(*price);
+            }
+        }
+
+        w.u32(self.log.len() as u32);
+        for (time, category, text) in &self.log {
+            w.f32(*time);
+            w.u32(*category as u32);
+            w.str(text);
+        }
+        w.0
+    }
+
+    pub fn load_from_bytes(models: &ShipModels, data: &[u8]) -> anyhow::Result<ShipGame> {
+        let mut r = SaveReader::new(data);
+        if r.bytes(MAGIC.len())? != MAGIC {
+            bail!("not a save file");
+        }
+        let version = r.u32()?;
+        if version != VERSION {
+            bail!("unsupported save file version {version}, expected {VERSION}");
+        }
+        let _saved_at = r.u64()?;
+
+        let mut ship_game = ShipGame::new(models, r.u64()?);
+        ship_game.world_time = r.f32()?;
+        ship_game.current_location = r.vec2()?;
+        ship_game.current_target = r.vec2()?;
+        for _ in 0..r.u32()? {
+            let location = r.u32()? as usize;
+            if location >= ship_game.locations.len() {
+                bail!("save file has an invalid waypoint: {location}");
+            }
+            ship_game.target_queue.push_back(location);
+        }
+        ship_game.autopilot = r.bool()?;
+        ship_game.current_heading = r.vec2()?;
+        ship_game.current_ship_speed = r.f32()?;
+        ship_game.ship_integrity = r.f32()?;
+        ship_game.hull_integrity = r.f32()?;
+        ship_game.fuel = r.f32()?;
+        ship_game.max_fuel = r.f32()?;
+        ship_game.last_event_day = r.u32()? as u64;
+
+        ship_game.rooms.clear();
+        ship_game.pf_map.clear();
+        for _ in 0..r.u32()? {
+            let room_type = r.room_type()?;
+            let position = r.vec2()?;
+            let (rooms, pf_map) = (&mut ship_game.rooms, &mut ship_game.pf_map);
+            ShipGame::add_room(rooms, pf_map, models, room_type, position);
+        }
+
+        ship_game.characters.clear();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let job = r.job()?;
+            let mut character = Character::new(models, job, name);
+            character.position = r.vec2()?;
+            for task in &mut character.schedule {
+                *task = if r.bool()? { Task::Work } else { Task::Sleep };
+            }
+            character.morale = r.f32()?;
+            character.energy = r.f32()?;
+            character.exhausted = r.bool()?;
+            character.time_slept = r.f32()?;
+            character.skill = r.f32()?.clamp(0.0, 1.0);
+            ship_game.characters.push(character);
+        }
+        if ship_game.characters.is_empty() {
+            bail!("the save file has no characters");
+        }
+        ship_game.selected_character = Some(0);
+
+        let mut saved_deliveries = Vec::new();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let completed = r.bool()?;
+            let failed = r.bool()?;
+            let start_time = r.f32()?;
+            let picked_up = r.bool()?;
+            saved_deliveries.push((name, completed, failed, start_time, picked_up));
+        }
+        // Deliveries that aren't in the save were lost along the way
+        ship_game.deliveries.retain_mut(|delivery| {
+            let saved = saved_deliveries
+                .iter()
+                .find(|(name, ..)| name == delivery.name);
+            if let Some(&(_, completed, failed, start_time, picked_up)) = saved {
+                delivery.completed = completed;
+                delivery.failed = failed;
+                delivery.start_time = start_time;
+                delivery.picked_up = picked_up;
+            }
+            saved.is_some()
+        });
+
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let job = r.job()?;
+            ship_game
+                .available_recruits
+                .push(CharacterTemplate { name, job });
+        }
+
+        let mut name_pool = Vec::new();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            if let Some(name) = NAMES.iter().find(|n| **n == name) {
+                name_pool.push(*name);
+            }
+        }
+        ship_game.name_pool = name_pool;
+
+        ship_game.credits = r.f32()?;
+        ship_game.cargo_hold.clear();
+        for _ in 0..r.u32()? {
+            ship_game.cargo_hold.push((r.string()?, r.u32()?, r.f32()?));
+        }
+        for location in &mut ship_game.locations {
+            location.port_inventory.clear();
+            for _ in 0..r.u32()? {
+                location
+                    .port_inventory
+                    .push((r.string()?, r.u32()?, r.f32()?));
+            }
+        }
+
+        ship_game.log.clear();
+        for _ in 0..r.u32()? {
+            ship_game
+                .log
+                .push((r.f32()?, r.log_category()?, r.string()?));
+        }
+
+        Ok(ship_game)
+    }
+
+    /// Writes the game to the save slot's file (or the browser's local
+    /// storage, on the web).
+    pub fn save(&self, slot: usize) -> anyhow::Result<()> {
+        storage::write(slot, &self.save_to_bytes())?;
+        storage::write_replay(slot, self.seed, &self.replay)
+    }
+
+    /// Deletes the save slot's file, if there is one.
+    pub fn delete_save(slot: usize) -> anyhow::Result<()> {
+        storage::delete(slot)
+    }
+
+    /// Loads the game from the save slot, if there's a save in it.
+    pub fn load(models: &ShipModels, slot: usize) -> anyhow::Result<Option<ShipGame>> {
+        let Some(data) = storage::read(slot)? else {
+            return Ok(None);
+        };
+        let mut ship_game = ShipGame::load_from_bytes(models, &data)?;
+        // The save is still fine without its replay, the replay of the rest
+        // of the voyage just won't have the start
+        match storage::read_replay(slot) {
+            Ok(Some((seed, replay))) if seed == ship_game.seed => ship_game.replay = replay,
+            Ok(Some(_)) => eprintln!("the replay of save slot {slot} is from another world"),
+            Ok(None) => {}
+            Err(err) => eprintln!("could not load the replay of save slot {slot}: {err:?}"),
+        }
+        Ok(Some(ship_game))
+    }
+
+    /// Returns a short description of the save in the slot, for the save
+    /// slot list, or None if the slot is empty.
+    pub fn describe_save(slot: usize) -> Option<String> {
+        let data = match storage::read(slot) {
+            Ok(data) => data?,
+            Err(err) => {
+                eprintln!("could not read save slot {slot}: {err:?}");
+                return Some("Unreadable save".to_string());
+            }
+        };
+        let mut r = SaveReader::new(&data);
+        let mut header = || -> anyhow::Result<(u64, f32)> {
+            if r.bytes(MAGIC.len())? != MAGIC || r.u32()? != VERSION {
+                bail!("not a save file from this version");
+            }
+            let saved_at = r.u64()?;
+            let _seed = r.u64()?;
+            Ok((saved_at, r.f32()?))
+        };
+        match header() {
+            Ok((saved_at, world_time)) => Some(format!(
+                "{}, day {world_time:.1}",
+                format_timestamp(saved_at)
+            )),
+            Err(_) => Some("Incompatible save".to_string()),
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, like
+/// "2023-04-28 18:00".
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let (hour, minute) = (seconds % 86400 / 3600, seconds % 3600 / 60);
+    // From Howard Hinnant's days_from_civil algorithms, in reverse
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}")
+}
+
+pub(super) struct SaveWriter(pub Vec<u8>);
+
+impl SaveWriter {
+    pub(super) fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(super) fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(super) fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(super) fn bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+
+    pub(super) fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    pub(super) fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+}
+
+pub(super) struct SaveReader<'a> {
+    data: &'a [u8],
+    cursor: usize,
+}
+
+impl SaveReader<'_> {
+    pub(super) fn new(data: &[u8]) -> SaveReader<'_> {
+        SaveReader { data, cursor: 0 }
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> anyhow::Result<&[u8]> {
+        let end = self.cursor.checked_add(len);
+        let bytes = end
+            .and_then(|end| self.data.get(self.cursor..end))
+            .context("save file ended unexpectedly")?;
+        self.cursor += len;
+        Ok(bytes)
+    }
+
+    pub(super) fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub(super) fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn bool(&mut self) -> anyhow::Result<bool> {
+        Ok(self.bytes(1)?[0] != 0)
+    }
+
+    pub(super) fn vec2(&mut self) -> anyhow::Result<Vec2> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    pub(super) fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?.to_vec();
+        String::from_utf8(bytes).context("save file has an invalid string")
+    }
+
+    fn room_type(&mut self) -> anyhow::Result<RoomType> {
+        let room_type = self.u32()? as usize;
+        RoomType::ALL
+            .get(room_type)
+            .copied()
+            .with_context(|| format!("save file has an invalid room type: {room_type}"))
+    }
+
+    fn log_category(&mut self) -> anyhow::Result<LogCategory> {
+        let category = self.u32()? as usize;
+        LogCategory::ALL
+            .get(category)
+            .copied()
+            .with_context(|| format!("save file has an invalid log category: {category}"))
+    }
+
+    fn job(&mut self) -> anyhow::Result<Job> {
+        let job = self.u32()? as usize;
+        Job::ALL
+            .get(job)
+            .copied()
+            .with_context(|| format!("save file has an invalid job: {job}"))
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod storage {
+    use crate::ship_game::{load_replay, save_replay, ReplayEvent};
+    use anyhow::Context;
+    use std::{fs, io, path::PathBuf};
+
+    fn save_path(slot: usize) -> anyhow::Result<PathBuf> {
+        let data_dir = dirs::data_dir().context("could not find a directory for the save file")?;
+        Ok(data_dir
+            .join("sailing-venus")
+            .join(format!("save_{slot}.bin")))
+    }
+
+    fn replay_path(slot: usize) -> anyhow::Result<PathBuf> {
+        Ok(save_path(slot)?.with_file_name(format!("replay_{slot}.bin")))
+    }
+
+    pub fn write(slot: usize, data: &[u8]) -> anyhow::Result<()> {
+        let path = save_path(slot)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("could not create {dir:?}"))?;
+        }
+        fs::write(&path, data).with_context(|| format!("could not write {path:?}"))
+    }
+
+    pub fn read(slot: usize) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = save_path(slot)?;
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("could not read {path:?}")),
+        }
+    }
+
+    pub fn write_replay(slot: usize, seed: u64, replay: &[ReplayEvent]) -> anyhow::Result<()> {
+        save_replay(seed, replay, &replay_path(slot)?)
+    }
+
+    pub fn read_replay(slot: usize) -> anyhow::Result<Option<(u64, Vec<ReplayEvent>)>> {
+        let path = replay_path(slot)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        load_replay(&path).map(Some)
+    }
+
+    pub fn delete(slot: usize) -> anyhow::Result<()> {
+        for path in [save_path(slot)?, replay_path(slot)?] {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(err).with_context(|| format!("could not delete {path:?}"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod storage {
+    use super::base64;
+    use crate::emscripten_h;
+    use crate::ship_game::ReplayEvent;
+
+    pub fn write(slot: usize, data: &[u8]) -> anyhow::Result<()> {
+        let encoded = base64::encode(data);
+        emscripten_h::run_javascript(&format!("localStorage.setItem('save-{slot}', '{encoded}')"));
+        Ok(())
+    }
+
+    pub fn read(slot: usize) -> anyhow::Result<Option<Vec<u8>>> {
+        let encoded = emscripten_h::run_javascript_string(&format!(
+            "localStorage.getItem('save-{slot}') || ''"
+        ));
+        if encoded.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(base64::decode(&encoded)?))
+    }
+
+    /// There are no files to play replays back from on the web, so they're
+    /// not saved.
+    pub fn write_replay(_slot: usize, _seed: u64, _replay: &[ReplayEvent]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    pub fn read_replay(_slot: usize) -> anyhow::Result<Option<(u64, Vec<ReplayEvent>)>> {
+        Ok(None)
+    }
+
+    pub fn delete(slot: usize) -> anyhow::Result<()> {
+        emscripten_h::run_javascript(&format!("localStorage.removeItem('save-{slot}')"));
+        Ok(())
+    }
+}
+
+/// Just enough base64 for storing the save in the browser's local storage,
+/// which only holds strings.
+#[cfg_attr(not(target_family = "wasm"), allow(dead_code))]
+mod base64 {
+    use anyhow::Context;
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn encode(data: &[u8]) -> String {
+        let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+        for chunk in data.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let bits = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3F] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded
+    }
+
+    pub fn decode(encoded: &str) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(encoded.len() / 4 * 3);
+        let mut bits = 0u32;
+        let mut bit_count = 0;
+        for c in encoded.bytes().take_while(|&c| c != b'=') {
+            let value = ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .with_context(|| format!("invalid base64 character: {:?}", c as char))?;
+            bits = bits << 6 | value as u32;
+            bit_count += 6;
+            if bit_count >= 8 {
+                bit_count -= 8;
+                data.push((bits >> bit_count) as u8);
+            }
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, format_timestamp};
+
+    #[test]
+    fn timestamps() {
+        assert_eq!("1970-01-01 00:00", format_timestamp(0));
+        assert_eq!("2000-02-29 12:34", format_timestamp(951827640));
+        assert_eq!("2023-04-28 18:00", format_timestamp(1682704800));
+    }
+
+    #[test]
+    fn base64_roundtrip() {
+        assert_eq!("", base64::encode(b""));
+        assert_eq!("Zg==", base64::encode(b"f"));
+        assert_eq!("Zm8=", base64::encode(b"fo"));
+        assert_eq!("Zm9v", base64::encode(b"foo"));
+        assert_eq!("Zm9vYmFy", base64::encode(b"foobar"));
+        let data = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(data, base64::decode(&base64::encode(&data)).unwrap());
+    }
+}
@@ -0,0 +1,189 @@
+//! Buying and selling goods at ports.
+
+use super::{LogCategory, ShipGame, CRITICAL_HULL, MAX_SHIP_SPEED, OUT_OF_FUEL_SPEED};
+
+pub struct TradeGood {
+    pub name: &'static str,
+    /// The weight of one unit, in tonnes.
+    pub weight: f32,
+    /// The price around which ports buy and sell the good.
+    pub base_price: f32,
+}
+
+/// The goods that can be traded, in addition to the deliveries' cargo.
+pub const TRADE_GOODS: &[TradeGood] = &[
+    TradeGood {
+        name: "Water",
+        weight: 1.0,
+        base_price: 5.0,
+    },
+    TradeGood {
+        name: "Food rations",
+        weight: 2.0,
+        base_price: 12.0,
+    },
+    TradeGood {
+        name: "Textiles",
+        weight: 1.0,
+        base_price: 18.0,
+    },
+    TradeGood {
+        name: "Sulfuric acid",
+        weight: 4.0,
+        base_price: 25.0,
+    },
+    TradeGood {
+        name: "Machine parts",
+        weight: 5.0,
+        base_price: 40.0,
+    },
+    TradeGood {
+        name: "Electronics",
+        weight: 1.0,
+        base_price: 60.0,
+    },
+];
+/// The weight of a delivery's cargo, in tonnes.
+const DELIVERY_WEIGHT: f32 = 10.0;
+/// How much cargo the ship can carry, in tonnes. The ship slows down the
+/// closer it gets to this.
+pub const MAX_CARGO_WEIGHT: f32 = 100.0;
+/// How many credits a delivery pays when it's sold at its destination.
+const DELIVERY_PAYMENT: f32 = 50.0;
+
+/// Returns the weight of one unit of the item, in tonnes.
+pub fn item_weight(name: &str) -> f32 {
+    TRADE_GOODS
+        .iter()
+        .find(|good| good.name == name)
+        .map_or(DELIVERY_WEIGHT, |good| good.weight)
+}
+
+impl ShipGame {
+    /// Returns the index of the location the ship is stopped at, if any.
+    pub fn port_here(&self) -> Option<usize> {
+        if self.current_ship_speed > 0.0 {
+            return None;
+        }
+        self.locations
+            .iter()
+            .position(|location| location.position.distance(self.current_location) < 1.0)
+    }
+
+    /// The total weight of the cargo hold, in tonnes.
+    pub fn cargo_weight(&self) -> f32 {
+        self.cargo_hold
+            .iter()
+            .map(|(name, quantity, _)| *quantity as f32 * item_weight(name))
+            .sum()
+    }
+
+    /// The fastest the ship can sail with its current cargo, hull and fuel.
+    pub fn max_ship_speed(&self) -> f32 {
+        max_ship_speed(self.cargo_weight(), self.hull_integrity, self.fuel)
+    }
+
+    /// Buys one unit of the item (by index) from the port the ship is at.
+    /// Returns the reason if it can't be bought.
+    pub fn buy(&mut self, item: usize) -> Option<String> {
+        let port = self.port_here()?;
+        let (name, quantity, price) = self.locations[port].port_inventory.get(item)?.clone();
+        if quantity == 0 {
+            return Some(format!("{name} is sold out"));
+        } else if self.credits < price {
+            return Some(format!("Not enough credits for {}", name.to_lowercase()));
+        } else if self.cargo_weight() + item_weight(&name) > MAX_CARGO_WEIGHT {
+            return Some("There's no room in the cargo hold".to_string());
+        }
+        self.credits -= price;
+        self.locations[port].port_inventory[item].1 -= 1;
+        match self.cargo_hold.iter_mut().find(|(held, ..)| *held == name) {
+            Some((_, held_quantity, buy_price)) => {
+                // Averaged, so that the hold shows what the cargo cost
+                let total = *buy_price * *held_quantity as f32 + price;
+                *held_quantity += 1;
+                *buy_price = total / *held_quantity as f32;
+            }
+            None => self.cargo_hold.push((name, 1, price)),
+        }
+        None
+    }
+
+    /// Sells one unit of the item (by index in the cargo hold) to the port
+    /// the ship is at. Deliveries can only be sold at their destination,
+    /// which completes them. Returns a message for the player, if there's
+    /// something to tell them.
+    pub fn sell(&mut self, item: usize) -> Option<String> {
+        let port = self.port_here()?;
+        let (name, ..) = self.cargo_hold.get(item)?.clone();
+        let port_name = &self.locations[port].name;
+        let delivery = self.deliveries.iter_mut().find(|d| d.name == name);
+        let message = if let Some(delivery) = delivery {
+            if delivery.destination_name != *port_name {
+                let destination = &delivery.destination_name;
+                return Some(format!("{name} needs to go to {destination}"));
+            }
+            delivery.completed = true;
+            self.credits += DELIVERY_PAYMENT;
+            let text = format!("Delivered {} to {port_name}", name.to_lowercase());
+            self.log_event(LogCategory::Delivery, text);
+            Some(format!("Delivered: {}!", name.to_lowercase()))
+        } else {
+            let inventory = &mut self.locations[port].port_inventory;
+            match inventory.iter_mut().find(|(sold, ..)| *sold == name) {
+                Some((_, quantity, price)) => {
+                    *quantity += 1;
+                    self.credits += *price;
+                }
+                None => {
+                    let good = TRADE_GOODS.iter().find(|good| good.name == name)?;
+                    inventory.push((name.clone(), 1, good.base_price));
+                    self.credits += good.base_price;
+                }
+            }
+            None
+        };
+        self.cargo_hold[item].1 -= 1;
+        if self.cargo_hold[item].1 == 0 {
+            self.cargo_hold.remove(item);
+        }
+        message
+    }
+
+    /// Removes the delivery's cargo from the hold, if it's there.
+    pub(super) fn unload_delivery(&mut self, delivery_name: &str) {
+        self.cargo_hold.retain(|(name, ..)| name != delivery_name);
+    }
+}
+
+fn max_ship_speed(cargo_weight: f32, hull_integrity: f32, fuel: f32) -> f32 {
+    let hull_multiplier = if hull_integrity < CRITICAL_HULL {
+        0.25
+    } else {
+        1.0
+    };
+    let max_speed =
+        MAX_SHIP_SPEED * (1.0 - cargo_weight / MAX_CARGO_WEIGHT).max(0.1) * hull_multiplier;
+    if fuel <= 0.0 {
+        max_speed.min(OUT_OF_FUEL_SPEED)
+    } else {
+        max_speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{max_ship_speed, MAX_CARGO_WEIGHT};
+
+    #[test]
+    fn ship_crawls_without_fuel() {
+        for (cargo_weight, hull_integrity) in [(0.0, 1.0), (MAX_CARGO_WEIGHT, 0.0)] {
+            let with_fuel = max_ship_speed(cargo_weight, hull_integrity, 1.0);
+            let without_fuel = max_ship_speed(cargo_weight, hull_integrity, 0.0);
+            // Slower, but still moving, so it can make it to a depot
+            assert!(without_fuel > 0.0);
+            assert!(without_fuel <= with_fuel);
+        }
+        assert!(max_ship_speed(0.0, 1.0, 0.0) < max_ship_speed(0.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,157 @@
+//! Generating the locations and deliveries of a world from a seed.
+
+use super::{splitmix64, Delivery, Faction, Location, TRADE_GOODS};
+use glam::Vec2;
+
+/// How far from the starting location the other locations can be, on both
+/// axes.
+const WORLD_HALF_SIZE: f32 = 100.0;
+/// How close to each other locations can be.
+const MIN_LOCATION_DISTANCE: f32 = 20.0;
+const ADJECTIVES: &[&str] = &[
+    "Amber", "Ashen", "Bright", "Copper", "Crimson", "Dusky", "Golden", "Hazy", "Hollow", "Iron",
+    "Misty", "Quiet", "Silent", "Sulfur", "Velvet", "Windy",
+];
+const NOUNS: &[&str] = &[
+    "Anchor", "Beacon", "Crater", "Drift", "Gate", "Harbor", "Haven", "Lantern", "Outpost",
+    "Perch", "Rest", "Spire", "Station", "Tower", "Wharf", "Yard",
+];
+const CARGO: &[&str] = &[
+    "Mineral refiner",
+    "Rocket fuel",
+    "Spare sails",
+    "Seed vault",
+    "Cloud samples",
+    "Medical kits",
+    "Balloon fabric",
+    "Sensor array",
+];
+
+/// Generates 8 to 16 locations and 3 to 5 deliveries between them. The same
+/// seed always results in the same world. The first location is where the
+/// ship starts, at the origin, and it's always a fuel depot.
+pub fn generate_world(seed: u64) -> (Vec<Location>, Vec<Delivery>) {
+    let mut counter = seed.wrapping_mul(0x100000001B3);
+    let mut random = || {
+        counter = counter.wrapping_add(1);
+        splitmix64(counter)
+    };
+    // Between 0 and 1
+    let mut random_f32 = || (random() >> 40) as f32 / (1u64 << 24) as f32;
+
+    let location_count = 8 + (random_f32() * 9.0) as usize;
+    let mut locations: Vec<Location> = Vec::with_capacity(location_count);
+    let mut attempts = 0;
+    while locations.len() < location_count && attempts < 10_000 {
+        attempts += 1;
+        let position = if locations.is_empty() {
+            Vec2::ZERO
+        } else {
+            let position = Vec2::new(random_f32(), random_f32()) * 2.0 - 1.0;
+            (position * WORLD_HALF_SIZE).round()
+        };
+        let too_close =
+            |location: &Location| location.position.distance(position) < MIN_LOCATION_DISTANCE;
+        if locations.iter().any(too_close) {
+            continue;
+        }
+        let adjective = ADJECTIVES[(random_f32() * ADJECTIVES.len() as f32) as usize];
+        let noun = NOUNS[(random_f32() * NOUNS.len() as f32) as usize];
+        let name = format!("{adjective} {noun}");
+        if locations.iter().any(|location| location.name == name) {
+            continue;
+        }
+        let first = locations.is_empty();
+        let faction = match (random_f32() * 3.0) as usize {
+            0 => Faction::PostUnion,
+            1 => Faction::Terraformers,
+            _ => Faction::Independent,
+        };
+        // A few of the goods, at prices that vary from port to port
+        let mut port_inventory = Vec::new();
+        for good in TRADE_GOODS {
+            if random_f32() < 0.5 {
+                let quantity = 5 + (random_f32() * 16.0) as u32;
+                let price = (good.base_price * (0.6 + random_f32() * 0.8)).round();
+                port_inventory.push((good.name.to_string(), quantity, price));
+            }
+        }
+        locations.push(Location {
+            name,
+            position,
+            is_fuel_depot: first || random_f32() < 0.35,
+            faction,
+            danger_level: if first { 0 } else { (random_f32() * 4.0) as u8 },
+            port_inventory,
+        });
+    }
+
+    let delivery_count = 3 + (random_f32() * 3.0) as usize;
+    let mut cargo = CARGO.to_vec();
+    let mut deliveries = Vec::with_capacity(delivery_count);
+    for i in 0..delivery_count {
+        let name = cargo.remove((random_f32() * cargo.len() as f32) as usize);
+        // The first delivery is already on board, so there's somewhere to go
+        let origin = if i == 0 {
+            0
+        } else {
+            (random_f32() * locations.len() as f32) as usize
+        };
+        let destination = loop {
+            let destination = (random_f32() * locations.len() as f32) as usize;
+            if destination != origin {
+                break destination;
+            }
+        };
+        let deadline_days = (random_f32() < 0.5).then(|| 2.0 + (random_f32() * 4.0).round());
+        let mut delivery = Delivery::new(
+            name,
+            locations[origin].name.clone(),
+            locations[destination].name.clone(),
+            deadline_days,
+        );
+        delivery.picked_up = origin == 0;
+        deliveries.push(delivery);
+    }
+
+    (locations, deliveries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_world() {
+        let (locations_a, deliveries_a) = generate_world(42);
+        let (locations_b, deliveries_b) = generate_world(42);
+        assert_eq!(locations_a.len(), locations_b.len());
+        for (a, b) in locations_a.iter().zip(&locations_b) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.faction, b.faction);
+        }
+        let names = |deliveries: &[Delivery]| {
+            deliveries
+                .iter()
+                .map(|d| (d.name, d.origin_name.clone(), d.destination_name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&deliveries_a), names(&deliveries_b));
+    }
+
+    #[test]
+    fn locations_are_spread_out() {
+        for seed in 0..50 {
+            let (locations, deliveries) = generate_world(seed);
+            assert!((8..=16).contains(&locations.len()), "seed {seed}");
+            assert!((3..=5).contains(&deliveries.len()), "seed {seed}");
+            assert_eq!(Vec2::ZERO, locations[0].position);
+            for (i, a) in locations.iter().enumerate() {
+                for b in &locations[i + 1..] {
+                    assert!(a.position.distance(b.position) >= MIN_LOCATION_DISTANCE);
+                }
+            }
+        }
+    }
+}